@@ -0,0 +1,198 @@
+//! The `#[derive(CmdLineAndConfigIntegration)]` macro for `ogre-config-meld` -- to be used through its re-export there,
+//! enabled by the `derive` feature
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, Field, Fields, Ident, LitStr, Token, Type};
+
+/// Implements `CmdLineAndConfigIntegration` for command line option structs, sparing the boilerplate.
+/// The root config type the options are merged into is given by the `#[config(root = <type>)]` struct attribute,
+/// while field attributes map the options to the trait methods:
+///   - `#[config(file)]`: the `Option<String>` field with the config file path -- see `config_file_path()`;
+///   - `#[config(write_effective)]`: the `bool` flag for `should_write_effective_config()`;
+///   - `#[config(show_effective)]`: the `bool` flag for `should_show_effective_config()`;
+///   - `#[config(skip)]`: options not to be merged into the config;
+///   - `#[config(path = "log.level")]`: options merged into the config field at the given dotted path.
+///
+/// Missing flags are taken as `false` & a missing config file option as `None`. Every other field -- except the `flatten`ed
+/// & `subcommand` ones -- is merged into the config field of the same name, through `MergeField`: like `Option`s that,
+/// if given, replace the config values -- these mappings being also given by `cli_to_config_paths()`, for the provenance. Like this:
+/// ```nocompile
+///   #[derive(clap::Parser, CmdLineAndConfigIntegration, Debug)]
+///   #[config(root = AppConfig)]
+///   struct CmdLineOptions {
+///       #[clap(long, short = 'c')]
+///       #[config(file)]
+///       config_file: Option<String>,
+///       #[clap(long)]
+///       #[config(write_effective)]
+///       write_effective_config: bool,
+///       #[clap(long)]
+///       #[config(show_effective)]
+///       show_effective_config: bool,
+///       /// merged into `AppConfig::port`
+///       #[clap(long)]
+///       port: Option<u16>,
+///       /// merged into `AppConfig::log::level`
+///       #[clap(long)]
+///       #[config(path = "log.level")]
+///       log_level: Option<u8>,
+///   }
+#[proc_macro_derive(CmdLineAndConfigIntegration, attributes(config))]
+pub fn derive_cmdline_and_config_integration(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// What a field of the command line options struct is for -- as told by its `#[config(...)]` attribute
+enum FieldRole {
+    File,
+    WriteEffective,
+    ShowEffective,
+    Skip,
+    /// Merged into the config field at the given path
+    Merge(Vec<Ident>),
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let root_config_type = root_config_type(input)?;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(&input.ident, "`CmdLineAndConfigIntegration` can only be derived for structs with named fields")),
+        },
+        _ => return Err(Error::new_spanned(&input.ident, "`CmdLineAndConfigIntegration` can only be derived for structs")),
+    };
+
+    let (mut file, mut write_effective, mut show_effective) = (None, None, None);
+    let (mut merges, mut cli_to_config_paths) = (Vec::new(), Vec::new());
+    for field in fields {
+        let field_ident = field.ident.as_ref().expect("named fields have idents");
+        match field_role(field)? {
+            FieldRole::File => set_once(&mut file, field, "file")?,
+            FieldRole::WriteEffective => set_once(&mut write_effective, field, "write_effective")?,
+            FieldRole::ShowEffective => set_once(&mut show_effective, field, "show_effective")?,
+            FieldRole::Skip => (),
+            FieldRole::Merge(config_field_path) => {
+                merges.push(quote_spanned! { field.span() =>
+                    ::ogre_config_meld::MergeField::merge_into(self.#field_ident, &mut config.#(#config_field_path).*);
+                });
+                let (option_name, config_path) = (field_ident.to_string(), config_field_path.iter().map(Ident::to_string).collect::<Vec<_>>().join("."));
+                cli_to_config_paths.push(quote!((#option_name, #config_path)));
+            },
+        }
+    }
+    let config_file_path = file.map_or_else(|| quote!(::std::option::Option::None), |file| quote!(::std::option::Option::as_deref(&self.#file)));
+    let write_effective = write_effective.map_or_else(|| quote!(false), |write_effective| quote!(self.#write_effective));
+    let show_effective = show_effective.map_or_else(|| quote!(false), |show_effective| quote!(self.#show_effective));
+
+    let options_type = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::ogre_config_meld::CmdLineAndConfigIntegration<#root_config_type> for #options_type #type_generics #where_clause {
+            fn config_file_path(&self) -> ::std::option::Option<&str> {
+                #config_file_path
+            }
+            fn should_write_effective_config(&self) -> bool {
+                #write_effective
+            }
+            fn should_show_effective_config(&self) -> bool {
+                #show_effective
+            }
+            fn cli_to_config_paths() -> &'static [(&'static str, &'static str)] {
+                &[#(#cli_to_config_paths),*]
+            }
+            #[allow(unused_mut)]
+            fn merge_with_config(self, mut config: #root_config_type) -> ::std::result::Result<#root_config_type, ::ogre_config_meld::Error> {
+                #(#merges)*
+                ::std::result::Result::Ok(config)
+            }
+        }
+    })
+}
+
+/// The type given by the `#[config(root = <type>)]` struct attribute
+fn root_config_type(input: &DeriveInput) -> syn::Result<Type> {
+    let mut root_config_type = None;
+    for attr in config_attrs(&input.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("root") {
+                root_config_type = Some(meta.value()?.parse::<Type>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported `config` struct attribute: expected `root = <the root config type>`"))
+            }
+        })?;
+    }
+    root_config_type.ok_or_else(|| Error::new_spanned(&input.ident, "missing the `#[config(root = <type>)]` attribute, telling the root config type the options are merged into"))
+}
+
+fn field_role(field: &Field) -> syn::Result<FieldRole> {
+    let mut role = None;
+    for attr in config_attrs(&field.attrs) {
+        attr.parse_nested_meta(|meta| {
+            let field_role = if meta.path.is_ident("file") {
+                FieldRole::File
+            } else if meta.path.is_ident("write_effective") {
+                FieldRole::WriteEffective
+            } else if meta.path.is_ident("show_effective") {
+                FieldRole::ShowEffective
+            } else if meta.path.is_ident("skip") {
+                FieldRole::Skip
+            } else if meta.path.is_ident("path") {
+                let config_field_path: LitStr = meta.value()?.parse()?;
+                FieldRole::Merge(config_field_path.value().split('.')
+                    .map(|field_name| syn::parse_str::<Ident>(field_name)
+                        .map_err(|_| Error::new_spanned(&config_field_path, format!("`{field_name}` isn't a valid field name: expected a dotted path, like \"log.level\""))))
+                    .collect::<syn::Result<_>>()?)
+            } else {
+                return Err(meta.error("unsupported `config` field attribute: expected `file`, `write_effective`, `show_effective`, `skip` or `path = \"<dotted.path>\"`"))
+            };
+            match role.replace(field_role) {
+                Some(_) => Err(meta.error("a field may have a single `config` attribute")),
+                None => Ok(()),
+            }
+        })?;
+    }
+    Ok(match role {
+        Some(role) => role,
+        None if is_flattened_or_subcommand(field) => FieldRole::Skip,
+        None => FieldRole::Merge(vec![field.ident.clone().expect("named fields have idents")]),
+    })
+}
+
+/// Records `field` as the one for the trait method implied by the `attribute`, which may only be used once
+fn set_once<'a>(slot: &mut Option<&'a Ident>, field: &'a Field, attribute: &str) -> syn::Result<()> {
+    match slot.replace(field.ident.as_ref().expect("named fields have idents")) {
+        Some(previous) => Err(Error::new(field.span(), format!("`#[config({attribute})]` was already given to the `{previous}` field"))),
+        None => Ok(()),
+    }
+}
+
+fn config_attrs(attrs: &[Attribute]) -> impl Iterator<Item=&Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("config"))
+}
+
+/// Tells if `field` is a `#[clap(flatten)]` or `#[command(subcommand)]`-like field -- not holding an option value to be merged
+fn is_flattened_or_subcommand(field: &Field) -> bool {
+    let mut flattened_or_subcommand = false;
+    for attr in field.attrs.iter().filter(|attr| ["clap", "command", "arg"].iter().any(|clap_attr| attr.path().is_ident(clap_attr))) {
+        // clap's own derive reports its malformed attributes, so parsing errors are not ours to tell
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("flatten") || meta.path.is_ident("subcommand") {
+                flattened_or_subcommand = true;
+            }
+            // skips whatever follows the argument names -- like ` = "name"` or `(value_parser!(u8).range(1..), ..)` --
+            // up to the next argument, as token trees: groups with commas inside count as one
+            while !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                meta.input.parse::<TokenTree>()?;
+            }
+            Ok(())
+        });
+    }
+    flattened_or_subcommand
+}
@@ -0,0 +1,639 @@
+//! Blocking variants of the load/save API, for programs not running an async runtime -- like small, fully synchronous CLI tools.
+//!
+//! They share everything but the IO calls with their async counterparts, at the crate's root -- which also offer what requires
+//! the `async` feature: encrypted config files (see [crate::encryptable_tokio_fs]), backups, recoveries & in-place rewrites of the
+//! effective config, [crate::ConfigMeld], remote configs, watching & reloading on SIGHUP.
+//! Encrypted files are refused here with [crate::Error::AsyncOnly]
+
+use crate::logic::{changed_field_paths, config_help, debug_config_paths, effective_config_for_output, emit_warning, explicit_config_file_err, is_config_url, is_refused_creation,
+                              merge_cmdline_args_with_configs_traced, persisted_or_warned, show_effective_config_and_changes, with_recovery_hint};
+use crate::logic::{check_loadable_extension, compress_if_gzipped, decompressed_text, durable_temp_file_path, followed_symlink,
+                                 is_gzipped, loaded_config, loaded_text, loading_format, lock_attempt, lock_file_path_of, locking_error,
+                                 parent_dir_error, parent_dir_of, post_loaded, saving_error, serialize_for_file,
+                                 tail_docs_for, too_many_symlinks, format_of, MAX_SYMLINKS};
+use crate::logic::ProvenanceTracer;
+use crate::logic::{config_with_secrets, secret_reading_error, secret_refs_in, SECRET_REF_MARKER};
+use crate::{config_from_str_with_options, resolve_config_file_path, CmdLineAndConfigIntegration, ConfigFileLock, EffectiveConfigTarget,
+            LoadOptions, LoadWarningKind, OgreRootConfig, OnCreateFailure, SaveOptions, SerdeFormat};
+use clap::ArgMatches;
+use std::fmt::Debug;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Blocking version of [crate::load_or_create_default()]: loads the configuration from the given `config_file_path`
+/// or creates it (with default values & comments) if it doesn't exist -- along with any missing parent directories
+pub fn load_or_create_default<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    tail_comments: &str,
+) -> Result<RootConfigType, crate::Error> {
+    load_or_create_default_reporting_creation(&config_file_path, tail_docs_for::<RootConfigType>(tail_comments), OnCreateFailure::Fail)
+        .map(|(config, ..)| config)
+}
+
+/// Blocking version of [crate::load_from_file()]: `None` if the file at `config_file_path` doesn't exist
+pub fn load_from_file<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+) -> Result<Option<RootConfigType>, crate::Error> {
+    load_from_file_with_options(config_file_path, &LoadOptions::default())
+}
+
+/// Blocking version of [crate::load_from_file_with_options()]
+pub fn load_from_file_with_options<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    load_options: &LoadOptions,
+) -> Result<Option<RootConfigType>, crate::Error> {
+    Ok(load_text_and_config_from_file(config_file_path, load_options)?
+        .map(|(_, config)| config))
+}
+
+/// Blocking version of [crate::save_to_file()]: saves the `config` to `config_file_path`, followed by the `tail_comment`
+/// -- or, if empty, by the docs of the config type (see [OgreRootConfig::docs()])
+pub fn save_to_file<RootConfigType: OgreRootConfig>(
+    config: &RootConfigType,
+    tail_comment: &str,
+    config_file_path: impl AsRef<Path> + Debug,
+) -> Result<(), crate::Error> {
+    save_to_file_with_options(config, tail_docs_for::<RootConfigType>(tail_comment), config_file_path, &SaveOptions::default())
+}
+
+/// Blocking version of [crate::save_to_file_with_options()]
+pub fn save_to_file_with_options(
+    config: &impl OgreRootConfig,
+    tail_comment: &str,
+    config_file_path: impl AsRef<Path> + Debug,
+    save_options: &SaveOptions,
+) -> Result<(), crate::Error> {
+    let txt_config = serialize_for_file(config, tail_comment, &config_file_path, save_options)?;
+    save_text_to_file(txt_config, config_file_path, save_options)
+}
+
+/// Blocking version of [crate::lock_config_file()]: waits up to `timeout` for the advisory lock on the config file at `config_file_path`
+pub fn lock_config_file(
+    config_file_path: impl AsRef<Path> + Debug,
+    timeout: Duration,
+) -> Result<ConfigFileLock, crate::Error> {
+    let config_file_path = config_file_path.as_ref();
+    let lock_file_path = lock_file_path_of(config_file_path);
+    let locking_err = |err| locking_error(config_file_path, &lock_file_path, err);
+    if let Some(parent_dir) = parent_dir_of(&lock_file_path) {
+        std::fs::create_dir_all(parent_dir).map_err(locking_err)?;
+    }
+    let lock_file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&lock_file_path)
+        .map_err(locking_err)?;
+    let deadline = Instant::now() + timeout;
+    while let Some(retry_interval) = lock_attempt(&lock_file, config_file_path, &lock_file_path, deadline, timeout)? {
+        std::thread::sleep(retry_interval);
+    }
+    Ok(ConfigFileLock { lock_file })
+}
+
+/// Blocking version of [crate::parse_cmdline_and_merge_with_loaded_configs()]: parses the command line, loads the configs & merges them,
+/// returning the effective configuration. The config help & showing the effective config work as usual, as does writing it elsewhere
+/// (see [EffectiveConfigTarget::Path]) -- but resetting, recovering & rewriting the config file in place (as those back it up),
+/// as well as remote configs, fail with [crate::Error::AsyncOnly].
+/// Command line errors (as well as `--help`, `--help-config` & `--version`) are returned as [crate::Error] variants -- see [crate::Error::exit_if_cli()]
+pub fn parse_cmdline_and_merge_with_loaded_configs<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    tail_docs: &str,
+) -> Result<RootConfigType, crate::Error> {
+    let tail_docs = tail_docs_for::<RootConfigType>(tail_docs);
+    let arg_matches = CmdLineOptionsType::command().try_get_matches()?;
+    let cmdline_options = CmdLineOptionsType::from_arg_matches(&arg_matches)
+        .map_err(|err| err.format(&mut CmdLineOptionsType::command()))?;
+    if cmdline_options.should_print_config_help() {
+        return Err(config_help(tail_docs)?)
+    }
+    load_and_merge_configs_for(cmdline_options, tail_docs, Some(&arg_matches))
+}
+
+/// The logic behind [parse_cmdline_and_merge_with_loaded_configs()], for already parsed `cmdline_options` -- out of the `arg_matches`, if given,
+/// telling the values given by environment variables from the ones given in the command line, when annotating the effective config
+fn load_and_merge_configs_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: CmdLineOptionsType,
+    tail_docs: &str,
+    arg_matches: Option<&ArgMatches>,
+) -> Result<RootConfigType, crate::Error> {
+    let should_annotate_effective_config = cmdline_options.should_annotate_effective_config();
+    let mut tracer = should_annotate_effective_config
+        .then(|| ProvenanceTracer::new::<RootConfigType>(arg_matches));
+    let effective_config_target = cmdline_options.effective_config_output_path();
+    let should_show_effective_config = cmdline_options.should_show_effective_config();
+    let meld_options = cmdline_options.meld_options();
+    let rewrite_tail_docs = if cmdline_options.include_docs_in_created_file() { tail_docs } else { "" };
+
+    if cmdline_options.should_reset_config() {
+        return Err(async_only("resetting the config file -- which backs it up"))
+    }
+    if effective_config_target == Some(EffectiveConfigTarget::InPlace) {
+        return Err(async_only("rewriting the config file with the effective config -- which backs it up"))
+    }
+    if cmdline_options.config_file_path().is_some_and(is_config_url) {
+        return Err(async_only("loading remote configs"))
+    }
+    if cmdline_options.should_debug_config_paths() {
+        debug_config_paths(&cmdline_options);
+    }
+
+    let config_resolution = resolve_config_file_path(&cmdline_options);
+    let (config_file_path, (loaded_config, loaded_txt)) = load_configs_falling_back_for(&cmdline_options, config_resolution.chosen, config_resolution.fallback, tail_docs)?;
+    if let Some(tracer) = &mut tracer {
+        tracer.loaded(&loaded_config, &config_file_path);
+    }
+    let loaded_value_tree = (effective_config_target.is_some() || should_show_effective_config)
+        .then(|| serde_json::to_value(&loaded_config).ok())
+        .flatten();
+    let effective_config = merge_cmdline_args_with_configs_traced(cmdline_options, loaded_config, Some(&config_file_path), tracer.as_mut())?;
+    let provenance = tracer.map(|tracer| tracer.provenance).unwrap_or_default();
+    let effective_value_tree = loaded_value_tree.is_some()
+        .then(|| serde_json::to_value(&effective_config).ok())
+        .flatten();
+
+    if should_show_effective_config {
+        show_effective_config_and_changes(&effective_config, should_annotate_effective_config.then_some(&provenance),
+                                          loaded_value_tree.as_ref(), effective_value_tree.as_ref(), &config_file_path)?;
+    }
+
+    if let Some(EffectiveConfigTarget::Path(output_path)) = &effective_config_target {
+        let changed_fields = changed_field_paths(loaded_value_tree.as_ref(), effective_value_tree.as_ref());
+        let result = effective_config_for_output(&effective_config, output_path, &config_file_path, loaded_txt.as_deref(), &meld_options, changed_fields, rewrite_tail_docs)
+            .and_then(|(txt_config, save_options)| save_text_to_file(txt_config, output_path, &save_options));
+        persisted_or_warned(result, &meld_options, output_path)?;
+    }
+
+    Ok(effective_config)
+}
+
+/// Blocking version of the async `lock_and_load_configs_for()` -- without locking, as rewrites are async-only
+fn load_configs_falling_back_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: &CmdLineOptionsType,
+    config_file_path: PathBuf,
+    fallback_path: Option<PathBuf>,
+    tail_docs: &str,
+) -> Result<(PathBuf, (RootConfigType, Option<String>)), crate::Error> {
+    let on_create_failure = cmdline_options.meld_options().on_create_failure;
+    let Some(fallback_path) = fallback_path else {
+        let configs = load_configs_for(cmdline_options, &config_file_path, on_create_failure, tail_docs)?;
+        return Ok((config_file_path, configs))
+    };
+    // creation failures must be seen, for the fallback to be used
+    match load_configs_for(cmdline_options, &config_file_path, OnCreateFailure::Fail, tail_docs) {
+        Err(err) if is_refused_creation(&err) => {
+            emit_warning(LoadWarningKind::Fallback, "", format!("the config file {config_file_path:?} couldn't be created -- using {fallback_path:?} instead: {err}"));
+            let configs = load_configs_for(cmdline_options, &fallback_path, on_create_failure, tail_docs)?;
+            Ok((fallback_path, configs))
+        },
+        result => result.map(|configs| (config_file_path, configs)),
+    }
+}
+
+/// Loads the config at `config_file_path`, as the `cmdline_options` require -- see [crate::CmdLineAndConfigIntegration::require_existing()]
+/// & [crate::CmdLineAndConfigIntegration::allow_create_at_explicit_path()]. Created files get the `tail_docs`, unless opted out,
+/// while failing to create them is dealt with according to `on_create_failure`.
+/// Also returns the text of loaded files -- kept for writing the effective config, so they are read only once
+fn load_configs_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: &CmdLineOptionsType,
+    config_file_path: &Path,
+    on_create_failure: OnCreateFailure,
+    tail_docs: &str,
+) -> Result<(RootConfigType, Option<String>), crate::Error> {
+    let tail_docs = if cmdline_options.include_docs_in_created_file() { tail_docs } else { "" };
+    let load_result = if cmdline_options.require_existing() {
+        load_existing_text_and_config(config_file_path)
+            .map(|(txt_config, config)| (config, Some(txt_config)))
+    } else if cmdline_options.config_file_path().is_some() && !cmdline_options.allow_create_at_explicit_path() {
+        load_existing_text_and_config(config_file_path)
+            .map(|(txt_config, config)| (config, Some(txt_config)))
+            .map_err(explicit_config_file_err)
+    } else {
+        load_or_create_default_reporting_creation(config_file_path, tail_docs, on_create_failure)
+            .map(|(config, _, txt_config)| (config, txt_config))
+    };
+    match load_result {
+        Err(err) if err.is_parsing_error() && cmdline_options.should_recover_config() =>
+            Err(async_only(&format!("recovering the config file -- which couldn't be parsed: {err}"))),
+        load_result => load_result.map_err(with_recovery_hint),
+    }
+}
+
+/// Blocking version of the async `load_existing_text_and_config()`
+fn load_existing_text_and_config<RootConfigType: OgreRootConfig>(config_file_path: &Path) -> Result<(String, RootConfigType), crate::Error> {
+    load_text_and_config_from_file(config_file_path, &LoadOptions::default())?
+        .ok_or_else(|| crate::Error::ConfigFileNotFound {
+            path: config_file_path.to_path_buf(),
+            hint: "the config file is required to exist -- no defaults were written".to_string(),
+        })
+}
+
+/// Blocking version of the async `load_or_create_default_reporting_creation()`
+fn load_or_create_default_reporting_creation<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    tail_comments: &str,
+    on_create_failure: OnCreateFailure,
+) -> Result<(RootConfigType, bool, Option<String>), crate::Error> {
+    if let Some((txt_config, config)) = load_text_and_config_from_file(&config_file_path, &LoadOptions::default())? {
+        return Ok((config, false, Some(txt_config)))
+    }
+    let default_config = RootConfigType::default();
+    let save_options = SaveOptions { create_parents: true, ..SaveOptions::default() };
+    let txt_config = serialize_for_file(&default_config, tail_comments, &config_file_path, &save_options)?;
+    let created_now = match save_text_to_file(txt_config, &config_file_path, &save_options) {
+        Err(err) if on_create_failure == OnCreateFailure::WarnAndUseDefaults && err.is_persistence_error() => {
+            emit_warning(LoadWarningKind::FailedPersistence, "", format!("the default config file {config_file_path:?} couldn't be created -- going on with the default values: {err}"));
+            false
+        },
+        result => result.map(|_| true)?,
+    };
+    let default_config = post_loaded(default_config, config_file_path.as_ref(), format_of(&config_file_path)?)?;
+    Ok((default_config, created_now, None))
+}
+
+/// Blocking version of the async `load_text_and_config_from_file()`
+fn load_text_and_config_from_file<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    load_options: &LoadOptions,
+) -> Result<Option<(String, RootConfigType)>, crate::Error> {
+    check_loadable_extension(&config_file_path, load_options)?;
+    let Some(txt_config) = read_config_text(&config_file_path)? else {
+        return Ok(None)
+    };
+    let format = loading_format(&config_file_path, load_options)?;
+    let config_dir = config_file_path.as_ref().parent().unwrap_or(Path::new(""));
+    let config = if txt_config.contains(SECRET_REF_MARKER) {
+        config_from_str_with_secret_refs(&txt_config, format, load_options, config_dir)
+    } else {
+        config_from_str_with_options(&txt_config, format, load_options)
+    };
+    let config = loaded_config(config, config_file_path.as_ref(), format)?;
+    Ok(Some((txt_config, config)))
+}
+
+/// Blocking version of the async `config_from_str_with_secret_refs()`
+fn config_from_str_with_secret_refs<RootConfigType: OgreRootConfig>(
+    txt_config: &str,
+    format: SerdeFormat,
+    load_options: &LoadOptions,
+    base_dir: &Path,
+) -> Result<RootConfigType, crate::Error> {
+    let Some((generic_config, secret_refs)) = secret_refs_in(txt_config, format, base_dir) else {
+        return config_from_str_with_options(txt_config, format, load_options)
+    };
+    let secrets = secret_refs.into_iter()
+        .map(|(pointer, secret_path)| std::fs::read_to_string(&secret_path)
+            .map_err(|err| secret_reading_error(&secret_path, &pointer, err))
+            .map(|secret| (pointer, secret)))
+        .collect::<Result<Vec<_>, _>>()?;
+    config_with_secrets(generic_config, secrets, format, load_options)
+}
+
+/// Reads the text of the config file at `config_file_path`, decompressing it if [is_gzipped()] -- `None` if it doesn't exist.
+/// Contents that aren't text are taken as encrypted -- which is only supported by the async API, so they are refused with [crate::Error::AsyncOnly]
+fn read_config_text(config_file_path: impl AsRef<Path> + Debug) -> Result<Option<String>, crate::Error> {
+    #[cfg(test)]
+    crate::logic::CONFIG_TEXT_READS.lock().unwrap().push(config_file_path.as_ref().to_path_buf());
+    let contents = match std::fs::read(&config_file_path) {
+        Ok(contents) => contents,
+        Err(err) => return loaded_text(Err(err), &config_file_path),
+    };
+    if is_gzipped(&config_file_path) {
+        return loaded_text(decompressed_text(&config_file_path, &contents), &config_file_path)
+    }
+    String::from_utf8(contents)
+        .map(Some)
+        .map_err(|_| async_only(&format!("loading {config_file_path:?}, which isn't text -- is it encrypted?")))
+}
+
+/// Blocking version of the async `save_text_to_file()`
+fn save_text_to_file(
+    txt_config: String,
+    config_file_path: impl AsRef<Path> + Debug,
+    save_options: &SaveOptions,
+) -> Result<(), crate::Error> {
+    let contents = compress_if_gzipped(&config_file_path, txt_config)
+        .map_err(|err| crate::Error::SavingConfig {
+            message: format!("Error compressing the config for saving into {config_file_path:?}"),
+            cause: Box::new(err),
+        })?;
+    let _lock = match save_options.locked {
+        Some(lock_timeout) => Some(lock_config_file(&config_file_path, lock_timeout)?),
+        None => None,
+    };
+    let saving_err = |err| saving_error(&config_file_path, err);
+    let is_symlink = std::fs::symlink_metadata(&config_file_path)
+        .is_ok_and(|metadata| metadata.file_type().is_symlink());
+    let target_file_path = if !is_symlink {
+        config_file_path.as_ref().to_path_buf()
+    } else if save_options.replace_symlink {
+        std::fs::remove_file(&config_file_path).map_err(saving_err)?;
+        config_file_path.as_ref().to_path_buf()
+    } else {
+        resolve_symlinks(config_file_path.as_ref()).map_err(saving_err)?
+    };
+    if save_options.create_parents {
+        if let Some(parent_dir) = parent_dir_of(&target_file_path) {
+            std::fs::create_dir_all(parent_dir)
+                .map_err(|err| parent_dir_error(parent_dir, &config_file_path, err))?;
+        }
+    }
+    if save_options.durable {
+        write_durably(&target_file_path, &contents).map_err(saving_err)
+    } else {
+        std::fs::write(&target_file_path, &contents).map_err(saving_err)
+    }
+}
+
+/// Blocking version of the async `resolve_symlinks()`
+fn resolve_symlinks(file_path: &Path) -> io::Result<PathBuf> {
+    let mut resolved_path = file_path.to_path_buf();
+    for _ in 0..MAX_SYMLINKS {
+        match std::fs::symlink_metadata(&resolved_path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                let link_target = std::fs::read_link(&resolved_path)?;
+                resolved_path = followed_symlink(&resolved_path, link_target);
+            },
+            Ok(_) => return Ok(resolved_path),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(resolved_path),
+            Err(err) => return Err(err),
+        }
+    }
+    Err(too_many_symlinks(file_path))
+}
+
+/// Blocking version of the async `write_durably()`
+fn write_durably(file_path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp_file_path = durable_temp_file_path(file_path);
+    let original_metadata = std::fs::metadata(file_path).ok();
+    let write_result = (|| {
+        if let Some(original_metadata) = &original_metadata {
+            std::fs::write(&temp_file_path, "")?;
+            restore_file_metadata(&temp_file_path, original_metadata)?;
+        }
+        std::fs::write(&temp_file_path, contents)?;
+        std::fs::OpenOptions::new().write(true).open(&temp_file_path)?
+            .sync_all()?;
+        std::fs::rename(&temp_file_path, file_path)
+    })();
+    if write_result.is_err() {
+        _ = std::fs::remove_file(&temp_file_path);
+    }
+    write_result?;
+    #[cfg(unix)]
+    {
+        let dir = parent_dir_of(file_path).unwrap_or(Path::new("."));
+        std::fs::File::open(dir)?
+            .sync_all()?;
+    }
+    Ok(())
+}
+
+/// Blocking version of the async `restore_file_metadata()`
+fn restore_file_metadata(file_path: &Path, original_metadata: &std::fs::Metadata) -> io::Result<()> {
+    std::fs::set_permissions(file_path, original_metadata.permissions())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = std::fs::metadata(file_path)?;
+        if (metadata.uid(), metadata.gid()) != (original_metadata.uid(), original_metadata.gid()) {
+            if let Err(err) = std::os::unix::fs::chown(file_path, Some(original_metadata.uid()), Some(original_metadata.gid())) {
+                crate::logic::warn_unrestored_ownership(file_path, original_metadata, err);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The error for the `operation`s only supported by the async API
+fn async_only(operation: &str) -> crate::Error {
+    crate::Error::AsyncOnly { operation: operation.to_string() }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "ron")]
+    use crate::testkit::SampleCliOptions;
+    use crate::testkit::*;
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn load_or_create_default_test() {
+        let config_path = temp_config_path("ron");
+        let created_config: AppRootConfig = load_or_create_default(&config_path, "blocking tail docs")
+            .expect("The default config should have been created");
+        assert_eq!(created_config, AppRootConfig::default(), "The created config should hold the default values");
+        let txt_config = std::fs::read_to_string(&config_path).unwrap();
+        assert!(txt_config.contains("blocking tail docs"), "The tail docs should be in the created file: '{txt_config}'");
+
+        let expected_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdError) } };
+        save_to_file(&expected_config, "", &config_path).unwrap();
+        let loaded_config: AppRootConfig = load_or_create_default(&config_path, "").unwrap();
+        assert_eq!(loaded_config, expected_config, "The existing config should have been loaded");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml", feature = "gzip"))]
+    #[test]
+    fn gzipped_configs() {
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        let expected_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdError) } };
+        for file_name in ["app.config.ron.gz", "app.config.yml.gz"] {
+            let config_path = config_dir.join(file_name);
+            save_to_file(&expected_config, "compressed tail docs", &config_path)
+                .unwrap_or_else(|err| panic!("Saving {file_name} failed: {err}"));
+            let compressed = std::fs::read(&config_path).unwrap();
+            assert_eq!(&compressed[..2], &[0x1f, 0x8b], "{file_name} should have been gzip-compressed");
+            let config: AppRootConfig = load_from_file(&config_path)
+                .unwrap_or_else(|err| panic!("Loading {file_name} failed: {err}"))
+                .expect("The config file should exist");
+            assert_eq!(config, expected_config, "{file_name} round trip didn't work");
+        }
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn durable_save() {
+        let config_dir = temp_config_dir();
+        let config_path = config_dir.join("nested").join("app.config.yml");
+        let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::Null) } };
+        let save_options = SaveOptions { durable: true, create_parents: true, ..SaveOptions::default() };
+        save_to_file_with_options(&config, "", &config_path, &save_options).expect("The durable save should have created the parents");
+        save_to_file_with_options(&config, "", &config_path, &save_options).expect("The durable save should have replaced the file");
+        let loaded_config: Option<AppRootConfig> = load_from_file(&config_path).unwrap();
+        assert_eq!(loaded_config, Some(config), "The durably saved config didn't round trip");
+        let leftovers = std::fs::read_dir(config_path.parent().unwrap()).unwrap()
+            .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().contains(".tmp-"))
+            .count();
+        assert_eq!(leftovers, 0, "No temporary files should have been left behind");
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[cfg(unix)]
+    #[cfg(feature = "ron")]
+    #[test]
+    #[ignore = "needs read-only dirs, which aren't enforced for root: run with `cargo test -- --ignored` as a regular user"]
+    fn refused_creation_falls_back() {
+        use crate::test_commons::fs_fixtures::{read_only_dir, remove_read_only_dir};
+        let program_dir = read_only_dir("cli-config-blocking_refused_creation_falls_back", &[]);
+        let fallback_dir = temp_config_dir();
+        let (config_path, fallback_path) = (program_dir.join("myapp.config.ron"), fallback_dir.join("myapp/myapp.config.ron"));
+        let cmdline_options = <SampleCliOptions as clap::Parser>::parse_from(["test"]);
+        let (used_path, (config, _)): (_, (AppRootConfig, _)) = load_configs_falling_back_for(&cmdline_options, config_path, Some(fallback_path.clone()), "")
+            .expect("The config should have been created in the fallback path");
+        assert_eq!(used_path, fallback_path, "The fallback path should have been used");
+        assert!(fallback_path.exists(), "The config should have been created in the fallback path");
+        assert_eq!(config, AppRootConfig::default(), "The default config should have been used");
+        remove_read_only_dir(&program_dir);
+        _ = std::fs::remove_dir_all(&fallback_dir);
+    }
+
+    #[cfg(unix)]
+    #[cfg(feature = "ron")]
+    #[test]
+    #[ignore = "needs read-only dirs, which aren't enforced for root: run with `cargo test -- --ignored` as a regular user"]
+    fn read_only_create() {
+        use crate::test_commons::fs_fixtures::{read_only_dir, remove_read_only_dir};
+        let read_only_dir = read_only_dir("cli-config-blocking_read_only_create", &[]);
+        let config_path = read_only_dir.join("app.config.ron");
+        let result = load_or_create_default::<AppRootConfig>(&config_path, "");
+        assert!(result.is_err_and(|err| err.is_persistence_error()), "Failing to create the default config should be reported");
+        remove_read_only_dir(&read_only_dir);
+    }
+
+    #[cfg(unix)]
+    #[cfg(feature = "ron")]
+    #[test]
+    fn durable_save_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let config_path = temp_config_path("ron");
+        save_to_file(&AppRootConfig::default(), "", &config_path).unwrap();
+        std::fs::set_permissions(&config_path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let durable = SaveOptions { durable: true, ..SaveOptions::default() };
+        save_to_file_with_options(&AppRootConfig::default(), "I am the docs", &config_path, &durable).unwrap();
+        let mode = std::fs::metadata(&config_path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600, "The permissions of the replaced file should have been kept");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn locked_saves() {
+        let config_path = temp_config_path("ron");
+        let config = |sink| AppRootConfig { log_sub_config: LogConfig { sink: Some(sink) } };
+        save_to_file(&config(Dummy::Null), "", &config_path).unwrap();
+        let locked = SaveOptions { locked: Some(Duration::from_millis(100)), ..SaveOptions::default() };
+        let lock = lock_config_file(&config_path, Duration::ZERO).expect("The lock should be free");
+        let result = save_to_file_with_options(&config(Dummy::StdError), "", &config_path, &locked);
+        assert!(matches!(result, Err(crate::Error::ConfigLocked { .. })), "The lock wait should have expired. Got {result:?}");
+        drop(lock);
+        save_to_file_with_options(&config(Dummy::StdOut), "", &config_path, &locked).expect("The lock should have been released");
+        let saved_config: Option<AppRootConfig> = load_from_file(&config_path).unwrap();
+        assert_eq!(saved_config, Some(config(Dummy::StdOut)), "The save should have been written once the lock was released");
+        _ = std::fs::remove_file(&config_path);
+        _ = std::fs::remove_file(lock_file_path_of(&config_path));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn secret_refs() {
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        std::fs::write(config_dir.join("sink.secret"), "stderror\n").unwrap();
+        let config_path = config_dir.join("app.config.yml");
+        std::fs::write(&config_path, "log_sub_config:\n  sink:\n    secret_ref: sink.secret\n").unwrap();
+        let config: Option<AppRootConfig> = load_from_file(&config_path).unwrap();
+        assert_eq!(config, Some(AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdError) } }), "The secret should have been read");
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn encrypted_files() {
+        let config_path = temp_config_path("ron");
+        std::fs::write(&config_path, [0x9c, 0xff, 0x00, 0xfe, 0x41]).unwrap();
+        let result = load_from_file::<AppRootConfig>(&config_path);
+        assert!(matches!(&result, Err(crate::Error::AsyncOnly { operation }) if operation.contains("encrypted")),
+                "The error should tell encrypted files require the async API. Got {result:?}");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn cmdline_merge() {
+        let config_path = temp_config_path("ron");
+        save_to_file(&AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::Null) } }, "", &config_path).unwrap();
+        let cmdline_options = SampleCliOptions {
+            config_file: Some(config_path.to_string_lossy().to_string()),
+            log: LogConfig { sink: Some(Dummy::StdOut) },
+            ..SampleCliOptions::default()
+        };
+        let effective_config = load_and_merge_configs_for(cmdline_options.clone(), "", None)
+            .expect("The configs should have been merged");
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdOut), "The command line option should have prevailed");
+        let loaded_config: Option<AppRootConfig> = load_from_file(&config_path).unwrap();
+        assert_eq!(loaded_config.unwrap().log_sub_config.sink, Some(Dummy::Null), "The config file shouldn't have been rewritten");
+
+        let in_place = SampleCliOptions { write_effective_config: true, ..cmdline_options };
+        let result = load_and_merge_configs_for(in_place, "", None);
+        assert!(matches!(result, Err(crate::Error::AsyncOnly { .. })), "In-place rewrites should require the async API. Got {result:?}");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn effective_config_output_reads_once() {
+
+        /// Shows the effective config & writes it to `output_file`, leaving the config file untouched
+        #[derive(clap::Parser, Debug)]
+        struct MaterializingOptions {
+            #[clap(skip)]
+            config_file: String,
+            #[clap(skip)]
+            output_file: PathBuf,
+        }
+        impl CmdLineAndConfigIntegration<AppRootConfig> for MaterializingOptions {
+            fn config_file_path(&self) -> Option<&str> { Some(&self.config_file) }
+            fn should_write_effective_config(&self) -> bool { false }
+            fn should_show_effective_config(&self) -> bool { true }
+            fn effective_config_output_path(&self) -> Option<EffectiveConfigTarget> { Some(EffectiveConfigTarget::Path(self.output_file.clone())) }
+            fn merge_with_config(self, mut config: AppRootConfig) -> Result<AppRootConfig, crate::Error> {
+                config.log_sub_config.sink = Some(Dummy::StdOut);
+                Ok(config)
+            }
+        }
+
+        let base_dir = temp_config_dir();
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let config_path = base_dir.join("app.config.yaml");
+        std::fs::write(&config_path, "log_sub_config:\n  sink: null\n").unwrap();
+        let output_path = base_dir.join("app.effective.ron");
+        let reads = || crate::logic::CONFIG_TEXT_READS.lock().unwrap().iter().filter(|read_path| **read_path == config_path).count();
+
+        let cmdline_options = MaterializingOptions { config_file: config_path.to_string_lossy().to_string(), output_file: output_path.clone() };
+        let effective_config = load_and_merge_configs_for(cmdline_options, "", None)
+            .expect("Writing the effective config elsewhere failed");
+        assert_eq!(reads(), 1, "The config file should have been read exactly once when showing & writing the effective config");
+        let output_txt = std::fs::read_to_string(&output_path).expect("The effective config should have been written to the output path");
+        let header = crate::RewriteHeader::parse(&output_txt).unwrap_or_else(|| panic!("The rewrite header is missing: '{output_txt}'"));
+        assert_eq!(header.changed_fields, vec!["log_sub_config.sink"], "The header should tell what changed from the config as loaded");
+        assert_eq!(load_from_file(&output_path).unwrap(), Some(effective_config), "The output file should hold the effective config");
+        _ = std::fs::remove_dir_all(&base_dir);
+    }
+}
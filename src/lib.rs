@@ -1,17 +1,35 @@
+#[cfg(not(any(feature = "ron", feature = "yaml")))]
+compile_error!("at least one config file format must be enabled -- through the `ron` and/or `yaml` features of `ogre-config-meld`");
+
 mod types;
 pub use types::*;
 
 mod logic;
 pub use logic::*;
 
+pub mod blocking;
+
 #[cfg(test)]
 mod test_commons;
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod testkit;
+
 // re-exports
 /////////////
 
 // allows user programs to use these dependencies without requiring them to directly depend on them.
 pub use clap;
+pub use serde;
+// `config_enum!()` names `serde`, for its derives, through this crate's path -- which also has to work inside this crate
+extern crate self as ogre_config_meld;
 
 // this export allows user programs to use the same fs encryption version
-pub use encryptable_tokio_fs;
\ No newline at end of file
+#[cfg(feature = "async")]
+pub use encryptable_tokio_fs;
+// allows user programs to `#[derive(CmdLineAndConfigIntegration)]` -- see [MergeField]
+#[cfg(feature = "derive")]
+pub use ogre_config_meld_derive::CmdLineAndConfigIntegration;
+// allows user programs to derive `JsonSchema` for their configs with the same `schemars` version
+#[cfg(feature = "schema")]
+pub use schemars;
@@ -0,0 +1,425 @@
+//! The [ConfigMeld] builder -- composing the effective config out of layers of config files, environment variables & the command line
+
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+use crate::logic::cli_logic::parse_cmdline_and_meld;
+use crate::logic::config_logic::{format_of, load_or_create_default_reporting_creation, read_config_text, tail_docs_for};
+use crate::logic::generic_value_logic::generic_from_txt;
+use crate::logic::overrides_logic::{apply_overrides_reporting, has_field};
+use crate::logic::provenance_logic::ProvenanceTracer;
+use crate::logic::warnings_logic::unknown_fields;
+use crate::{CmdLineAndConfigIntegration, ConfigLayer, ConfigMeld, LoadedConfig, NoCmdLine, OgreRootConfig, OnCreateFailure, Provenance, RealFs, SerdeFormat};
+
+impl<RootConfigType: OgreRootConfig> ConfigMeld<RootConfigType> {
+    /// A builder without layers -- which would load the defaults of `RootConfigType`
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new(),
+            args: None,
+            tail_docs: String::new(),
+            strict_unknown_keys: false,
+            types: PhantomData,
+        }
+    }
+}
+
+impl<RootConfigType: OgreRootConfig> Default for ConfigMeld<RootConfigType> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<RootConfigType: OgreRootConfig, CmdLineOptionsType> ConfigMeld<RootConfigType, CmdLineOptionsType> {
+
+    /// Layers the config file at `config_file_path`. The first one is *the* config file: created with the defaults (& the tail docs)
+    /// if missing -- and, with a command line layer, the one that may be rewritten (see [CmdLineAndConfigIntegration]), instead of the
+    /// one the command line options would resolve to. Wherever it is added, it is the bottom layer.
+    /// Further files are overlays, skipped if missing: just the fields present in them are applied -- for cascades like
+    /// `/etc/myapp.yaml`, then `~/.config/myapp.yaml`
+    pub fn file(mut self, config_file_path: impl Into<PathBuf>) -> Self {
+        self.layers.push(ConfigLayer::File(config_file_path.into()));
+        self
+    }
+
+    /// Layers the environment variables named `<PREFIX>_<FIELD>` -- the upper cased field names, with the ones of nested sections
+    /// joined by `__`, as in `MYAPP_LOG_SUB_CONFIG__SINK` (the naming of [crate::to_env_exports()]). Their values are coerced to
+    /// the types of the fields, as in [crate::apply_config_overrides()]. Variables not naming config fields are ignored
+    /// -- unless [ConfigMeld::strict_unknown_keys()] is set & the `prefix` isn't empty
+    pub fn env_prefix(mut self, prefix: &str) -> Self {
+        self.layers.push(ConfigLayer::Env(prefix.to_string()));
+        self
+    }
+
+    /// Layers the `NewCmdLineOptionsType` command line options -- parsed from the program's command line, or from [ConfigMeld::args()]
+    /// -- merged as in [crate::parse_cmdline_and_merge_with_loaded_configs()]: through [CmdLineAndConfigIntegration], which also tells
+    /// the config file (if none was layered) & whether the effective config is to be shown or rewritten. There may be a single such layer
+    pub fn cli<NewCmdLineOptionsType>(self) -> ConfigMeld<RootConfigType, NewCmdLineOptionsType> {
+        let mut layers = self.layers;
+        layers.retain(|layer| *layer != ConfigLayer::CmdLine);
+        layers.push(ConfigLayer::CmdLine);
+        ConfigMeld {
+            layers,
+            args: self.args,
+            tail_docs: self.tail_docs,
+            strict_unknown_keys: self.strict_unknown_keys,
+            types: PhantomData,
+        }
+    }
+
+    /// Parses the command line layer from `args` -- whose first element is the program name -- instead of the program's command line
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<OsString>>) -> Self {
+        self.args = Some(args.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// The docs appended to the config files created (or regenerated) with the effective config -- see [crate::save_to_file()].
+    /// Defaults to the ones of the config type (see [OgreRootConfig::docs()])
+    pub fn tail_docs(mut self, tail_docs: &str) -> Self {
+        self.tail_docs = tail_docs.to_string();
+        self
+    }
+
+    /// If `true`, fields of the config files unknown to the config types -- as well as environment variables with the prefix
+    /// that don't name config fields -- are refused with [crate::Error::UnknownFields]. Defaults to `false`, where they are ignored
+    /// (see [crate::load_with_warnings()] for reporting them instead)
+    pub fn strict_unknown_keys(mut self, strict_unknown_keys: bool) -> Self {
+        self.strict_unknown_keys = strict_unknown_keys;
+        self
+    }
+
+    /// The config file -- the first one layered, if any -- & the other layers, split around the command line one
+    fn into_meld_layers(self) -> (Option<PathBuf>, MeldLayers, String, Option<Vec<OsString>>) {
+        let mut config_file_path = None;
+        let mut meld_layers = MeldLayers { strict_unknown_keys: self.strict_unknown_keys, ..MeldLayers::default() };
+        let mut is_after_cmdline = false;
+        for layer in self.layers {
+            match layer {
+                ConfigLayer::File(path) if config_file_path.is_none() => config_file_path = Some(path),
+                ConfigLayer::CmdLine => is_after_cmdline = true,
+                layer if is_after_cmdline => meld_layers.after_cmdline.push(layer),
+                layer => meld_layers.before_cmdline.push(layer),
+            }
+        }
+        (config_file_path, meld_layers, self.tail_docs, self.args)
+    }
+}
+
+impl<RootConfigType: OgreRootConfig> ConfigMeld<RootConfigType, NoCmdLine> {
+
+    /// Loads the effective config: the config file (or the defaults, if none was layered), overlaid by the other layers, in order
+    pub async fn load(self) -> Result<RootConfigType, crate::Error> {
+        self.meld(false).await
+            .map(|(config, ..)| config)
+    }
+
+    /// Same as [Self::load()], but also telling where each value of the effective config came from (see [Provenance])
+    /// & the config file it was based on -- which must have been layered (see [ConfigMeld::file()])
+    pub async fn load_traced(self) -> Result<(LoadedConfig<RootConfigType>, Provenance), crate::Error> {
+        let (config, config_file, provenance) = self.meld(true).await?;
+        let Some((config_file_path, created_now)) = config_file else {
+            return Err(crate::Error::ConfigFileNotFound {
+                path: PathBuf::new(),
+                hint: "No config file was layered, so the effective config can't be tied to one -- see `ConfigMeld::file()`".to_string(),
+            })
+        };
+        Ok((LoadedConfig::of(config, &config_file_path, created_now).await?, provenance))
+    }
+
+    /// Loads the config file, if any, & applies the other layers -- tracing the provenance of the values if `trace` is set.
+    /// Returns the effective config, along with the config file & whether it was just created
+    async fn meld(self, trace: bool) -> Result<(RootConfigType, Option<(PathBuf, bool)>, Provenance), crate::Error> {
+        let (config_file_path, meld_layers, tail_docs, _) = self.into_meld_layers();
+        let mut tracer = trace.then(|| ProvenanceTracer::new::<RootConfigType>(None));
+        let (config, config_file) = match config_file_path {
+            Some(config_file_path) => {
+                let (config, created_now, txt_config) = load_or_create_default_reporting_creation::<RootConfigType>(
+                    &config_file_path, tail_docs_for::<RootConfigType>(&tail_docs), OnCreateFailure::Fail).await?;
+                if let (true, Some(txt_config)) = (meld_layers.strict_unknown_keys, &txt_config) {
+                    refuse_unknown_fields(txt_config, format_of(&config_file_path)?, &config, &config_file_path)?;
+                }
+                if let Some(tracer) = &mut tracer {
+                    tracer.loaded(&config, &config_file_path);
+                }
+                (config, Some((config_file_path, created_now)))
+            },
+            None => (RootConfigType::default(), None),
+        };
+        let config = overlaid(config, &meld_layers.before_cmdline, meld_layers.strict_unknown_keys, tracer.as_mut()).await?;
+        Ok((config, config_file, tracer.map(|tracer| tracer.provenance).unwrap_or_default()))
+    }
+}
+
+impl<
+    RootConfigType: OgreRootConfig,
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+> ConfigMeld<RootConfigType, CmdLineOptionsType> {
+
+    /// Loads the effective config: the config file -- the first one layered or, lacking it, the one given by the command line options --
+    /// overlaid by the other layers, in order. The command line layer is merged as in [crate::parse_cmdline_and_merge_with_loaded_configs()],
+    /// so the options telling to show, rewrite or reset the config are honored -- as are command line errors, returned as [crate::Error] variants
+    pub async fn load(self) -> Result<RootConfigType, crate::Error> {
+        self.meld(false).await
+            .map(|(loaded_config, _)| loaded_config.config)
+    }
+
+    /// Same as [Self::load()], but also telling where each value of the effective config came from (see [Provenance])
+    /// & the config file it was based on -- see [crate::parse_cmdline_and_merge_with_loaded_configs_traced()]
+    pub async fn load_traced(self) -> Result<(LoadedConfig<RootConfigType>, Provenance), crate::Error> {
+        self.meld(true).await
+    }
+
+    async fn meld(self, trace: bool) -> Result<(LoadedConfig<RootConfigType>, Provenance), crate::Error> {
+        let (config_file_path, meld_layers, tail_docs, args) = self.into_meld_layers();
+        parse_cmdline_and_meld::<CmdLineOptionsType, RootConfigType>(args, config_file_path.as_deref(), &tail_docs, &meld_layers, trace).await
+    }
+}
+
+/// The layers of a [ConfigMeld] other than the config file & the command line -- split around the latter
+#[derive(Debug, Default)]
+pub(crate) struct MeldLayers {
+    /// The layers applied to the loaded config, before merging the command line options
+    pub(crate) before_cmdline: Vec<ConfigLayer>,
+    /// The layers applied after merging the command line options
+    pub(crate) after_cmdline: Vec<ConfigLayer>,
+    /// See [ConfigMeld::strict_unknown_keys()]
+    pub(crate) strict_unknown_keys: bool,
+}
+
+/// Applies the `layers` to `config`, in order -- telling the `tracer`, if given, where the values came from.
+/// See [ConfigMeld::strict_unknown_keys()] for `strict_unknown_keys`
+pub(crate) async fn overlaid<RootConfigType: OgreRootConfig>(
+    mut config: RootConfigType,
+    layers: &[ConfigLayer],
+    strict_unknown_keys: bool,
+    mut tracer: Option<&mut ProvenanceTracer<'_>>,
+) -> Result<RootConfigType, crate::Error> {
+    for layer in layers {
+        config = match layer {
+            ConfigLayer::File(overlay_file_path) => {
+                let config = file_overlaid(config, overlay_file_path, strict_unknown_keys).await?;
+                if let Some(tracer) = tracer.as_deref_mut() {
+                    tracer.loaded(&config, overlay_file_path);
+                }
+                config
+            },
+            ConfigLayer::Env(prefix) => {
+                let config = env_overlaid(config, prefix, strict_unknown_keys)?;
+                if let Some(tracer) = tracer.as_deref_mut() {
+                    tracer.env_overlaid(&config, prefix);
+                }
+                config
+            },
+            ConfigLayer::CmdLine => config,
+        };
+    }
+    Ok(config)
+}
+
+/// Overlays the fields present in the config file at `overlay_file_path` -- if it exists -- on `config`
+async fn file_overlaid<RootConfigType: OgreRootConfig>(
+    config: RootConfigType,
+    overlay_file_path: &Path,
+    strict_unknown_keys: bool,
+) -> Result<RootConfigType, crate::Error> {
+    let overlay_err = |cause: Box<dyn std::error::Error + Send + Sync>| crate::Error::LoadingConfig {
+        message: format!("Error overlaying the config file {overlay_file_path:?}"),
+        cause,
+    };
+    let txt_config = match read_config_text(&RealFs, overlay_file_path).await {
+        Ok(txt_config) => txt_config,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(config),
+        Err(err) => return Err(overlay_err(Box::new(err))),
+    };
+    let format = format_of(overlay_file_path)?;
+    let overlay = generic_from_txt(&txt_config, format).map_err(overlay_err)?;
+    let mut generic_config = serde_json::to_value(&config).map_err(|err| overlay_err(Box::new(err)))?;
+    overlay_value(&mut generic_config, overlay);
+    let config = serde_json::from_value(generic_config).map_err(|err| overlay_err(Box::new(err)))?;
+    if strict_unknown_keys {
+        refuse_unknown_fields(&txt_config, format, &config, overlay_file_path)?;
+    }
+    Ok(config)
+}
+
+/// Sets the values present in `overlay` into `base`: sections are merged field by field, while other values are replaced.
+/// Sections holding no value -- like RON's `()` -- leave the base ones untouched
+fn overlay_value(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_fields), Value::Object(overlay_fields)) => {
+            for (field_name, overlay_value_) in overlay_fields {
+                match base_fields.get_mut(&field_name) {
+                    Some(base_value) => overlay_value(base_value, overlay_value_),
+                    None => _ = base_fields.insert(field_name, overlay_value_),
+                }
+            }
+        },
+        (Value::Object(_), Value::Null) => (),
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Overlays the environment variables named after the fields of `config` -- see [ConfigMeld::env_prefix()]
+fn env_overlaid<RootConfigType: OgreRootConfig>(
+    config: RootConfigType,
+    prefix: &str,
+    strict_unknown_keys: bool,
+) -> Result<RootConfigType, crate::Error> {
+    let generic_config = serde_json::to_value(&config)
+        .map_err(|err| crate::Error::LoadingConfig {
+            message: format!("Error overlaying the environment variables prefixed by '{prefix}': the config can't be represented generically"),
+            cause: Box::new(err),
+        })?;
+    let var_prefix = env_var_prefix(prefix);
+    let (mut overrides, mut unknown_vars) = (Vec::new(), Vec::new());
+    for (var_name, value) in std::env::vars_os() {
+        let (Some(var_name), Some(value)) = (var_name.to_str(), value.to_str()) else { continue };
+        let Some(field_name) = var_name.strip_prefix(&var_prefix) else { continue };
+        let field_path = field_name.to_lowercase().replace("__", ".");
+        if has_field(&generic_config, &field_path) {
+            overrides.push((var_name.to_string(), format!("{field_path}={value}")));
+        } else if !var_prefix.is_empty() {
+            unknown_vars.push(var_name.to_string());
+        }
+    }
+    if strict_unknown_keys && !unknown_vars.is_empty() {
+        unknown_vars.sort();
+        return Err(crate::Error::UnknownFields {
+            message: format!("The environment variables {unknown_vars:?} don't name config fields"),
+            fields: unknown_vars,
+        })
+    }
+    overrides.sort();
+    let config_overrides = overrides.iter().map(|(_, config_override)| config_override).collect::<Vec<_>>();
+    apply_overrides_reporting(config, &config_overrides, |config_override, reason| {
+        let var_name = overrides.iter()
+            .find(|(_, var_override)| var_override == config_override)
+            .map_or("<environment>", |(var_name, _)| var_name.as_str());
+        crate::Error::LoadingConfig {
+            message: format!("Invalid config value in the environment variable {var_name}"),
+            cause: reason.into(),
+        }
+    })
+}
+
+/// The name of the environment variable for the field at the dotted `field_path` -- see [ConfigMeld::env_prefix()]
+pub(crate) fn env_var_name(prefix: &str, field_path: &str) -> String {
+    format!("{}{}", env_var_prefix(prefix), field_path.to_uppercase().replace('.', "__"))
+}
+
+/// What the names of the environment variables for the config fields start with: the upper cased `prefix` & `_` -- if any
+fn env_var_prefix(prefix: &str) -> String {
+    match prefix {
+        "" => String::new(),
+        prefix => format!("{}_", prefix.to_uppercase()),
+    }
+}
+
+/// Fails with [crate::Error::UnknownFields] if the `txt_config` the `config` was loaded from -- the config file at `config_file_path` --
+/// has fields unknown to the config types
+pub(crate) fn refuse_unknown_fields<RootConfigType: OgreRootConfig>(
+    txt_config: &str,
+    format: SerdeFormat,
+    config: &RootConfigType,
+    config_file_path: &Path,
+) -> Result<(), crate::Error> {
+    let fields = unknown_fields(txt_config, format, config);
+    if fields.is_empty() {
+        return Ok(())
+    }
+    Err(crate::Error::UnknownFields {
+        message: format!("The config file {config_file_path:?} has fields unknown to the config types: {}", fields.join(", ")),
+        fields,
+    })
+}
+
+
+#[cfg(all(test, feature = "ron", feature = "yaml"))]
+mod tests {
+    use super::*;
+    use crate::testkit::*;
+    use crate::Source;
+
+    #[tokio::test]
+    async fn layers_in_order() {
+        let config_path = temp_config_path("ron");
+        let overlay_path = temp_config_path("overlay.yaml");
+        let missing_overlay_path = temp_config_path("missing.yaml");
+        std::fs::write(&overlay_path, "log_sub_config:\n  sink: stderror\n").unwrap();
+        std::env::set_var("CLI_CONFIG_BUILDER_LAYERS_LOG_SUB_CONFIG__SINK", "stdout");
+
+        let config: AppRootConfig = ConfigMeld::new()
+            .file(&config_path)
+            .env_prefix("cli_config_builder_layers")
+            .file(&overlay_path)
+            .file(&missing_overlay_path)
+            .load().await
+            .expect("Layering should have worked");
+        assert!(config_path.exists(), "The config file should have been created with the defaults");
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdError), "The overlay file, added last, should have won");
+
+        let (loaded_config, provenance) = ConfigMeld::<AppRootConfig>::new()
+            .file(&config_path)
+            .file(&overlay_path)
+            .env_prefix("cli_config_builder_layers")
+            .load_traced().await
+            .expect("Layering should have worked");
+        assert_eq!(loaded_config.config.log_sub_config.sink, Some(Dummy::StdOut), "The environment, added last, should have won");
+        assert_eq!(loaded_config.path, config_path, "The first file should be the config file");
+        assert_eq!(provenance.source_of("log_sub_config.sink"), Some(&Source::EnvVar("CLI_CONFIG_BUILDER_LAYERS_LOG_SUB_CONFIG__SINK".to_string())),
+                   "The provenance should name the environment variable");
+        assert!(!missing_overlay_path.exists(), "Missing overlays should be skipped, not created");
+    }
+
+    #[tokio::test]
+    async fn layers_around_the_cmdline() {
+        let config_path = temp_config_path("yaml");
+        let overlay_path = temp_config_path("overlay.ron");
+        std::fs::write(&config_path, "log_sub_config:\n  sink: null\n").unwrap();
+        std::fs::write(&overlay_path, "(log_sub_config: (sink: Some(stdout)))").unwrap();
+        std::env::set_var("CLI_CONFIG_BUILDER_CMDLINE_LOG_SUB_CONFIG__SINK", "stdout");
+
+        let (loaded_config, provenance) = ConfigMeld::<AppRootConfig>::new()
+            .file(&config_path)
+            .env_prefix("CLI_CONFIG_BUILDER_CMDLINE")
+            .cli::<SampleCliOptions>()
+            .args(["test", "--sink", "stderror"])
+            .load_traced().await
+            .expect("Layering should have worked");
+        assert_eq!(loaded_config.config.log_sub_config.sink, Some(Dummy::StdError), "The command line should have won over the environment");
+        assert_eq!(provenance.source_of("log_sub_config.sink"), Some(&Source::CliFlag("--sink".to_string())), "The provenance should name the option");
+
+        let config = ConfigMeld::<AppRootConfig>::new()
+            .file(&config_path)
+            .cli::<SampleCliOptions>()
+            .file(&overlay_path)
+            .args(["test", "--sink", "stderror"])
+            .load().await
+            .expect("Layering should have worked");
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdOut), "The overlay file, added after the command line, should have won");
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), "log_sub_config:\n  sink: null\n", "The config file shouldn't have been touched");
+    }
+
+    #[tokio::test]
+    async fn strict_unknown_keys() {
+        let config_path = temp_config_path("yaml");
+        std::fs::write(&config_path, "log_sub_config:\n  sink: stdout\n  colour: true\n").unwrap();
+
+        let config = ConfigMeld::<AppRootConfig>::new().file(&config_path).load().await
+            .expect("Unknown fields should be ignored by default");
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdOut), "The known fields should have been loaded");
+        let result = ConfigMeld::<AppRootConfig>::new().file(&config_path).strict_unknown_keys(true).load().await;
+        assert!(matches!(result, Err(crate::Error::UnknownFields { ref fields, .. }) if fields == &["log_sub_config.colour"]),
+                "The unknown field should have been refused. Got {result:?}");
+
+        std::fs::write(&config_path, "log_sub_config:\n  sink: stdout\n").unwrap();
+        std::env::set_var("CLI_CONFIG_BUILDER_STRICT_COLOUR", "true");
+        ConfigMeld::<AppRootConfig>::new().file(&config_path).env_prefix("CLI_CONFIG_BUILDER_STRICT").load().await
+            .expect("Unknown environment variables should be ignored by default");
+        let result = ConfigMeld::<AppRootConfig>::new().file(&config_path).env_prefix("CLI_CONFIG_BUILDER_STRICT").strict_unknown_keys(true).load().await;
+        assert!(matches!(result, Err(crate::Error::UnknownFields { ref fields, .. }) if fields == &["CLI_CONFIG_BUILDER_STRICT_COLOUR"]),
+                "The unknown environment variable should have been refused. Got {result:?}");
+    }
+}
@@ -1,76 +1,526 @@
 //! Operations for the program's Command Line Interface -- mostly delegated to `clap`
 
+use std::ffi::OsString;
 use std::io;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use crate::{save_to_file, CmdLineAndConfigIntegration, OgreRootConfig};
-use encryptable_tokio_fs::fs;
+#[cfg(feature = "async")]
+use crate::logic::builder_logic::{overlaid, refuse_unknown_fields, MeldLayers};
+use crate::logic::diff_logic::diff_value_trees;
+use crate::logic::provenance_logic::{annotated_effective_config, ProvenanceTracer};
+use crate::logic::config_logic::{format_of, serialize_for_file_with_header};
+#[cfg(feature = "async")]
+use crate::logic::config_logic::{backup_config_file_in, config_preserving_layout, file_format, load_existing_text_and_config, load_or_create_default_reporting_creation, post_loaded, read_config_text, save_text_replacing_file, save_text_to_file, tail_docs_for};
+#[cfg(feature = "http")]
+use crate::logic::remote_logic::load_from_url_reporting_format;
+#[cfg(feature = "async")]
+use crate::logic::subcommand_logic::write_reset_report;
+use crate::logic::{keeping_secret_refs, SecretRefsSource};
+use crate::logic::warnings_logic::emit_warning;
+use crate::{apply_config_overrides, CmdLineAndConfigIntegration, ConfigLocation, ConfigResolution, ConfigSearchEntry, ConfigSearchPath, FieldChange, LoadWarningKind, MeldOptions, OgreRootConfig, Provenance, RewriteHeader, SaveOptions};
+#[cfg(feature = "async")]
+use crate::{is_frozen, lock_config_file, recover_config_file, reset_config_file, ConfigFileLock, ConfigFs, ConfigMeld, EffectiveConfigTarget, FileMetadata, FROZEN_MARKER, LoadedConfig, LoadedFileFingerprint, OnBackupFailure, OnCreateFailure, RealFs, RewriteStyle};
 use clap::Parser;
+#[cfg(feature = "async")]
+use clap::ArgMatches;
+#[cfg(feature = "async")]
+use encryptable_tokio_fs::fs;
 
-/// Similarly to [parse_cmdline_args()],
+/// Similarly to [try_parse_cmdline_args()],
 /// parse the CLI options from the program's command line args,
 /// but also load the configs and [merge_cmdline_args_with_configs()],
-/// then return the effective configuration the application must use
+/// then return the effective configuration the application must use -- see [parse_cmdline_and_merge_with_loaded_configs_traced()]
+/// for also getting the path of the config file used.
+/// Command line errors (as well as `--help` & `--version`) are returned as [crate::Error] variants
+/// -- see [crate::Error::exit_if_cli()]. See [effective_config_from_parts()] for merging into a config at hand, without touching any files.
+#[cfg(feature = "async")]
 pub async fn parse_cmdline_and_merge_with_loaded_configs<
     CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
     RootConfigType: OgreRootConfig,
 >(
     tail_docs: &str,
 ) -> Result<RootConfigType, crate::Error> {
+    ConfigMeld::<RootConfigType>::new()
+        .cli::<CmdLineOptionsType>()
+        .tail_docs(tail_docs)
+        .load().await
+}
 
-    let cmdline_options: CmdLineOptionsType = parse_cmdline_args();
-    let should_write_effective_config = cmdline_options.should_write_effective_config();
+/// Same as [parse_cmdline_and_merge_with_loaded_configs()], but also telling where each value of the effective configuration
+/// came from -- the defaults, the config file, an environment variable, a command line option or a config override -- for audit logs & UIs
+/// (see [Provenance]) -- as well as the config file it was loaded from (see [LoadedConfig]), which may be rewritten through it
+#[cfg(feature = "async")]
+pub async fn parse_cmdline_and_merge_with_loaded_configs_traced<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    tail_docs: &str,
+) -> Result<(LoadedConfig<RootConfigType>, Provenance), crate::Error> {
+    ConfigMeld::<RootConfigType>::new()
+        .cli::<CmdLineOptionsType>()
+        .tail_docs(tail_docs)
+        .load_traced().await
+}
+
+/// Parses the command line -- the program's one or, if given, the `args` -- then loads & merges the configs with the other
+/// `meld_layers`, for [ConfigMeld::load()] & [ConfigMeld::load_traced()] (if `trace`). If given, `config_file_path` is used
+/// instead of the one the command line options resolve to
+#[cfg(feature = "async")]
+pub(crate) async fn parse_cmdline_and_meld<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    args: Option<Vec<OsString>>,
+    config_file_path: Option<&Path>,
+    tail_docs: &str,
+    meld_layers: &MeldLayers,
+    trace: bool,
+) -> Result<(LoadedConfig<RootConfigType>, Provenance), crate::Error> {
+
+    let tail_docs = tail_docs_for::<RootConfigType>(tail_docs);
+    // the matches tell where the values came from -- see [Provenance]
+    let arg_matches = match args {
+        Some(args) => CmdLineOptionsType::command().try_get_matches_from(args)?,
+        None => CmdLineOptionsType::command().try_get_matches()?,
+    };
+    let cmdline_options = CmdLineOptionsType::from_arg_matches(&arg_matches)
+        .map_err(|err| err.format(&mut CmdLineOptionsType::command()))?;
+
+    if cmdline_options.should_reset_config() {
+        let mut report = Vec::new();
+        reset_config_for(&cmdline_options, config_file_path, tail_docs, &mut report).await?;
+        return Err(crate::Error::ConfigReset { rendered_report: String::from_utf8_lossy(&report).into_owned() })
+    }
+    if cmdline_options.should_print_config_help() {
+        return Err(config_help(tail_docs)?)
+    }
+
+    load_and_merge_layered_configs_for(cmdline_options, config_file_path, tail_docs, Some(&arg_matches), trace, meld_layers).await
+}
+
+/// The logic behind [parse_cmdline_and_merge_with_loaded_configs()], for already parsed `cmdline_options`:
+/// loads the configs, merges them with the CLI options and, if requested, shows & rewrites the effective configuration
+#[cfg(all(feature = "async", test))]
+async fn load_and_merge_configs_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: CmdLineOptionsType,
+    tail_docs: &str,
+) -> Result<RootConfigType, crate::Error> {
+    load_and_merge_configs_traced_for(cmdline_options, None, tail_docs, None, false).await
+        .map(|(loaded_config, _)| loaded_config.config)
+}
+
+/// The logic behind [parse_cmdline_and_merge_with_loaded_configs()] & its `_traced()` version, for already parsed `cmdline_options`:
+/// loads the configs, merges them with the CLI options and, if requested, shows & rewrites the effective configuration.
+/// The [Provenance] of the values is traced if `trace` is set (or if the effective config is to be shown annotated with it) -- in which case
+/// the `arg_matches` the `cmdline_options` were parsed from, if given, allow telling values given by environment variables from the ones
+/// given in the command line. If given, `config_file_path` is used instead of the one the `cmdline_options` resolve to.
+/// The effective config is returned along with the file it came from -- fingerprinted after any rewrite
+#[cfg(all(feature = "async", any(test, feature = "test-util")))]
+pub(crate) async fn load_and_merge_configs_traced_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: CmdLineOptionsType,
+    config_file_path: Option<&Path>,
+    tail_docs: &str,
+    arg_matches: Option<&ArgMatches>,
+    trace: bool,
+) -> Result<(LoadedConfig<RootConfigType>, Provenance), crate::Error> {
+    load_and_merge_layered_configs_for(cmdline_options, config_file_path, tail_docs, arg_matches, trace, &MeldLayers::default()).await
+}
+
+/// [load_and_merge_configs_traced_for()] with the `meld_layers` of a [ConfigMeld] applied around the command line options:
+/// the ones before them are overlaid on the loaded config -- so they are also rewritten -- & the ones after them, on the merged config
+#[cfg(feature = "async")]
+pub(crate) async fn load_and_merge_layered_configs_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: CmdLineOptionsType,
+    config_file_path: Option<&Path>,
+    tail_docs: &str,
+    arg_matches: Option<&ArgMatches>,
+    trace: bool,
+    meld_layers: &MeldLayers,
+) -> Result<(LoadedConfig<RootConfigType>, Provenance), crate::Error> {
+
+    let should_annotate_effective_config = cmdline_options.should_annotate_effective_config();
+    let mut tracer = (trace || should_annotate_effective_config)
+        .then(|| ProvenanceTracer::new::<RootConfigType>(arg_matches));
+    let effective_config_target = cmdline_options.effective_config_output_path();
+    let should_write_effective_config = effective_config_target == Some(EffectiveConfigTarget::InPlace);
     let should_show_effective_config = cmdline_options.should_show_effective_config();
+    let meld_options = cmdline_options.meld_options();
+    // the docs are kept on rewrites -- unless the file wasn't meant to have them
+    let rewrite_tail_docs = if cmdline_options.include_docs_in_created_file() { tail_docs } else { "" };
 
-    let config_file_path = get_config_file_path::<CmdLineOptionsType, RootConfigType>();
-    let loaded_config = super::load_or_create_default(&config_file_path, tail_docs).await?;
-    let effective_config = merge_cmdline_args_with_configs(cmdline_options, loaded_config)?;
+    if cmdline_options.should_debug_config_paths() {
+        debug_config_paths(&cmdline_options);
+    }
 
+    if let Some(url) = cmdline_options.config_file_path().filter(|path| config_file_path.is_none() && is_config_url(path)) {
+        let url = url.to_string();
+        return load_and_merge_remote_configs_for(cmdline_options, &url, rewrite_tail_docs, tracer, meld_layers).await
+    }
+
+    let (config_file_path, fallback_path) = match config_file_path {
+        Some(config_file_path) => (config_file_path.to_path_buf(), None),
+        None => {
+            let config_resolution = resolve_config_file_path(&cmdline_options);
+            (config_resolution.chosen, config_resolution.fallback)
+        },
+    };
+    // the loaded text is kept for the rewrite, so the file is read only once -- & the rewrite acts on what was actually merged
+    let (config_file_path, _lock, (loaded_config, created_now, loaded_txt)) =
+        lock_and_load_configs_for(&cmdline_options, config_file_path, fallback_path, should_write_effective_config, tail_docs).await?;
+    if let (true, Some(loaded_txt)) = (meld_layers.strict_unknown_keys, &loaded_txt) {
+        refuse_unknown_fields(loaded_txt, format_of(&config_file_path)?, &loaded_config, &config_file_path)?;
+    }
+    if let Some(tracer) = &mut tracer {
+        tracer.loaded(&loaded_config, &config_file_path);
+    }
+    // taken after loading, as the file may have just been created or recovered
+    let loaded_fingerprint = match should_write_effective_config {
+        true => LoadedFileFingerprint::of(&config_file_path).await?,
+        false => None,
+    };
+    // the value tree of the loaded config, for skipping rewrites that wouldn't change anything & for showing & recording what changed
+    // -- & for tracing the merge
+    let loaded_value_tree = (effective_config_target.is_some() || should_show_effective_config || cfg!(feature = "tracing"))
+        .then(|| serde_json::to_value(&loaded_config).ok())
+        .flatten();
+    let loaded_config = overlaid(loaded_config, &meld_layers.before_cmdline, meld_layers.strict_unknown_keys, tracer.as_mut()).await?;
+    let effective_config = merge_cmdline_args_with_configs_traced(cmdline_options, loaded_config, Some(&config_file_path), tracer.as_mut())?;
+    let effective_config = overlaid(effective_config, &meld_layers.after_cmdline, meld_layers.strict_unknown_keys, tracer.as_mut()).await?;
+    let provenance = tracer.map(|tracer| tracer.provenance).unwrap_or_default();
+    let effective_value_tree = loaded_value_tree.is_some()
+        .then(|| serde_json::to_value(&effective_config).ok())
+        .flatten();
+    #[cfg(feature = "tracing")]
+    {
+        // only the field paths are recorded -- never their values, which may be secrets
+        let changed_fields = changed_field_paths(loaded_value_tree.as_ref(), effective_value_tree.as_ref());
+        tracing::info!(name: "config.merge", path = ?config_file_path, created = created_now, changed_fields_count = changed_fields.len(), ?changed_fields,
+                       "config file merged with the command line");
+    }
+
+    if should_show_effective_config {
+        show_effective_config_and_changes(&effective_config, should_annotate_effective_config.then_some(&provenance),
+                                          loaded_value_tree.as_ref(), effective_value_tree.as_ref(), &config_file_path)?;
+    }
+
+    let is_config_unchanged = should_write_effective_config && loaded_value_tree.is_some() && loaded_value_tree == effective_value_tree;
+    if is_config_unchanged && fs::try_exists(&config_file_path).await.unwrap_or(false) {
+        emit_warning(LoadWarningKind::RewriteSkipped, "", format!("the config file {config_file_path:?} already holds the effective config, so it was not rewritten"));
+    } else if let Some(effective_config_target) = &effective_config_target {
+        let changed_fields = changed_field_paths(loaded_value_tree.as_ref(), effective_value_tree.as_ref());
+        let (output_path, result) = match effective_config_target {
+            EffectiveConfigTarget::InPlace => (&config_file_path, write_effective_config(&RealFs, &effective_config, &config_file_path, loaded_txt.as_deref(), loaded_fingerprint.as_ref(),
+                                                                                            &meld_options, changed_fields, rewrite_tail_docs).await),
+            EffectiveConfigTarget::Path(output_path) => (output_path, write_effective_config_elsewhere(&RealFs, &effective_config, output_path, &config_file_path, loaded_txt.as_deref(),
+                                                                                                     &meld_options, changed_fields, rewrite_tail_docs).await),
+        };
+        persisted_or_warned(result, &meld_options, output_path)?;
+    }
+
+    Ok((LoadedConfig::of(effective_config, &config_file_path, created_now).await?, provenance))
+}
+
+/// Similar to [load_and_merge_configs_traced_for()], but for when the config file path is the `url` of a remote config
+/// -- which is fetched as specified by [MeldOptions::remote_options] and may not be rewritten, although the effective config
+/// may be written elsewhere (see [EffectiveConfigTarget::Path]), followed by the `tail_docs`
+#[cfg(feature = "http")]
+async fn load_and_merge_remote_configs_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: CmdLineOptionsType,
+    url: &str,
+    tail_docs: &str,
+    mut tracer: Option<ProvenanceTracer<'_>>,
+    meld_layers: &MeldLayers,
+) -> Result<(LoadedConfig<RootConfigType>, Provenance), crate::Error> {
+    let effective_config_target = cmdline_options.effective_config_output_path();
+    if effective_config_target == Some(EffectiveConfigTarget::InPlace) {
+        return Err(crate::Error::CliParsing {
+            rendered_help: format!("error: the effective config can't be written to the remote config '{url}': use a local config file to have it rewritten\n"),
+            exit_hint: 2,
+        })
+    }
+    let should_show_effective_config = cmdline_options.should_show_effective_config();
+    let should_annotate_effective_config = cmdline_options.should_annotate_effective_config();
+    let meld_options = cmdline_options.meld_options();
+    let (loaded_config, format) = load_from_url_reporting_format(url, &meld_options.remote_options).await?;
+    if let Some(tracer) = &mut tracer {
+        tracer.loaded(&loaded_config, Path::new(url));
+    }
+    let loaded_value_tree = effective_config_target.is_some()
+        .then(|| serde_json::to_value(&loaded_config).ok())
+        .flatten();
+    let loaded_config = overlaid(loaded_config, &meld_layers.before_cmdline, meld_layers.strict_unknown_keys, tracer.as_mut()).await?;
+    let effective_config = merge_cmdline_args_with_configs_traced(cmdline_options, loaded_config, None, tracer.as_mut())?;
+    let effective_config = overlaid(effective_config, &meld_layers.after_cmdline, meld_layers.strict_unknown_keys, tracer.as_mut()).await?;
+    let provenance = tracer.map(|tracer| tracer.provenance).unwrap_or_default();
     if should_show_effective_config {
-        eprintln!("EFFECTIVE PROGRAM CONFIGURATION: {effective_config:#?}\n");
-        io::stderr()
-            .flush()
-            .map_err(|err| crate::Error::LoadingConfig {
-                message: "Error dumping the Effective Program Configuration to stderr".to_string(),
-                cause: err.into(),
-            })?;
+        show_effective_config(&effective_config, should_annotate_effective_config.then_some(&provenance))?;
     }
+    if let Some(EffectiveConfigTarget::Path(output_path)) = &effective_config_target {
+        let effective_value_tree = serde_json::to_value(&effective_config).ok();
+        let changed_fields = changed_field_paths(loaded_value_tree.as_ref(), effective_value_tree.as_ref());
+        let result = write_effective_config_elsewhere(&RealFs, &effective_config, output_path, Path::new(url), None, &meld_options, changed_fields, tail_docs).await;
+        persisted_or_warned(result, &meld_options, output_path)?;
+    }
+    let loaded_config = LoadedConfig { config: effective_config, path: PathBuf::from(url), format, created_now: false, fingerprint: None };
+    Ok((loaded_config, provenance))
+}
 
-    if should_write_effective_config {
-        let mut backup_config_file_path = config_file_path.clone();
-        backup_config_file_path.push("~");
+/// Without the `http` feature, remote configs are reported as unsupported
+#[cfg(all(feature = "async", not(feature = "http")))]
+async fn load_and_merge_remote_configs_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    _cmdline_options: CmdLineOptionsType,
+    url: &str,
+    _tail_docs: &str,
+    _tracer: Option<ProvenanceTracer<'_>>,
+    _meld_layers: &MeldLayers,
+) -> Result<(LoadedConfig<RootConfigType>, Provenance), crate::Error> {
+    Err(crate::Error::UnsupportedConfigFileFormat {
+        message: format!("`cli-config`: Loading configs from URLs -- like '{url}' -- requires the `http` feature"),
+    })
+}
 
-        // generate the docs for the new configs
-        let doc_comments = format!(
-            r#"
-Rewriten from merging the previous configs & the command line options at {date_str}
-(previous configuration file backed up to {backup_config_file_path:?})
+/// Dumps the config file paths probed for the `cmdline_options` to stderr -- see [CmdLineAndConfigIntegration::should_debug_config_paths()]
+pub(crate) fn debug_config_paths<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(cmdline_options: &CmdLineOptionsType) {
+    eprintln!("PROBED CONFIG PATHS:");
+    let config_resolution = resolve_config_file_path(cmdline_options);
+    for config_file_candidate in &config_resolution.considered {
+        eprintln!("  {config_file_candidate:?}{}", if config_file_candidate.exists() { " (exists)" } else { "" });
+    }
+    eprintln!("CHOSEN CONFIG PATH: {:?}{}", config_resolution.chosen, if config_resolution.exists { "" } else { " (to be created)" });
+    eprintln!();
+}
+
+/// Tells if the config file `path` given in the command line is, actually, the URL of a remote config
+pub(crate) fn is_config_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Dumps the `effective_config` to stderr -- see [CmdLineAndConfigIntegration::should_show_effective_config()].
+/// If a `provenance` is given, it is dumped in YAML, with each value annotated with its source
+pub(crate) fn show_effective_config(effective_config: &impl OgreRootConfig, provenance: Option<&Provenance>) -> Result<(), crate::Error> {
+    match provenance.and_then(|provenance| annotated_effective_config(effective_config, provenance)) {
+        Some(annotated_config) => eprintln!("EFFECTIVE PROGRAM CONFIGURATION (with the source of each value):\n{annotated_config}"),
+        None => eprintln!("EFFECTIVE PROGRAM CONFIGURATION: {effective_config:#?}\n"),
+    }
+    io::stderr()
+        .flush()
+        .map_err(|err| crate::Error::LoadingConfig {
+            message: "Error dumping the Effective Program Configuration to stderr".to_string(),
+            cause: err.into(),
+        })
+}
+
+/// [show_effective_config()], followed by what changed from the config loaded from `config_file_path` -- if both the
+/// `loaded_value_tree` & `effective_value_tree` are given. Shared by the async & blocking flows
+pub(crate) fn show_effective_config_and_changes(
+    effective_config: &impl OgreRootConfig,
+    provenance: Option<&Provenance>,
+    loaded_value_tree: Option<&serde_json::Value>,
+    effective_value_tree: Option<&serde_json::Value>,
+    config_file_path: &Path,
+) -> Result<(), crate::Error> {
+    show_effective_config(effective_config, provenance)?;
+    if let (Some(loaded_value_tree), Some(effective_value_tree)) = (loaded_value_tree, effective_value_tree) {
+        eprintln!("{}", changes_vs_file_report(&diff_value_trees(loaded_value_tree, effective_value_tree), config_file_path));
+    }
+    Ok(())
+}
 
-COMMAND LINE OPTIONS: {cmdline_options:#?}
+/// Lists the `changes` of the effective config vs the one loaded from `config_file_path` -- shown along with the effective config
+pub(crate) fn changes_vs_file_report(changes: &[FieldChange], config_file_path: &Path) -> String {
+    let mut report = format!("EFFECTIVE CONFIG CHANGES VS THE CONFIG FILE {config_file_path:?}:\n");
+    if changes.is_empty() {
+        report.push_str("  (none)\n");
+    }
+    for change in changes {
+        report.push_str(&format!("  {change}\n"));
+    }
+    report
+}
 
-PREVIOUS CONFIG: {loaded_config:#?}
+/// Rewrites the config file at `config_file_path` -- whose contents were the `original_txt`, when loaded -- with the `effective_config`,
+/// documenting where it came from.
+/// The previous file is backed up as per [MeldOptions::backup_policy] (to `<name>.bak-<timestamp>`, by default),
+/// while its permissions & ownership (when privileged) are kept in the rewritten file
+/// -- see [backup_config_file()] & [MeldOptions] (including what to do when the backup fails).
+/// If the file no longer matches the `loaded_fingerprint`, the rewrite is aborted with [crate::Error::ConfigChangedOnDisk],
+/// unless [MeldOptions::force_overwrite] is set. Frozen files are never rewritten -- see [is_frozen()].
+/// See [MeldOptions::rewrite_style] for keeping the file's layout & comments -- otherwise, the regenerated file starts with
+/// a [RewriteHeader] telling how it came to be -- including the `changed_fields` -- & ends with the original `tail_docs`.
+#[cfg(feature = "async")]
+#[allow(clippy::too_many_arguments)]
+async fn write_effective_config<RootConfigType: OgreRootConfig>(
+    config_fs: &impl ConfigFs,
+    effective_config: &RootConfigType,
+    config_file_path: &Path,
+    original_txt: Option<&str>,
+    loaded_fingerprint: Option<&LoadedFileFingerprint>,
+    meld_options: &MeldOptions,
+    changed_fields: Vec<String>,
+    tail_docs: &str,
+) -> Result<(), crate::Error> {
+    if original_txt.is_some_and(is_frozen) {
+        return Err(crate::Error::ConfigFrozen {
+            path: config_file_path.to_path_buf(),
+            message: format!("The config file {config_file_path:?} is marked as frozen (with a `{FROZEN_MARKER}` comment line), so the effective config wasn't written to it \
+                              -- remove the marker to allow rewrites"),
+        })
+    }
+    if let Some(loaded_fingerprint) = loaded_fingerprint.filter(|_| !meld_options.force_overwrite) {
+        if loaded_fingerprint.has_changed(config_file_path).await? {
+            return Err(crate::Error::ConfigChangedOnDisk {
+                path: config_file_path.to_path_buf(),
+                message: format!("The config file {config_file_path:?} was changed since it was loaded: it was left as is, so those changes aren't lost \
+                                  -- re-run the program to have them merged into the effective config"),
+            })
+        }
+    }
+    // symlinked files are written through, so only regular files lose their metadata to the backup
+    let original_metadata = config_fs.symlink_metadata(config_file_path).await.ok().filter(|metadata| metadata.is_file);
+    // the lock is already held by the caller
+    let mut save_options = SaveOptions { locked: None, ..meld_options.save_options.clone() };
+    let mut header = RewriteHeader::now(meld_options.program_version.clone(), changed_fields);
+    let preserved_txt = match (meld_options.rewrite_style, &original_txt) {
+        (RewriteStyle::PreserveLayout, Some(original_txt)) => {
+            let preserved_txt = config_preserving_layout(original_txt, effective_config, config_file_path, &save_options);
+            if preserved_txt.is_none() {
+                emit_warning(LoadWarningKind::Fallback, "", format!("the layout of the config file {config_file_path:?} couldn't be preserved -- regenerating it with the effective config"));
+            }
+            preserved_txt
+        },
+        _ => None,
+    };
+    let backup_config_file_path = match backup_config_file_in(config_fs, config_file_path, &meld_options.backup_policy).await {
+        Ok(backup_config_file_path) => backup_config_file_path,
+        Err(err) if meld_options.on_backup_failure == OnBackupFailure::OverwriteWithoutBackup => {
+            emit_warning(LoadWarningKind::FailedPersistence, "", format!("the config file {config_file_path:?} couldn't be backed up -- overwriting it without a backup: {err}"));
+            // the file stays in place, so replacing it atomically keeps its contents intact should the write fail
+            save_options.durable = true;
+            header.backup_failed = true;
+            return save_effective_config(config_fs, effective_config, preserved_txt, config_file_path, original_txt, None, &save_options, &header, tail_docs).await
+        },
+        Err(err) => return Err(err),
+    };
+    header.backup = backup_config_file_path;
+    // the file is recreated with the original permissions & ownership before receiving the (possibly secret) effective config
+    save_effective_config(config_fs, effective_config, preserved_txt, config_file_path, original_txt, original_metadata.as_ref(), &save_options, &header, tail_docs).await?;
+    #[cfg(feature = "tracing")]
+    tracing::info!(name: "config.rewrite", path = ?config_file_path, backup = ?header.backup, "config file rewritten with the effective config");
+    Ok(())
+}
 
-"#,
-            date_str = chrono::Local::now().format("%a %b %e %H:%M:%S %Z %Y"),
-            // // recompute previously consumed information -- instead of always cloning unneededly
-            cmdline_options = parse_cmdline_args::<CmdLineOptionsType>(),
-            loaded_config = super::config_logic::load_or_create_default::<RootConfigType>(
-                &config_file_path,
-                tail_docs
-            )
-            .await?,
-        );
+/// Writes the `effective_config` to `output_path` -- instead of rewriting the config file it came from, at `config_file_path` (or URL)
+/// -- see [EffectiveConfigTarget::Path]. The file is simply (re)written, in the format implied by its extension, starting with
+/// a [RewriteHeader] telling where the effective config came from & the `changed_fields`, and ending with the `tail_docs`.
+/// The secret references of the `source_txt` -- the config file's text, when loaded -- are kept, still pointing to the same files
+#[cfg(feature = "async")]
+#[allow(clippy::too_many_arguments)]
+async fn write_effective_config_elsewhere<RootConfigType: OgreRootConfig>(
+    config_fs: &impl ConfigFs,
+    effective_config: &RootConfigType,
+    output_path: &Path,
+    config_file_path: &Path,
+    source_txt: Option<&str>,
+    meld_options: &MeldOptions,
+    changed_fields: Vec<String>,
+    tail_docs: &str,
+) -> Result<(), crate::Error> {
+    let (txt_config, save_options) = effective_config_for_output(effective_config, output_path, config_file_path, source_txt, meld_options, changed_fields, tail_docs)?;
+    save_text_to_file(config_fs, txt_config, output_path, &save_options).await
+}
 
-        fs::rename(&config_file_path, &backup_config_file_path).await
-            .map_err(|err| crate::Error::SavingConfig {
-                message: format!("Error rewriting the config file {config_file_path:?} with a new effective configuration: the file couldn't be renamed to {backup_config_file_path:?}"),
-                cause: err.into(),
-            })?;
+/// The text of the `effective_config` to be written to `output_path`, along with the options to save it with -- shared by the async &
+/// blocking flows, which only differ in how it is saved. See [write_effective_config_elsewhere()]
+pub(crate) fn effective_config_for_output<RootConfigType: OgreRootConfig>(
+    effective_config: &RootConfigType,
+    output_path: &Path,
+    config_file_path: &Path,
+    source_txt: Option<&str>,
+    meld_options: &MeldOptions,
+    changed_fields: Vec<String>,
+    tail_docs: &str,
+) -> Result<(String, SaveOptions), crate::Error> {
+    let save_options = SaveOptions { format: None, create_parents: true, ..meld_options.save_options.clone() };
+    let header = RewriteHeader {
+        source: Some(config_file_path.to_path_buf()),
+        ..RewriteHeader::now(meld_options.program_version.clone(), changed_fields)
+    };
+    // the secret references of the config file are kept, still pointing to the same files
+    let source = match source_txt {
+        Some(source_txt) => Some(SecretRefsSource { txt: source_txt, format: format_of(config_file_path)?, path: config_file_path }),
+        None => None,
+    };
+    let txt_config = serialize_for_file_with_header(effective_config, &header, tail_docs, output_path, &save_options)?;
+    let txt_config = keeping_secret_refs(txt_config, format_of(output_path)?, output_path, source, None)?;
+    Ok((txt_config, save_options))
+}
 
-        save_to_file(&effective_config, &doc_comments, &config_file_path).await?;
+/// The outcome of writing the effective config to `output_path`, out of the write `result`: persistence errors are just warned about
+/// if [MeldOptions::best_effort_persist] is set, going on with the effective config in memory only
+pub(crate) fn persisted_or_warned(result: Result<(), crate::Error>, meld_options: &MeldOptions, output_path: &Path) -> Result<(), crate::Error> {
+    match result {
+        Err(err) if meld_options.best_effort_persist && err.is_persistence_error() => {
+            emit_warning(LoadWarningKind::FailedPersistence, "", format!("the effective config couldn't be written to {output_path:?} -- going on with it in memory only: {err}"));
+            Ok(())
+        },
+        result => result,
     }
+}
 
-    Ok(effective_config)
+/// Saves the `effective_config` to `config_file_path`, preceded by the `header` & followed by the `tail_docs` -- see [write_effective_config()].
+/// If the original layout was preserved, its `preserved_txt` is saved as-is, instead. Either way, the values loaded from secret files
+/// are written back as the secret references of the `original_txt` -- see [keeping_secret_refs()]. The `replaced_metadata` of the config file, if it was moved
+/// away to its backup, is kept -- see [save_text_replacing_file()]
+#[cfg(feature = "async")]
+#[allow(clippy::too_many_arguments)]
+async fn save_effective_config<RootConfigType: OgreRootConfig>(
+    config_fs: &impl ConfigFs,
+    effective_config: &RootConfigType,
+    preserved_txt: Option<String>,
+    config_file_path: &Path,
+    original_txt: Option<&str>,
+    replaced_metadata: Option<&FileMetadata>,
+    save_options: &SaveOptions,
+    header: &RewriteHeader,
+    tail_docs: &str,
+) -> Result<(), crate::Error> {
+    let txt_config = match preserved_txt {
+        Some(preserved_txt) => preserved_txt,
+        None => serialize_for_file_with_header(effective_config, header, tail_docs, config_file_path, save_options)?,
+    };
+    // the secret references of the file are written back, rather than the secrets they were resolved to
+    let format = file_format(config_file_path, save_options.format)?;
+    let source = original_txt.map(|original_txt| SecretRefsSource { txt: original_txt, format, path: config_file_path });
+    let txt_config = keeping_secret_refs(txt_config, format, config_file_path, source, None)?;
+    save_text_replacing_file(config_fs, txt_config, config_file_path, save_options, replaced_metadata).await
+}
+
+/// The dotted paths of the fields that differ between the `loaded` & `effective` configs' value trees -- the ones the command line changed
+pub(crate) fn changed_field_paths(loaded: Option<&serde_json::Value>, effective: Option<&serde_json::Value>) -> Vec<String> {
+    match (loaded, effective) {
+        (Some(loaded), Some(effective)) => diff_value_trees(loaded, effective).into_iter()
+            .map(|field_change| field_change.path)
+            .collect(),
+        _ => vec![],
+    }
 }
 
 /// Determines the exact path for the configuration file to be used, taking into account:
@@ -82,57 +532,416 @@ PREVIOUS CONFIG: {loaded_config:#?}
 ///
 /// Note that the returned `PathBuf` may either specify an existing file to read
 /// or an unexisting file to be created.
+///
+/// As the command line is parsed again, prefer [get_config_file_path_from()] if it was already parsed -- or
+/// [parse_cmdline_and_merge_with_loaded_configs_traced()], which also returns the path of the config file used.
 pub fn get_config_file_path<
     CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
     RootConfigType: OgreRootConfig,
 >() -> PathBuf {
+    let cmdline_options: CmdLineOptionsType = parse_cmdline_args();
+    get_config_file_path_from(&cmdline_options)
+}
+
+/// The logic behind [get_config_file_path()], for already parsed `cmdline_options` -- sparing parsing the command line again.
+/// This is the path the configs are loaded from (or created at) by [parse_cmdline_and_merge_with_loaded_configs()]
+/// -- unless creating it there is refused, when its [ConfigResolution::fallback] is used
+pub fn get_config_file_path_from<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(cmdline_options: &CmdLineOptionsType) -> PathBuf {
+    resolve_config_file_path(cmdline_options).chosen
+}
+
+/// Tells if the configuration file [get_config_file_path()] resolves to already exists -- as opposed to being
+/// created with the default values when the configs get loaded
+pub fn config_file_exists<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>() -> bool {
+    let cmdline_options: CmdLineOptionsType = parse_cmdline_args();
+    config_file_exists_for(&cmdline_options)
+}
+
+/// The logic behind [config_file_exists()], for already parsed `cmdline_options`
+fn config_file_exists_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(cmdline_options: &CmdLineOptionsType) -> bool {
+    resolve_config_file_path(cmdline_options).exists
+}
 
-    // Provides a configuration file name if none was specified in CLI.
-    // Priority goes for any existing files in the order presented in `CONFIG_SUFFIXES`
-    fn default_config_file_path() -> PathBuf {
+/// Resolves which configuration file to use, as [get_config_file_path()] does, for already parsed `cmdline_options`,
+/// also telling every candidate that was considered -- useful for diagnostics (like a `--print-config-path` option).
+/// The file given in the command line, if any, is the only candidate. Otherwise, the first existing file
+/// in the [CmdLineAndConfigIntegration::config_search_path()] is chosen or, if none exists, the one to be created.
+pub fn resolve_config_file_path<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(cmdline_options: &CmdLineOptionsType) -> ConfigResolution {
+    match cmdline_options.config_file_path() {
+        Some(config_file_path) => {
+            let config_file_path = PathBuf::from(config_file_path);
+            ConfigResolution {
+                exists: config_file_path.exists(),
+                considered: vec![config_file_path.clone()],
+                chosen: config_file_path,
+                fallback: None,
+            }
+        },
+        None => SearchContext::current().resolve(&cmdline_options.config_search_path()),
+    }
+}
 
+/// Returns every path probed, in order, when looking for the default configuration file -- used when
+/// no config file is given in the command line (see [CmdLineAndConfigIntegration::config_file_path()]).
+/// The first existing one is used or, if none exists, one is created -- see [CmdLineAndConfigIntegration::config_search_path()],
+/// which is taken from the CLI options having no args at all.
+/// Useful for diagnosing config discovery issues -- see [CmdLineAndConfigIntegration::should_debug_config_paths()].
+pub fn probed_config_paths<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>() -> Vec<PathBuf> {
+    let config_search_path = std::env::args().next()
+        .and_then(|program_name| CmdLineOptionsType::try_parse_from([program_name]).ok())
+        .map(|cmdline_options| cmdline_options.config_search_path())
+        .unwrap_or_else(|| ConfigSearchPath::single(ConfigLocation::default()));
+    SearchContext::current().resolve(&config_search_path).considered
+}
+
+/// The environment the configuration file is searched in -- injectable for tests
+struct SearchContext {
+    /// The running executable -- or the program's path, as given in its first arg, for [ConfigLocation::InvocationPath]
+    program_path: PathBuf,
+    invocation_path: PathBuf,
+    current_dir: PathBuf,
+    platform_config_dir: Option<PathBuf>,
+    system_config_dir: Option<PathBuf>,
+    env_var: fn(&str) -> Option<OsString>,
+    /// If set, the default config is created in the per-user [ConfigLocation::PlatformConfigDir] when creating it in the chosen
+    /// location is refused -- on Windows, where programs usually live in `C:\Program Files\`. See [ConfigResolution::fallback]
+    user_dir_fallback: bool,
+}
+
+impl SearchContext {
+
+    /// The environment of the running program
+    fn current() -> Self {
+        let invocation_path = std::env::args_os().next()
+            .map(PathBuf::from)
+            .expect("Program name couldn't be retrieve from args. Please specify which configuration file to use via command line.");
+        Self {
+            program_path: std::env::current_exe().unwrap_or_else(|_| invocation_path.clone()),
+            invocation_path,
+            current_dir: PathBuf::from("."),
+            platform_config_dir: dirs::config_dir(),
+            system_config_dir: if cfg!(unix) {
+                Some(PathBuf::from("/etc"))
+            } else if cfg!(windows) {
+                std::env::var_os("PROGRAMDATA").map(PathBuf::from)
+            } else {
+                None
+            },
+            env_var: |name| std::env::var_os(name),
+            user_dir_fallback: cfg!(windows),
+        }
+    }
+
+    /// Chooses the first existing candidate in `config_search_path` -- or the first candidate where the config should be created.
+    /// Candidates whose existence can't be verified (like in unreadable dirs) are skipped with a warning.
+    /// See [Self::user_dir_fallback] for when the config can't be created where it should
+    fn resolve(&self, config_search_path: &ConfigSearchPath) -> ConfigResolution {
+        let mut considered = config_search_path.entries.iter()
+            .flat_map(|entry| match entry {
+                ConfigSearchEntry::EnvVar(env_var) => (self.env_var)(env_var).map(PathBuf::from).into_iter().collect(),
+                ConfigSearchEntry::Location(location) => self.location_candidates(location),
+            })
+            .collect::<Vec<_>>();
+        // without a per-user dir in this platform, there is nowhere to fall back to
+        let user_dir_candidates = match &self.platform_config_dir {
+            Some(_) if self.user_dir_fallback => self.location_candidates(&ConfigLocation::PlatformConfigDir),
+            _ => vec![],
+        };
+        // configs created in the fallback dir must be found in the next runs
+        for candidate in &user_dir_candidates {
+            if !considered.contains(candidate) {
+                considered.push(candidate.clone());
+            }
+        }
+        let existing = considered.iter()
+            .find(|candidate| match candidate.try_exists() {
+                Ok(exists) => exists,
+                Err(err) => {
+                    emit_warning(LoadWarningKind::Fallback, "", format!("skipping the config file candidate {candidate:?}, as its existence couldn't be verified: {err}"));
+                    false
+                },
+            });
+        let (chosen, exists, fallback) = match existing {
+            Some(existing) => (existing.clone(), true, None),
+            None => {
+                let creation_candidates = self.location_candidates(&config_search_path.create_in);
+                let creation_candidates = if creation_candidates.is_empty() { self.location_candidates(&ConfigLocation::BesideExecutable) } else { creation_candidates };
+                // whether the config may be created where it should is only known by trying it
+                let fallback = user_dir_candidates.into_iter().next()
+                    .filter(|user_dir_candidate| *user_dir_candidate != creation_candidates[0]);
+                (creation_candidates[0].clone(), false, fallback)
+            },
+        };
+        ConfigResolution { chosen, considered, exists, fallback }
+    }
+
+    /// The config file candidates at `config_location`, in priority order: the program's name (without any `.exe` extension)
+    /// followed by each of the supported config suffixes (of the enabled formats), in the location's directory.
+    /// Empty if the location doesn't exist in this platform
+    fn location_candidates(&self, config_location: &ConfigLocation) -> Vec<PathBuf> {
         const CONFIG_SUFFIXES: &[&str] = &[
+            #[cfg(feature = "ron")]
             ".config.ron",
+            #[cfg(feature = "yaml")]
             ".config.yaml",
         ];
-        let program_name = std::env::args().next()
-            .expect("Program name couldn't be retrieve from args. Please specify which configuration file to use via command line.")
-            .to_owned();
+        let program_path = &self.program_path;
+        let program_name = match program_path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("exe") => program_path.file_stem(),
+            _ => program_path.file_name(),
+        }.unwrap_or_default().to_string_lossy();
+        let beside_executable = || program_path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let config_dir = match config_location {
+            ConfigLocation::BesideExecutable => beside_executable(),
+            ConfigLocation::PlatformConfigDir => self.platform_config_dir.as_ref()
+                .map(|platform_config_dir| platform_config_dir.join(&*program_name))
+                .unwrap_or_else(beside_executable),
+            ConfigLocation::Custom(config_dir) => config_dir.clone(),
+            ConfigLocation::CurrentDir => self.current_dir.clone(),
+            ConfigLocation::SystemConfigDir => match &self.system_config_dir {
+                Some(system_config_dir) => system_config_dir.join(&*program_name),
+                None => return vec![],
+            },
+            ConfigLocation::InvocationPath => return CONFIG_SUFFIXES.iter()
+                .map(|suffix| {
+                    let mut config_file = self.invocation_path.as_os_str().to_os_string();
+                    config_file.push(suffix);
+                    PathBuf::from(config_file)
+                })
+                .collect(),
+        };
+        CONFIG_SUFFIXES.iter()
+            .map(|suffix| config_dir.join(format!("{program_name}{suffix}")))
+            .collect()
+    }
+}
 
-        // first, try to find any existing file possibilities
-        for suffix in CONFIG_SUFFIXES {
-            let config_file_candidate = format!("{program_name}{suffix}");
-            let config_file_candidate = Path::new(&config_file_candidate);
-            // if it exists, return it
-            if config_file_candidate.exists() {
-                return config_file_candidate.to_path_buf()
-            }
+/// Loads the configs from `config_file_path`, creating a default one if it doesn't exist --
+/// unless `cmdline_options` states the file must already be there or it was explicitly specified
+/// (see [CmdLineAndConfigIntegration::allow_create_at_explicit_path()]).
+/// Unparseable files are recovered if `cmdline_options` asks so (see [CmdLineAndConfigIntegration::should_recover_config()]).
+/// Created files get `tail_docs` appended, unless opted out (see [CmdLineAndConfigIntegration::include_docs_in_created_file()]),
+/// while failing to create them is dealt with according to `on_create_failure`.
+/// Also tells if the file was just created -- or recreated, when recovered -- along with the text the config was loaded from
+/// (or created with), if there is a file
+#[cfg(feature = "async")]
+async fn load_configs_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: &CmdLineOptionsType,
+    config_file_path: &Path,
+    on_create_failure: OnCreateFailure,
+    tail_docs: &str,
+) -> Result<(RootConfigType, bool, Option<String>), crate::Error> {
+    let tail_docs = if cmdline_options.include_docs_in_created_file() { tail_docs } else { "" };
+    let load_result = if cmdline_options.require_existing() {
+        load_existing_text_and_config(config_file_path).await.map(|(txt_config, config)| (config, false, Some(txt_config)))
+    } else if cmdline_options.config_file_path().is_some() && !cmdline_options.allow_create_at_explicit_path() {
+        load_existing_text_and_config(config_file_path).await
+            .map(|(txt_config, config)| (config, false, Some(txt_config)))
+            .map_err(explicit_config_file_err)
+    } else {
+        load_or_create_default_reporting_creation(config_file_path, tail_docs, on_create_failure).await
+    };
+    match load_result {
+        Err(err) if err.is_parsing_error() && cmdline_options.should_recover_config() => {
+            let broken_config_file_path = recover_config_file::<RootConfigType>(config_file_path, tail_docs).await?;
+            emit_warning(LoadWarningKind::Fallback, "", format!("the config file {config_file_path:?} couldn't be parsed, so it was moved to {broken_config_file_path:?} \
+                                                                 and a new one was created with the default values: {err}"));
+            let recovered_config = post_loaded(RootConfigType::default(), config_file_path, format_of(config_file_path)?)?;
+            Ok((recovered_config, true, read_config_text(&RealFs, config_file_path).await.ok()))
+        },
+        load_result => load_result.map_err(with_recovery_hint),
+    }
+}
+
+/// [load_configs_for()] the config file at `config_file_path` -- locked, if `should_lock` -- falling back to create it at `fallback_path`
+/// if creating it there is refused (see [ConfigResolution::fallback]). Returns the path of the config file used, along with its lock & configs
+#[cfg(feature = "async")]
+async fn lock_and_load_configs_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: &CmdLineOptionsType,
+    config_file_path: PathBuf,
+    fallback_path: Option<PathBuf>,
+    should_lock: bool,
+    tail_docs: &str,
+) -> Result<(PathBuf, Option<ConfigFileLock>, (RootConfigType, bool, Option<String>)), crate::Error> {
+    let on_create_failure = cmdline_options.meld_options().on_create_failure;
+    let Some(fallback_path) = fallback_path else {
+        let (lock, configs) = locked_load_configs_for(cmdline_options, &config_file_path, should_lock, on_create_failure, tail_docs).await?;
+        return Ok((config_file_path, lock, configs))
+    };
+    // creation failures must be seen, for the fallback to be used
+    match locked_load_configs_for(cmdline_options, &config_file_path, should_lock, OnCreateFailure::Fail, tail_docs).await {
+        Err(err) if is_refused_creation(&err) => {
+            emit_warning(LoadWarningKind::Fallback, "", format!("the config file {config_file_path:?} couldn't be created -- using {fallback_path:?} instead: {err}"));
+            let (lock, configs) = locked_load_configs_for(cmdline_options, &fallback_path, should_lock, on_create_failure, tail_docs).await?;
+            Ok((fallback_path, lock, configs))
+        },
+        result => result.map(|(lock, configs)| (config_file_path, lock, configs)),
+    }
+}
+
+/// [load_configs_for()] the config file at `config_file_path` after locking it, if `should_lock` -- as concurrent rewrites
+/// would race on the backup & save, the whole load-merge-rewrite sequence is serialized
+#[cfg(feature = "async")]
+async fn locked_load_configs_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: &CmdLineOptionsType,
+    config_file_path: &Path,
+    should_lock: bool,
+    on_create_failure: OnCreateFailure,
+    tail_docs: &str,
+) -> Result<(Option<ConfigFileLock>, (RootConfigType, bool, Option<String>)), crate::Error> {
+    let meld_options = cmdline_options.meld_options();
+    let lock = if should_lock {
+        match lock_config_file(config_file_path, meld_options.lock_timeout).await {
+            Ok(lock) => Some(lock),
+            Err(err) if meld_options.best_effort_persist && err.is_persistence_error() => {
+                emit_warning(LoadWarningKind::FailedPersistence, "", format!("couldn't lock the config file {config_file_path:?} for rewriting it: {err}"));
+                None
+            },
+            Err(err) => return Err(err),
         }
+    } else {
+        None
+    };
+    let configs = load_configs_for(cmdline_options, config_file_path, on_create_failure, tail_docs).await?;
+    Ok((lock, configs))
+}
+
+/// Tells if `err` means the config file couldn't be created where it was supposed to -- as opposed to it being locked by someone else
+pub(crate) fn is_refused_creation(err: &crate::Error) -> bool {
+    err.is_persistence_error() && !matches!(err, crate::Error::ConfigLocked { .. })
+}
 
-        // if no existing file was found, use the first in our priority list
-        let uncreated_config_file = format!("{program_name}{}", CONFIG_SUFFIXES[0]);
-        Path::new(&uncreated_config_file).to_path_buf()
+/// Tells, in the `err` of loading the config file explicitly given in the command line, that it doesn't exist -- if that is the case
+pub(crate) fn explicit_config_file_err(err: crate::Error) -> crate::Error {
+    match err {
+        crate::Error::ConfigFileNotFound { path, .. } => crate::Error::ConfigFileNotFound {
+            path,
+            hint: "the explicitly specified config file doesn't exist: is the path correct? Omit it to use (or create) the default config file".to_string(),
+        },
+        err => err,
     }
+}
 
-    let cmdline_options: CmdLineOptionsType = parse_cmdline_args();
+/// Hints, in the `err` of loading a config file that couldn't be parsed, how to recover it -- see [CmdLineAndConfigIntegration::should_recover_config()]
+pub(crate) fn with_recovery_hint(err: crate::Error) -> crate::Error {
+    match err {
+        crate::Error::LoadingConfig { message, cause } if cause.downcast_ref::<crate::Error>().is_some_and(crate::Error::is_parsing_error) => {
+            crate::Error::LoadingConfig {
+                message: format!("{message} -- hint: if the file is corrupted, use the 'recover config' option (like `--recover-config`) \
+                                  to move it away and start over with the default values"),
+                cause,
+            }
+        },
+        err => err,
+    }
+}
+
+/// Regenerates the default config file at `config_file_path` -- or at the path given by `cmdline_options` -- backing up the existing one,
+/// then reports the outcome to `out` -- see [CmdLineAndConfigIntegration::should_reset_config()]
+#[cfg(feature = "async")]
+async fn reset_config_for<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: &CmdLineOptionsType,
+    config_file_path: Option<&Path>,
+    tail_docs: &str,
+    out: &mut impl Write,
+) -> Result<(), crate::Error> {
+    if let Some(url) = cmdline_options.config_file_path().filter(|path| config_file_path.is_none() && is_config_url(path)) {
+        return Err(crate::Error::CliParsing {
+            rendered_help: format!("error: the remote config '{url}' can't be reset: use a local config file to have it reset\n"),
+            exit_hint: 2,
+        })
+    }
+    let config_file_path = config_file_path.map_or_else(|| get_config_file_path_from(cmdline_options), Path::to_path_buf);
+    let backup_config_file_path = reset_config_file::<RootConfigType>(&config_file_path, tail_docs, &cmdline_options.meld_options().backup_policy).await?;
+    write_reset_report(out, &config_file_path, backup_config_file_path.as_deref())
+        .map_err(|err| crate::Error::Io {
+            message: format!("Error reporting the reset of the config file {config_file_path:?}"),
+            cause: err,
+        })
+}
 
-    cmdline_options
-        .config_file_path()
-        .map(Path::new)
-        .map(Path::to_path_buf)
-        .unwrap_or_else(default_config_file_path)
+/// Writes the `tail_docs` -- documenting the config model -- to `out`, for [CmdLineAndConfigIntegration::should_print_config_help()]
+pub(crate) fn write_config_help(tail_docs: &str, out: &mut impl Write) -> Result<(), crate::Error> {
+    match tail_docs {
+        "" => writeln!(out, "The config fields aren't documented: see the config file for the available fields & their values"),
+        tail_docs => writeln!(out, "{}", tail_docs.trim_end()),
+    }
+        .map_err(|err| crate::Error::Io {
+            message: "Error printing the docs of the config fields".to_string(),
+            cause: err,
+        })
+}
 
+/// The [crate::Error::ConfigHelp] outcome of `--help-config`, rendering the `tail_docs` -- see [write_config_help()]
+pub(crate) fn config_help(tail_docs: &str) -> Result<crate::Error, crate::Error> {
+    let mut rendered_docs = Vec::new();
+    write_config_help(tail_docs, &mut rendered_docs)?;
+    Ok(crate::Error::ConfigHelp { rendered_docs: String::from_utf8_lossy(&rendered_docs).into_owned() })
 }
 
-/// Parse the CLI options from the program's command line args.
-/// Most likely you'd like to use [parse_cmdline_and_merge_with_configs()]
+/// Parse the CLI options from the program's command line args, exiting the program if they are not valid
+/// (or if `--help` or `--version` were given).
+/// Most likely you'd like to use [parse_cmdline_and_merge_with_loaded_configs()]
 pub fn parse_cmdline_args<CmdLineOptionsType: Parser>() -> CmdLineOptionsType {
     <CmdLineOptionsType as Parser>::parse()
 }
 
+/// Similar to [parse_cmdline_args()], but returning [crate::Error::CliParsing], [crate::Error::CliHelp] or
+/// [crate::Error::CliVersion] instead of exiting the program
+pub fn try_parse_cmdline_args<CmdLineOptionsType: Parser>() -> Result<CmdLineOptionsType, crate::Error> {
+    <CmdLineOptionsType as Parser>::try_parse()
+        .map_err(crate::Error::from)
+}
+
+/// The in-memory counterpart of [parse_cmdline_and_merge_with_loaded_configs()], for the "I already have a config" case:
+/// parses the CLI options from the given `args` (whose first element is the program name) & merges them into `loaded_config`
+/// as in [merge_cmdline_args_with_configs()] -- no files are ever read or written, so the options telling to resolve, rewrite
+/// or reset a config file are ignored. Command line errors are reported just like in [try_parse_cmdline_args()].
+/// This is the fully injectable entry point, useful for tests & ephemeral runs.
+#[doc(alias = "merge_into")]
+pub fn effective_config_from_parts<
+    CmdLineOptionsType: Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    args: impl IntoIterator<Item = impl Into<OsString> + Clone>,
+    loaded_config: RootConfigType,
+) -> Result<RootConfigType, crate::Error> {
+    let cmdline_options = CmdLineOptionsType::try_parse_from(args)?;
+    merge_cmdline_args_with_configs(cmdline_options, loaded_config)
+}
+
 /// Returns the "effective configuration" applications should use:
 /// given the specific `root_config` and `cmdline_options`, merge the former
-/// into the latter
+/// into the latter -- applying the verbosity flags first, if the application opted in for them
+/// (see [CmdLineAndConfigIntegration::verbosity_mapping()]), then any `--set key=value` overrides
+/// (see [CmdLineAndConfigIntegration::config_overrides()])
 pub fn merge_cmdline_args_with_configs<
     CmdLineOptionsType: Parser + CmdLineAndConfigIntegration<RootConfigType>,
     RootConfigType: OgreRootConfig,
@@ -140,5 +949,1468 @@ pub fn merge_cmdline_args_with_configs<
     cmdline_options: CmdLineOptionsType,
     root_config: RootConfigType,
 ) -> Result<RootConfigType, crate::Error> {
+    let root_config = apply_verbosity_flags(&cmdline_options, root_config);
+    let root_config = apply_config_overrides(root_config, cmdline_options.config_overrides())?;
     cmdline_options.merge_with_config(root_config)
 }
+
+/// Similar to [merge_cmdline_args_with_configs()], but borrowing the `cmdline_options` -- which remain usable afterwards
+/// -- see [CmdLineAndConfigIntegration::merge_with_config_ref()]
+pub fn merge_cmdline_args_ref_with_configs<
+    CmdLineOptionsType: Parser + CmdLineAndConfigIntegration<RootConfigType> + Clone,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: &CmdLineOptionsType,
+    root_config: RootConfigType,
+) -> Result<RootConfigType, crate::Error> {
+    let root_config = apply_verbosity_flags(cmdline_options, root_config);
+    let root_config = apply_config_overrides(root_config, cmdline_options.config_overrides())?;
+    cmdline_options.merge_with_config_ref(root_config)
+}
+
+/// Similar to [merge_cmdline_args_with_configs()], but also informing the `config_path` the `root_config` was loaded from
+/// -- see [CmdLineAndConfigIntegration::merge_with_config_at()]
+pub fn merge_cmdline_args_with_configs_at<
+    CmdLineOptionsType: Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: CmdLineOptionsType,
+    root_config: RootConfigType,
+    config_path: &Path,
+) -> Result<RootConfigType, crate::Error> {
+    merge_cmdline_args_with_configs_traced(cmdline_options, root_config, Some(config_path), None)
+}
+
+/// The logic behind [merge_cmdline_args_with_configs_at()], also informing the `tracer` of the outcome of each stage, if given.
+/// Without a `config_path`, the options are merged through [CmdLineAndConfigIntegration::merge_with_config()]
+pub(crate) fn merge_cmdline_args_with_configs_traced<
+    CmdLineOptionsType: Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: CmdLineOptionsType,
+    root_config: RootConfigType,
+    config_path: Option<&Path>,
+    mut tracer: Option<&mut ProvenanceTracer<'_>>,
+) -> Result<RootConfigType, crate::Error> {
+    let root_config = apply_verbosity_flags(&cmdline_options, root_config);
+    if let Some(tracer) = tracer.as_deref_mut() {
+        let verbosity_level = cmdline_options.verbosity_mapping().and_then(|verbosity_mapping| verbosity_mapping.verbosity_args().level());
+        tracer.flagged(&root_config, if verbosity_level.is_some_and(|level| level < 0) { "--quiet" } else { "--verbose" });
+    }
+    let root_config = apply_config_overrides(root_config, cmdline_options.config_overrides())?;
+    if let Some(tracer) = tracer.as_deref_mut() {
+        tracer.overridden(&root_config);
+    }
+    let effective_config = match config_path {
+        Some(config_path) => cmdline_options.merge_with_config_at(root_config, config_path)?,
+        None => cmdline_options.merge_with_config(root_config)?,
+    };
+    if let Some(tracer) = tracer {
+        tracer.merged::<CmdLineOptionsType>(&effective_config, CmdLineOptionsType::cli_to_config_paths());
+    }
+    Ok(effective_config)
+}
+
+/// Applies the `-v` / `-q` flags to `root_config`, if `cmdline_options` opted in for them
+/// -- see [CmdLineAndConfigIntegration::verbosity_mapping()]
+fn apply_verbosity_flags<
+    CmdLineOptionsType: Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    cmdline_options: &CmdLineOptionsType,
+    root_config: RootConfigType,
+) -> RootConfigType {
+    match cmdline_options.verbosity_mapping() {
+        Some(verbosity_mapping) => match verbosity_mapping.verbosity_args().level() {
+            Some(level) => verbosity_mapping.apply_verbosity(level, root_config),
+            None => root_config,
+        },
+        None => root_config,
+    }
+}
+
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use super::*;
+    use crate::testkit::*;
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    use crate::logic::config_logic::compose_file_docs;
+    use crate::{config_file_backups, load_existing, save_to_file};
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    use crate::load_or_create_default;
+    #[cfg(feature = "yaml")]
+    use crate::{SerdeFormat, Source};
+    #[cfg(feature = "yaml")]
+    use clap::{CommandFactory, FromArgMatches};
+    #[cfg(feature = "yaml")]
+    use crate::test_commons::warnings_fixtures::{capture_warnings, captured_warnings};
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn enum_spellings_match() {
+        use clap::ValueEnum;
+        for variant in Dummy::value_variants() {
+            let cli_value = variant.to_possible_value().expect("no skipped variants").get_name().to_string();
+            let file_value = serde_yaml::to_value(variant).unwrap();
+            assert_eq!(file_value.as_str(), Some(cli_value.as_str()), "Config file & CLI spellings differ for {variant:?}");
+        }
+
+        // a value given in the command line must be written as the same string in the config file
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--sink", "stdout"]);
+        let effective_config = merge_cmdline_args_with_configs(cmdline_options, AppRootConfig::default()).unwrap();
+        let yaml_config = serde_yaml::to_string(&effective_config).unwrap();
+        assert!(yaml_config.contains("sink: stdout"), "The CLI value wasn't written as-is in the config: {yaml_config}");
+        let reloaded_config: AppRootConfig = serde_yaml::from_str(&yaml_config).unwrap();
+        assert_eq!(reloaded_config, effective_config, "Config file round-trip failed");
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn effective_config_from_parts_test() {
+        let config_path = temp_config_path("ron");
+        let loaded_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::Null) } };
+
+        let unchanged_config = effective_config_from_parts::<SampleCliOptions, _>(["test"], loaded_config.clone()).unwrap();
+        assert_eq!(unchanged_config, loaded_config, "No CLI options should keep the loaded config");
+
+        let config_path_str = config_path.to_string_lossy();
+        let effective_config = effective_config_from_parts::<SampleCliOptions, _>(["test", "--config-file", &config_path_str, "--sink", "stdout"], loaded_config).unwrap();
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdOut), "The CLI options weren't merged");
+        assert!(!config_path.exists(), "No files should have been touched");
+    }
+
+    #[test]
+    fn effective_config_from_parts_without_files() {
+        let config_dir = temp_config_dir();
+        let config_file = config_dir.join("app.config.yaml").to_string_lossy().to_string();
+        let base_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::Null) } };
+
+        let effective_config = effective_config_from_parts::<SampleCliOptions, _>(["test", "--config-file", &config_file, "--write-effective-config", "--sink", "stderror"], base_config.clone())
+            .expect("Merging into the base config failed");
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdError), "The CLI options weren't merged");
+        let effective_config = effective_config_from_parts::<SampleCliOptions, _>(["test", "--reset-config", "--set", "log_sub_config.sink=stdout"], base_config)
+            .expect("Merging the overrides failed");
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdOut), "The config overrides weren't applied");
+        assert!(!config_dir.exists(), "No files should have been touched");
+    }
+
+    #[test]
+    fn cli_errors() {
+        let result = effective_config_from_parts::<SampleCliOptions, _>(["test", "--unknown-option"], AppRootConfig::default());
+        match result {
+            Err(crate::Error::CliParsing { rendered_help, exit_hint }) => {
+                assert!(rendered_help.contains("unexpected argument '--unknown-option'"), "Unexpected error message: '{rendered_help}'");
+                assert_eq!(exit_hint, 2, "Unexpected exit code");
+            },
+            _ => panic!("An invalid option should have been reported as a CLI parsing error. Got {result:?}"),
+        }
+
+        let result = effective_config_from_parts::<SampleCliOptions, _>(["test", "--sink", "nowhere"], AppRootConfig::default());
+        assert!(matches!(result, Err(crate::Error::CliParsing { ref rendered_help, .. }) if rendered_help.contains("nowhere")),
+                "An invalid value should have been reported as a CLI parsing error. Got {result:?}");
+
+        let result = effective_config_from_parts::<SampleCliOptions, _>(["test", "--help"], AppRootConfig::default());
+        assert!(matches!(result, Err(crate::Error::CliHelp { ref rendered_help }) if rendered_help.contains("--config-file")),
+                "`--help` should have been reported. Got {result:?}");
+
+        let result = effective_config_from_parts::<SampleCliOptions, _>(["test", "--version"], AppRootConfig::default());
+        assert!(matches!(result, Err(crate::Error::CliVersion { ref rendered_version }) if rendered_version.contains(env!("CARGO_PKG_VERSION"))),
+                "`--version` should have been reported. Got {result:?}");
+    }
+
+    #[test]
+    fn merge_by_ref() {
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--sink", "stdout", "--set", "log_sub_config.sink=null"]);
+        let effective_config = merge_cmdline_args_ref_with_configs(&cmdline_options, AppRootConfig::default())
+            .expect("Merging by reference failed");
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdOut), "The CLI options weren't merged");
+        // the options are still usable
+        assert_eq!(cmdline_options.log.sink, Some(Dummy::StdOut), "The CLI options should be intact");
+        assert_eq!(cmdline_options.set, vec!["log_sub_config.sink=null"], "The CLI options should be intact");
+    }
+
+    #[test]
+    fn config_overrides() {
+        let effective_sink = |args: &[&str]| {
+            let cmdline_options = SampleCliOptions::parse_from(args);
+            merge_cmdline_args_with_configs(cmdline_options, AppRootConfig::default()).map(|config| config.log_sub_config.sink)
+        };
+        assert_eq!(effective_sink(&["test", "--set", "log_sub_config.sink=stderror"]).unwrap(), Some(Dummy::StdError), "The `--set` override wasn't applied");
+        assert_eq!(effective_sink(&["test", "--set", "log_sub_config.sink=stderror", "--sink", "stdout"]).unwrap(), Some(Dummy::StdOut),
+                   "Typed options should take precedence over `--set`");
+        assert_eq!(effective_sink(&["test", "-q", "--set", "log_sub_config.sink=stdout"]).unwrap(), Some(Dummy::StdOut),
+                   "`--set` should take precedence over the verbosity flags");
+        assert!(matches!(effective_sink(&["test", "--set", "log_sub_config.sink=nowhere"]), Err(crate::Error::CliParsing { .. })),
+                "Invalid overrides should be reported as CLI errors");
+    }
+
+    #[test]
+    fn verbosity() {
+        let effective_sink = |args: &[&str]| {
+            let cmdline_options = SampleCliOptions::parse_from(args);
+            merge_cmdline_args_with_configs(cmdline_options, AppRootConfig::default()).unwrap().log_sub_config.sink
+        };
+        assert_eq!(effective_sink(&["test"]), None, "No verbosity flags should leave the config untouched");
+        assert_eq!(effective_sink(&["test", "-q"]), Some(Dummy::Null), "Wrong mapping for `-q`");
+        assert_eq!(effective_sink(&["test", "-v"]), Some(Dummy::StdError), "Wrong mapping for `-v`");
+        assert_eq!(effective_sink(&["test", "-vv"]), Some(Dummy::StdOut), "Wrong mapping for `-vv`");
+        assert_eq!(effective_sink(&["test", "-vv", "--sink", "stderror"]), Some(Dummy::StdError), "Explicit log options should beat the verbosity flags");
+        assert!(SampleCliOptions::try_parse_from(["test", "-v", "-q"]).is_err(), "`-v` & `-q` should conflict");
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn reset_config_with_existing_file() {
+        let config_path = temp_config_path("ron");
+        let old_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::Null) } };
+        save_to_file(&old_config, "", &config_path).await.unwrap();
+        let old_config_txt = std::fs::read_to_string(&config_path).unwrap();
+
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--reset-config"]);
+        let mut out = Vec::new();
+        reset_config_for(&cmdline_options, None, "", &mut out).await.unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let backup_path = config_file_backups(&config_path).await.unwrap().pop().expect("The previous config file should have been backed up");
+
+        assert!(output.contains(&*config_path_str) && output.contains(&*backup_path.to_string_lossy()),
+                "Both the config & backup paths should have been reported. Output: '{output}'");
+        assert_eq!(std::fs::read_to_string(&backup_path).unwrap(), old_config_txt, "The backup doesn't hold the previous config");
+        let reset_config: AppRootConfig = load_existing(&config_path).await.unwrap();
+        assert_eq!(reset_config, AppRootConfig::default(), "The config file wasn't reset to the defaults");
+        _ = std::fs::remove_file(&config_path);
+        config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn reset_config_with_no_file() {
+        let config_path = temp_config_path("ron");
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--reset-config"]);
+        let mut out = Vec::new();
+        reset_config_for(&cmdline_options, None, "", &mut out).await.unwrap();
+        let output = String::from_utf8(out).unwrap();
+
+        assert!(output.contains("created"), "The creation of the config file should have been reported. Output: '{output}'");
+        let reset_config: AppRootConfig = load_existing(&config_path).await.unwrap();
+        assert_eq!(reset_config, AppRootConfig::default(), "The config file wasn't created with the defaults");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn reset_yaml_config() {
+        let config_path = temp_config_path("yaml");
+        save_to_file(&AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } }, "", &config_path).await.unwrap();
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--reset-config"]);
+        reset_config_for(&cmdline_options, None, "", &mut io::sink()).await.unwrap();
+
+        let reset_config_txt = std::fs::read_to_string(&config_path).unwrap();
+        let reset_config: AppRootConfig = serde_yaml::from_str(&reset_config_txt)
+            .unwrap_or_else(|err| panic!("The reset config file isn't YAML: {err}. Contents: '{reset_config_txt}'"));
+        assert_eq!(reset_config, AppRootConfig::default(), "The config file wasn't reset to the defaults");
+        _ = std::fs::remove_file(&config_path);
+        config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn reset_config_from_cmdline() {
+        let config_path = temp_config_path("yaml");
+        std::fs::write(&config_path, "log_sub_config:\n  sink: stdout\n").unwrap();
+        let config_path_str = config_path.to_string_lossy();
+        let args = ["test", "--config-file", &config_path_str, "--reset-config"].map(OsString::from).to_vec();
+        let result = parse_cmdline_and_meld::<SampleCliOptions, AppRootConfig>(Some(args), None, "", &MeldLayers::default(), false).await;
+
+        assert!(matches!(&result, Err(crate::Error::ConfigReset { rendered_report }) if rendered_report.contains(&*config_path_str)),
+                "The reset should have been reported as a distinguishable outcome, rather than exiting. Got {result:?}");
+        let reset_config: AppRootConfig = load_existing(&config_path).await.unwrap();
+        assert_eq!(reset_config, AppRootConfig::default(), "The config file wasn't reset to the defaults");
+        _ = std::fs::remove_file(&config_path);
+        config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[tokio::test]
+    async fn recover_config() {
+        let config_dir = &temp_config_dir();
+        std::fs::create_dir_all(config_dir).unwrap();
+        let test = |file_name: &'static str, broken_contents: &'static str| async move {
+            let config_path = config_dir.join(file_name);
+            std::fs::write(&config_path, broken_contents).unwrap();
+            let config_path_str = config_path.to_string_lossy();
+            let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--recover-config"]);
+            let (recovered_config, created_now, _): (AppRootConfig, _, _) = load_configs_for(&cmdline_options, &config_path, OnCreateFailure::Fail, "").await
+                .unwrap_or_else(|err| panic!("{file_name} wasn't recovered: {err}"));
+            assert!(created_now, "The recovered {file_name} should have been reported as recreated");
+            assert_eq!(recovered_config, AppRootConfig::default(), "The recovered {file_name} config should be the default one");
+            assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap(), AppRootConfig::default(),
+                       "A new default {file_name} should have been written");
+
+            let broken_file_prefix = format!("{file_name}.broken-");
+            let broken_files = std::fs::read_dir(config_dir).unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with(&broken_file_prefix))
+                .collect::<Vec<_>>();
+            assert!(broken_files.iter().any(|broken_file| std::fs::read_to_string(broken_file).unwrap() == broken_contents),
+                    "The broken {file_name} wasn't preserved. Candidates: {broken_files:?}");
+            broken_files.iter().for_each(|broken_file| _ = std::fs::remove_file(broken_file));
+            _ = std::fs::remove_file(&config_path);
+        };
+        test("app.config.ron", "(\n<<<<<<< HEAD\n  log_sub_config: (").await;
+        test("app.config.yaml", "log_sub_config:\n  sink: [unclosed\n").await;
+        _ = std::fs::remove_dir_all(config_dir);
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn unparseable_config_hint() {
+        let config_path = temp_config_path("ron");
+        std::fs::write(&config_path, "(log_sub_config: (sink: Some(stdout)").unwrap();
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str]);
+        let result: Result<AppRootConfig, _> = load_configs_for(&cmdline_options, &config_path, OnCreateFailure::Fail, "").await.map(|(config, ..)| config);
+        match result {
+            Err(crate::Error::LoadingConfig { ref message, .. }) if message.contains("--recover-config") => (),
+            _ => panic!("The parsing error should hint on the recovery option. Got {result:?}"),
+        }
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), "(log_sub_config: (sink: Some(stdout)", "The broken file should be left untouched");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn require_existing() {
+        let config_path = temp_config_path("ron");
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--require-existing-config"]);
+        let result: Result<AppRootConfig, _> = load_configs_for(&cmdline_options, &config_path, OnCreateFailure::Fail, "").await.map(|(config, ..)| config);
+        match result {
+            Err(crate::Error::ConfigFileNotFound { path, .. }) => assert_eq!(path, config_path, "Wrong path reported"),
+            _ => panic!("A missing config file should have been reported as an error. Got {result:?}"),
+        }
+        assert!(!config_path.exists(), "No config file should have been created when `require_existing()` is set");
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn missing_explicit_config_file() {
+        let config_path = temp_config_path("ron");
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str]);
+        let result: Result<AppRootConfig, _> = load_configs_for(&cmdline_options, &config_path, OnCreateFailure::Fail, "").await.map(|(config, ..)| config);
+        match result {
+            Err(crate::Error::ConfigFileNotFound { path, hint }) => {
+                assert_eq!(path, config_path, "Wrong path reported");
+                assert!(hint.contains("explicitly specified"), "Unexpected hint: '{hint}'");
+            },
+            _ => panic!("A missing explicit config file should have been reported as an error. Got {result:?}"),
+        }
+        assert!(!config_path.exists(), "No config file should have been created at the explicitly specified path");
+    }
+
+    #[tokio::test]
+    async fn missing_default_config_file() {
+        // the default config file is looked for (& created) in a temp dir, rather than beside the test executable
+        #[derive(clap::Parser, Debug)]
+        struct TempDirOptions {
+            #[clap(skip)]
+            config_dir: PathBuf,
+        }
+        impl CmdLineAndConfigIntegration<AppRootConfig> for TempDirOptions {
+            fn config_file_path(&self) -> Option<&str> { None }
+            fn should_write_effective_config(&self) -> bool { false }
+            fn should_show_effective_config(&self) -> bool { false }
+            fn config_search_path(&self) -> ConfigSearchPath { ConfigSearchPath::single(ConfigLocation::Custom(self.config_dir.clone())) }
+            fn merge_with_config(self, config: AppRootConfig) -> Result<AppRootConfig, crate::Error> { Ok(config) }
+        }
+        let cmdline_options = TempDirOptions { config_dir: temp_config_path("d") };
+        let config_path = get_config_file_path_from(&cmdline_options);
+        assert!(config_path.starts_with(&cmdline_options.config_dir), "The default config file should have been in the temp dir: {config_path:?}");
+        let result: Result<AppRootConfig, _> = load_configs_for(&cmdline_options, &config_path, OnCreateFailure::Fail, "").await.map(|(config, ..)| config);
+        assert!(result.is_ok(), "A missing default config file should have been created. Got {result:?}");
+        assert!(config_path.exists(), "The default config file wasn't created at {config_path:?}");
+        _ = std::fs::remove_dir_all(&cmdline_options.config_dir);
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn allow_create_at_explicit_path() {
+        let config_path = temp_config_path("ron");
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--allow-create-at-explicit-path"]);
+        let result: Result<AppRootConfig, _> = load_configs_for(&cmdline_options, &config_path, OnCreateFailure::Fail, "").await.map(|(config, ..)| config);
+        assert!(result.is_ok(), "The explicit config file should have been created. Got {result:?}");
+        assert!(config_path.exists(), "The explicit config file wasn't created at {config_path:?}");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn write_effective_config_test() {
+        let config_path = temp_config_path("ron");
+        save_to_file(&AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::Null) } }, "", &config_path).await.unwrap();
+        let old_config_txt = std::fs::read_to_string(&config_path).unwrap();
+
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--sink", "stdout", "--write-effective-config"]);
+        let effective_config: AppRootConfig = load_and_merge_configs_for(cmdline_options, "").await
+            .expect("Rewriting the effective config failed");
+        let backup_path = config_file_backups(&config_path).await.unwrap().pop().expect("The previous config file should have been backed up");
+
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdOut), "The CLI options weren't merged");
+        assert_eq!(std::fs::read_to_string(&backup_path).unwrap(), old_config_txt, "The backup doesn't hold the previous config");
+        let rewritten_config: AppRootConfig = load_existing(&config_path).await.unwrap();
+        assert_eq!(rewritten_config, effective_config, "The config file doesn't hold the effective config");
+        let rewritten_config_txt = std::fs::read_to_string(&config_path).unwrap();
+        let header = RewriteHeader::parse(&rewritten_config_txt).unwrap_or_else(|| panic!("The rewrite header is missing: '{rewritten_config_txt}'"));
+        assert_eq!(header.backup, Some(backup_path.clone()), "The backup path wasn't recorded in the rewritten config");
+        assert_eq!(header.changed_fields, vec!["log_sub_config.sink"], "Wrong fields recorded as changed by the command line");
+        assert_eq!(header.crate_version, env!("CARGO_PKG_VERSION"), "Wrong crate version recorded");
+        assert!(!rewritten_config_txt.contains("SampleCliOptions"), "The command line options shouldn't be dumped into the rewritten config: '{rewritten_config_txt}'");
+        _ = std::fs::remove_file(&config_path);
+        _ = std::fs::remove_file(crate::logic::config_logic::lock_file_path_of(&config_path));
+        config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn failed_rewrite_keeps_backup() {
+        let config_path = Path::new("/app/app.config.ron");
+        let original_txt = "(log_sub_config: (sink: Some(null)))";
+        let config_fs = MemoryFs::new().with_file(config_path, original_txt);
+        let effective_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+
+        // the config file is moved to the backup, but writing the new contents fails -- after the (empty) temp file
+        // getting the permissions of the replaced one was written
+        config_fs.fail_on_nth(FsOperation::Write, 2, std::io::ErrorKind::StorageFull);
+        let result = write_effective_config(&config_fs, &effective_config, config_path, Some(original_txt), None, &MeldOptions::default(), vec![], "").await;
+        assert!(matches!(&result, Err(crate::Error::SavingConfig { .. })), "The failed write should have been reported. Got {result:?}");
+        let [backup_path] = config_fs.file_paths().try_into()
+            .unwrap_or_else(|file_paths| panic!("Only the backup should be left behind. Got {file_paths:?}"));
+        let backup_txt = config_fs.contents(&backup_path).expect("The backup should be readable");
+        assert_eq!(backup_txt, original_txt, "The backup doesn't hold the previous config");
+        let backed_up_config: AppRootConfig = crate::config_from_str(&backup_txt, crate::SerdeFormat::Ron).unwrap();
+        assert_eq!(backed_up_config.log_sub_config.sink, Some(Dummy::Null), "The backup should still be loadable");
+
+        // ... so it may be restored
+        config_fs.heal();
+        config_fs.rename(&backup_path, config_path).await
+            .expect("The backup should be restorable");
+        assert_eq!(config_fs.contents(config_path).as_deref(), Some(original_txt), "The restored config file doesn't hold the previous config");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn write_effective_config_reads_once() {
+        use crate::logic::config_logic::CONFIG_TEXT_READS;
+        let config_path = temp_config_path("yaml");
+        let reads = || CONFIG_TEXT_READS.lock().unwrap().iter().filter(|read_path| **read_path == config_path).count();
+        save_to_file(&AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::Null) } }, "", &config_path).await.unwrap();
+
+        let reads_before = reads();
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--sink", "stdout", "--write-effective-config"]);
+        let _: AppRootConfig = load_and_merge_configs_for(cmdline_options, "").await
+            .expect("Rewriting the effective config failed");
+        assert_eq!(reads() - reads_before, 1, "The config file should have been read exactly once when rewriting it");
+        let rewritten_config_txt = std::fs::read_to_string(&config_path).unwrap();
+        let header = RewriteHeader::parse(&rewritten_config_txt).unwrap_or_else(|| panic!("The rewrite header is missing: '{rewritten_config_txt}'"));
+        assert_eq!(header.changed_fields, vec!["log_sub_config.sink"], "The header should tell what changed from the config as loaded, before the merge");
+        _ = std::fs::remove_file(&config_path);
+        _ = std::fs::remove_file(crate::logic::config_logic::lock_file_path_of(&config_path));
+        config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+    }
+
+    #[cfg(unix)]
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn write_effective_config_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let base_dir = temp_config_dir();
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let config_path = base_dir.join("secret.config.ron");
+        save_to_file(&AppRootConfig::default(), "", &config_path).await.unwrap();
+        std::fs::set_permissions(&config_path, std::fs::Permissions::from_mode(0o600)).unwrap();
+
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--sink", "stdout", "--write-effective-config"]);
+        let effective_config: AppRootConfig = load_and_merge_configs_for(cmdline_options, "").await
+            .expect("Rewriting the effective config failed");
+
+        let rewritten_config: AppRootConfig = load_existing(&config_path).await.unwrap();
+        assert_eq!(rewritten_config, effective_config, "The config file doesn't hold the effective config");
+        let mode = std::fs::metadata(&config_path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600, "The permissions of the rewritten config file should have been kept");
+        _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[cfg(unix)]
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn write_effective_config_through_symlink() {
+        let base_dir = temp_config_dir();
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let (target_path, link_path) = (base_dir.join("managed.ron"), base_dir.join("myapp.config.ron"));
+        save_to_file(&AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::Null) } }, "", &target_path).await.unwrap();
+        let old_config_txt = std::fs::read_to_string(&target_path).unwrap();
+        std::os::unix::fs::symlink(&target_path, &link_path).unwrap();
+
+        let link_path_str = link_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &link_path_str, "--sink", "stdout", "--write-effective-config"]);
+        let effective_config: AppRootConfig = load_and_merge_configs_for(cmdline_options, "").await
+            .expect("Rewriting the effective config failed");
+
+        assert!(std::fs::symlink_metadata(&link_path).unwrap().file_type().is_symlink(), "The config symlink should have been preserved");
+        let target_config: AppRootConfig = load_existing(&target_path).await.unwrap();
+        assert_eq!(target_config, effective_config, "The effective config should have been written to the link's target");
+        let backup_path = config_file_backups(&link_path).await.unwrap().pop().expect("The link's target should have been backed up");
+        assert_eq!(std::fs::read_to_string(&backup_path).unwrap(), old_config_txt, "The backup doesn't hold the previous config");
+        _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[cfg(unix)]
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    #[ignore = "needs read-only dirs, which aren't enforced for root: run with `cargo test -- --ignored` as a regular user"]
+    async fn best_effort_persist() {
+        use crate::test_commons::fs_fixtures::{read_only_dir, remove_read_only_dir};
+        let read_only_dir = read_only_dir("cli-config-best_effort_persist", &[("app.config.ron", "(log_sub_config: (sink: Some(null)))")]);
+
+        /// Rewrites the config with the given sink, persisting it on a best effort basis
+        #[derive(clap::Parser, Debug)]
+        struct BestEffortOptions {
+            #[clap(skip)]
+            config_file: String,
+            #[clap(skip)]
+            best_effort_persist: bool,
+        }
+        impl CmdLineAndConfigIntegration<AppRootConfig> for BestEffortOptions {
+            fn config_file_path(&self) -> Option<&str> { Some(&self.config_file) }
+            fn should_write_effective_config(&self) -> bool { true }
+            fn should_show_effective_config(&self) -> bool { false }
+            fn meld_options(&self) -> MeldOptions { MeldOptions { best_effort_persist: self.best_effort_persist, ..MeldOptions::default() } }
+            fn merge_with_config(self, mut config: AppRootConfig) -> Result<AppRootConfig, crate::Error> {
+                config.log_sub_config.sink = Some(Dummy::StdOut);
+                Ok(config)
+            }
+        }
+        let config_file = read_only_dir.join("app.config.ron").to_string_lossy().to_string();
+
+        let result = load_and_merge_configs_for(BestEffortOptions { config_file: config_file.clone(), best_effort_persist: false }, "").await;
+        assert!(result.is_err_and(|err| err.is_persistence_error()), "Failing to rewrite the config should be reported by default");
+
+        let effective_config = load_and_merge_configs_for(BestEffortOptions { config_file: config_file.clone(), best_effort_persist: true }, "").await
+            .expect("The effective config should have been used, despite not being persisted");
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdOut), "The CLI options weren't merged");
+        assert_eq!(std::fs::read_to_string(&config_file).unwrap(), "(log_sub_config: (sink: Some(null)))", "The config file should have been left untouched");
+        remove_read_only_dir(&read_only_dir);
+    }
+
+    #[cfg(feature = "http")]
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn remote_config() {
+        use crate::test_commons::http_fixtures::mock_config_server;
+        let base_url = mock_config_server(&[("/app.config.yaml", "application/yaml", "log_sub_config:\n  sink: stderror\n")]).await;
+        let url = format!("{base_url}/app.config.yaml");
+
+        let effective_config: AppRootConfig = load_and_merge_configs_for(SampleCliOptions::parse_from(["test", "--config-file", &url]), "").await
+            .expect("Loading the remote config failed");
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdError), "Wrong remote config loaded");
+
+        let effective_config: AppRootConfig = load_and_merge_configs_for(SampleCliOptions::parse_from(["test", "--config-file", &url, "--sink", "stdout"]), "").await
+            .expect("Loading the remote config failed");
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdOut), "The CLI options weren't merged");
+
+        let result = load_and_merge_configs_for::<_, AppRootConfig>(SampleCliOptions::parse_from(["test", "--config-file", &url, "--write-effective-config"]), "").await;
+        assert!(matches!(&result, Err(crate::Error::CliParsing { rendered_help, .. }) if rendered_help.contains(&url)),
+                "Rewriting remote configs should be rejected. Got {result:?}");
+
+        let result = load_and_merge_configs_for::<_, AppRootConfig>(SampleCliOptions::parse_from(["test", "--config-file", &format!("{base_url}/missing.yaml")]), "").await;
+        assert!(matches!(result, Err(crate::Error::RemoteConfig { .. })), "Missing remote configs should be reported. Got {result:?}");
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn on_backup_failure() {
+        use crate::test_commons::fs_fixtures::max_file_name_len;
+        // backups fail for their names exceeding the filesystem limits -- yet the lock & temporary files' names fit
+        let Some(max_file_name_len) = max_file_name_len(&std::env::temp_dir()) else {
+            eprintln!("The temp dir has no file name length limit: skipping the test");
+            return
+        };
+        let longest_suffix_len = ".lock".len().max(format!(".tmp-{}", std::process::id()).len());
+        let config_file_name = format!("{:x<len$}.ron", "on_backup_failure-", len = max_file_name_len - longest_suffix_len - ".ron".len());
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        let config_path = config_dir.join(config_file_name);
+        save_to_file(&AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::Null) } }, "", &config_path).await.unwrap();
+        let effective_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+
+        let result = write_effective_config(&RealFs, &effective_config, &config_path, None, None, &MeldOptions::default(), vec![], "").await;
+        assert!(matches!(result, Err(crate::Error::SavingConfig { .. })), "The backup failure should have been reported. Got {result:?}");
+        assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap().log_sub_config.sink, Some(Dummy::Null), "The config file should have been left untouched");
+
+        let meld_options = MeldOptions { on_backup_failure: OnBackupFailure::OverwriteWithoutBackup, ..MeldOptions::default() };
+        write_effective_config(&RealFs, &effective_config, &config_path, None, None, &meld_options, vec![], "").await
+            .expect("The config file should have been overwritten without a backup");
+        assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap(), effective_config, "The config file doesn't hold the effective config");
+        let header = RewriteHeader::parse(&std::fs::read_to_string(&config_path).unwrap());
+        assert!(header.is_some_and(|header| header.backup_failed && header.backup.is_none()), "The missing backup should have been documented");
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn unchanged_effective_config() {
+        capture_warnings();
+        let config_path = temp_config_path("yaml");
+        let config_path_str = config_path.to_string_lossy();
+        // comments & formatting differences don't count as changes
+        let config_txt = "# hand written\nlog_sub_config:   { sink: stderror }\n";
+        std::fs::write(&config_path, config_txt).unwrap();
+        let modified = std::fs::metadata(&config_path).unwrap().modified().unwrap();
+
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--write-effective-config"]);
+        let effective_config: AppRootConfig = load_and_merge_configs_for(cmdline_options, "").await
+            .expect("The no-op run failed");
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdError), "Wrong effective config");
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), config_txt, "The unchanged config shouldn't have been rewritten");
+        assert_eq!(std::fs::metadata(&config_path).unwrap().modified().unwrap(), modified, "The config file's mtime shouldn't have changed");
+        assert!(config_file_backups(&config_path).await.unwrap().is_empty(), "No backups should have been made");
+        let warnings = captured_warnings(&format!("{config_path:?}"));
+        assert!(warnings.iter().any(|warning| warning.kind == LoadWarningKind::RewriteSkipped), "The skipped rewrite should have been given to the warnings sink: {warnings:?}");
+
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--sink", "stdout", "--write-effective-config"]);
+        let effective_config: AppRootConfig = load_and_merge_configs_for(cmdline_options, "").await
+            .expect("The overriding run failed");
+        assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap(), effective_config, "The overridden config should have been rewritten");
+        _ = std::fs::remove_file(&config_path);
+        _ = std::fs::remove_file(config_path.with_extension("yaml.lock"));
+        config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[tokio::test]
+    async fn layout_preserving_rewrites() {
+        let effective_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+        let meld_options = MeldOptions { rewrite_style: RewriteStyle::PreserveLayout, ..MeldOptions::default() };
+        let commented_configs = [
+            ("yaml", "# hand written\n\nlog_sub_config:   # the logs\n  # where they go\n  sink: stderror   # not stdout\n# the end\n", "stderror", "stdout"),
+            ("ron", "// hand written\n(\n    log_sub_config: ( // the logs\n        /* where they go */\n        sink: Some(stderror), // not stdout\n    ),\n)\n// the end\n", "stderror", "stdout"),
+        ];
+        for (extension, config_txt, old_value, new_value) in commented_configs {
+            let config_path = temp_config_path(extension);
+            std::fs::write(&config_path, config_txt).unwrap();
+            write_effective_config(&RealFs, &effective_config, &config_path, Some(config_txt), None, &meld_options, vec![], "").await
+                .unwrap_or_else(|err| panic!("Rewriting the {extension} config failed: {err}"));
+            assert_eq!(std::fs::read_to_string(&config_path).unwrap(), config_txt.replacen(old_value, new_value, 1),
+                       "Everything but the changed {extension} value should have been kept");
+            assert_eq!(config_file_backups(&config_path).await.unwrap().len(), 1, "The {extension} config should have been backed up");
+            _ = std::fs::remove_file(&config_path);
+            config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+        }
+
+        // layouts that can't be edited in place are regenerated
+        let config_path = temp_config_path("yaml");
+        let flow_config_txt = "{log_sub_config: {sink: stderror}}  # flow style\n";
+        std::fs::write(&config_path, flow_config_txt).unwrap();
+        write_effective_config(&RealFs, &effective_config, &config_path, Some(flow_config_txt), None, &meld_options, vec![], "").await
+            .expect("Regenerating the config failed");
+        assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap(), effective_config, "The config should have been regenerated");
+        _ = std::fs::remove_file(&config_path);
+        config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn external_modification() {
+        let config_path = temp_config_path("ron");
+        save_to_file(&AppRootConfig::default(), "", &config_path).await.unwrap();
+        let effective_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+
+        // unmodified files are rewritten normally
+        let loaded_fingerprint = LoadedFileFingerprint::of(&config_path).await.unwrap().expect("The config file should exist");
+        write_effective_config(&RealFs, &effective_config, &config_path, None, Some(&loaded_fingerprint), &MeldOptions::default(), vec![], "").await
+            .expect("Rewriting the unmodified config file failed");
+        assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap(), effective_config, "The config file wasn't rewritten");
+
+        // files edited after being loaded are left alone
+        let loaded_fingerprint = LoadedFileFingerprint::of(&config_path).await.unwrap().expect("The config file should exist");
+        let edited_config_txt = "(log_sub_config: (sink: Some(stderror)))";
+        std::fs::write(&config_path, edited_config_txt).unwrap();
+        let result = write_effective_config(&RealFs, &effective_config, &config_path, None, Some(&loaded_fingerprint), &MeldOptions::default(), vec![], "").await;
+        assert!(matches!(&result, Err(crate::Error::ConfigChangedOnDisk { path, .. }) if path == &config_path), "The external modification should have been reported. Got {result:?}");
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), edited_config_txt, "The edits should have been kept");
+
+        // ... unless forced
+        let meld_options = MeldOptions { force_overwrite: true, ..MeldOptions::default() };
+        write_effective_config(&RealFs, &effective_config, &config_path, None, Some(&loaded_fingerprint), &meld_options, vec![], "").await
+            .expect("Forcing the rewrite failed");
+        assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap(), effective_config, "The config file wasn't overwritten");
+        _ = std::fs::remove_file(&config_path);
+        config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn frozen_config() {
+        let config_path = temp_config_path("yaml");
+        let frozen_config_txt = "# frozen\nlog_sub_config:\n  sink: stderror\n";
+        std::fs::write(&config_path, frozen_config_txt).unwrap();
+        let effective_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+        let meld_options = MeldOptions { force_overwrite: true, ..MeldOptions::default() };
+        let result = write_effective_config(&RealFs, &effective_config, &config_path, Some(frozen_config_txt), None, &meld_options, vec![], "").await;
+        assert!(matches!(&result, Err(crate::Error::ConfigFrozen { path, .. }) if path == &config_path), "The rewrite of the frozen config should have been refused. Got {result:?}");
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), frozen_config_txt, "The frozen config file should have been left untouched");
+        assert!(config_file_backups(&config_path).await.unwrap().is_empty(), "No backup should have been made");
+
+        assert!(is_frozen("(\n  // frozen\n  log_sub_config: (sink: None),\n)"), "The RON marker wasn't recognized");
+        assert!(!is_frozen("# not frozen\nlog_sub_config:\n  sink: frozen\n"), "Only comment lines holding just the marker should freeze the config");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[tokio::test]
+    async fn docs_kept_on_rewrites() {
+        let tail_docs = "I am the docs\nof the config fields";
+        for (extension, expected_docs) in [("ron", "\nI am the docs\nof the config fields\n"), ("yaml", "\n# I am the docs\n# of the config fields")] {
+            let config_path = temp_config_path(extension);
+            save_to_file(&AppRootConfig::default(), tail_docs, &config_path).await.unwrap();
+            let effective_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+            write_effective_config(&RealFs, &effective_config, &config_path, None, None, &MeldOptions::default(), vec![], tail_docs).await
+                .expect("The effective config should have been written");
+            let rewritten_txt = std::fs::read_to_string(&config_path).unwrap();
+            let header_position = rewritten_txt.find("REWRITE HEADER").expect("The rewrite header is missing");
+            let docs_position = rewritten_txt.find(expected_docs).unwrap_or_else(|| panic!("The docs should have survived the {extension} rewrite: '{rewritten_txt}'"));
+            assert!(header_position < docs_position, "The rewrite header should come before the docs: '{rewritten_txt}'");
+            assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap(), effective_config, "The docs broke the rewritten {extension} config");
+            for backup in config_file_backups(&config_path).await.unwrap() {
+                _ = std::fs::remove_file(backup);
+            }
+            _ = std::fs::remove_file(&config_path);
+        }
+        assert_eq!(compose_file_docs("", tail_docs), tail_docs, "Without a header, the docs should be kept as-is");
+        assert_eq!(compose_file_docs("header\n", ""), "header\n", "Without docs, the header should be kept as-is");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn provenance() {
+
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(default)]
+        struct ServiceConfig {
+            name: String,
+            port: u16,
+            color: bool,
+            log: ServiceLogConfig,
+        }
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(default)]
+        struct ServiceLogConfig {
+            file: String,
+            level: u8,
+            color: bool,
+        }
+        impl OgreRootConfig for ServiceConfig {}
+
+        /// Sets the fields named after its options
+        #[derive(clap::Parser, Debug)]
+        struct ServiceOptions {
+            #[clap(long)]
+            config_file: String,
+            #[clap(long)]
+            level: Option<u8>,
+            #[clap(long, env = "CLI_CONFIG_PROVENANCE_COLOR")]
+            color: Option<bool>,
+            #[clap(long = "set")]
+            set: Vec<String>,
+        }
+        impl CmdLineAndConfigIntegration<ServiceConfig> for ServiceOptions {
+            fn config_file_path(&self) -> Option<&str> { Some(&self.config_file) }
+            fn should_write_effective_config(&self) -> bool { false }
+            fn should_show_effective_config(&self) -> bool { false }
+            fn config_overrides(&self) -> &[String] { &self.set }
+            fn merge_with_config(self, mut config: ServiceConfig) -> Result<ServiceConfig, crate::Error> {
+                config.log.level = self.level.unwrap_or(config.log.level);
+                config.color = self.color.unwrap_or(config.color);
+                // not set by an option of its own, despite being named like one
+                config.log.color = config.color;
+                Ok(config)
+            }
+            fn cli_to_config_paths() -> &'static [(&'static str, &'static str)] {
+                &[("level", "log.level")]
+            }
+        }
+
+        let config_path = temp_config_path("yaml");
+        std::fs::write(&config_path, "log:\n  file: /var/log/service.log\n  level: 1\n").unwrap();
+        std::env::set_var("CLI_CONFIG_PROVENANCE_COLOR", "true");
+        let arg_matches = ServiceOptions::command()
+            .try_get_matches_from(["test", "--config-file", &config_path.to_string_lossy(), "--level", "3", "--set", "port=8080"])
+            .unwrap();
+        let cmdline_options = ServiceOptions::from_arg_matches(&arg_matches).unwrap();
+        let (LoadedConfig { config: effective_config, .. }, provenance) = load_and_merge_configs_traced_for(cmdline_options, None, "", Some(&arg_matches), true).await
+            .expect("Melding the configs failed");
+        std::env::remove_var("CLI_CONFIG_PROVENANCE_COLOR");
+        assert_eq!(effective_config.log, ServiceLogConfig { file: "/var/log/service.log".to_string(), level: 3, color: true }, "Wrong effective config");
+        assert_eq!(provenance.source_of("name"), Some(&Source::Default), "`name` wasn't set by anyone");
+        assert_eq!(provenance.source_of("log.file"), Some(&Source::ConfigFile(config_path.clone())), "`log.file` came from the config file");
+        assert_eq!(provenance.source_of("log.level"), Some(&Source::CliFlag("--level".to_string())), "`log.level` was overridden in the command line");
+        assert_eq!(provenance.source_of("port"), Some(&Source::Override), "`port` came from a config override");
+        assert_eq!(provenance.source_of("color"), Some(&Source::EnvVar("CLI_CONFIG_PROVENANCE_COLOR".to_string())), "`color` came from an environment variable");
+        assert_eq!(provenance.source_of("log.color"), Some(&Source::CliFlag("<command line>".to_string())), "`log.color` isn't where the `--color` option goes");
+        assert_eq!(provenance.len(), 6, "Only the leaves should be tracked: {provenance:?}");
+
+        let annotated_config = annotated_effective_config(&effective_config, &provenance).expect("The config couldn't be annotated");
+        assert!(annotated_config.contains("port: 8080    # from a config override\n"), "Wrong annotations: '{annotated_config}'");
+        assert!(annotated_config.contains("level: 3    # from the command line option --level\n"), "Wrong nested annotations: '{annotated_config}'");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn loaded_config() {
+        let config_path = temp_config_path("yaml");
+        let cmdline_options = || SampleCliOptions::parse_from(["test", "--config-file", &config_path.to_string_lossy(), "--allow-create-at-explicit-path", "--sink", "stdout"]);
+
+        let (loaded_config, _) = load_and_merge_configs_traced_for::<_, AppRootConfig>(cmdline_options(), None, "", None, false).await
+            .expect("Melding the configs failed");
+        assert_eq!(loaded_config.config.log_sub_config.sink, Some(Dummy::StdOut), "The command line options should have been merged");
+        assert_eq!(loaded_config.path, config_path, "Wrong path");
+        assert_eq!(loaded_config.format, SerdeFormat::Yaml, "Wrong format");
+        assert!(loaded_config.created_now, "The config file should have been reported as created");
+        assert_eq!(loaded_config.fingerprint, LoadedFileFingerprint::of(&config_path).await.unwrap(), "Wrong fingerprint");
+
+        let (loaded_config, _) = load_and_merge_configs_traced_for::<_, AppRootConfig>(cmdline_options(), None, "", None, false).await
+            .expect("Melding the configs failed");
+        assert_eq!(loaded_config.path, config_path, "Wrong path");
+        assert_eq!(loaded_config.format, SerdeFormat::Yaml, "Wrong format");
+        assert!(!loaded_config.created_now, "The config file already existed");
+        assert!(loaded_config.fingerprint.is_some(), "The existing config file should have been fingerprinted");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn changes_vs_file() {
+        let config_path = Path::new("app.config.yaml");
+        let loaded_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdError) } };
+        let effective_config = SampleCliOptions::parse_from(["test", "--sink", "stdout"]).merge_with_config(loaded_config.clone()).unwrap();
+        let report = changes_vs_file_report(&crate::diff_configs(&loaded_config, &effective_config), config_path);
+        assert_eq!(report, "EFFECTIVE CONFIG CHANGES VS THE CONFIG FILE \"app.config.yaml\":\n  log_sub_config.sink: \"stderror\" -> \"stdout\"\n",
+                   "Wrong changes report");
+        let report = changes_vs_file_report(&crate::diff_configs(&loaded_config, &loaded_config), config_path);
+        assert!(report.ends_with(":\n  (none)\n"), "Unchanged configs should be reported as such: '{report}'");
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn config_file_exists_test() {
+        let config_path = temp_config_path("ron");
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str]);
+
+        assert!(!config_file_exists_for(&cmdline_options), "The config file shouldn't exist yet");
+        save_to_file(&AppRootConfig::default(), "", &config_path).await.unwrap();
+        assert!(config_file_exists_for(&cmdline_options), "The config file should exist");
+        std::fs::remove_file(&config_path).unwrap();
+        assert!(!config_file_exists_for(&cmdline_options), "The config file shouldn't exist anymore");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn resolved_config_file_path() {
+        let config_path = temp_config_path("yaml");
+        let cmdline_options = || SampleCliOptions::parse_from(["test", "--config-file", &config_path.to_string_lossy(), "--allow-create-at-explicit-path"]);
+        assert_eq!(get_config_file_path_from(&cmdline_options()), config_path, "The explicitly given config file should have been resolved");
+        let (loaded_config, _) = load_and_merge_configs_traced_for::<_, AppRootConfig>(cmdline_options(), None, "", None, false).await.unwrap();
+        assert_eq!(loaded_config.path, config_path, "The returned path should be the resolved one");
+        assert!(loaded_config.created_now && config_path.exists(), "The config file should have been created at the returned path");
+        std::fs::write(&config_path, "log_sub_config:\n  sink: stdout\n").unwrap();
+        let (loaded_config, _) = load_and_merge_configs_traced_for::<_, AppRootConfig>(cmdline_options(), None, "", None, false).await.unwrap();
+        assert_eq!(loaded_config.path, config_path, "The returned path should be the resolved one");
+        assert_eq!(loaded_config.config.log_sub_config.sink, Some(Dummy::StdOut), "The config should have been read from the returned path");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn probed_config_paths_test() {
+        let probed_paths = probed_config_paths::<SampleCliOptions, AppRootConfig>();
+        let has_candidate = |suffix: &str| probed_paths.iter().any(|path| path.to_string_lossy().ends_with(suffix));
+        assert!(has_candidate(".config.ron") && has_candidate(".config.yaml"), "Both RON & YAML candidates should have been probed. Got {probed_paths:?}");
+        assert!(probed_paths[0].to_string_lossy().ends_with(".config.ron"), "RON should be probed first. Got {probed_paths:?}");
+        let cmdline_options = SampleCliOptions::parse_from(["test"]);
+        assert!(probed_paths.contains(&get_config_file_path_from(&cmdline_options)), "The default config file path should be one of the probed ones");
+    }
+
+    /// A [SearchContext] for `program_path`, with nothing else known about the environment
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    fn search_context_for(program_path: &str) -> SearchContext {
+        SearchContext {
+            program_path: PathBuf::from(program_path),
+            invocation_path: PathBuf::from(program_path),
+            current_dir: PathBuf::from("."),
+            platform_config_dir: None,
+            system_config_dir: None,
+            env_var: |_| None,
+            user_dir_fallback: false,
+        }
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn config_file_names() {
+        let candidates = |program_path: &str, config_location| search_context_for(program_path).location_candidates(&config_location);
+
+        assert_eq!(candidates("/opt/myapp/myapp.exe", ConfigLocation::BesideExecutable)[0], PathBuf::from("/opt/myapp/myapp.config.ron"), "The '.exe' extension should have been stripped");
+        assert_eq!(candidates("/opt/myapp/MYAPP.EXE", ConfigLocation::BesideExecutable)[0], PathBuf::from("/opt/myapp/MYAPP.config.ron"), "The '.exe' extension should have been stripped regardless of its case");
+        assert_eq!(candidates("/opt/myapp/myapp.v2", ConfigLocation::BesideExecutable)[0], PathBuf::from("/opt/myapp/myapp.v2.config.ron"), "Only the '.exe' extension should be stripped");
+        assert_eq!(candidates("./target/debug/myapp", ConfigLocation::Custom(PathBuf::from("/etc/myapp")))[0], PathBuf::from("/etc/myapp/myapp.config.ron"),
+                   "The invocation directory shouldn't leak into other locations");
+        assert_eq!(candidates("myapp.exe", ConfigLocation::BesideExecutable)[0], PathBuf::from("myapp.config.ron"), "A bare program name should be kept relative");
+        // compatibility with the old behavior
+        assert_eq!(candidates("./target/debug/myapp.exe", ConfigLocation::InvocationPath), [PathBuf::from("./target/debug/myapp.exe.config.ron"), PathBuf::from("./target/debug/myapp.exe.config.yaml")],
+                   "The invocation path should have been used as-is");
+        assert!(candidates("/usr/bin/myapp", ConfigLocation::SystemConfigDir).is_empty(), "An unknown system config dir should have no candidates");
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[tokio::test]
+    async fn config_location() {
+        let base_dir = temp_config_dir();
+        let search_context = search_context_for("/usr/bin/myapp");
+
+        let beside_executable = search_context.location_candidates(&ConfigLocation::BesideExecutable);
+        assert_eq!(beside_executable, [PathBuf::from("/usr/bin/myapp.config.ron"), PathBuf::from("/usr/bin/myapp.config.yaml")], "Wrong candidates beside the executable");
+
+        let custom = search_context.location_candidates(&ConfigLocation::Custom(base_dir.join("custom")));
+        assert_eq!(custom, [base_dir.join("custom/myapp.config.ron"), base_dir.join("custom/myapp.config.yaml")], "Wrong candidates in the custom dir");
+
+        let platform = SearchContext { platform_config_dir: Some(base_dir.join("platform")), ..search_context_for("/usr/bin/myapp") }
+            .location_candidates(&ConfigLocation::PlatformConfigDir);
+        assert_eq!(platform, [base_dir.join("platform/myapp/myapp.config.ron"), base_dir.join("platform/myapp/myapp.config.yaml")], "Wrong candidates in the platform dir");
+        let unknown_platform = search_context.location_candidates(&ConfigLocation::PlatformConfigDir);
+        assert_eq!(unknown_platform, beside_executable, "An unknown platform dir should fall back to the executable's location");
+
+        // first run: the config dir is created
+        let _: AppRootConfig = load_or_create_default(&platform[0], "").await.unwrap();
+        assert!(platform[0].exists(), "The default config file wasn't created in the platform dir");
+
+        // existing files in the chosen dir are discovered
+        #[derive(clap::Parser, Debug)]
+        struct LocatedOptions {
+            #[clap(skip)]
+            config_dir: PathBuf,
+        }
+        impl CmdLineAndConfigIntegration<AppRootConfig> for LocatedOptions {
+            fn config_file_path(&self) -> Option<&str> { None }
+            fn should_write_effective_config(&self) -> bool { false }
+            fn should_show_effective_config(&self) -> bool { false }
+            fn config_location(&self) -> ConfigLocation { ConfigLocation::Custom(self.config_dir.clone()) }
+            fn merge_with_config(self, config: AppRootConfig) -> Result<AppRootConfig, crate::Error> { Ok(config) }
+        }
+        let cmdline_options = LocatedOptions { config_dir: base_dir.join("custom") };
+        let resolution = resolve_config_file_path(&cmdline_options);
+        let [ron_candidate, yaml_candidate] = &resolution.considered[..] else { panic!("Two candidates were expected") };
+        assert_eq!(resolution.chosen, *ron_candidate, "With no existing files, the RON one should be used");
+        assert!(!resolution.exists, "No config file should exist yet");
+        std::fs::create_dir_all(base_dir.join("custom")).unwrap();
+        std::fs::write(yaml_candidate, "log_sub_config:\n  sink: stdout\n").unwrap();
+        assert_eq!(get_config_file_path_from(&cmdline_options), *yaml_candidate, "The existing YAML config should have been discovered");
+        _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn current_dir_first() {
+        let base_dir = temp_config_dir();
+        let (current_dir, program_dir) = (base_dir.join("project"), base_dir.join("bin"));
+        let search_context = SearchContext {
+            current_dir: current_dir.clone(),
+            ..search_context_for(&program_dir.join("app").to_string_lossy())
+        };
+        let search_path = ConfigSearchPath::current_dir_first(ConfigLocation::BesideExecutable);
+
+        let resolution = search_context.resolve(&search_path);
+        assert_eq!(resolution.chosen, program_dir.join("app.config.ron"), "With no existing files, the config should be created beside the executable");
+        assert_eq!(resolution.considered[..2], [current_dir.join("app.config.ron"), current_dir.join("app.config.yaml")], "The current dir should be searched first");
+
+        for config_path in [program_dir.join("app.config.ron"), current_dir.join("app.config.yaml")] {
+            std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+            std::fs::write(&config_path, "").unwrap();
+        }
+        assert_eq!(search_context.resolve(&search_path).chosen, current_dir.join("app.config.yaml"), "The config in the current dir should be preferred");
+        assert_eq!(ConfigSearchPath::current_dir_first(ConfigLocation::CurrentDir), ConfigSearchPath::single(ConfigLocation::CurrentDir),
+                   "The current dir shouldn't be searched twice");
+
+        // opting in, through the command line options
+        #[derive(clap::Parser, Debug)]
+        struct CurrentDirFirstOptions {}
+        impl CmdLineAndConfigIntegration<AppRootConfig> for CurrentDirFirstOptions {
+            fn config_file_path(&self) -> Option<&str> { None }
+            fn should_write_effective_config(&self) -> bool { false }
+            fn should_show_effective_config(&self) -> bool { false }
+            fn prefer_current_dir_config(&self) -> bool { true }
+            fn merge_with_config(self, config: AppRootConfig) -> Result<AppRootConfig, crate::Error> { Ok(config) }
+        }
+        assert_eq!(CurrentDirFirstOptions {}.config_search_path(), search_path, "Opting in should search the current dir first");
+        assert_eq!(<SampleCliOptions as clap::Parser>::parse_from(["test"]).config_search_path(), ConfigSearchPath::single(ConfigLocation::BesideExecutable),
+                   "By default, only the config location should be searched");
+        _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn config_search_path() {
+        let base_dir = temp_config_dir();
+        let [current_dir, platform_dir, system_dir] = ["cwd", "platform", "system"].map(|dir| base_dir.join(dir));
+        // `env_var` can't capture the file it points to
+        static ENV_FILE: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+        let env_file = ENV_FILE.get_or_init(|| base_dir.join("from-env.config.ron")).clone();
+        let search_context = SearchContext {
+            current_dir: current_dir.clone(),
+            platform_config_dir: Some(platform_dir.clone()),
+            system_config_dir: Some(system_dir.clone()),
+            env_var: |name| (name == "MYAPP_CONFIG").then(|| ENV_FILE.get().expect("set above").into()),
+            ..search_context_for("/usr/bin/myapp")
+        };
+        let search_path = ConfigSearchPath::standard("MYAPP_CONFIG");
+        let create = |path: &Path| {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "()").unwrap();
+        };
+
+        // nothing exists: created in the platform dir, after considering every candidate
+        let resolution = search_context.resolve(&search_path);
+        assert_eq!(resolution.chosen, platform_dir.join("myapp/myapp.config.ron"), "With no existing files, the config should be created in the platform dir");
+        assert!(!resolution.exists, "No config file should exist yet");
+        assert_eq!(resolution.considered, [
+            env_file.clone(),
+            current_dir.join("myapp.config.ron"), current_dir.join("myapp.config.yaml"),
+            platform_dir.join("myapp/myapp.config.ron"), platform_dir.join("myapp/myapp.config.yaml"),
+            system_dir.join("myapp/myapp.config.ron"), system_dir.join("myapp/myapp.config.yaml"),
+        ], "Wrong candidates considered");
+
+        // each location, from the lowest to the highest precedence
+        let steps = [
+            (system_dir.join("myapp/myapp.config.yaml"), "the system dir"),
+            (platform_dir.join("myapp/myapp.config.yaml"), "the platform dir"),
+            (platform_dir.join("myapp/myapp.config.ron"), "RON over YAML in the same dir"),
+            (current_dir.join("myapp.config.yaml"), "the current dir"),
+            (env_file.clone(), "the env var"),
+        ];
+        for (config_file, precedence) in steps {
+            create(&config_file);
+            let resolution = search_context.resolve(&search_path);
+            assert_eq!(resolution.chosen, config_file, "Wrong precedence for {precedence}");
+            assert!(resolution.exists, "The chosen config file should exist for {precedence}");
+        }
+
+        // an unset env var is simply not considered
+        let unset_env = SearchContext { env_var: |_| None, ..search_context };
+        assert_eq!(unset_env.resolve(&search_path).chosen, current_dir.join("myapp.config.yaml"), "The current dir should be used when the env var isn't set");
+        _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn user_dir_fallback() {
+        let base_dir = temp_config_dir();
+        let (program_dir, user_dir) = (base_dir.join("Program Files/MyApp"), base_dir.join("AppData/Roaming"));
+        let search_context = SearchContext {
+            platform_config_dir: Some(user_dir.clone()),
+            user_dir_fallback: true,
+            ..search_context_for(&program_dir.join("myapp.exe").to_string_lossy())
+        };
+        let search_path = ConfigSearchPath::single(ConfigLocation::BesideExecutable);
+
+        let resolution = search_context.resolve(&search_path);
+        assert_eq!(resolution.chosen, program_dir.join("myapp.config.ron"), "The config should be created beside the executable, if allowed");
+        assert_eq!(resolution.fallback, Some(user_dir.join("myapp/myapp.config.ron")),
+                   "The config should be created in the per-user dir if creating it beside the executable is refused");
+        assert_eq!(resolution.considered, [
+            program_dir.join("myapp.config.ron"), program_dir.join("myapp.config.yaml"),
+            user_dir.join("myapp/myapp.config.ron"), user_dir.join("myapp/myapp.config.yaml"),
+        ], "The per-user dir should also be searched, for the created config to be found in the next runs");
+
+        let no_user_dir = SearchContext { platform_config_dir: None, ..search_context };
+        let resolution = no_user_dir.resolve(&search_path);
+        assert_eq!(resolution.fallback, None, "Without a per-user dir, there should be nowhere to fall back to");
+        assert_eq!(resolution.considered, [program_dir.join("myapp.config.ron"), program_dir.join("myapp.config.yaml")],
+                   "Without a per-user dir, no other candidates should have been considered");
+
+        let no_fallback = SearchContext { platform_config_dir: Some(user_dir.clone()), user_dir_fallback: false, ..no_user_dir };
+        assert_eq!(no_fallback.resolve(&search_path).fallback, None, "The fallback should be opt-in");
+    }
+
+    #[cfg(unix)]
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    #[ignore = "needs read-only dirs, which aren't enforced for root: run with `cargo test -- --ignored` as a regular user"]
+    async fn refused_creation_falls_back() {
+        use crate::test_commons::fs_fixtures::{read_only_dir, remove_read_only_dir};
+        let program_dir = read_only_dir("cli-config-refused_creation_falls_back", &[]);
+        let fallback_dir = temp_config_dir();
+        let (config_path, fallback_path) = (program_dir.join("myapp.config.ron"), fallback_dir.join("myapp/myapp.config.ron"));
+        let cmdline_options = SampleCliOptions::parse_from(["test"]);
+
+        let (used_path, _lock, (config, created_now, _)): (_, _, (AppRootConfig, _, _)) =
+            lock_and_load_configs_for(&cmdline_options, config_path.clone(), Some(fallback_path.clone()), true, "").await
+                .expect("The config should have been created in the fallback path");
+        assert_eq!(used_path, fallback_path, "The fallback path should have been used");
+        assert!(created_now && fallback_path.exists(), "The config should have been created in the fallback path");
+        assert_eq!(config, AppRootConfig::default(), "The default config should have been used");
+
+        let result = lock_and_load_configs_for::<_, AppRootConfig>(&cmdline_options, config_path, None, false, "").await;
+        assert!(result.is_err_and(|err| err.is_persistence_error()), "Without a fallback, the refused creation should have been reported");
+        remove_read_only_dir(&program_dir);
+        _ = std::fs::remove_dir_all(&fallback_dir);
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn merge_with_config_at() {
+
+        /// Options that record the config path they were merged with into the config
+        #[derive(clap::Parser, Debug)]
+        struct PathAwareOptions {
+            #[clap(long)]
+            config_file: Option<String>,
+        }
+        impl CmdLineAndConfigIntegration<AppRootConfig> for PathAwareOptions {
+            fn config_file_path(&self) -> Option<&str> { self.config_file.as_deref() }
+            fn should_write_effective_config(&self) -> bool { false }
+            fn should_show_effective_config(&self) -> bool { false }
+            fn allow_create_at_explicit_path(&self) -> bool { true }
+            fn merge_with_config(self, _config: AppRootConfig) -> Result<AppRootConfig, crate::Error> {
+                panic!("`merge_with_config_at()` should have been called instead")
+            }
+            fn merge_with_config_at(self, mut config: AppRootConfig, config_path: &Path) -> Result<AppRootConfig, crate::Error> {
+                if Some(config_path) == self.config_file.as_deref().map(Path::new) {
+                    config.log_sub_config.sink = Some(Dummy::StdOut);
+                }
+                Ok(config)
+            }
+        }
+
+        let config_path = temp_config_path("ron");
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = PathAwareOptions::parse_from(["test", "--config-file", &config_path_str]);
+        let effective_config: AppRootConfig = load_and_merge_configs_for(cmdline_options, "").await.unwrap();
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdOut), "The config file path wasn't passed through to `merge_with_config_at()`");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[tokio::test]
+    async fn effective_config_output_path() {
+
+        /// Writes the effective config to `output_file`, leaving the config file untouched
+        #[derive(clap::Parser, Debug)]
+        struct MaterializingOptions {
+            #[clap(skip)]
+            config_file: String,
+            #[clap(skip)]
+            output_file: PathBuf,
+        }
+        impl CmdLineAndConfigIntegration<AppRootConfig> for MaterializingOptions {
+            fn config_file_path(&self) -> Option<&str> { Some(&self.config_file) }
+            fn should_write_effective_config(&self) -> bool { false }
+            fn should_show_effective_config(&self) -> bool { false }
+            fn effective_config_output_path(&self) -> Option<EffectiveConfigTarget> { Some(EffectiveConfigTarget::Path(self.output_file.clone())) }
+            fn merge_with_config(self, mut config: AppRootConfig) -> Result<AppRootConfig, crate::Error> {
+                config.log_sub_config.sink = Some(Dummy::StdOut);
+                Ok(config)
+            }
+        }
+
+        let base_dir = temp_config_dir();
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let config_path = base_dir.join("app.config.yaml");
+        let config_txt = "log_sub_config:\n  sink: null\n";
+        std::fs::write(&config_path, config_txt).unwrap();
+        let output_path = base_dir.join("rendered").join("app.effective.ron");
+
+        let cmdline_options = MaterializingOptions { config_file: config_path.to_string_lossy().to_string(), output_file: output_path.clone() };
+        let effective_config: AppRootConfig = load_and_merge_configs_for(cmdline_options, "I am the docs").await
+            .expect("Writing the effective config elsewhere failed");
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdOut), "The CLI options weren't merged");
+        let output_txt = std::fs::read_to_string(&output_path).expect("The effective config should have been written to the output path");
+        assert!(output_txt.contains("I am the docs"), "The docs should follow the written effective config: '{output_txt}'");
+        let written_config: AppRootConfig = crate::load_from_file(&output_path).await.unwrap().unwrap();
+        assert_eq!(written_config, effective_config, "The output file should hold the effective config, in the format of its extension");
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), config_txt, "The config file should have been left untouched");
+        let files_count = std::fs::read_dir(&base_dir).unwrap().count();
+        assert_eq!(files_count, 2, "No backups should have been made");
+        _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn config_help() {
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--help-config"]);
+        assert!(cmdline_options.should_print_config_help(), "`--help-config` should ask for the config help");
+        let mut out = Vec::new();
+        write_config_help("log_sub_config:\n  sink: where the logs go\n", &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "log_sub_config:\n  sink: where the logs go\n", "The config docs should have been printed as-is");
+        let mut out = Vec::new();
+        write_config_help("", &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("aren't documented"), "Missing docs should have been reported");
+    }
+
+    #[tokio::test]
+    async fn config_help_from_cmdline() {
+        let args = ["test", "--help-config"].map(OsString::from).to_vec();
+        let result = parse_cmdline_and_meld::<SampleCliOptions, AppRootConfig>(Some(args), None, "log_sub_config:\n  sink: where the logs go\n", &MeldLayers::default(), false).await;
+        assert!(matches!(&result, Err(crate::Error::ConfigHelp { rendered_docs }) if rendered_docs == "log_sub_config:\n  sink: where the logs go\n"),
+                "`--help-config` should have been reported with the config docs, rather than exiting. Got {result:?}");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn post_load_hook() {
+
+        /// A config with paths relative to the config file
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(default)]
+        struct StorageConfig {
+            data_dir: PathBuf,
+            replicas: u8,
+        }
+        impl OgreRootConfig for StorageConfig {
+            fn post_load(&mut self, context: &crate::LoadContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+                if self.replicas > 9 {
+                    return Err(format!("too many replicas: {}", self.replicas).into())
+                }
+                self.data_dir = context.config_dir().ok_or("configs should be loaded from files")?.join(&self.data_dir);
+                Ok(())
+            }
+        }
+
+        #[derive(clap::Parser, Debug)]
+        struct StorageOptions {
+            #[clap(long)]
+            config_file: Option<String>,
+            #[clap(long)]
+            replicas: Option<u8>,
+        }
+        impl CmdLineAndConfigIntegration<StorageConfig> for StorageOptions {
+            fn config_file_path(&self) -> Option<&str> { self.config_file.as_deref() }
+            fn should_write_effective_config(&self) -> bool { true }
+            fn should_show_effective_config(&self) -> bool { false }
+            fn merge_with_config(self, mut config: StorageConfig) -> Result<StorageConfig, crate::Error> {
+                config.replicas = self.replicas.unwrap_or(config.replicas);
+                Ok(config)
+            }
+        }
+
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        let config_path = config_dir.join("storage.yaml");
+        std::fs::write(&config_path, "data_dir: data\nreplicas: 1\n").unwrap();
+        let config_path_str = config_path.to_string_lossy();
+        let cmdline_options = StorageOptions::parse_from(["test", "--config-file", &config_path_str, "--replicas", "3"]);
+        let effective_config: StorageConfig = load_and_merge_configs_for(cmdline_options, "").await
+            .expect("Loading & rewriting the config failed");
+        assert_eq!(effective_config, StorageConfig { data_dir: config_dir.join("data"), replicas: 3 }, "The hook should have resolved the relative path");
+        let rewritten_config: StorageConfig = serde_yaml::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        assert_eq!(rewritten_config.data_dir, config_dir.join("data"), "The rewritten file should hold the adjusted path");
+
+        std::fs::write(&config_path, "data_dir: data\nreplicas: 10\n").unwrap();
+        let cmdline_options = StorageOptions::parse_from(["test", "--config-file", &config_path_str]);
+        let result = load_and_merge_configs_for::<_, StorageConfig>(cmdline_options, "").await;
+        assert!(matches!(&result, Err(crate::Error::LoadingConfig { cause, .. }) if cause.to_string() == "too many replicas: 10"),
+                "The hook's error should have been reported. Got {result:?}");
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn secret_refs_kept_on_rewrites() {
+
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct DbConfig {
+            user: String,
+            password: String,
+        }
+        impl OgreRootConfig for DbConfig {}
+
+        #[derive(clap::Parser, Debug)]
+        struct DbOptions {
+            #[clap(long)]
+            config_file: Option<String>,
+            #[clap(long)]
+            user: Option<String>,
+            #[clap(long)]
+            preserve_layout: bool,
+            #[clap(long)]
+            output: Option<PathBuf>,
+        }
+        impl CmdLineAndConfigIntegration<DbConfig> for DbOptions {
+            fn config_file_path(&self) -> Option<&str> { self.config_file.as_deref() }
+            fn should_write_effective_config(&self) -> bool { true }
+            fn should_show_effective_config(&self) -> bool { false }
+            fn effective_config_output_path(&self) -> Option<EffectiveConfigTarget> {
+                Some(self.output.clone().map_or(EffectiveConfigTarget::InPlace, EffectiveConfigTarget::Path))
+            }
+            fn meld_options(&self) -> MeldOptions {
+                let rewrite_style = if self.preserve_layout { RewriteStyle::PreserveLayout } else { RewriteStyle::default() };
+                MeldOptions { rewrite_style, ..MeldOptions::default() }
+            }
+            fn merge_with_config(self, mut config: DbConfig) -> Result<DbConfig, crate::Error> {
+                config.user = self.user.unwrap_or(config.user);
+                Ok(config)
+            }
+        }
+
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(config_dir.join("secrets")).unwrap();
+        std::fs::write(config_dir.join("secrets/db_password"), "s3cr3t\n").unwrap();
+        let config_path = config_dir.join("db.yaml");
+        let config_path_str = config_path.to_string_lossy();
+        let output_path = config_dir.join("effective/db.yaml");
+        let output_path_str = output_path.to_string_lossy();
+        for (i, extra_args) in [vec![], vec!["--preserve-layout"], vec!["--output", &output_path_str]].into_iter().enumerate() {
+            std::fs::write(&config_path, "# the db\nuser: admin\npassword: { secret_ref: secrets/db_password }\n").unwrap();
+            let user = format!("user{i}");
+            let args = ["test", "--config-file", &config_path_str, "--user", &user].into_iter().chain(extra_args.iter().copied());
+            let effective_config: DbConfig = load_and_merge_configs_for(DbOptions::parse_from(args), "").await
+                .expect("Loading & writing the effective config failed");
+            assert_eq!(effective_config.password, "s3cr3t", "The effective config should hold the secret");
+            let written_path = if extra_args.contains(&"--output") { &output_path } else { &config_path };
+            let written_txt = std::fs::read_to_string(written_path).unwrap();
+            assert!(!written_txt.contains("s3cr3t") && written_txt.contains("secret_ref") && written_txt.contains(&user),
+                    "The effective config written with {extra_args:?} should keep the secret reference: '{written_txt}'");
+            let written_config: DbConfig = crate::load_existing(written_path).await.unwrap();
+            assert_eq!(written_config, DbConfig { user, password: "s3cr3t".to_string() }, "The config written with {extra_args:?} should load back the same");
+        }
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn include_docs_in_created_file() {
+
+        /// Options that opt out of the docs in created config files
+        #[derive(clap::Parser, Debug)]
+        struct NoDocsOptions {
+            #[clap(long)]
+            config_file: Option<String>,
+        }
+        impl CmdLineAndConfigIntegration<AppRootConfig> for NoDocsOptions {
+            fn config_file_path(&self) -> Option<&str> { self.config_file.as_deref() }
+            fn should_write_effective_config(&self) -> bool { false }
+            fn should_show_effective_config(&self) -> bool { false }
+            fn allow_create_at_explicit_path(&self) -> bool { true }
+            fn include_docs_in_created_file(&self) -> bool { false }
+            fn merge_with_config(self, config: AppRootConfig) -> Result<AppRootConfig, crate::Error> { Ok(config) }
+        }
+
+        let config_path = temp_config_path("ron");
+        let config_path_str = config_path.to_string_lossy();
+
+        let cmdline_options = SampleCliOptions::parse_from(["test", "--config-file", &config_path_str, "--allow-create-at-explicit-path"]);
+        let _: (AppRootConfig, _, _) = load_configs_for(&cmdline_options, &config_path, OnCreateFailure::Fail, "I am the docs").await.unwrap();
+        let created_config_txt = std::fs::read_to_string(&config_path).unwrap();
+        assert!(created_config_txt.contains("DOCS") && created_config_txt.contains("I am the docs"), "The docs should be in the created file by default: '{created_config_txt}'");
+
+        _ = std::fs::remove_file(&config_path);
+        let cmdline_options = NoDocsOptions::parse_from(["test", "--config-file", &config_path_str]);
+        let (created_config, ..): (AppRootConfig, _, _) = load_configs_for(&cmdline_options, &config_path, OnCreateFailure::Fail, "I am the docs").await.unwrap();
+        assert_eq!(created_config, AppRootConfig::default(), "The default config should have been returned");
+        let created_config_txt = std::fs::read_to_string(&config_path).unwrap();
+        assert!(!created_config_txt.contains("DOCS") && !created_config_txt.contains("I am the docs"), "The docs should have been left out of the created file: '{created_config_txt}'");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    /// The spans & events recorded by [RecordingSubscriber] -- their names along with their fields, rendered
+    #[cfg(all(feature = "tracing", feature = "ron"))]
+    type Recorded = std::sync::Arc<std::sync::Mutex<Vec<(String, std::collections::BTreeMap<String, String>)>>>;
+
+    /// A `tracing` subscriber recording the names & fields of all spans & events
+    #[cfg(all(feature = "tracing", feature = "ron"))]
+    struct RecordingSubscriber {
+        recorded: Recorded,
+        next_span_id: std::sync::atomic::AtomicU64,
+    }
+
+    #[cfg(all(feature = "tracing", feature = "ron"))]
+    impl RecordingSubscriber {
+        fn record(&self, name: &str, record_fields: impl FnOnce(&mut dyn tracing::field::Visit)) {
+            struct FieldsVisitor(std::collections::BTreeMap<String, String>);
+            impl tracing::field::Visit for FieldsVisitor {
+                fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                    self.0.insert(field.name().to_string(), format!("{value:?}"));
+                }
+            }
+            let mut visitor = FieldsVisitor(Default::default());
+            record_fields(&mut visitor);
+            self.recorded.lock().unwrap().push((name.to_string(), visitor.0));
+        }
+    }
+
+    #[cfg(all(feature = "tracing", feature = "ron"))]
+    impl tracing::Subscriber for RecordingSubscriber {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            self.record(span.metadata().name(), |visitor| span.record(visitor));
+            tracing::span::Id::from_u64(self.next_span_id.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+        }
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+        fn event(&self, event: &tracing::Event<'_>) {
+            self.record(event.metadata().name(), |visitor| event.record(visitor));
+        }
+        fn enter(&self, _span: &tracing::span::Id) {}
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[cfg(all(feature = "tracing", feature = "ron"))]
+    #[tokio::test]
+    async fn tracing_events() {
+        let recorded = Recorded::default();
+        let _default_subscriber = tracing::subscriber::set_default(RecordingSubscriber { recorded: recorded.clone(), next_span_id: 1.into() });
+        let recorded_fields = |name: &str| recorded.lock().unwrap().iter()
+            .filter(|(recorded_name, _)| recorded_name == name)
+            .map(|(_, fields)| fields.clone())
+            .collect::<Vec<_>>();
+
+        // created with the defaults
+        let config_path = temp_config_path("ron");
+        let config_path_str = config_path.to_string_lossy();
+        let _: AppRootConfig = load_and_merge_configs_for(SampleCliOptions::parse_from(["test", "-c", &config_path_str, "--allow-create-at-explicit-path"]), "").await.unwrap();
+        let load_spans = recorded_fields("config.load");
+        assert!(load_spans.iter().any(|fields| fields["path"].contains(&*config_path_str)), "The `config.load` span wasn't entered for the config file. Got {load_spans:?}");
+        let [created] = recorded_fields("config.create_default").try_into().expect("A single `config.create_default` event was expected");
+        assert_eq!(created["created"], "true", "The config file should have been reported as created");
+
+        // loaded, merged & rewritten
+        recorded.lock().unwrap().clear();
+        let _: AppRootConfig = load_and_merge_configs_for(SampleCliOptions::parse_from(["test", "-c", &config_path_str, "--sink", "stdout", "--write-effective-config"]), "").await.unwrap();
+        let [loaded] = recorded_fields("config.loaded").try_into().expect("A single `config.loaded` event was expected");
+        assert_eq!(loaded["format"], "Ron", "Wrong format recorded");
+        assert!(loaded.contains_key("parsing_time"), "The parsing time wasn't recorded. Got {loaded:?}");
+        let [merged] = recorded_fields("config.merge").try_into().expect("A single `config.merge` event was expected");
+        assert_eq!(merged["changed_fields_count"], "1", "Wrong changed fields count. Got {merged:?}");
+        assert!(merged["changed_fields"].contains("log_sub_config.sink"), "The changed field wasn't recorded. Got {merged:?}");
+        let [rewritten] = recorded_fields("config.rewrite").try_into().expect("A single `config.rewrite` event was expected");
+        let backup_path = config_file_backups(&config_path).await.unwrap().pop().expect("The config file should have been backed up");
+        assert!(rewritten["backup"].contains(&*backup_path.to_string_lossy()), "The backup path wasn't recorded. Got {rewritten:?}");
+
+        // the config values are never recorded
+        let recorded_values = format!("{:?}", recorded.lock().unwrap());
+        assert!(!recorded_values.contains("stdout"), "Config values shouldn't be recorded. Got {recorded_values}");
+
+        _ = std::fs::remove_file(&config_path);
+        _ = std::fs::remove_file(crate::logic::config_logic::lock_file_path_of(&config_path));
+        config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+    }
+}
@@ -0,0 +1,208 @@
+//! Forward compatible deserialization of the configs -- tolerating the structural drift between a config file & the config types,
+//! as when binaries of different versions share the same files during rolling deployments. See [crate::LoadOptions::forward_compatible]
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeSeed, Deserializer, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_json::Value;
+use crate::logic::interpolation_logic::interpolating_seed;
+use crate::{EnvInterpolation, OgreRootConfig};
+
+/// Deserializes the `generic_config` read from a config file into `RootConfigType`, tolerating structural drift:
+/// struct fields unknown to the struct types are ignored -- even by the ones denying unknown fields -- while the missing
+/// ones take their values from the same place in `RootConfigType::default()`.\
+/// Unknown fields are ignored in the structs reachable through other structs, `Option`s & sequences, but defaults only exist
+/// for the ones reachable through structs & `Option`s defaulting to `Some`: the fields missing from sequence elements & from
+/// `Option` sections defaulting to `None` only take their `#[serde(default)]`s -- failing without them.
+/// The structs inside maps & enum variants are deserialized as usual
+pub(crate) fn forward_compatible_config<RootConfigType: OgreRootConfig>(
+    generic_config: Value,
+    env_interpolation: EnvInterpolation,
+) -> Result<RootConfigType, serde_json::Error> {
+    let default_config = serde_json::to_value(RootConfigType::default())?;
+    interpolating_seed(env_interpolation)
+        .deserialize(ForwardCompatible { loaded: generic_config, defaults: Some(default_config) })
+}
+
+/// A value read from the config file, along with its default -- if known -- for filling in the struct fields missing from it
+struct ForwardCompatible {
+    loaded: Value,
+    defaults: Option<Value>,
+}
+
+impl ForwardCompatible {
+    fn without_defaults(loaded: Value) -> Self {
+        Self { loaded, defaults: None }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for ForwardCompatible {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for ForwardCompatible {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.loaded.deserialize_any(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.loaded {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.loaded {
+            Value::Array(elements) => {
+                let mut elements = SeqDeserializer::new(elements.into_iter().map(ForwardCompatible::without_defaults));
+                let value = visitor.visit_seq(&mut elements)?;
+                elements.end()?;
+                Ok(value)
+            },
+            loaded => loaded.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        // empty structs -- like RON's `()` -- have no fields in their generic representation
+        let mut loaded_fields = match self.loaded {
+            Value::Object(loaded_fields) => loaded_fields,
+            Value::Null => serde_json::Map::new(),
+            loaded => return loaded.deserialize_struct(name, fields, visitor),
+        };
+        let mut default_fields = match self.defaults {
+            Some(Value::Object(default_fields)) => default_fields,
+            _ => serde_json::Map::new(),
+        };
+        // unknown fields are left out, while the missing ones are taken from the defaults
+        let known_fields = fields.iter()
+            .filter_map(|&field| match (loaded_fields.remove(field), default_fields.remove(field)) {
+                (Some(loaded), defaults) => Some((field, ForwardCompatible { loaded, defaults })),
+                (None, Some(defaults)) => Some((field, ForwardCompatible::without_defaults(defaults))),
+                (None, None) => None,
+            })
+            .collect::<Vec<_>>();
+        let mut known_fields = MapDeserializer::new(known_fields.into_iter());
+        let value = visitor.visit_map(&mut known_fields)?;
+        known_fields.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        self.loaded.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.loaded.deserialize_map(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct identifier ignored_any
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct ServiceConfig {
+        port: u16,
+        workers: Vec<Worker>,
+        cache: Option<Cache>,
+    }
+
+    impl Default for ServiceConfig {
+        fn default() -> Self {
+            Self { port: 8080, workers: vec![], cache: Some(Cache { size: 64, ttl_secs: 60 }) }
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Cache {
+        size: u32,
+        ttl_secs: u32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Worker {
+        name: String,
+        #[serde(default)]
+        threads: u8,
+    }
+
+    impl OgreRootConfig for ServiceConfig {}
+
+    #[test]
+    fn structural_drift() {
+        // written by a newer version, with fields unknown to this one
+        let generic_config = serde_json::json!({
+            "port": 9090,
+            "tls": {"cert": "server.pem"},
+            "workers": [{"name": "io", "threads": 2, "affinity": [0, 1]}],
+            "cache": {"size": 128, "eviction": "lru", "ttl_secs": 5},
+        });
+        let config: ServiceConfig = forward_compatible_config(generic_config, EnvInterpolation::Disabled)
+            .expect("Unknown fields should have been ignored");
+        assert_eq!(config, ServiceConfig { port: 9090, workers: vec![Worker { name: "io".to_string(), threads: 2 }], cache: Some(Cache { size: 128, ttl_secs: 5 }) },
+                   "Wrong config loaded from a newer file");
+
+        // written by an older version, lacking the newer fields
+        let generic_config = serde_json::json!({"workers": [{"name": "io"}], "cache": {"size": 128}});
+        let config: ServiceConfig = forward_compatible_config(generic_config, EnvInterpolation::Disabled)
+            .expect("Missing fields should have taken their defaults");
+        assert_eq!(config, ServiceConfig { port: 8080, workers: vec![Worker { name: "io".to_string(), threads: 0 }], cache: Some(Cache { size: 128, ttl_secs: 60 }) },
+                   "Wrong config loaded from an older file");
+
+        // values of the wrong type are still errors
+        let generic_config = serde_json::json!({"port": "http"});
+        assert!(forward_compatible_config::<ServiceConfig>(generic_config, EnvInterpolation::Disabled).is_err(), "Values of the wrong type should be reported");
+    }
+
+    #[test]
+    fn missing_fields_without_defaults() {
+        #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Optional {
+            cache: Option<Cache>,
+        }
+        impl OgreRootConfig for Optional {}
+
+        // sequence elements have no defaults of their own: only the `#[serde(default)]`s fill in their missing fields
+        let generic_config = serde_json::json!({"workers": [{"name": "io", "affinity": [0, 1]}]});
+        let config: ServiceConfig = forward_compatible_config(generic_config, EnvInterpolation::Disabled)
+            .expect("Unknown fields of sequence elements should have been ignored & the missing ones taken their `#[serde(default)]`");
+        assert_eq!(config.workers, vec![Worker { name: "io".to_string(), threads: 0 }], "Wrong sequence elements loaded");
+        let generic_config = serde_json::json!({"workers": [{"threads": 2}]});
+        let err = forward_compatible_config::<ServiceConfig>(generic_config, EnvInterpolation::Disabled)
+            .expect_err("Fields missing from sequence elements, without `#[serde(default)]`, can't be filled in");
+        assert!(err.to_string().contains("missing field `name`"), "Unexpected error: {err}");
+
+        // as are `Option` sections defaulting to `None`
+        let generic_config = serde_json::json!({"cache": {"size": 128, "eviction": "lru"}});
+        let err = forward_compatible_config::<Optional>(generic_config, EnvInterpolation::Disabled)
+            .expect_err("Fields missing from `Option` sections defaulting to `None` can't be filled in");
+        assert!(err.to_string().contains("missing field `ttl_secs`"), "Unexpected error: {err}");
+        let generic_config = serde_json::json!({"cache": {"size": 128, "eviction": "lru", "ttl_secs": 5}});
+        let config: Optional = forward_compatible_config(generic_config, EnvInterpolation::Disabled)
+            .expect("Unknown fields of `Option` sections should have been ignored");
+        assert_eq!(config, Optional { cache: Some(Cache { size: 128, ttl_secs: 5 }) }, "Wrong `Option` section loaded");
+    }
+}
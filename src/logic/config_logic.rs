@@ -1,79 +1,1080 @@
 //! Operations for the program's config file
 
 use std::fmt::Debug;
+use std::fs::TryLockError;
 use std::io::ErrorKind;
-use std::path::Path;
-use crate::logic::serde::{AutomaticSerde, ConfigSerde};
-use crate::OgreRootConfig;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+#[cfg(feature = "async")]
+use crate::logic::cli_logic::is_config_url;
+#[cfg(feature = "async")]
+use crate::logic::generic_value_logic::generic_from_txt;
+#[cfg(feature = "async")]
+use crate::logic::layout_logic::preserving_layout;
+#[cfg(feature = "async")]
+use crate::logic::secrets_logic::{config_from_str_with_secret_refs, keeping_secret_refs, SecretRefsSource, SECRET_REF_MARKER};
+use crate::logic::serde_helpers::expand_path::writing_into;
+use crate::logic::warnings_logic::emit_warning;
+use crate::logic::serde::{AutomaticSerde, ConfigSerde, SerdeFormat};
+#[cfg(feature = "async")]
+use crate::logic::serde::tail_docs_of;
+use crate::{LoadContext, LoadOptions, LoadWarningKind, OgreRootConfig, RewriteHeader, SaveOptions};
+#[cfg(feature = "async")]
+use crate::{BackupPolicy, ConfigFileLock, ConfigFs, FileMetadata, LoadedConfig, LoadedFileFingerprint, OnCreateFailure, RealFs};
+#[cfg(feature = "async")]
 use encryptable_tokio_fs::fs;
 use once_cell::sync::Lazy;
 
 /// Loads the configuration from the given `config_file_path`
-/// or creates it (with default values & comments) if it doesn't exist.
+/// or creates it (with default values & comments) if it doesn't exist -- along with any missing parent directories.
 /// See also the low level [load_from_file()] and [save_to_file()].
+#[cfg(feature = "async")]
 pub async fn load_or_create_default<RootConfigType: OgreRootConfig>(
     config_file_path: impl AsRef<Path> + Debug,
     tail_comments: &str,
 ) -> Result<RootConfigType, crate::Error> {
-    let config = load_from_file(&config_file_path).await?;
-    match config {
-        Some(config) => Ok(config),
+    load_or_create_default_traced(config_file_path, tail_comments).await
+        .map(|loaded_config| loaded_config.config)
+}
+
+/// Same as [load_or_create_default()], but also telling where the config came from & whether the file was just created
+/// -- allowing it to be rewritten later with [LoadedConfig::save_to_file()]
+#[cfg(feature = "async")]
+pub async fn load_or_create_default_traced<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    tail_comments: &str,
+) -> Result<LoadedConfig<RootConfigType>, crate::Error> {
+    let (config, created_now, _) = load_or_create_default_reporting_creation(&config_file_path, tail_docs_for::<RootConfigType>(tail_comments), OnCreateFailure::Fail).await?;
+    LoadedConfig::of(config, config_file_path.as_ref(), created_now).await
+}
+
+/// Similar to [load_or_create_default()], but allowing failures to create the default file -- like on read-only filesystems --
+/// to be tolerated, as determined by `on_create_failure`
+#[cfg(feature = "async")]
+pub async fn load_or_create_default_with_policy<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    tail_comments: &str,
+    on_create_failure: OnCreateFailure,
+) -> Result<RootConfigType, crate::Error> {
+    load_or_create_default_reporting_creation(config_file_path, tail_comments, on_create_failure).await
+        .map(|(config, ..)| config)
+}
+
+/// The logic behind [load_or_create_default_with_policy()], also telling if the default config file was created,
+/// along with the text the config was loaded from (or created with) -- `None` if the file couldn't be created.
+/// The file is read only once, so callers needing its contents -- like for rewriting it -- should use the returned text
+#[cfg(feature = "async")]
+pub(crate) async fn load_or_create_default_reporting_creation<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    tail_comments: &str,
+    on_create_failure: OnCreateFailure,
+) -> Result<(RootConfigType, bool, Option<String>), crate::Error> {
+    let loaded = load_text_and_config_from_file(&config_file_path, &LoadOptions::default()).await?;
+    match loaded {
+        Some((txt_config, _, config)) => Ok((config, false, Some(txt_config))),
         None => {
             let default_config = RootConfigType::default();
-            save_to_file(&default_config, tail_comments, config_file_path).await?;
-            Ok(default_config)
+            let save_options = SaveOptions { create_parents: true, ..SaveOptions::default() };
+            let txt_config = serialize_for_file(&default_config, tail_comments, &config_file_path, &save_options)?;
+            let (created_now, txt_config) = match save_text_to_file(&RealFs, txt_config.clone(), &config_file_path, &save_options).await {
+                Err(err) if on_create_failure == OnCreateFailure::WarnAndUseDefaults && err.is_persistence_error() => {
+                    emit_warning(LoadWarningKind::FailedPersistence, "", format!("the default config file {config_file_path:?} couldn't be created -- going on with the default values: {err}"));
+                    (false, None)
+                },
+                result => result.map(|_| (true, Some(txt_config)))?,
+            };
+            #[cfg(feature = "tracing")]
+            tracing::info!(name: "config.create_default", path = ?config_file_path, created = created_now, "config file missing -- going on with the defaults");
+            let default_config = post_loaded(default_config, config_file_path.as_ref(), format_of(&config_file_path)?)?;
+            Ok((default_config, created_now, txt_config))
         }
     }
 }
 
+/// Loads the configuration from the given `config_file_path`, failing with
+/// [crate::Error::ConfigFileNotFound] if it doesn't exist -- no default file is created.
+/// See also [load_or_create_default()].
+#[cfg(feature = "async")]
+pub async fn load_existing<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+) -> Result<RootConfigType, crate::Error> {
+    load_existing_text_and_config(config_file_path).await
+        .map(|(_, config)| config)
+}
+
+/// The logic behind [load_existing()], also returning the (decompressed) text the config was parsed from
+#[cfg(feature = "async")]
+pub(crate) async fn load_existing_text_and_config<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+) -> Result<(String, RootConfigType), crate::Error> {
+    load_text_and_config_from_file(&config_file_path, &LoadOptions::default())
+        .await?
+        .map(|(txt_config, _, config)| (txt_config, config))
+        .ok_or_else(|| crate::Error::ConfigFileNotFound {
+            path: config_file_path.as_ref().to_path_buf(),
+            hint: "the config file is required to exist -- no defaults were written".to_string(),
+        })
+}
+
 /// Saves the `config` to `config_file_path`,
 /// including the given `tail_documentation` at the end of the file
-/// (maybe gathered from the original [config_model] sources).
+/// (maybe gathered from the original [config_model] sources) -- or, if empty, the docs of the config type (see [OgreRootConfig::docs()]).
 /// See also the higher level [load_or_create_default()].
-pub async fn save_to_file(
+#[cfg(feature = "async")]
+pub async fn save_to_file<RootConfigType: OgreRootConfig>(
+    config: &RootConfigType,
+    tail_comment: &str,
+    config_file_path: impl AsRef<Path> + Debug,
+) -> Result<(), crate::Error> {
+    save_to_file_with_options(config, tail_docs_for::<RootConfigType>(tail_comment), config_file_path, &SaveOptions::default()).await
+}
+
+/// The given `tail_docs` -- or, if they are empty, the docs provided by the config type (see [OgreRootConfig::docs()])
+pub(crate) fn tail_docs_for<RootConfigType: OgreRootConfig>(tail_docs: &str) -> &str {
+    match tail_docs {
+        "" => RootConfigType::docs().unwrap_or_default(),
+        tail_docs => tail_docs,
+    }
+}
+
+/// Similar to [save_to_file()], but allowing the saving behavior to be tuned through `save_options`
+#[cfg(feature = "async")]
+pub async fn save_to_file_with_options(
     config: &impl OgreRootConfig,
     tail_comment: &str,
     config_file_path: impl AsRef<Path> + Debug,
+    save_options: &SaveOptions,
 ) -> Result<(), crate::Error> {
-    let Some(file_extension) = ext_with_dot(&config_file_path) else {
-        let cause = crate::Error::UnsupportedConfigFileFormat {
-            message: "Config file without an extension is not supported".to_string(),
-        };
-        return Err(crate::Error::SavingConfig {
-            message: format!(
-                "Error instantiating the automatic serde for file {config_file_path:?}"
-            ),
-            cause: Box::new(cause),
-        });
+    let txt_config = serialize_for_file(config, tail_comment, &config_file_path, save_options)?;
+    save_text_to_file(&RealFs, txt_config, config_file_path, save_options).await
+}
+
+/// Saves the already serialized `txt_config` to `config_file_path` through `config_fs`, as [save_to_file_with_options()] does
+#[cfg(feature = "async")]
+pub(crate) async fn save_text_to_file(
+    config_fs: &impl ConfigFs,
+    txt_config: String,
+    config_file_path: impl AsRef<Path> + Debug,
+    save_options: &SaveOptions,
+) -> Result<(), crate::Error> {
+    save_text_replacing_file(config_fs, txt_config, config_file_path, save_options, None).await
+}
+
+/// Same as [save_text_to_file()], but for a config file that was moved away (like to its backup) -- whose `replaced_metadata`, if given,
+/// is applied to the new file before it receives the (possibly secret) contents: it is then always written durably, through a temporary file
+#[cfg(feature = "async")]
+pub(crate) async fn save_text_replacing_file(
+    config_fs: &impl ConfigFs,
+    txt_config: String,
+    config_file_path: impl AsRef<Path> + Debug,
+    save_options: &SaveOptions,
+    replaced_metadata: Option<&FileMetadata>,
+) -> Result<(), crate::Error> {
+    let contents = compress_if_gzipped(&config_file_path, txt_config)
+        .map_err(|err| crate::Error::SavingConfig {
+            message: format!("Error compressing the config for saving into {config_file_path:?}"),
+            cause: Box::new(err),
+        })?;
+    let _lock = match save_options.locked {
+        Some(lock_timeout) => Some(lock_config_file(&config_file_path, lock_timeout).await?),
+        None => None,
+    };
+    let saving_err = |err| saving_error(&config_file_path, err);
+    let is_symlink = config_fs.symlink_metadata(config_file_path.as_ref()).await
+        .is_ok_and(|metadata| metadata.is_symlink);
+    let target_file_path = if !is_symlink {
+        config_file_path.as_ref().to_path_buf()
+    } else if save_options.replace_symlink {
+        config_fs.remove_file(config_file_path.as_ref()).await.map_err(saving_err)?;
+        config_file_path.as_ref().to_path_buf()
+    } else {
+        resolve_symlinks(config_fs, config_file_path.as_ref()).await.map_err(saving_err)?
+    };
+    if save_options.create_parents {
+        if let Some(parent_dir) = parent_dir_of(&target_file_path) {
+            config_fs.create_dir_all(parent_dir).await
+                .map_err(|err| parent_dir_error(parent_dir, &config_file_path, err))?;
+        }
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(name: "config.save", path = ?config_file_path, target = ?target_file_path, durable = save_options.durable, "saving the config file");
+    if save_options.durable || replaced_metadata.is_some() {
+        write_durably(config_fs, &target_file_path, &contents, replaced_metadata).await.map_err(saving_err)
+    } else {
+        config_fs.write(&target_file_path, &contents).await.map_err(saving_err)
+    }
+}
+
+/// The error for failing to save the config file at `config_file_path` -- shared by the async & blocking saves
+pub(crate) fn saving_error(config_file_path: impl Debug, err: std::io::Error) -> crate::Error {
+    crate::Error::SavingConfig {
+        message: format!("Error saving config into {config_file_path:?}"),
+        cause: Box::new(err),
+    }
+}
+
+/// The error for failing to create the `parent_dir` of the config file at `config_file_path` -- see [SaveOptions::create_parents]
+pub(crate) fn parent_dir_error(parent_dir: &Path, config_file_path: impl Debug, err: std::io::Error) -> crate::Error {
+    crate::Error::Io {
+        message: format!("Error creating the parent directory {parent_dir:?} for the config file {config_file_path:?}"),
+        cause: err,
+    }
+}
+
+/// The directory holding `file_path` -- `None` for bare file names, which live in the current directory
+pub(crate) fn parent_dir_of(file_path: &Path) -> Option<&Path> {
+    file_path.parent().filter(|parent_dir| !parent_dir.as_os_str().is_empty())
+}
+
+/// Follows the (possibly chained & dangling) symlink at `file_path`, returning the path of the final target
+/// -- where relative links are taken relative to the link's directory
+#[cfg(feature = "async")]
+async fn resolve_symlinks(config_fs: &impl ConfigFs, file_path: &Path) -> std::io::Result<PathBuf> {
+    let mut resolved_path = file_path.to_path_buf();
+    for _ in 0..MAX_SYMLINKS {
+        match config_fs.symlink_metadata(&resolved_path).await {
+            Ok(metadata) if metadata.is_symlink => {
+                let link_target = config_fs.read_link(&resolved_path).await?;
+                resolved_path = followed_symlink(&resolved_path, link_target);
+            },
+            Ok(_) => return Ok(resolved_path),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(resolved_path),
+            Err(err) => return Err(err),
+        }
+    }
+    Err(too_many_symlinks(file_path))
+}
+
+/// How many chained symlinks are followed when saving a config file, before giving up -- see [SaveOptions::replace_symlink]
+pub(crate) const MAX_SYMLINKS: usize = 40;
+
+/// The path the symlink at `link_path` points to, given its `link_target` -- taken relative to the link's directory, if relative
+pub(crate) fn followed_symlink(link_path: &Path, link_target: PathBuf) -> PathBuf {
+    match link_path.parent() {
+        Some(link_dir) if link_target.is_relative() => join_relative(link_dir, &link_target),
+        _ => link_target,
+    }
+}
+
+/// The error for symlinks chained beyond [MAX_SYMLINKS], resolving `file_path`
+pub(crate) fn too_many_symlinks(file_path: &Path) -> std::io::Error {
+    std::io::Error::other(format!("too many levels of symbolic links resolving {file_path:?}"))
+}
+
+/// Joins the `relative` path to `base_dir`, resolving any `.` & `..` lexically if `base_dir` is a verbatim path -- like Windows'
+/// `\\?\UNC\server\share\dir` or `\\?\C:\dir` -- as those are taken literally, without the OS resolving them
+pub(crate) fn join_relative(base_dir: &Path, relative: &Path) -> PathBuf {
+    use std::path::{Component, Prefix};
+    let is_verbatim = matches!(base_dir.components().next(),
+                               Some(Component::Prefix(prefix)) if matches!(prefix.kind(), Prefix::Verbatim(_) | Prefix::VerbatimUNC(..) | Prefix::VerbatimDisk(_)));
+    if !is_verbatim {
+        return base_dir.join(relative)
+    }
+    let mut joined = PathBuf::new();
+    for component in base_dir.join(relative).components() {
+        match component {
+            Component::CurDir => (),
+            // popping never goes above the prefix & root
+            Component::ParentDir => _ = joined.pop(),
+            component => joined.push(component),
+        }
+    }
+    joined
+}
+
+/// Writes `contents` to a fsynced temporary file, then atomically renames it to `file_path`, fsyncing its directory afterwards
+/// -- on platforms where fsyncing directories isn't meaningful, that last step is skipped.
+/// The permissions of the replaced file -- the one at `file_path` or, if it was moved away, the given `replaced_metadata` -- are kept
+#[cfg(feature = "async")]
+async fn write_durably(config_fs: &impl ConfigFs, file_path: &Path, contents: &[u8], replaced_metadata: Option<&FileMetadata>) -> std::io::Result<()> {
+    let temp_file_path = durable_temp_file_path(file_path);
+    let original_metadata = match replaced_metadata {
+        Some(replaced_metadata) => Some(replaced_metadata.clone()),
+        None => config_fs.metadata(file_path).await.ok(),
     };
-    let txt_config = AutomaticSerde::for_file_extension(&file_extension)
+    let write_result = async {
+        // the replaced file's permissions are kept -- applied before the (possibly secret) contents are written
+        if let Some(original_metadata) = &original_metadata {
+            config_fs.write(&temp_file_path, b"").await?;
+            config_fs.restore_metadata(&temp_file_path, original_metadata).await?;
+        }
+        config_fs.write(&temp_file_path, contents).await?;
+        config_fs.sync(&temp_file_path).await?;
+        config_fs.rename(&temp_file_path, file_path).await
+    }.await;
+    if write_result.is_err() {
+        _ = config_fs.remove_file(&temp_file_path).await;
+    }
+    write_result?;
+    #[cfg(unix)]
+    {
+        let dir = match file_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        config_fs.sync(dir).await?;
+    }
+    Ok(())
+}
+
+/// The temporary file the contents of `file_path` are written to before replacing it -- see [SaveOptions::durable]
+pub(crate) fn durable_temp_file_path(file_path: &Path) -> PathBuf {
+    let mut temp_file_name = file_path.file_name().unwrap_or_default().to_os_string();
+    temp_file_name.push(format!(".tmp-{}", std::process::id()));
+    file_path.with_file_name(temp_file_name)
+}
+
+/// Applies the permissions -- and, on Unix, the ownership -- from the `original_metadata` of a replaced file to the one at `file_path`.
+/// Failing to restore the ownership (as it requires privileges) is just warned about
+#[cfg(feature = "async")]
+pub(crate) async fn restore_file_metadata(file_path: &Path, original_metadata: &std::fs::Metadata) -> std::io::Result<()> {
+    fs::set_permissions(file_path, original_metadata.permissions()).await?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = fs::metadata(file_path).await?;
+        if (metadata.uid(), metadata.gid()) != (original_metadata.uid(), original_metadata.gid()) {
+            // `tokio::fs` has no `chown()`, so it is done the same way it does its operations: off the async workers
+            let (owned_file_path, uid, gid) = (file_path.to_path_buf(), original_metadata.uid(), original_metadata.gid());
+            let chown_result = tokio::task::spawn_blocking(move || std::os::unix::fs::chown(owned_file_path, Some(uid), Some(gid))).await
+                .unwrap_or_else(|join_err| Err(std::io::Error::other(join_err)));
+            if let Err(err) = chown_result {
+                warn_unrestored_ownership(file_path, original_metadata, err);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Warns that the ownership from the `original_metadata` of a replaced file couldn't be restored to the one at `file_path`
+#[cfg(unix)]
+pub(crate) fn warn_unrestored_ownership(file_path: &Path, original_metadata: &std::fs::Metadata, err: std::io::Error) {
+    use std::os::unix::fs::MetadataExt;
+    emit_warning(LoadWarningKind::FailedPersistence, "", format!("couldn't restore the ownership ({}:{}) of the rewritten file {file_path:?}: {err}",
+              original_metadata.uid(), original_metadata.gid()));
+}
+
+/// Parses the configuration from `txt_config`, in the given `format` -- the same way config files are loaded,
+/// but for configs that don't live in files, like the ones stored in databases.
+/// See also [config_to_string()] & the file-based [load_from_file()].
+pub fn config_from_str<RootConfigType: OgreRootConfig>(
+    txt_config: &str,
+    format: SerdeFormat,
+) -> Result<RootConfigType, crate::Error> {
+    config_from_str_with_options(txt_config, format, &LoadOptions::default())
+}
+
+/// Same as [config_from_str()], but allowing the `load_options` to be specified
+pub fn config_from_str_with_options<RootConfigType: OgreRootConfig>(
+    txt_config: &str,
+    format: SerdeFormat,
+    load_options: &LoadOptions,
+) -> Result<RootConfigType, crate::Error> {
+    AutomaticSerde::new(format)
+        .with_load_options(load_options)
+        .deserialize_config(txt_config)
+}
+
+/// Parses the configuration read from `reader`, in the given `format` -- for configs coming from streams, like sockets or pipes.
+/// Note that neither format has an incremental deserializer, so the whole text is read before being parsed: this is a convenience,
+/// not a way to lower the peak memory of large configs. As the text isn't kept, none of the [LoadOptions] nor [OgreRootConfig::post_load()]
+/// apply -- see [config_from_str_with_options()] for those
+pub fn load_from_reader<RootConfigType: OgreRootConfig>(
+    mut reader: impl std::io::Read,
+    format: SerdeFormat,
+) -> Result<RootConfigType, crate::Error> {
+    let mut txt_config = String::new();
+    reader.read_to_string(&mut txt_config)
+        .map_err(|err| crate::Error::Io {
+            message: format!("Error reading the {format:?} config from a reader"),
+            cause: err,
+        })?;
+    match format {
+        #[cfg(feature = "ron")]
+        SerdeFormat::Ron => ron::Options::default().from_str(&txt_config)
+            .map_err(|err| crate::Error::Ron {
+                message: "RON deserialization error for the config read from a reader".to_string(),
+                cause: err.into(),
+            }),
+        #[cfg(feature = "yaml")]
+        SerdeFormat::Yaml => serde_yaml::from_str(&txt_config)
+            .map_err(|err| crate::Error::Yaml {
+                message: "YAML deserialization error for the config read from a reader".to_string(),
+                cause: err,
+            }),
+    }
+}
+
+/// Serializes the `config` in the given `format`, with the `tail_docs` commented out at the end -- the same way config files
+/// are saved, but for configs that don't live in files. See also [config_from_str()] & the file-based [save_to_file()].
+pub fn config_to_string(
+    config: &impl OgreRootConfig,
+    format: SerdeFormat,
+    tail_docs: &str,
+) -> Result<String, crate::Error> {
+    config_to_string_with_options(config, format, tail_docs, &SaveOptions::default())
+}
+
+/// Same as [config_to_string()], but allowing the `save_options` to be specified
+pub fn config_to_string_with_options(
+    config: &impl OgreRootConfig,
+    format: SerdeFormat,
+    tail_docs: &str,
+    save_options: &SaveOptions,
+) -> Result<String, crate::Error> {
+    AutomaticSerde::new(format)
+        .with_save_options(save_options)
+        .serialize_config(config, tail_docs)
+}
+
+/// Serializes the `config` (including the `tail_comment`) in the format implied by `config_file_path`'s extension
+/// -- after [OgreRootConfig::pre_save()]
+pub(crate) fn serialize_for_file(
+    config: &impl OgreRootConfig,
+    tail_comment: &str,
+    config_file_path: impl AsRef<Path> + Debug,
+    save_options: &SaveOptions,
+) -> Result<String, crate::Error> {
+    let format = saving_format(&config_file_path, save_options)?;
+    let pre_saved_config = config.pre_save();
+    let config = pre_saved_config.as_ref().unwrap_or(config);
+    let destination_dir = config_file_path.as_ref().parent().unwrap_or(Path::new(""));
+    writing_into(destination_dir, || config_to_string_with_options(config, format, tail_comment, save_options))
+        .map_err(|err| crate::Error::SavingConfig {
+            message: format!("Error serializing config for saving into {config_file_path:?}"),
+            cause: Box::new(err),
+        })
+}
+
+/// Same as [serialize_for_file()], but preceded by the `header` -- rendered as a comment of the file's format
+pub(crate) fn serialize_for_file_with_header(
+    config: &impl OgreRootConfig,
+    header: &RewriteHeader,
+    tail_comment: &str,
+    config_file_path: impl AsRef<Path> + Debug,
+    save_options: &SaveOptions,
+) -> Result<String, crate::Error> {
+    let format = saving_format(&config_file_path, save_options)?;
+    let txt_config = serialize_for_file(config, tail_comment, &config_file_path, save_options)?;
+    Ok(format!("{}\n{txt_config}", header.render(format)))
+}
+
+/// The format the config file at `config_file_path` is to be saved in -- see [SaveOptions::format]
+fn saving_format(config_file_path: impl AsRef<Path> + Debug, save_options: &SaveOptions) -> Result<SerdeFormat, crate::Error> {
+    file_format(&config_file_path, save_options.format)
         .map_err(|err| crate::Error::SavingConfig {
             message: format!(
                 "Error instantiating the automatic serde for file {config_file_path:?}"
             ),
             cause: Box::new(err),
-        })?
-        .serialize_config(config, tail_comment)
+        })
+}
+
+/// Edits `original_txt` -- the text of the config file at `config_file_path` -- to hold the `config`, preserving its layout
+/// (see [crate::RewriteStyle::PreserveLayout]). Returns `None` if that can't be done -- including when the edited text
+/// doesn't load back into the same `config`
+#[cfg(feature = "async")]
+pub(crate) fn config_preserving_layout<RootConfigType: OgreRootConfig>(
+    original_txt: &str,
+    config: &RootConfigType,
+    config_file_path: &Path,
+    save_options: &SaveOptions,
+) -> Option<String> {
+    let format = file_format(config_file_path, save_options.format).ok()?;
+    let txt_config = serialize_for_file(config, "", config_file_path, save_options).ok()?;
+    let edited_txt = preserving_layout(original_txt, &txt_config, format)?;
+    let edited_config = config_from_str::<RootConfigType>(&edited_txt, format).ok()?;
+    let pre_saved_config = config.pre_save();
+    let config = pre_saved_config.as_ref().unwrap_or(config);
+    // compared as written into the file, so paths keep the forms they had in it -- see [crate::serde_helpers::expand_path]
+    let config_dir = config_file_path.parent().unwrap_or(Path::new(""));
+    let (edited_value, value) = writing_into(config_dir, || (serde_json::to_value(&edited_config).ok(), serde_json::to_value(config).ok()));
+    (edited_value? == value?)
+        .then_some(edited_txt)
+}
+
+/// Composes the docs of config files rewritten by this crate: the `header` telling how the file came to be,
+/// followed by the original `tail_docs` -- so both get commented out together when serialized. Either may be empty
+#[cfg(feature = "async")]
+pub(crate) fn compose_file_docs(header: &str, tail_docs: &str) -> String {
+    match (header.trim().is_empty(), tail_docs.trim().is_empty()) {
+        (true, _) => tail_docs.to_string(),
+        (false, true) => header.to_string(),
+        (false, false) => format!("{}\n\n{}", header.trim_end(), tail_docs.trim_start_matches('\n')),
+    }
+}
+
+/// Regenerates the config file at `config_file_path` with the default values & the given `tail_comment`
+/// (preceded by a note on the reset), backing up the existing file, if any -- in which case, the backup path is returned.
+/// The backup is made according to the `backup_policy` -- see [backup_config_file()].
+#[cfg(feature = "async")]
+pub async fn reset_config_file<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    tail_comment: &str,
+    backup_policy: &BackupPolicy,
+) -> Result<Option<PathBuf>, crate::Error> {
+    let backup_config_file_path = backup_config_file(&config_file_path, backup_policy).await?;
+    let header = format!("Reset to the default values at {date_str}\n(previous configuration file backed up to {backup_description})",
+                         date_str = chrono::Local::now().format("%a %b %e %H:%M:%S %Z %Y"),
+                         backup_description = match &backup_config_file_path {
+                             Some(backup_config_file_path) => format!("{backup_config_file_path:?}"),
+                             None => format!("{:?}", "<no previous file>"),
+                         });
+    save_to_file(&RootConfigType::default(), &compose_file_docs(&header, tail_comment), &config_file_path).await?;
+    Ok(backup_config_file_path)
+}
+
+/// Moves the (unparseable) config file at `config_file_path` away to `<name>.broken-<timestamp>` -- suffixed by a sequence number
+/// (like `<name>.broken-<timestamp>-1`) if another one was already recovered within the same second, so none is overwritten --,
+/// then creates a new one with the default values & the given `tail_comment` (preceded by a note on the recovery).
+/// Returns the path the broken file was moved to.
+#[cfg(feature = "async")]
+pub async fn recover_config_file<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    tail_comment: &str,
+) -> Result<PathBuf, crate::Error> {
+    let config_file_path = config_file_path.as_ref();
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let broken_path = |stamp: &str| {
+        let mut broken_file_name = config_file_path.file_name().unwrap_or_default().to_os_string();
+        broken_file_name.push(format!(".broken-{stamp}"));
+        config_file_path.with_file_name(broken_file_name)
+    };
+    // as renaming replaces existing files, recoveries within the same second get a sequence number suffix
+    let mut broken_config_file_path = broken_path(&timestamp);
+    for sequence in 1.. {
+        if !RealFs.exists(&broken_config_file_path).await {
+            break;
+        }
+        broken_config_file_path = broken_path(&format!("{timestamp}-{sequence}"));
+    }
+    fs::rename(config_file_path, &broken_config_file_path).await
         .map_err(|err| crate::Error::SavingConfig {
-            message: format!("Error serializing config for saving into {config_file_path:?}"),
-            cause: Box::new(err),
+            message: format!("Error recovering the config file {config_file_path:?}: the broken file couldn't be renamed to {broken_config_file_path:?}"),
+            cause: err.into(),
         })?;
-    fs::write(&config_file_path, &txt_config).await.map_err(|err| crate::Error::SavingConfig {
-        message: format!("Error saving config into {config_file_path:?}"),
-        cause: Box::new(err),
-    })?;
+    let header = format!("Recovered with the default values at {date_str}\n(the unparseable previous configuration file was moved to {broken_config_file_path:?})",
+                         date_str = chrono::Local::now().format("%a %b %e %H:%M:%S %Z %Y"));
+    save_to_file(&RootConfigType::default(), &compose_file_docs(&header, tail_comment), config_file_path).await?;
+    Ok(broken_config_file_path)
+}
+
+/// Reads the single value pointed to by the JSON `pointer` (like `/log_sub_config/sink` -- the leading '/' is optional)
+/// from the config file at `config_file_path`, without knowing the config types -- working across all supported formats.
+/// Returns `Ok(None)` if the pointed value doesn't exist -- and an error if the file doesn't.
+///
+/// Enums are represented as in `serde_json`: unit variants are strings & other variants are single-entry objects.
+#[cfg(feature = "async")]
+pub async fn read_value_at(
+    config_file_path: impl AsRef<Path> + Debug,
+    pointer: &str,
+) -> Result<Option<serde_json::Value>, crate::Error> {
+    let loading_err = |cause: Box<dyn std::error::Error + Send + Sync>| crate::Error::LoadingConfig {
+        message: format!("Error reading the value at '{pointer}' from the config file {config_file_path:?}"),
+        cause,
+    };
+    let txt_config = read_config_text(&RealFs, &config_file_path).await
+        .map_err(|err| loading_err(err.into()))?;
+    let format = format_of(&config_file_path)
+        .map_err(|err| loading_err(Box::new(err)))?;
+    let generic_config = generic_from_txt(&txt_config, format)
+        .map_err(loading_err)?;
+    let pointer = match pointer {
+        "" => String::new(),
+        pointer if pointer.starts_with('/') => pointer.to_string(),
+        pointer => format!("/{pointer}"),
+    };
+    Ok(generic_config.pointer(&pointer).cloned())
+}
+
+/// Sets the single value pointed to by the JSON `pointer` (like `/log_sub_config/sink` -- the leading '/' is optional)
+/// in the config file at `config_file_path` to `value`, then rewrites the file with the given `tail_comment` -- or, if empty,
+/// with the docs the file ends with. The change is validated by deserializing it into `RootConfigType`: on failure, the file is left untouched.
+/// Secret references (`{ secret_ref: "<path>" }`) are kept as references, never written as the secrets they point to -- unless the
+/// value being set is (or is inside) one of them.
+///
+/// `value` follows the same representation as in [read_value_at()].
+#[cfg(feature = "async")]
+pub async fn set_value_at<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    pointer: &str,
+    value: serde_json::Value,
+    tail_comment: &str,
+) -> Result<(), crate::Error> {
+    let setting_err = |cause: Box<dyn std::error::Error + Send + Sync>| crate::Error::SavingConfig {
+        message: format!("Error setting the value at '{pointer}' in the config file {config_file_path:?}"),
+        cause,
+    };
+    let format = format_of(&config_file_path)?;
+    let (txt_config, config) = load_existing_text_and_config::<RootConfigType>(&config_file_path).await?;
+    let mut generic_config = serde_json::to_value(&config)
+        .map_err(|err| setting_err(err.into()))?;
+    let pointer = pointer.strip_prefix('/').unwrap_or(pointer);
+    let (parent_pointer, key) = match pointer.rsplit_once('/') {
+        Some((parent_pointer, key)) => (format!("/{parent_pointer}"), key),
+        None => (String::new(), pointer),
+    };
+    let key = key.replace("~1", "/").replace("~0", "~");
+    let slot = match generic_config.pointer_mut(&parent_pointer) {
+        Some(serde_json::Value::Object(parent)) => parent.get_mut(&key),
+        Some(serde_json::Value::Array(parent)) => key.parse::<usize>().ok().and_then(|index| parent.get_mut(index)),
+        _ => None,
+    }.ok_or_else(|| setting_err(format!("there is no value at '/{pointer}' in the config").into()))?;
+    *slot = value;
+    let config: RootConfigType = serde_json::from_value(generic_config)
+        .map_err(|err| setting_err(format!("the new value doesn't fit the config: {err}").into()))?;
+    let file_tail_docs = match tail_comment {
+        "" => tail_docs_of(&txt_config, format),
+        _ => None,
+    };
+    let tail_docs = tail_docs_for::<RootConfigType>(file_tail_docs.as_deref().unwrap_or(tail_comment));
+    // the secrets were resolved when loading, so their references are put back -- but the one being set, if it is one
+    let source = SecretRefsSource { txt: &txt_config, format, path: config_file_path.as_ref() };
+    let new_txt_config = serialize_for_file(&config, tail_docs, &config_file_path, &SaveOptions::default())?;
+    let new_txt_config = keeping_secret_refs(new_txt_config, format, config_file_path.as_ref(), Some(source), Some(&format!("/{pointer}")))?;
+    save_text_to_file(&RealFs, new_txt_config, config_file_path, &SaveOptions::default()).await
+}
+
+/// Converts the config file at `src_config_file_path` into `dst_config_file_path` -- loading it in the format implied by its extension
+/// & saving it in the one implied by the destination's, like `app.config.ron` into `app.config.yaml` (gzipping included).
+/// The `tail_docs` end the converted file -- or, if empty, the docs the source file ends with, re-commented in the destination's style
+/// (inline comments can't survive the format change), falling back to the docs of the config type (see [OgreRootConfig::docs()]).
+/// Fails with [crate::Error::ConfigFileNotFound] if the source file doesn't exist & with [crate::Error::UnsupportedConfigFileFormat]
+/// for unsupported extensions on either side -- in which case, nothing is written.
+/// Secret references (`{ secret_ref: "<path>" }`) are converted as references, never as the secrets they point to.
+#[cfg(feature = "async")]
+pub async fn convert_config<RootConfigType: OgreRootConfig>(
+    src_config_file_path: impl AsRef<Path> + Debug,
+    dst_config_file_path: impl AsRef<Path> + Debug,
+    tail_docs: &str,
+) -> Result<(), crate::Error> {
+    // both sides are checked upfront, so a bad destination isn't only told after loading the source
+    format_of(&src_config_file_path)?;
+    format_of(&dst_config_file_path)?;
+    let (txt_config, config) = load_existing_text_and_config::<RootConfigType>(&src_config_file_path).await?;
+    let src_format = format_of(&src_config_file_path)?;
+    let src_tail_docs = match tail_docs {
+        "" => tail_docs_of(&txt_config, src_format),
+        _ => None,
+    };
+    let tail_docs = tail_docs_for::<RootConfigType>(src_tail_docs.as_deref().unwrap_or(tail_docs));
+    // the secrets were resolved when loading, so their references are put back -- still pointing to the same files
+    let source = SecretRefsSource { txt: &txt_config, format: src_format, path: src_config_file_path.as_ref() };
+    let dst_txt_config = serialize_for_file(&config, tail_docs, &dst_config_file_path, &SaveOptions::default())?;
+    let dst_txt_config = keeping_secret_refs(dst_txt_config, format_of(&dst_config_file_path)?, dst_config_file_path.as_ref(), Some(source), None)?;
+    save_text_to_file(&RealFs, dst_txt_config, dst_config_file_path, &SaveOptions::default()).await
+}
+
+/// Backs up the config file at `config_file_path` by moving it to the backup named & placed according to `backup_policy`
+/// (like `<name>.bak-YYYYmmdd-HHMMSS`, by default), returning the backup path -- or `None` if there was no file to back up.
+/// Symlinked config files are left in place: their targets' contents are copied to the backup instead.
+/// Only the `backup_policy.keep` most recent backups are kept (at least the one just made): older ones are removed.
+#[cfg(feature = "async")]
+pub async fn backup_config_file(
+    config_file_path: impl AsRef<Path> + Debug,
+    backup_policy: &BackupPolicy,
+) -> Result<Option<PathBuf>, crate::Error> {
+    backup_config_file_in(&RealFs, config_file_path.as_ref(), backup_policy).await
+}
+
+/// Same as [backup_config_file()], but through `config_fs`
+#[cfg(feature = "async")]
+pub(crate) async fn backup_config_file_in(
+    config_fs: &impl ConfigFs,
+    config_file_path: &Path,
+    backup_policy: &BackupPolicy,
+) -> Result<Option<PathBuf>, crate::Error> {
+    if !config_fs.exists(config_file_path).await {
+        return Ok(None);
+    }
+    let backup_naming = BackupNaming::new(config_file_path, backup_policy)?;
+    config_fs.create_dir_all(&backup_naming.backups_dir).await
+        .map_err(|err| crate::Error::Io {
+            message: format!("Error creating the backup directory {:?} for the config file {config_file_path:?}", backup_naming.backups_dir),
+            cause: err,
+        })?;
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    // backups made within the same second get a sequence number suffix
+    let mut backup_config_file_path = backup_naming.backup_path(&timestamp);
+    for sequence in 1.. {
+        if !config_fs.exists(&backup_config_file_path).await {
+            break;
+        }
+        backup_config_file_path = backup_naming.backup_path(&format!("{timestamp}-{sequence}"));
+    }
+    let is_symlink = config_fs.symlink_metadata(config_file_path).await
+        .is_ok_and(|metadata| metadata.is_symlink);
+    if is_symlink {
+        config_fs.copy(config_file_path, &backup_config_file_path).await
+            .map_err(|err| crate::Error::SavingConfig {
+                message: format!("Error backing up the symlinked config file {config_file_path:?}: its target couldn't be copied to {backup_config_file_path:?}"),
+                cause: err.into(),
+            })?;
+    } else {
+        let rename_result = config_fs.rename(config_file_path, &backup_config_file_path).await;
+        move_if_not_renamed(config_fs, rename_result, config_file_path, &backup_config_file_path).await?;
+    }
+    prune_config_file_backups(config_fs, config_file_path, &backup_naming, backup_policy.keep.max(1)).await?;
+    Ok(Some(backup_config_file_path))
+}
+
+/// Completes the move of `config_file_path` to `backup_config_file_path` given the outcome of renaming it:
+/// renames failing for crossing filesystems (like with bind mounts) fall back to copying & removing the file
+#[cfg(feature = "async")]
+async fn move_if_not_renamed(
+    config_fs: &impl ConfigFs,
+    rename_result: std::io::Result<()>,
+    config_file_path: &Path,
+    backup_config_file_path: &Path,
+) -> Result<(), crate::Error> {
+    match rename_result {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            config_fs.copy(config_file_path, backup_config_file_path).await
+                .map_err(|err| crate::Error::SavingConfig {
+                    message: format!("Error backing up the config file {config_file_path:?}: the file couldn't be renamed to {backup_config_file_path:?} \
+                                      -- which is on another filesystem -- nor copied there"),
+                    cause: err.into(),
+                })?;
+            config_fs.remove_file(config_file_path).await
+                .map_err(|err| crate::Error::SavingConfig {
+                    message: format!("Error backing up the config file {config_file_path:?}: the file was copied to {backup_config_file_path:?} \
+                                      -- as it couldn't be renamed across filesystems -- but couldn't be removed afterwards"),
+                    cause: err.into(),
+                })
+        },
+        Err(err) => Err(crate::Error::SavingConfig {
+            message: format!("Error backing up the config file {config_file_path:?}: the file couldn't be renamed to {backup_config_file_path:?}"),
+            cause: err.into(),
+        }),
+    }
+}
+
+/// Takes the advisory lock for rewriting the config file at `config_file_path` -- an exclusive lock on its `<name>.lock` sibling,
+/// created if needed (along with any missing parent directories). Other processes holding the lock are waited for
+/// up to `timeout`, failing with [crate::Error::ConfigLocked] afterwards. The lock is released when the returned guard is dropped.
+///
+/// Being advisory, only cooperating writers are held back: plain loads don't take the lock.
+/// See also [SaveOptions::locked] & [crate::MeldOptions::lock_timeout].
+#[cfg(feature = "async")]
+pub async fn lock_config_file(
+    config_file_path: impl AsRef<Path> + Debug,
+    timeout: Duration,
+) -> Result<ConfigFileLock, crate::Error> {
+    let config_file_path = config_file_path.as_ref();
+    let lock_file_path = lock_file_path_of(config_file_path);
+    let locking_err = |err| locking_error(config_file_path, &lock_file_path, err);
+    if let Some(parent_dir) = parent_dir_of(&lock_file_path) {
+        fs::create_dir_all(parent_dir).await.map_err(locking_err)?;
+    }
+    // `try_lock()` is only offered by `std`'s files -- which opening would block
+    let lock_file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&lock_file_path).await
+        .map_err(locking_err)?
+        .into_std().await;
+    let deadline = Instant::now() + timeout;
+    while let Some(retry_interval) = lock_attempt(&lock_file, config_file_path, &lock_file_path, deadline, timeout)? {
+        tokio::time::sleep(retry_interval).await;
+    }
+    Ok(ConfigFileLock { lock_file })
+}
+
+/// The `<name>.lock` sibling of the config file at `config_file_path`, through which it is locked -- see [lock_config_file()]
+pub(crate) fn lock_file_path_of(config_file_path: &Path) -> PathBuf {
+    let mut lock_file_name = config_file_path.file_name().unwrap_or_default().to_os_string();
+    lock_file_name.push(".lock");
+    config_file_path.with_file_name(lock_file_name)
+}
+
+/// The error for failing to lock the config file at `config_file_path` through its `lock_file_path`
+pub(crate) fn locking_error(config_file_path: &Path, lock_file_path: &Path, err: std::io::Error) -> crate::Error {
+    crate::Error::Io {
+        message: format!("Error locking the config file {config_file_path:?} through {lock_file_path:?}"),
+        cause: err,
+    }
+}
+
+/// Tries to lock the `lock_file` -- at `lock_file_path`, for the config file at `config_file_path` -- returning `None` if it was locked
+/// or for how long to wait before retrying, if someone else holds the lock. Fails with [crate::Error::ConfigLocked] past the `deadline`
+/// -- set by the `timeout`
+pub(crate) fn lock_attempt(
+    lock_file: &std::fs::File,
+    config_file_path: &Path,
+    lock_file_path: &Path,
+    deadline: Instant,
+    timeout: Duration,
+) -> Result<Option<Duration>, crate::Error> {
+    const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+    match lock_file.try_lock() {
+        Ok(()) => Ok(None),
+        Err(TryLockError::WouldBlock) => {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(crate::Error::ConfigLocked { path: lock_file_path.to_path_buf(), timeout });
+            }
+            Ok(Some(RETRY_INTERVAL.min(deadline - now)))
+        },
+        Err(TryLockError::Error(err)) => Err(locking_error(config_file_path, lock_file_path, err)),
+    }
+}
+
+#[cfg(feature = "async")]
+impl LoadedFileFingerprint {
+    /// Fingerprints the file at `file_path` as it is now -- `None` if it doesn't exist
+    pub async fn of(file_path: impl AsRef<Path> + Debug) -> Result<Option<Self>, crate::Error> {
+        match fs::metadata(&file_path).await {
+            Ok(metadata) => Ok(Some(Self { modified: metadata.modified().ok(), len: metadata.len() })),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(crate::Error::Io {
+                message: format!("Error fingerprinting the config file {file_path:?}"),
+                cause: err,
+            }),
+        }
+    }
+
+    /// Tells if the file at `file_path` no longer matches this fingerprint -- including it being gone
+    pub async fn has_changed(&self, file_path: impl AsRef<Path> + Debug) -> Result<bool, crate::Error> {
+        Ok(Self::of(file_path).await?.as_ref() != Some(self))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<RootConfigType: OgreRootConfig> LoadedConfig<RootConfigType> {
+
+    /// Wraps the `config` just loaded from (or created at) `config_file_path`, fingerprinting the file as it is now
+    pub(crate) async fn of(config: RootConfigType, config_file_path: &Path, created_now: bool) -> Result<Self, crate::Error> {
+        Ok(Self {
+            config,
+            path: config_file_path.to_path_buf(),
+            format: format_of(config_file_path)?,
+            created_now,
+            fingerprint: LoadedFileFingerprint::of(config_file_path).await?,
+        })
+    }
+
+    /// Saves the config back to the file it was loaded from -- as [save_to_file()] does --, including the given `tail_comment`.
+    /// If the file was changed by someone else since it was loaded (as told by the [Self::fingerprint]), it is left as is and
+    /// [crate::Error::ConfigChangedOnDisk] is returned. Remote configs can't be saved. Once saved, the fingerprint is updated,
+    /// so the config may be saved again
+    pub async fn save_to_file(&mut self, tail_comment: &str) -> Result<(), crate::Error> {
+        if self.path.to_str().is_some_and(is_config_url) {
+            return Err(crate::Error::SavingConfig {
+                message: format!("The config loaded from '{}' can't be saved back to it", self.path.display()),
+                cause: "remote configs are read-only".into(),
+            })
+        }
+        if let Some(fingerprint) = &self.fingerprint {
+            if fingerprint.has_changed(&self.path).await? {
+                return Err(crate::Error::ConfigChangedOnDisk {
+                    path: self.path.clone(),
+                    message: format!("The config file {:?} was changed since it was loaded: it was left as is, so those changes aren't lost", self.path),
+                })
+            }
+        }
+        // the secret references of the file are kept, rather than the secrets they were resolved to
+        let original_txt = loaded_text(read_config_text(&RealFs, &self.path).await, &self.path)?;
+        let source = original_txt.as_deref().map(|original_txt| SecretRefsSource { txt: original_txt, format: self.format, path: &self.path });
+        let txt_config = serialize_for_file(&self.config, tail_docs_for::<RootConfigType>(tail_comment), &self.path, &SaveOptions::default())?;
+        let txt_config = keeping_secret_refs(txt_config, self.format, &self.path, source, None)?;
+        save_text_to_file(&RealFs, txt_config, &self.path, &SaveOptions::default()).await?;
+        self.created_now = false;
+        self.fingerprint = LoadedFileFingerprint::of(&self.path).await?;
+        Ok(())
+    }
+}
+
+/// How the backups of a config file are named & where they are kept -- according to a [BackupPolicy]
+#[cfg(feature = "async")]
+struct BackupNaming {
+    backups_dir: PathBuf,
+    /// The backup file name, split around the `{timestamp}` placeholder -- with `{name}` already expanded
+    before_timestamp: String,
+    after_timestamp: String,
+}
+
+#[cfg(feature = "async")]
+impl BackupNaming {
+
+    fn new(config_file_path: &Path, backup_policy: &BackupPolicy) -> Result<Self, crate::Error> {
+        let config_dir = match config_file_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let backups_dir = match &backup_policy.directory {
+            Some(directory) => join_relative(config_dir, directory),
+            None => config_dir.to_path_buf(),
+        };
+        let name = config_file_path.file_name().unwrap_or_default().to_string_lossy();
+        let pattern = &backup_policy.suffix_or_pattern;
+        let pattern = if pattern.contains("{name}") || pattern.contains("{timestamp}") {
+            pattern.clone()
+        } else {
+            format!("{{name}}{pattern}{{timestamp}}")
+        };
+        let pattern = pattern.replace("{name}", &name);
+        match pattern.split_once("{timestamp}") {
+            Some((before_timestamp, after_timestamp)) if !after_timestamp.contains("{timestamp}") && !pattern.contains(['/', '\\']) => Ok(Self {
+                backups_dir,
+                before_timestamp: before_timestamp.to_string(),
+                after_timestamp: after_timestamp.to_string(),
+            }),
+            _ => Err(crate::Error::SavingConfig {
+                message: format!("Error backing up the config file {config_file_path:?}: invalid backup pattern '{}'", backup_policy.suffix_or_pattern),
+                cause: "backup patterns must be file names with the `{timestamp}` placeholder exactly once -- see `BackupPolicy::suffix_or_pattern`".into(),
+            }),
+        }
+    }
+
+    fn backup_path(&self, stamp: &str) -> PathBuf {
+        self.backups_dir.join(format!("{}{stamp}{}", self.before_timestamp, self.after_timestamp))
+    }
+
+    /// The (timestamp, sequence) of the backup `file_name` -- or `None` if it isn't a backup
+    fn parse_stamp<'a>(&self, file_name: &'a str) -> Option<(&'a str, u32)> {
+        // "YYYYmmdd-HHMMSS" + an optional "-<sequence>"
+        let stamp = file_name.strip_prefix(&self.before_timestamp)?.strip_suffix(&self.after_timestamp)?;
+        let (timestamp, sequence) = stamp.split_at(stamp.len().min(15));
+        let sequence = match sequence.strip_prefix('-') {
+            Some(sequence) => sequence.parse::<u32>().ok()?,
+            None if sequence.is_empty() => 0,
+            None => return None,
+        };
+        let is_timestamp = timestamp.len() == 15 && timestamp.char_indices().all(|(i, c)| if i == 8 { c == '-' } else { c.is_ascii_digit() });
+        is_timestamp.then_some((timestamp, sequence))
+    }
+}
+
+/// Lists the existing backups of `config_file_path` made by [backup_config_file()] with the default [BackupPolicy],
+/// from the oldest to the most recent. See [config_file_backups_with_policy()]
+#[cfg(feature = "async")]
+pub async fn config_file_backups(config_file_path: impl AsRef<Path> + Debug) -> Result<Vec<PathBuf>, crate::Error> {
+    config_file_backups_with_policy(config_file_path, &BackupPolicy::default()).await
+}
+
+/// Lists the existing backups of `config_file_path` made by [backup_config_file()] with the given `backup_policy`,
+/// from the oldest to the most recent
+#[cfg(feature = "async")]
+pub async fn config_file_backups_with_policy(
+    config_file_path: impl AsRef<Path> + Debug,
+    backup_policy: &BackupPolicy,
+) -> Result<Vec<PathBuf>, crate::Error> {
+    let config_file_path = config_file_path.as_ref();
+    list_config_file_backups(&RealFs, config_file_path, &BackupNaming::new(config_file_path, backup_policy)?).await
+}
+
+#[cfg(feature = "async")]
+async fn list_config_file_backups(config_fs: &impl ConfigFs, config_file_path: &Path, backup_naming: &BackupNaming) -> Result<Vec<PathBuf>, crate::Error> {
+    let listing_err = |err: std::io::Error| crate::Error::Io {
+        message: format!("Error listing the backups of the config file {config_file_path:?}"),
+        cause: err,
+    };
+    let mut backups = Vec::new();
+    let entry_paths = match config_fs.read_dir(&backup_naming.backups_dir).await {
+        Ok(entry_paths) => entry_paths,
+        // a backup directory yet to be created
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(listing_err(err)),
+    };
+    for entry_path in entry_paths {
+        let file_name = entry_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if let Some((timestamp, sequence)) = backup_naming.parse_stamp(&file_name) {
+            backups.push(((timestamp.to_string(), sequence), entry_path));
+        }
+    }
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, backup_path)| backup_path).collect())
+}
+
+/// Removes all but the `keep` most recent backups of `config_file_path` -- see [backup_config_file()]
+#[cfg(feature = "async")]
+async fn prune_config_file_backups(config_fs: &impl ConfigFs, config_file_path: &Path, backup_naming: &BackupNaming, keep: usize) -> Result<(), crate::Error> {
+    let backups = list_config_file_backups(config_fs, config_file_path, backup_naming).await?;
+    let excess = backups.len().saturating_sub(keep);
+    for backup_path in backups.into_iter().take(excess) {
+        config_fs.remove_file(&backup_path).await
+            .map_err(|err| crate::Error::SavingConfig {
+                message: format!("Error pruning the old backup {backup_path:?} of the config file {config_file_path:?}"),
+                cause: err.into(),
+            })?;
+    }
     Ok(())
 }
 
 /// Attempts to read & parse the configuration from the given `config_file_path`.
-/// Returns `Ok(None)` if the file doesn't exist.
+/// Returns `Ok(None)` if the file doesn't exist -- nothing is created -- so callers may tell "no config" apart from
+/// "a config holding the defaults", handling the absence themselves (unlike [load_or_create_default()]).
+/// Values given as `{ secret_ref: "<path>" }` are replaced by the contents of the referenced files -- relative to the config file's
+/// directory, with a trailing newline trimmed -- so secrets may be kept out of the config file.
+/// Files ending in `.gz` -- like `config.ron.gz` -- are transparently decompressed (and compressed by [save_to_file()]).
 /// See also the higher level [load_or_create_default()].
+#[cfg(feature = "async")]
 pub async fn load_from_file<RootConfigType: OgreRootConfig>(
     config_file_path: impl AsRef<Path> + Debug,
 ) -> Result<Option<RootConfigType>, crate::Error> {
-    let Some(file_extension) = ext_with_dot(&config_file_path) else {
+    load_from_file_with_options(config_file_path, &LoadOptions::default()).await
+}
+
+/// Same as [load_from_file()], but allowing the `load_options` to be specified
+#[cfg(feature = "async")]
+pub async fn load_from_file_with_options<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    load_options: &LoadOptions,
+) -> Result<Option<RootConfigType>, crate::Error> {
+    Ok(load_text_and_config_from_file(config_file_path, load_options).await?
+        .map(|(_, _, config)| config))
+}
+
+/// Same as [load_from_file()], but tolerating config files written for other versions of `RootConfigType` -- missing fields
+/// take their default values & unknown ones are ignored, so only syntax errors & values of the wrong type fail.
+/// See [LoadOptions::forward_compatible]
+#[cfg(feature = "async")]
+pub async fn load_forward_compatible<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+) -> Result<Option<RootConfigType>, crate::Error> {
+    load_from_file_with_options(config_file_path, &LoadOptions { forward_compatible: true, ..LoadOptions::default() }).await
+}
+
+/// The logic behind [load_from_file_with_options()], also returning the (decompressed) text the config was parsed from, along with its format
+#[cfg(feature = "async")]
+pub(crate) async fn load_text_and_config_from_file<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    load_options: &LoadOptions,
+) -> Result<Option<(String, SerdeFormat, RootConfigType)>, crate::Error> {
+    let loading = async {
+        check_loadable_extension(&config_file_path, load_options)?;
+        let Some(txt_config) = loaded_text(read_config_text(&RealFs, &config_file_path).await, &config_file_path)? else {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(name: "config.missing", path = ?config_file_path, "config file not found");
+            return Ok(None)
+        };
+        let format = loading_format(&config_file_path, load_options)?;
+        let config_dir = config_file_path.as_ref().parent().unwrap_or(Path::new(""));
+        #[cfg(feature = "tracing")]
+        let parsing_start = Instant::now();
+        let config = if txt_config.contains(SECRET_REF_MARKER) {
+            config_from_str_with_secret_refs(&txt_config, format, load_options, config_dir).await
+        } else {
+            config_from_str_with_options(&txt_config, format, load_options)
+        };
+        let config = loaded_config(config, config_file_path.as_ref(), format)?;
+        #[cfg(feature = "tracing")]
+        tracing::info!(name: "config.loaded", path = ?config_file_path, ?format, parsing_time = ?parsing_start.elapsed(), "config file loaded");
+        Ok(Some((txt_config, format, config)))
+    };
+    #[cfg(feature = "tracing")]
+    let loading = tracing::Instrument::instrument(loading, tracing::debug_span!("config.load", path = ?config_file_path));
+    loading.await
+}
+
+/// Fails if the format of the config file at `config_file_path` can't be told from its extension, nor from the `load_options`
+/// -- checked before reading it. Shared by the async & blocking loads, like the other steps of [load_text_and_config_from_file()]
+pub(crate) fn check_loadable_extension(config_file_path: impl AsRef<Path> + Debug, load_options: &LoadOptions) -> Result<(), crate::Error> {
+    if ext_with_dot(&config_file_path).is_none() && load_options.format.is_none() {
         let cause = crate::Error::UnsupportedConfigFileFormat {
-            message: "Config file without an extension is not supported".to_string(),
+            message: "Config file without an extension is not supported -- unless its format is given".to_string(),
         };
         return Err(crate::Error::LoadingConfig {
             message: format!(
@@ -82,51 +1083,286 @@ pub async fn load_from_file<RootConfigType: OgreRootConfig>(
             cause: Box::new(cause),
         });
     };
-    let txt_config_result = fs::read_to_string(&config_file_path).await;
-    let txt_config = match txt_config_result {
-        Ok(txt_config) => Ok(txt_config),
-        Err(err) => {
-            if err.kind() == ErrorKind::NotFound {
-                return Ok(None);
-            }
-            Err(crate::Error::LoadingConfig {
-                message: format!("Error loading config from {config_file_path:?}"),
-                cause: Box::new(err),
-            })
-        }
-    }?;
-    let config = AutomaticSerde::for_file_extension(&file_extension)
+    Ok(())
+}
+
+/// The text of the config file at `config_file_path`, out of the `read_result` of reading it -- `None` if it doesn't exist
+pub(crate) fn loaded_text(read_result: std::io::Result<String>, config_file_path: impl AsRef<Path> + Debug) -> Result<Option<String>, crate::Error> {
+    match read_result {
+        Ok(txt_config) => Ok(Some(txt_config)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(crate::Error::LoadingConfig {
+            message: format!("Error loading config from {config_file_path:?}"),
+            cause: Box::new(err),
+        }),
+    }
+}
+
+/// The format the config file at `config_file_path` is to be parsed in -- see [LoadOptions::format]
+pub(crate) fn loading_format(config_file_path: impl AsRef<Path> + Debug, load_options: &LoadOptions) -> Result<SerdeFormat, crate::Error> {
+    file_format(&config_file_path, load_options.format)
         .map_err(|err| crate::Error::LoadingConfig {
             message: format!(
                 "Error instantiating the automatic serde for file {config_file_path:?}"
             ),
             cause: Box::new(err),
-        })?
-        .deserialize_config(&txt_config)
+        })
+}
+
+/// The config parsed from the file at `config_file_path` -- out of the `parsing_result` -- after [post_loaded()]
+pub(crate) fn loaded_config<RootConfigType: OgreRootConfig>(
+    parsing_result: Result<RootConfigType, crate::Error>,
+    config_file_path: &Path,
+    format: SerdeFormat,
+) -> Result<RootConfigType, crate::Error> {
+    let config = parsing_result
         .map_err(|err| crate::Error::LoadingConfig {
             message: format!("Error deserializing config after loading from {config_file_path:?}"),
             cause: Box::new(err),
         })?;
-    Ok(Some(config))
+    post_loaded(config, config_file_path, format)
+}
+
+/// Runs the [OgreRootConfig::post_load()] hook on the `config` just loaded from (or created at) `config_file_path` -- or URL
+pub(crate) fn post_loaded<RootConfigType: OgreRootConfig>(mut config: RootConfigType, config_file_path: &Path, format: SerdeFormat) -> Result<RootConfigType, crate::Error> {
+    config.post_load(&LoadContext { path: config_file_path.to_path_buf(), format })
+        .map_err(|cause| crate::Error::LoadingConfig {
+            message: format!("Error adjusting the config loaded from {config_file_path:?}, in `OgreRootConfig::post_load()`"),
+            cause,
+        })?;
+    Ok(config)
+}
+
+/// The comment marking a config file as frozen -- `// frozen` in RON or `# frozen` in YAML files -- see [is_frozen()]
+pub const FROZEN_MARKER: &str = "frozen";
+
+/// Tells if `txt_config` has a line holding just the [FROZEN_MARKER] comment -- meaning the config file must never be
+/// rewritten automatically, like by `--write-effective-config` in production, which is refused with [crate::Error::ConfigFrozen]
+pub fn is_frozen(txt_config: &str) -> bool {
+    txt_config.lines()
+        .filter_map(|line| line.trim().strip_prefix("//").or_else(|| line.trim().strip_prefix('#')))
+        .any(|comment| comment.trim() == FROZEN_MARKER)
 }
 
+/// The extension of the file suffix denoting gzip-compressed config files -- like in `config.ron.gz`
+const GZIP_EXTENSION: &str = ".gz";
+
+/// The extension (with the dot) determining the format of the config file at `path`
+/// -- for gzip-compressed files, the one before [GZIP_EXTENSION], like `.ron` for `config.ron.gz`
 fn ext_with_dot(path: impl AsRef<Path>) -> Option<String> {
     path.as_ref()
         .file_name()
         .and_then(|os| os.to_str())
+        .map(|name| name.strip_suffix(GZIP_EXTENSION).unwrap_or(name))
         .and_then(|name| name.rfind('.').map(|idx| &name[idx..]))
         .map(ToString::to_string)
 }
 
+/// The format of the config file at `path`, as implied by its extension -- see [ext_with_dot()]
+pub(crate) fn format_of(path: impl AsRef<Path> + Debug) -> Result<SerdeFormat, crate::Error> {
+    file_format(path, None)
+}
+
+/// The format of the config file at `path`: the `format_override`, if given, or the one implied by its extension.
+/// Known extensions must agree with the override -- see [SaveOptions::format] & [LoadOptions::format]
+pub(crate) fn file_format(path: impl AsRef<Path> + Debug, format_override: Option<SerdeFormat>) -> Result<SerdeFormat, crate::Error> {
+    let file_extension = ext_with_dot(&path);
+    match (file_extension.as_deref().map(SerdeFormat::for_file_extension), format_override) {
+        (Some(Ok(implied_format)), Some(format)) if implied_format != format => Err(crate::Error::UnsupportedConfigFileFormat {
+            message: format!("`cli-config`: The config file {path:?} was requested to be in {format:?}, but its '{}' extension implies {implied_format:?} \
+                              -- rename it or drop the format override", file_extension.unwrap_or_default()),
+        }),
+        (_, Some(format)) => Ok(format),
+        (Some(implied_format), None) => implied_format,
+        (None, None) => Err(crate::Error::UnsupportedConfigFileFormat {
+            message: format!("`cli-config`: Config file without an extension is not supported -- unless its format is given: {path:?}"),
+        }),
+    }
+}
+
+/// Tells if the config file at `path` is gzip-compressed, as implied by its [GZIP_EXTENSION]
+pub(crate) fn is_gzipped(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .file_name()
+        .and_then(|os| os.to_str())
+        .is_some_and(|name| name.ends_with(GZIP_EXTENSION))
+}
+
+/// Reads the text of the config file at `config_file_path` through `config_fs`, decompressing it if [is_gzipped()]
+#[cfg(feature = "async")]
+pub(crate) async fn read_config_text(config_fs: &impl ConfigFs, config_file_path: impl AsRef<Path> + Debug) -> std::io::Result<String> {
+    #[cfg(test)]
+    CONFIG_TEXT_READS.lock().unwrap().push(config_file_path.as_ref().to_path_buf());
+    if !is_gzipped(&config_file_path) {
+        return config_fs.read_to_string(config_file_path.as_ref()).await
+    }
+    decompressed_text(&config_file_path, &config_fs.read(config_file_path.as_ref()).await?)
+}
+
+/// The text of the gzip-`compressed` config file at `config_file_path` -- see [is_gzipped()]
+#[cfg(feature = "gzip")]
+pub(crate) fn decompressed_text(config_file_path: impl AsRef<Path> + Debug, compressed: &[u8]) -> std::io::Result<String> {
+    let mut txt_config = String::new();
+    std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(compressed), &mut txt_config)
+        .map_err(|err| std::io::Error::new(ErrorKind::InvalidData,
+                                           format!("couldn't decompress {config_file_path:?} -- is it really gzip-compressed, as its extension implies? {err}")))?;
+    Ok(txt_config)
+}
+
+/// Refuses gzip-compressed config files, as their support wasn't built in
+#[cfg(not(feature = "gzip"))]
+pub(crate) fn decompressed_text(config_file_path: impl AsRef<Path> + Debug, _compressed: &[u8]) -> std::io::Result<String> {
+    Err(gzip_unsupported(config_file_path))
+}
+
+/// The error for gzip-compressed config files, when the `gzip` feature is off
+#[cfg(not(feature = "gzip"))]
+fn gzip_unsupported(config_file_path: impl AsRef<Path> + Debug) -> std::io::Error {
+    std::io::Error::new(ErrorKind::Unsupported,
+                        format!("{config_file_path:?} is gzip-compressed, as its extension implies -- which requires the `gzip` feature of `ogre-config-meld`"))
+}
+
+/// The config files read by [read_config_text()] -- & by its blocking version -- for tests to assert how many times a file was read
+#[cfg(test)]
+pub(crate) static CONFIG_TEXT_READS: std::sync::Mutex<Vec<PathBuf>> = std::sync::Mutex::new(Vec::new());
+
+/// Returns the bytes to be written to `config_file_path` for `txt_config` -- gzip-compressed if [is_gzipped()]
+pub(crate) fn compress_if_gzipped(config_file_path: impl AsRef<Path>, txt_config: String) -> std::io::Result<Vec<u8>> {
+    if !is_gzipped(&config_file_path) {
+        return Ok(txt_config.into_bytes())
+    }
+    compressed_text(config_file_path.as_ref(), &txt_config)
+}
+
+/// The gzip-compressed bytes of `txt_config`, for the config file at `config_file_path` -- see [is_gzipped()]
+#[cfg(feature = "gzip")]
+fn compressed_text(_config_file_path: &Path, txt_config: &str) -> std::io::Result<Vec<u8>> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut encoder, txt_config.as_bytes())?;
+    encoder.finish()
+}
+
+/// Refuses gzip-compressed config files, as their support wasn't built in
+#[cfg(not(feature = "gzip"))]
+fn compressed_text(config_file_path: &Path, _txt_config: &str) -> std::io::Result<Vec<u8>> {
+    Err(gzip_unsupported(config_file_path))
+}
+
+///////////////
+// Config Cache
+///////////////
+
+#[cfg(feature = "async")]
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+/// Cached configs, keyed by their types & canonical file paths -- each one in its own cell, so loading a config
+/// doesn't hold back the loading of any other
+#[cfg(feature = "async")]
+type ConfigsCache = HashMap<(TypeId, PathBuf), Arc<tokio::sync::OnceCell<Arc<dyn Any + Send + Sync>>>>;
+
+/// Process-wide cache for [get_or_init_config()] -- only locked to get (or insert) the cells, never across loads
+#[cfg(feature = "async")]
+static CONFIGS_CACHE: Lazy<std::sync::Mutex<ConfigsCache>> = Lazy::new(Default::default);
+
+/// Similar to [load_or_create_default()], but the configuration is loaded only once and shared,
+/// process-wide, by all subsequent calls for the same `config_file_path` & `RootConfigType`
+/// -- regardless of how the path is spelled (relative, through `..` or symlinks).
+/// See [invalidate_cache()] to force the next call to reload the file.
+#[cfg(feature = "async")]
+pub async fn get_or_init_config<RootConfigType: OgreRootConfig + Send + Sync + 'static>(
+    config_file_path: impl AsRef<Path> + Debug,
+    tail_docs: &str,
+) -> Result<Arc<RootConfigType>, crate::Error> {
+    let key = (TypeId::of::<RootConfigType>(), canonical_cache_path(config_file_path.as_ref()).await);
+    let cell = Arc::clone(CONFIGS_CACHE.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .entry(key)
+        .or_default());
+    let cached_config = cell.get_or_try_init(|| async {
+        load_or_create_default::<RootConfigType>(&config_file_path, tail_docs).await
+            .map(|config| Arc::new(config) as Arc<dyn Any + Send + Sync>)
+    }).await?;
+    Ok(Arc::clone(cached_config)
+        .downcast::<RootConfigType>()
+        .expect("BUG! the configs cache is keyed by type: downcasting can't fail"))
+}
+
+/// The canonical form of `config_file_path`, for keying the configs cache -- made out of its parent directory,
+/// if the file doesn't exist yet (as it is created by [get_or_init_config()]), or just its absolute form, if the directory doesn't exist either
+#[cfg(feature = "async")]
+async fn canonical_cache_path(config_file_path: &Path) -> PathBuf {
+    if let Ok(canonical_path) = tokio::fs::canonicalize(config_file_path).await {
+        return canonical_path
+    }
+    let parent = config_file_path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    match (tokio::fs::canonicalize(parent).await, config_file_path.file_name()) {
+        (Ok(canonical_parent), Some(file_name)) => canonical_parent.join(file_name),
+        _ => std::path::absolute(config_file_path).unwrap_or_else(|_| config_file_path.to_path_buf()),
+    }
+}
+
+/// Forgets all configs cached by [get_or_init_config()], so they will be reloaded on their next calls
+/// -- useful for tests & for reloading the configuration
+#[cfg(feature = "async")]
+pub async fn invalidate_cache() {
+    CONFIGS_CACHE.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clear();
+}
+
 //////////////
 // Config Docs
 //////////////
 
+use std::hash::{Hash, Hasher};
 use regex::{Regex, RegexBuilder};
 
 /// Gives access to the configuration documentation, so they may be
 /// included when saving config files, for a better user experience
 pub fn documented_config_models(configs_root_dir: &include_dir::Dir<'_>) -> String {
+    documented_config_models_for(configs_root_dir, None)
+}
+
+/// Similar to [documented_config_models()], but also linking each config section (fields holding sub-config structs)
+/// to its fuller explanation at `docs_base_url`, using the field name as the anchor -- like `// see https://docs.example.com/#log_sub_config`
+pub fn documented_config_models_with_docs_url(configs_root_dir: &include_dir::Dir<'_>, docs_base_url: &str) -> String {
+    documented_config_models_for(configs_root_dir, Some(docs_base_url))
+}
+
+/// The logic behind [documented_config_models()] & [documented_config_models_with_docs_url()].
+/// The docs are computed once per set of model sources & `docs_base_url`: as programs re-saving their configs often
+/// -- like settings UIs -- would otherwise re-run all the regex replacements on every save, further calls just hash the sources
+fn documented_config_models_for(configs_root_dir: &include_dir::Dir<'_>, docs_base_url: Option<&str>) -> String {
+    cached_docs(configs_root_dir, docs_base_url, || computed_config_models_docs(configs_root_dir, docs_base_url))
+}
+
+/// Returns the docs cached for the model sources in `configs_root_dir` & the `docs_base_url` -- calling `compute` to get them
+/// if they weren't computed yet. The cache is keyed by a hash of the sources, as the same models may be given by distinct dirs
+fn cached_docs(configs_root_dir: &include_dir::Dir<'_>, docs_base_url: Option<&str>, compute: impl FnOnce() -> String) -> String {
+    static DOCS_CACHE: Lazy<std::sync::Mutex<HashMap<u64, String>>> = Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for file in configs_root_dir.files() {
+        file.path().hash(&mut hasher);
+        file.contents().hash(&mut hasher);
+    }
+    docs_base_url.hash(&mut hasher);
+    let key = hasher.finish();
+    if let Some(docs) = DOCS_CACHE.lock().unwrap_or_else(std::sync::PoisonError::into_inner).get(&key) {
+        return docs.clone()
+    }
+    // computed without holding the lock -- at worst, racing callers compute the same docs
+    let docs = compute();
+    DOCS_CACHE.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+        .insert(key, docs.clone());
+    docs
+}
+
+/// Computes the docs for [documented_config_models_for()], out of the model sources in `configs_root_dir`
+fn computed_config_models_docs(configs_root_dir: &include_dir::Dir<'_>, docs_base_url: Option<&str>) -> String {
     // Regexes and their replacements to apply to model source files when writing the docs
     static REPLACEMENTS: Lazy<[(Regex, &str); 6]> = Lazy::new(|| {
         [
@@ -134,7 +1370,7 @@ pub fn documented_config_models(configs_root_dir: &include_dir::Dir<'_>) -> Stri
             ("\nmod [^\n]*|\npub use [^\n]*", ""), // remove 'mod' & 'pub use' clauses
             ("\nuse [^\n]*", ""),                  // remove 'use' clauses
             ("\n#[^\n]*", ""),                     // remove macros & #[derive(...)] clauses
-            ("\nimpl .*?\n}.*?\n?", "\n"),         // remove any impls
+            ("\nimpl [^\n]*?(?:\\{ *\\}|\\{.*?\n\\})[^\n]*", "\n"), // remove any impls
             ("\n\n+", "\n\n"), // standardize the number of consecutive empty lines
         ]
         .map(|(regex, replacement)| {
@@ -155,6 +1391,9 @@ pub fn documented_config_models(configs_root_dir: &include_dir::Dir<'_>) -> Stri
         merged_docs.push_str(&src);
     }
 
+    // gathered before the replacements, as they strip out the serde attributes
+    let enums = enum_variants(&merged_docs);
+
     // replace
     let docs_section =
         REPLACEMENTS
@@ -163,25 +1402,560 @@ pub fn documented_config_models(configs_root_dir: &include_dir::Dir<'_>) -> Stri
                 regex.replace_all(&docs_section, *replacement).to_string()
             });
 
-    docs_section
+    let docs_section = annotate_enum_fields(&docs_section, &enums);
+    match docs_base_url {
+        Some(docs_base_url) => annotate_section_fields(&docs_section, docs_base_url),
+        None => docs_section,
+    }
 }
 
-#[cfg(test)]
+/// Collects, from the model sources in `src`, the name of each enum along with its variants,
+/// spelled as they should appear in the config files (taking serde's `rename_all` & `rename` into account)
+fn enum_variants(src: &str) -> Vec<(String, Vec<String>)> {
+    static ENUM: Lazy<Regex> = Lazy::new(|| {
+        RegexBuilder::new(r"((?:\n[ \t]*#\[[^\n]*)*)\n[ \t]*pub enum ([A-Za-z0-9_]+)[^{]*\{(.*?)\n\}")
+            .dot_matches_new_line(true)
+            .build()
+            .expect("Error parsing Regex")
+    });
+    static RENAME_ALL: Lazy<Regex> = Lazy::new(|| Regex::new(r#"#\[serde\(.*rename_all *= *"([^"]+)""#).expect("Error parsing Regex"));
+    static RENAME: Lazy<Regex> = Lazy::new(|| Regex::new(r#"#\[serde\(.*rename *= *"([^"]+)""#).expect("Error parsing Regex"));
+    static VARIANT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([A-Z][A-Za-z0-9_]*)").expect("Error parsing Regex"));
+
+    ENUM.captures_iter(src)
+        .map(|captures| {
+            let rename_all = RENAME_ALL.captures(&captures[1]).map(|rename_all| rename_all[1].to_string());
+            let mut variant_rename = None;
+            let mut variants = Vec::new();
+            for line in captures[3].lines().map(str::trim) {
+                if let Some(rename) = RENAME.captures(line) {
+                    variant_rename = Some(rename[1].to_string());
+                } else if let Some(variant) = VARIANT.captures(line) {
+                    let spelling = variant_rename.take()
+                        .unwrap_or_else(|| renamed_variant(&variant[1], rename_all.as_deref()));
+                    variants.push(spelling);
+                }
+            }
+            (captures[2].to_string(), variants)
+        })
+        .collect()
+}
+
+/// Applies serde's `rename_all` `rule` to the enum `variant`
+fn renamed_variant(variant: &str, rule: Option<&str>) -> String {
+    let words = || variant.split_inclusive(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        .fold(Vec::<String>::new(), |mut words, chunk| {
+            // split "StdOut" into ["Std", "Out"]
+            let starts_word = chunk.starts_with(|c: char| c.is_ascii_uppercase());
+            match words.last_mut() {
+                Some(word) if !starts_word => word.push_str(chunk),
+                _ => words.push(chunk.to_string()),
+            }
+            words
+        });
+    match rule {
+        Some("lowercase") => variant.to_ascii_lowercase(),
+        Some("UPPERCASE") => variant.to_ascii_uppercase(),
+        Some("camelCase") => variant[..1].to_ascii_lowercase() + &variant[1..],
+        Some("snake_case") => words().join("_").to_ascii_lowercase(),
+        Some("SCREAMING_SNAKE_CASE") => words().join("_").to_ascii_uppercase(),
+        Some("kebab-case") => words().join("-").to_ascii_lowercase(),
+        Some("SCREAMING-KEBAB-CASE") => words().join("-").to_ascii_uppercase(),
+        _ => variant.to_string(),   // "PascalCase" or no renaming at all
+    }
+}
+
+/// Appends, to each field declaration in `docs` typed with one of the `enums`, the list of the values it accepts
+fn annotate_enum_fields(docs: &str, enums: &[(String, Vec<String>)]) -> String {
+    static FIELD: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[ \t]*pub [A-Za-z0-9_]+ *:(.*)$").expect("Error parsing Regex"));
+    static WORD: Lazy<Regex> = Lazy::new(|| Regex::new(r"[A-Za-z0-9_]+").expect("Error parsing Regex"));
+
+    let mut annotated_docs = String::with_capacity(docs.len());
+    for (i, line) in docs.split('\n').enumerate() {
+        if i > 0 {
+            annotated_docs.push('\n');
+        }
+        annotated_docs.push_str(line);
+        let Some(field) = FIELD.captures(line) else {
+            continue
+        };
+        let field_enum = WORD.find_iter(&field[1])
+            .find_map(|word| enums.iter().find(|(enum_name, _)| enum_name == word.as_str()));
+        if let Some((_, variants)) = field_enum {
+            annotated_docs.push_str("    // possible values: ");
+            annotated_docs.push_str(&variants.join(", "));
+        }
+    }
+    annotated_docs
+}
+
+/// Appends, to each field declaration in `docs` typed with one of the structs declared there, a link to its section at `docs_base_url`
+fn annotate_section_fields(docs: &str, docs_base_url: &str) -> String {
+    static STRUCT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^[ \t]*pub struct ([A-Za-z0-9_]+)").expect("Error parsing Regex"));
+    static FIELD: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[ \t]*pub ([A-Za-z0-9_]+) *:(.*)$").expect("Error parsing Regex"));
+    static WORD: Lazy<Regex> = Lazy::new(|| Regex::new(r"[A-Za-z0-9_]+").expect("Error parsing Regex"));
+
+    let structs = STRUCT.captures_iter(docs)
+        .map(|captures| captures[1].to_string())
+        .collect::<Vec<_>>();
+    let docs_base_url = docs_base_url.trim_end_matches('#');
+    let mut annotated_docs = String::with_capacity(docs.len());
+    for (i, line) in docs.split('\n').enumerate() {
+        if i > 0 {
+            annotated_docs.push('\n');
+        }
+        annotated_docs.push_str(line);
+        let Some(field) = FIELD.captures(line) else {
+            continue
+        };
+        let is_section = WORD.find_iter(&field[2])
+            .any(|word| structs.iter().any(|struct_name| struct_name == word.as_str()));
+        if is_section {
+            annotated_docs.push_str(&format!("    // see {docs_base_url}#{}", &field[1]));
+        }
+    }
+    annotated_docs
+}
+
+#[cfg(all(test, feature = "async", feature = "ron", feature = "yaml"))]
 mod tests {
     use super::*;
-    use crate::test_commons::config_models::*;
+    use crate::testkit::*;
     use include_dir::{include_dir, Dir};
     use ron::ser::{to_string_pretty, PrettyConfig};
 
     static DOCS: Lazy<String> = Lazy::new(|| {
         // For docs extraction that will be placed alongside the config file
-        static CONFIGS_DIR_SRC: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/src/test_commons/");
+        static CONFIGS_DIR_SRC: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/src/testkit/");
         documented_config_models(&CONFIGS_DIR_SRC)
     });
 
+    #[test]
+    fn string_round_trips() {
+        let expected_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdError) } };
+        for format in [SerdeFormat::Ron, SerdeFormat::Yaml] {
+            let txt_config = config_to_string(&expected_config, format, "I\nhave\nmultiline\ntail docs")
+                .unwrap_or_else(|err| panic!("Serializing to {format:?} failed: {err}"));
+            assert!(txt_config.contains("tail docs"), "The tail docs are missing from the {format:?} config: '{txt_config}'");
+            let config: AppRootConfig = config_from_str(&txt_config, format)
+                .unwrap_or_else(|err| panic!("Deserializing from {format:?} failed: {err}"));
+            assert_eq!(config, expected_config, "{format:?} round trip didn't work");
+        }
+        let result = config_from_str::<AppRootConfig>("(log_sub_config: (sink: Some(stdout)))", SerdeFormat::Yaml);
+        assert!(result.is_err_and(|err| err.is_parsing_error()), "Contents in the wrong format should be reported as parsing errors");
+    }
+
+    #[test]
+    fn relative_joins() {
+        assert_eq!(join_relative(Path::new("/etc/app"), Path::new("../backups")), PathBuf::from("/etc/app/../backups"), "Regular paths should be joined as-is");
+        #[cfg(windows)]
+        {
+            assert_eq!(join_relative(Path::new(r"\\?\UNC\server\share\app"), Path::new(r"..\backups\.\old")), PathBuf::from(r"\\?\UNC\server\share\backups\old"),
+                       "Verbatim UNC paths should have been resolved lexically");
+            assert_eq!(join_relative(Path::new(r"\\?\C:\app"), Path::new(r"..\..\..\backups")), PathBuf::from(r"\\?\C:\backups"),
+                       "Verbatim paths shouldn't go above their roots");
+            assert_eq!(join_relative(Path::new(r"\\server\share\app"), Path::new(r"..\backups")), PathBuf::from(r"\\server\share\app\..\backups"),
+                       "Non-verbatim UNC paths are resolved by the OS, so they should be joined as-is");
+        }
+    }
+
+    /// Backups & atomic writes work on Windows network shares -- set `CLI_CONFIG_UNC_DIR` to a writable `\\server\share\dir` to run it
+    #[cfg(windows)]
+    #[tokio::test]
+    async fn unc_paths() {
+        let Some(unc_dir) = std::env::var_os("CLI_CONFIG_UNC_DIR").map(PathBuf::from) else {
+            eprintln!("CLI_CONFIG_UNC_DIR is not set: skipping the test");
+            return
+        };
+        let config_path = unc_dir.join("cli-config-unc_paths.yaml");
+        let expected_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+        save_to_file(&AppRootConfig::default(), "", &config_path).await.unwrap();
+        let backup_policy = BackupPolicy { directory: Some(PathBuf::from(r"..\cli-config-unc_paths-backups")), ..BackupPolicy::default() };
+        let backup_path = backup_config_file(&config_path, &backup_policy).await.unwrap().expect("The config file should have been backed up");
+        let save_options = SaveOptions { durable: true, ..SaveOptions::default() };
+        save_to_file_with_options(&expected_config, "", &config_path, &save_options).await.unwrap();
+        assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap(), expected_config, "The durable write over the share failed");
+        _ = std::fs::remove_file(&config_path);
+        _ = std::fs::remove_dir_all(backup_path.parent().unwrap());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn gzipped_configs() {
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        let expected_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdError) } };
+        for file_name in ["app.config.ron.gz", "app.config.yml.gz"] {
+            let config_path = config_dir.join(file_name);
+            save_to_file(&expected_config, "compressed tail docs", &config_path).await
+                .unwrap_or_else(|err| panic!("Saving {file_name} failed: {err}"));
+            let compressed = std::fs::read(&config_path).unwrap();
+            assert_eq!(&compressed[..2], &[0x1f, 0x8b], "{file_name} should have been gzip-compressed");
+            let config: AppRootConfig = load_from_file(&config_path).await
+                .unwrap_or_else(|err| panic!("Loading {file_name} failed: {err}"))
+                .expect("The config file should exist");
+            assert_eq!(config, expected_config, "{file_name} round trip didn't work");
+            let mut txt_config = String::new();
+            std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(compressed.as_slice()), &mut txt_config).unwrap();
+            assert!(txt_config.contains("compressed tail docs"), "The tail docs should be in the decompressed {file_name}: '{txt_config}'");
+        }
+
+        // a plain text file named as if it were compressed
+        let misnamed_path = config_dir.join("misnamed.ron.gz");
+        std::fs::write(&misnamed_path, "(log_sub_config: (sink: Some(stdout)))").unwrap();
+        let error_message = load_from_file::<AppRootConfig>(&misnamed_path).await
+            .expect_err("Loading a non-gzip file named '.gz' should have failed")
+            .to_string();
+        assert!(error_message.contains("is it really gzip-compressed"), "The error should tell the file isn't gzip-compressed. Got {error_message}");
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[tokio::test]
+    async fn gzipped_configs_without_the_feature() {
+        let config_path = temp_config_path("ron.gz");
+        let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdError) } };
+        let error_message = save_to_file(&config, "", &config_path).await
+            .expect_err("Saving a '.gz' config file without the `gzip` feature should have failed")
+            .to_string();
+        assert!(error_message.contains("`gzip` feature"), "The error should tell the `gzip` feature is needed. Got {error_message}");
+        assert!(!config_path.exists(), "Nothing should have been written");
+        std::fs::write(&config_path, [0x1f, 0x8b]).unwrap();
+        let error_message = load_from_file::<AppRootConfig>(&config_path).await
+            .expect_err("Loading a '.gz' config file without the `gzip` feature should have failed")
+            .to_string();
+        assert!(error_message.contains("`gzip` feature"), "The error should tell the `gzip` feature is needed. Got {error_message}");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[tokio::test]
+    async fn backup_policies() {
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        let config_path = config_dir.join("app.config.ron");
+        let backup_policy = BackupPolicy {
+            suffix_or_pattern: "{timestamp}_{name}.orig".to_string(),
+            directory: Some(PathBuf::from("backups")),
+            keep: 2,
+        };
+
+        // backups go to the (created) directory, named after the pattern, pruned to `keep`
+        let mut backup_paths = Vec::new();
+        for generation in 1..=3 {
+            std::fs::write(&config_path, format!("generation {generation}")).unwrap();
+            let backup_path = backup_config_file(&config_path, &backup_policy).await.unwrap()
+                .expect("An existing config file should have been backed up");
+            assert_eq!(backup_path.parent(), Some(config_dir.join("backups").as_path()), "The backup should be in the backup directory");
+            let backup_name = backup_path.file_name().unwrap().to_string_lossy().to_string();
+            assert!(backup_name.ends_with("_app.config.ron.orig"), "The backup name '{backup_name}' doesn't follow the pattern");
+            backup_paths.push(backup_path);
+        }
+        let backups = config_file_backups_with_policy(&config_path, &backup_policy).await.unwrap();
+        assert_eq!(backups, backup_paths[1..], "Only the 2 most recent backups should have been kept");
+        assert_eq!(std::fs::read_to_string(&backups[1]).unwrap(), "generation 3", "The most recent backup doesn't hold the last config");
+        assert!(config_file_backups(&config_path).await.unwrap().is_empty(), "The default policy shouldn't see these backups");
+
+        // plain suffixes are followed by the timestamp
+        std::fs::write(&config_path, "()").unwrap();
+        let suffix_policy = BackupPolicy { suffix_or_pattern: "~".to_string(), ..BackupPolicy::default() };
+        let backup_path = backup_config_file(&config_path, &suffix_policy).await.unwrap().unwrap();
+        assert!(backup_path.file_name().unwrap().to_string_lossy().starts_with("app.config.ron~20"), "Wrong suffixed backup name: {backup_path:?}");
+
+        // unusable directories & patterns are reported
+        std::fs::write(&config_path, "()").unwrap();
+        let unusable_dir_policy = BackupPolicy { directory: Some(PathBuf::from("app.config.ron/backups")), ..BackupPolicy::default() };
+        let result = backup_config_file(&config_path, &unusable_dir_policy).await;
+        assert!(matches!(&result, Err(crate::Error::Io { message, .. }) if message.contains("backup directory")), "The unusable backup directory should have been reported. Got {result:?}");
+        let bad_pattern_policy = BackupPolicy { suffix_or_pattern: "{name}.orig".to_string(), ..BackupPolicy::default() };
+        let result = backup_config_file(&config_path, &bad_pattern_policy).await;
+        assert!(matches!(&result, Err(crate::Error::SavingConfig { message, .. }) if message.contains("invalid backup pattern")), "The bad pattern should have been reported. Got {result:?}");
+        assert!(config_path.exists(), "The config file should have been left in place");
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[tokio::test]
+    async fn large_config() {
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct LargeConfig {
+            hosts: Vec<String>,
+        }
+        impl OgreRootConfig for LargeConfig {}
+
+        let config_path = temp_config_path("yaml");
+        let config = LargeConfig { hosts: (0..100_000).map(|i| format!("host-{i:08}.cluster.internal.example.com")).collect() };
+        save_to_file(&config, "", &config_path).await
+            .expect("Saving the large config failed");
+        let file_size = std::fs::metadata(&config_path).unwrap().len();
+        assert!(file_size > 4 * 1024 * 1024, "The generated config should have several MB -- it has just {file_size} bytes");
+
+        let loaded_config: LargeConfig = load_existing(&config_path).await
+            .expect("Loading the large config failed");
+        assert_eq!(loaded_config, config, "The large config didn't round trip");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[tokio::test]
+    async fn string_and_file_serdes_match() {
+        let config_path = temp_config_path("yaml");
+        let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+        save_to_file(&config, "docs", &config_path).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), config_to_string(&config, SerdeFormat::Yaml, "docs").unwrap(),
+                   "Saved files should hold exactly what `config_to_string()` produces");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[tokio::test]
+    async fn backup_across_filesystems() {
+        let config_path = Path::new("/app/app.config.ron");
+        let backup_path = Path::new("/app/app.config.ron.bak");
+        let config_fs = MemoryFs::new().with_file(config_path, "()");
+
+        // renames crossing filesystems fall back to copying & removing
+        config_fs.fail_on(FsOperation::Rename, ErrorKind::CrossesDevices);
+        let backup_path_made = backup_config_file_in(&config_fs, config_path, &BackupPolicy::default()).await
+            .expect("The file should have been copied & removed")
+            .expect("An existing config file should have been backed up");
+        assert_eq!(config_fs.file_paths(), vec![backup_path_made.clone()], "The config file should have been moved to the backup");
+        assert_eq!(config_fs.contents(&backup_path_made).as_deref(), Some("()"), "The config file should have been copied to the backup");
+
+        // ... but, when that isn't possible either, both failures are reported -- leaving the config file in place
+        config_fs.heal();
+        config_fs.rename(&backup_path_made, config_path).await.unwrap();
+        config_fs.fail_on(FsOperation::Rename, ErrorKind::CrossesDevices);
+        config_fs.fail_on(FsOperation::Copy, ErrorKind::StorageFull);
+        let result = backup_config_file_in(&config_fs, config_path, &BackupPolicy::default()).await;
+        assert!(matches!(&result, Err(crate::Error::SavingConfig { message, .. }) if message.contains("nor copied")), "The failed copy should have been reported. Got {result:?}");
+        assert_eq!(config_fs.contents(config_path).as_deref(), Some("()"), "The config file should have been left untouched");
+
+        // other rename failures are reported as they are
+        config_fs.heal();
+        let failed_rename = Err(std::io::Error::from(ErrorKind::PermissionDenied));
+        let result = move_if_not_renamed(&config_fs, failed_rename, config_path, backup_path).await;
+        assert!(matches!(&result, Err(crate::Error::SavingConfig { message, .. }) if message.contains("couldn't be renamed")), "The failed rename should have been reported. Got {result:?}");
+        assert_eq!(config_fs.file_paths(), vec![config_path.to_path_buf()], "Nothing should have been copied nor removed");
+    }
+
+    #[tokio::test]
+    async fn missing_file_not_created() {
+        let config_path = temp_config_path("ron");
+        let config: Option<AppRootConfig> = crate::load_from_file(&config_path).await
+            .expect("A missing config file shouldn't be an error");
+        assert_eq!(config, None, "A missing config file should be told apart from one holding the defaults");
+        assert!(!config_path.exists(), "Nothing should have been created");
+
+        save_to_file(&AppRootConfig::default(), "", &config_path).await.unwrap();
+        let config: Option<AppRootConfig> = crate::load_from_file(&config_path).await.unwrap();
+        assert_eq!(config, Some(AppRootConfig::default()), "A config file holding the defaults should be loaded as such");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[tokio::test]
+    async fn post_load_normalization() {
+
+        /// A config whose identifiers are normalized to lowercase when loaded
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct ServiceConfig {
+            service_name: String,
+        }
+        impl Default for ServiceConfig {
+            fn default() -> Self {
+                Self { service_name: "Billing".to_string() }
+            }
+        }
+        impl OgreRootConfig for ServiceConfig {
+            fn post_load(&mut self, _context: &LoadContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+                self.service_name = self.service_name.to_lowercase();
+                Ok(())
+            }
+        }
+
+        let config_path = temp_config_path("ron");
+        let config: ServiceConfig = load_or_create_default(&config_path, "").await.unwrap();
+        assert_eq!(config.service_name, "billing", "The hook should have adjusted the default config just written");
+
+        std::fs::write(&config_path, r#"(service_name: "Payments")"#).unwrap();
+        let config: Option<ServiceConfig> = crate::load_from_file(&config_path).await.unwrap();
+        assert_eq!(config.map(|config| config.service_name).as_deref(), Some("payments"), "The hook should have adjusted the loaded config");
+        let config: ServiceConfig = load_or_create_default(&config_path, "").await.unwrap();
+        assert_eq!(config.service_name, "payments", "The hook should have adjusted the existing config");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[test]
+    fn load_context_dirs() {
+        let context = LoadContext { path: PathBuf::from("/etc/app/app.config.ron"), format: SerdeFormat::Ron };
+        assert_eq!(context.config_dir(), Some(Path::new("/etc/app")), "Wrong dir for a config file");
+        let context = LoadContext { path: PathBuf::from("https://config.example.com/app.config.ron"), format: SerdeFormat::Ron };
+        assert_eq!(context.config_dir(), None, "Remote configs have no dir to resolve paths against");
+    }
+
+    #[tokio::test]
+    async fn pre_save_adjustment() {
+
+        /// A config with a field derived at runtime, not to be persisted
+        #[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(default)]
+        struct ClusterConfig {
+            seed_host: String,
+            discovered_peers: Vec<String>,
+        }
+        impl OgreRootConfig for ClusterConfig {
+            fn pre_save(&self) -> Option<Self> {
+                Some(Self { discovered_peers: vec![], ..self.clone() })
+            }
+        }
+
+        let config = ClusterConfig { seed_host: "seed.example.com".to_string(), discovered_peers: vec!["peer-1.example.com".to_string()] };
+        let config_path = temp_config_path("yaml");
+        save_to_file(&config, "", &config_path).await.unwrap();
+        let txt_config = std::fs::read_to_string(&config_path).unwrap();
+        assert!(txt_config.contains("seed.example.com") && !txt_config.contains("peer-1"), "Only the adjusted config should have been written: '{txt_config}'");
+        assert_eq!(config.discovered_peers, vec!["peer-1.example.com".to_string()], "The config itself should be left untouched");
+        let loaded_config: Option<ClusterConfig> = crate::load_from_file(&config_path).await.unwrap();
+        assert_eq!(loaded_config, Some(ClusterConfig { seed_host: "seed.example.com".to_string(), discovered_peers: vec![] }), "Wrong config loaded back");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[tokio::test]
+    async fn config_conversion() {
+        let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdError) } };
+        let ron_path = temp_config_path("ron");
+        let yaml_path = temp_config_path("yaml");
+        let converted_ron_path = temp_config_path("ron");
+        save_to_file(&config, "I am the docs", &ron_path).await.unwrap();
+
+        convert_config::<AppRootConfig>(&ron_path, &yaml_path, "I am the docs").await
+            .expect("The RON config should have been converted to YAML");
+        let yaml_txt = std::fs::read_to_string(&yaml_path).unwrap();
+        assert!(yaml_txt.contains("sink: stderror"), "The converted file should be in YAML: '{yaml_txt}'");
+        assert!(yaml_txt.contains("# I am the docs"), "The tail docs should have been kept: '{yaml_txt}'");
+        assert_eq!(load_from_file(&yaml_path).await.unwrap(), Some(config.clone()), "The YAML config doesn't hold the original one");
+
+        convert_config::<AppRootConfig>(&yaml_path, &converted_ron_path, "I am the docs").await
+            .expect("The YAML config should have been converted back to RON");
+        assert_eq!(std::fs::read_to_string(&converted_ron_path).unwrap(), std::fs::read_to_string(&ron_path).unwrap(),
+                   "The round trip should have given back the original RON file");
+
+        // unsupported extensions are refused on either side -- writing nothing
+        let unsupported_path = temp_config_path("toml");
+        let result = convert_config::<AppRootConfig>(&ron_path, &unsupported_path, "").await;
+        assert!(matches!(&result, Err(crate::Error::UnsupportedConfigFileFormat { .. })), "The unsupported destination should have been refused. Got {result:?}");
+        assert!(!unsupported_path.exists(), "Nothing should have been written");
+        std::fs::write(&unsupported_path, "").unwrap();
+        let result = convert_config::<AppRootConfig>(&unsupported_path, &yaml_path, "").await;
+        assert!(matches!(&result, Err(crate::Error::UnsupportedConfigFileFormat { .. })), "The unsupported source should have been refused. Got {result:?}");
+        let missing_path = temp_config_path("yaml");
+        let result = convert_config::<AppRootConfig>(&missing_path, &ron_path, "").await;
+        assert!(matches!(&result, Err(crate::Error::ConfigFileNotFound { .. })), "The missing source should have been reported. Got {result:?}");
+
+        [ron_path, yaml_path, converted_ron_path, unsupported_path].iter().for_each(|path| _ = std::fs::remove_file(path));
+    }
+
+    #[tokio::test]
+    async fn secret_refs_kept_on_conversion() {
+
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct DbConfig {
+            user: String,
+            password: String,
+        }
+        impl OgreRootConfig for DbConfig {}
+
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(config_dir.join("secrets")).unwrap();
+        std::fs::create_dir_all(config_dir.join("converted")).unwrap();
+        std::fs::write(config_dir.join("secrets/db_password"), "s3cr3t\n").unwrap();
+        let ron_path = config_dir.join("db.ron");
+        std::fs::write(&ron_path, r#"(user: "admin", password: (secret_ref: "secrets/db_password"))"#).unwrap();
+
+        for yaml_path in [config_dir.join("db.yaml"), config_dir.join("converted/db.yaml")] {
+            convert_config::<DbConfig>(&ron_path, &yaml_path, "").await.unwrap();
+            let yaml_txt = std::fs::read_to_string(&yaml_path).unwrap();
+            assert!(!yaml_txt.contains("s3cr3t") && yaml_txt.contains("secret_ref"), "The converted file should keep the secret reference: '{yaml_txt}'");
+            let converted_config: DbConfig = load_existing(&yaml_path).await.unwrap();
+            assert_eq!(converted_config.password, "s3cr3t", "The converted {yaml_path:?} should still reference the same secret file");
+        }
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[tokio::test]
+    async fn docs_kept_on_conversion() {
+        let (ron_path, yaml_path) = (temp_config_path("ron"), temp_config_path("yaml"));
+        save_to_file(&AppRootConfig::default(), "I am\nthe docs", &ron_path).await.unwrap();
+        convert_config::<AppRootConfig>(&ron_path, &yaml_path, "").await
+            .expect("The RON config should have been converted to YAML");
+        let yaml_txt = std::fs::read_to_string(&yaml_path).unwrap();
+        assert!(yaml_txt.contains("\n# I am\n# the docs"), "The RON docs should have been carried over as YAML comments: '{yaml_txt}'");
+        assert!(!yaml_txt.contains("/*"), "No RON comment should have been carried over: '{yaml_txt}'");
+        assert_eq!(load_from_file(&yaml_path).await.unwrap(), Some(AppRootConfig::default()), "The converted file should load");
+        _ = std::fs::remove_file(&ron_path);
+        _ = std::fs::remove_file(&yaml_path);
+    }
+
+    #[tokio::test]
+    async fn format_override() {
+        let is_unsupported_format = |cause: &(dyn std::error::Error + Send + Sync + 'static)| matches!(cause.downcast_ref::<crate::Error>(), Some(crate::Error::UnsupportedConfigFileFormat { .. }));
+        let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+
+        let ron_path = temp_config_path("ron");
+        let result = save_to_file_with_options(&config, "", &ron_path, &SaveOptions { format: Some(SerdeFormat::Yaml), ..SaveOptions::default() }).await;
+        assert!(matches!(&result, Err(crate::Error::SavingConfig { cause, .. }) if is_unsupported_format(cause.as_ref())),
+                "Saving YAML into a '.ron' file should have been refused. Got {result:?}");
+        assert!(!ron_path.exists(), "Nothing should have been written on the contradiction");
+        save_to_file_with_options(&config, "", &ron_path, &SaveOptions { format: Some(SerdeFormat::Ron), ..SaveOptions::default() }).await
+            .expect("Agreeing overrides should be accepted");
+        let result = load_from_file_with_options::<AppRootConfig>(&ron_path, &LoadOptions { format: Some(SerdeFormat::Yaml), ..LoadOptions::default() }).await;
+        assert!(matches!(&result, Err(crate::Error::LoadingConfig { cause, .. }) if is_unsupported_format(cause.as_ref())),
+                "Loading a '.ron' file as YAML should have been refused. Got {result:?}");
+        _ = std::fs::remove_file(&ron_path);
+
+        // the override is authoritative for unknown extensions
+        let conf_path = temp_config_path("conf");
+        save_to_file_with_options(&config, "", &conf_path, &SaveOptions { format: Some(SerdeFormat::Yaml), ..SaveOptions::default() }).await
+            .expect("The override should have been used for the unknown extension");
+        assert!(std::fs::read_to_string(&conf_path).unwrap().contains("sink: stdout"), "The config should have been saved in YAML");
+        let loaded_config = load_from_file_with_options::<AppRootConfig>(&conf_path, &LoadOptions { format: Some(SerdeFormat::Yaml), ..LoadOptions::default() }).await
+            .expect("The override should have been used for the unknown extension");
+        assert_eq!(loaded_config, Some(config), "The config didn't load back");
+        assert!(load_from_file::<AppRootConfig>(&conf_path).await.is_err(), "Without the override, the unknown extension should have been refused");
+        _ = std::fs::remove_file(&conf_path);
+    }
+
+    #[tokio::test]
+    async fn loaded_config() {
+        let config_path = temp_config_path("ron");
+        let mut created_config: LoadedConfig<AppRootConfig> = load_or_create_default_traced(&config_path, "").await
+            .expect("The default config file couldn't be created");
+        assert_eq!(created_config.config, AppRootConfig::default(), "Wrong config");
+        assert_eq!(created_config.path, config_path, "Wrong path");
+        assert_eq!(created_config.format, SerdeFormat::Ron, "Wrong format");
+        assert!(created_config.created_now, "The config file should have been reported as created");
+        assert_eq!(created_config.fingerprint, LoadedFileFingerprint::of(&config_path).await.unwrap(), "Wrong fingerprint");
+
+        created_config.config.log_sub_config.sink = Some(Dummy::StdOut);
+        created_config.save_to_file("").await.expect("The config couldn't be saved back");
+        assert!(!created_config.created_now, "Once saved, the config file is no longer a new one");
+        assert_eq!(created_config.fingerprint, LoadedFileFingerprint::of(&config_path).await.unwrap(), "The fingerprint should have been updated");
+
+        let mut loaded_config: LoadedConfig<AppRootConfig> = load_or_create_default_traced(&config_path, "").await
+            .expect("The existing config file couldn't be loaded");
+        assert_eq!(loaded_config.config.log_sub_config.sink, Some(Dummy::StdOut), "The config wasn't saved to the same file");
+        assert_eq!(loaded_config.path, config_path, "Wrong path");
+        assert_eq!(loaded_config.format, SerdeFormat::Ron, "Wrong format");
+        assert!(!loaded_config.created_now, "The config file already existed");
+        assert_eq!(loaded_config.fingerprint, LoadedFileFingerprint::of(&config_path).await.unwrap(), "Wrong fingerprint");
+
+        // someone else changes the file
+        std::fs::write(&config_path, "(log_sub_config: (sink: Some(null)))").unwrap();
+        let result = loaded_config.save_to_file("").await;
+        assert!(matches!(result, Err(crate::Error::ConfigChangedOnDisk { .. })), "Saving over changes made by others should be refused. Got {result:?}");
+        assert!(std::fs::read_to_string(&config_path).unwrap().contains("null"), "The changes of others should have been kept");
+        _ = std::fs::remove_file(&config_path);
+    }
+
     #[tokio::test]
     async fn load_or_create_default_test() {
-        let _config_path = std::env::temp_dir().join("cli-config-load_and_save.ron");
+        let _config_path = temp_config_path("ron");
         let config_path = _config_path.to_string_lossy();
         let _expected_config = AppRootConfig::default();
         let observed_config_new_file: AppRootConfig =
@@ -202,6 +1976,484 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn get_or_init_config_test() {
+        let config_path = temp_config_path("ron");
+        invalidate_cache().await;
+        let first_config: Arc<AppRootConfig> = get_or_init_config(&config_path, "").await.unwrap();
+        assert!(config_path.exists(), "The config file should have been created on the first call");
+        std::fs::remove_file(&config_path).unwrap();
+        let second_config: Arc<AppRootConfig> = get_or_init_config(&config_path, "").await.unwrap();
+        assert!(Arc::ptr_eq(&first_config, &second_config), "The cached config should have been shared");
+        assert!(!config_path.exists(), "The config file should not have been re-read (and, so, re-created) on the second call");
+        invalidate_cache().await;
+        let _third_config: Arc<AppRootConfig> = get_or_init_config(&config_path, "").await.unwrap();
+        assert!(config_path.exists(), "After invalidating the cache, the config file should have been loaded (and, so, created) again");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[tokio::test]
+    async fn get_or_init_config_canonical_keys() {
+        let config_dir = temp_config_path("dir");
+        std::fs::create_dir_all(config_dir.join("sub")).unwrap();
+        let config_path = config_dir.join("config.ron");
+        let first_config: Arc<AppRootConfig> = get_or_init_config(&config_path, "").await.unwrap();
+        for other_spelling in [config_dir.join(".").join("config.ron"), config_dir.join("sub").join("..").join("config.ron")] {
+            let other_config: Arc<AppRootConfig> = get_or_init_config(&other_spelling, "").await.unwrap();
+            assert!(Arc::ptr_eq(&first_config, &other_config), "{other_spelling:?} should have shared the config cached for {config_path:?}");
+        }
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[tokio::test]
+    async fn get_or_init_config_doesnt_block_other_keys() {
+        let config_dir = temp_config_path("dir");
+        std::fs::create_dir_all(&config_dir).unwrap();
+        let (slow_path, fast_path) = (config_dir.join("slow.ron"), config_dir.join("fast.ron"));
+        // keeps the slow config loading until the fast one is done
+        let slow_key = (TypeId::of::<AppRootConfig>(), canonical_cache_path(&slow_path).await);
+        let slow_cell = Arc::clone(CONFIGS_CACHE.lock().unwrap().entry(slow_key).or_default());
+        let (fast_loaded_sender, fast_loaded_receiver) = tokio::sync::oneshot::channel::<()>();
+        let slow_load = tokio::spawn(async move {
+            slow_cell.get_or_init(|| async {
+                _ = fast_loaded_receiver.await;
+                Arc::new(AppRootConfig::default()) as Arc<dyn Any + Send + Sync>
+            }).await;
+        });
+        tokio::task::yield_now().await;
+        let fast_config = tokio::time::timeout(Duration::from_secs(5), get_or_init_config::<AppRootConfig>(&fast_path, "")).await
+            .expect("Loading a config should not wait for the loading of another one");
+        assert!(fast_config.is_ok(), "Loading {fast_path:?} failed: {:?}", fast_config.err());
+        fast_loaded_sender.send(()).unwrap();
+        slow_load.await.unwrap();
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[tokio::test]
+    async fn durable_save() {
+        let durable = SaveOptions { durable: true, ..SaveOptions::default() };
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        for file_name in ["app.config.ron", "app.config.yaml"] {
+            let config_path = config_dir.join(file_name);
+            _ = std::fs::remove_file(&config_path);
+            for sink in [Dummy::StdOut, Dummy::Null] {
+                let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(sink) } };
+                save_to_file_with_options(&config, "I am the docs", &config_path, &durable).await
+                    .unwrap_or_else(|err| panic!("Durably saving {file_name} failed: {err}"));
+                let loaded_config: AppRootConfig = load_existing(&config_path).await.unwrap();
+                assert_eq!(loaded_config, config, "Durably saved {file_name} didn't round-trip");
+            }
+            let temp_file_prefix = format!("{file_name}.tmp-");
+            let leftovers = std::fs::read_dir(&config_dir).unwrap()
+                .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().starts_with(&temp_file_prefix))
+                .count();
+            assert_eq!(leftovers, 0, "Temporary files were left behind for {file_name}");
+            _ = std::fs::remove_file(&config_path);
+        }
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlinked_save() {
+        let base_dir = temp_config_dir();
+        let (store_dir, app_dir) = (base_dir.join("store"), base_dir.join("app"));
+        std::fs::create_dir_all(&store_dir).unwrap();
+        std::fs::create_dir_all(&app_dir).unwrap();
+        let (target_path, link_path) = (store_dir.join("myapp.yaml"), app_dir.join("myapp.config.yaml"));
+        let config = |sink| AppRootConfig { log_sub_config: LogConfig { sink: Some(sink) } };
+
+        // default: written through the link, even durably -- with the temp file in the target's dir
+        save_to_file(&config(Dummy::Null), "", &target_path).await.unwrap();
+        std::os::unix::fs::symlink("../store/myapp.yaml", &link_path).unwrap();
+        let durable = SaveOptions { durable: true, ..SaveOptions::default() };
+        for save_options in [SaveOptions::default(), durable] {
+            save_to_file_with_options(&config(Dummy::StdOut), "", &link_path, &save_options).await
+                .unwrap_or_else(|err| panic!("Saving through the symlink with {save_options:?} failed: {err}"));
+            assert!(std::fs::symlink_metadata(&link_path).unwrap().file_type().is_symlink(), "The symlink should have been kept with {save_options:?}");
+            let target_config: AppRootConfig = load_existing(&target_path).await.unwrap();
+            assert_eq!(target_config, config(Dummy::StdOut), "The link's target wasn't updated with {save_options:?}");
+            save_to_file(&config(Dummy::Null), "", &target_path).await.unwrap();
+        }
+
+        // backups copy the target, leaving the link in place
+        let backup_path = backup_config_file(&link_path, &BackupPolicy { keep: 1, ..BackupPolicy::default() }).await.unwrap().expect("The symlinked config should have been backed up");
+        assert!(std::fs::symlink_metadata(&link_path).unwrap().file_type().is_symlink(), "Backing up shouldn't move the symlink away");
+        assert!(!std::fs::symlink_metadata(&backup_path).unwrap().file_type().is_symlink(), "The backup should be a regular file");
+        assert_eq!(std::fs::read_to_string(&backup_path).unwrap(), std::fs::read_to_string(&target_path).unwrap(), "The backup should hold the target's contents");
+
+        // opting out: the link is replaced by a regular file, leaving the target alone
+        let replace = SaveOptions { replace_symlink: true, ..SaveOptions::default() };
+        save_to_file_with_options(&config(Dummy::StdError), "", &link_path, &replace).await.unwrap();
+        assert!(!std::fs::symlink_metadata(&link_path).unwrap().file_type().is_symlink(), "The symlink should have been replaced");
+        let link_config: AppRootConfig = load_existing(&link_path).await.unwrap();
+        assert_eq!(link_config, config(Dummy::StdError), "The replacing file doesn't hold the saved config");
+        let target_config: AppRootConfig = load_existing(&target_path).await.unwrap();
+        assert_eq!(target_config, config(Dummy::Null), "The former link's target shouldn't have been touched");
+        _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[tokio::test]
+    async fn locked_saves() {
+        let config_path = temp_config_path("ron");
+        let config = |sink| AppRootConfig { log_sub_config: LogConfig { sink: Some(sink) } };
+        save_to_file(&config(Dummy::Null), "", &config_path).await.unwrap();
+        let locked = |timeout_millis| SaveOptions { locked: Some(Duration::from_millis(timeout_millis)), ..SaveOptions::default() };
+
+        // a contending save waits for the lock to be released
+        let lock = lock_config_file(&config_path, Duration::ZERO).await.expect("The lock should be free");
+        let contending_save = tokio::spawn({
+            let config_path = config_path.clone();
+            async move { save_to_file_with_options(&config(Dummy::StdOut), "", &config_path, &locked(5000)).await }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let held_config: AppRootConfig = load_existing(&config_path).await.unwrap();
+        assert_eq!(held_config, config(Dummy::Null), "The config shouldn't have been written while the lock was held");
+        drop(lock);
+        contending_save.await.unwrap().expect("The contending save should have succeeded once the lock was released");
+        let saved_config: AppRootConfig = load_existing(&config_path).await.unwrap();
+        assert_eq!(saved_config, config(Dummy::StdOut), "The contending save should have been written after the lock was released");
+
+        // waiting expires
+        let _lock = lock_config_file(&config_path, Duration::ZERO).await.expect("The lock should have been released");
+        let result = save_to_file_with_options(&config(Dummy::StdError), "", &config_path, &locked(100)).await;
+        assert!(matches!(result, Err(crate::Error::ConfigLocked { .. })), "The lock wait should have expired. Got {result:?}");
+        let saved_config: AppRootConfig = load_existing(&config_path).await.unwrap();
+        assert_eq!(saved_config, config(Dummy::StdOut), "The config shouldn't have been written without the lock");
+        _ = std::fs::remove_file(&config_path);
+        _ = std::fs::remove_file(lock_file_path_of(&config_path));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[ignore = "needs read-only dirs, which aren't enforced for root: run with `cargo test -- --ignored` as a regular user"]
+    async fn read_only_create() {
+        use crate::test_commons::fs_fixtures::{read_only_dir, remove_read_only_dir};
+        use crate::test_commons::warnings_fixtures::{capture_warnings, captured_warnings};
+        capture_warnings();
+        let read_only_dir = read_only_dir("cli-config-read_only_create", &[]);
+        let config_path = read_only_dir.join("app.config.ron");
+
+        let result = load_or_create_default::<AppRootConfig>(&config_path, "").await;
+        assert!(result.is_err_and(|err| err.is_persistence_error()), "Failing to create the default config should be reported by default");
+
+        let config: AppRootConfig = load_or_create_default_with_policy(&config_path, "", OnCreateFailure::WarnAndUseDefaults).await
+            .expect("The defaults should have been used");
+        assert_eq!(config, AppRootConfig::default(), "The default config should have been returned");
+        assert!(!config_path.exists(), "Nothing should have been written");
+        let warnings = captured_warnings(&format!("{config_path:?}"));
+        assert!(warnings.iter().any(|warning| warning.kind == LoadWarningKind::FailedPersistence),
+                "The failed creation should have been warned about through the sink: {warnings:?}");
+        remove_read_only_dir(&read_only_dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn durable_save_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let config_path = temp_config_path("ron");
+        save_to_file(&AppRootConfig::default(), "", &config_path).await.unwrap();
+        std::fs::set_permissions(&config_path, std::fs::Permissions::from_mode(0o600)).unwrap();
+
+        let durable = SaveOptions { durable: true, ..SaveOptions::default() };
+        save_to_file_with_options(&AppRootConfig::default(), "I am the docs", &config_path, &durable).await.unwrap();
+        let mode = std::fs::metadata(&config_path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600, "The permissions of the replaced file should have been kept");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[tokio::test]
+    async fn create_parents() {
+        let base_dir = temp_config_dir();
+        let config_path = base_dir.join("myapp").join("app.config.ron");
+
+        let result = save_to_file(&AppRootConfig::default(), "", &config_path).await;
+        assert!(matches!(result, Err(crate::Error::SavingConfig { .. })), "Parents shouldn't be created by default. Got {result:?}");
+        assert!(!base_dir.exists(), "No directories should have been created");
+
+        let _: AppRootConfig = load_or_create_default(&config_path, "").await
+            .expect("The two missing levels of parent directories should have been created");
+        assert!(config_path.exists(), "The config file wasn't created at {config_path:?}");
+
+        let create_parents = SaveOptions { create_parents: true, ..SaveOptions::default() };
+        let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+        save_to_file_with_options(&config, "", &config_path, &create_parents).await
+            .expect("Existing parents should be left as they are");
+        assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap(), config, "The config wasn't saved into the existing parents");
+        _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[tokio::test]
+    async fn read_value_at_test() {
+        let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdError) } };
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        for file_name in ["app.config.ron", "app.config.yaml"] {
+            let config_path = config_dir.join(file_name);
+            save_to_file(&config, "I am the docs", &config_path).await.unwrap();
+            assert_eq!(read_value_at(&config_path, "/log_sub_config/sink").await.unwrap(), Some(serde_json::json!("stderror")), "Wrong nested value read from {file_name}");
+            assert_eq!(read_value_at(&config_path, "log_sub_config/sink").await.unwrap(), Some(serde_json::json!("stderror")), "The leading '/' should be optional for {file_name}");
+            assert_eq!(read_value_at(&config_path, "log_sub_config").await.unwrap(), Some(serde_json::json!({"sink": "stderror"})), "Wrong sub-config read from {file_name}");
+            assert_eq!(read_value_at(&config_path, "/log_sub_config/nothing").await.unwrap(), None, "Missing values should be reported as `None` for {file_name}");
+            _ = std::fs::remove_file(&config_path);
+        }
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[tokio::test]
+    async fn set_value_at_test() {
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        for file_name in ["app.config.ron", "app.config.yaml"] {
+            let config_path = config_dir.join(file_name);
+            save_to_file(&AppRootConfig::default(), "", &config_path).await.unwrap();
+
+            set_value_at::<AppRootConfig>(&config_path, "log_sub_config/sink", serde_json::json!("stdout"), "I am the docs").await
+                .unwrap_or_else(|err| panic!("Setting a nested value in {file_name} failed: {err}"));
+            assert_eq!(read_value_at(&config_path, "/log_sub_config/sink").await.unwrap(), Some(serde_json::json!("stdout")), "The value set in {file_name} wasn't read back");
+            let config: AppRootConfig = load_existing(&config_path).await.unwrap();
+            assert_eq!(config.log_sub_config.sink, Some(Dummy::StdOut), "The value set in {file_name} wasn't loaded back");
+
+            let config_txt = std::fs::read_to_string(&config_path).unwrap();
+            let result = set_value_at::<AppRootConfig>(&config_path, "/log_sub_config/sink", serde_json::json!("nowhere"), "").await;
+            assert!(matches!(result, Err(crate::Error::SavingConfig { .. })), "An invalid value should have been rejected for {file_name}. Got {result:?}");
+            let result = set_value_at::<AppRootConfig>(&config_path, "/log_sub_config/nothing/deeper", serde_json::json!(1), "").await;
+            assert!(matches!(result, Err(crate::Error::SavingConfig { .. })), "A non-existing pointer should have been rejected for {file_name}. Got {result:?}");
+            assert_eq!(std::fs::read_to_string(&config_path).unwrap(), config_txt, "Rejected changes shouldn't touch {file_name}");
+            _ = std::fs::remove_file(&config_path);
+        }
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[tokio::test]
+    async fn repeated_recoveries() {
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        let config_path = config_dir.join("app.config.ron");
+        let mut broken_paths = vec![];
+        // recovered in a row -- most likely within the same second
+        for broken_contents in ["(log_sub_config: (", "(log_sub_config: (sink: Some(stdout)"] {
+            std::fs::write(&config_path, broken_contents).unwrap();
+            let broken_path = recover_config_file::<AppRootConfig>(&config_path, "").await.unwrap();
+            assert_eq!(std::fs::read_to_string(&broken_path).unwrap(), broken_contents, "The broken file should have been kept at {broken_path:?}");
+            broken_paths.push(broken_path);
+        }
+        assert_ne!(broken_paths[0], broken_paths[1], "Each recovery should keep its own broken file");
+        assert_eq!(std::fs::read_to_string(&broken_paths[0]).unwrap(), "(log_sub_config: (", "The first broken file shouldn't have been overwritten");
+        assert_eq!(load_existing::<AppRootConfig>(&config_path).await.unwrap(), AppRootConfig::default(), "The defaults should have been written");
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[tokio::test]
+    async fn set_value_at_keeps_secret_refs_and_docs() {
+
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct DbConfig {
+            user: String,
+            password: String,
+        }
+        impl OgreRootConfig for DbConfig {}
+
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(config_dir.join("secrets")).unwrap();
+        std::fs::write(config_dir.join("secrets/db_password"), "s3cr3t\n").unwrap();
+        let config_path = config_dir.join("db.yaml");
+        save_to_file(&DbConfig { user: "admin".to_string(), password: "<ref>".to_string() }, "I am the docs", &config_path).await.unwrap();
+        let config_txt = std::fs::read_to_string(&config_path).unwrap().replace("password: <ref>", "password:\n  secret_ref: secrets/db_password");
+        std::fs::write(&config_path, config_txt).unwrap();
+
+        set_value_at::<DbConfig>(&config_path, "/user", serde_json::json!("root"), "").await.unwrap();
+        let config_txt = std::fs::read_to_string(&config_path).unwrap();
+        assert!(!config_txt.contains("s3cr3t") && config_txt.contains("secret_ref"), "The secret should have been kept as a reference: '{config_txt}'");
+        assert!(config_txt.contains("# I am the docs"), "The docs of the file should have been kept: '{config_txt}'");
+        assert_eq!(load_existing::<DbConfig>(&config_path).await.unwrap(), DbConfig { user: "root".to_string(), password: "s3cr3t".to_string() },
+                   "The value should have been set, with the secret still referenced");
+
+        // setting the secret itself replaces its reference
+        set_value_at::<DbConfig>(&config_path, "/password", serde_json::json!("n3w"), "").await.unwrap();
+        let config_txt = std::fs::read_to_string(&config_path).unwrap();
+        assert!(config_txt.contains("n3w") && !config_txt.contains("secret_ref"), "The value set should have replaced the reference: '{config_txt}'");
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[tokio::test]
+    async fn backup_rotation() {
+        let config_path = Path::new("/app/app.config.ron");
+        let config_fs = MemoryFs::new();
+        config_fs.create_dir_all(config_path.parent().unwrap()).await.unwrap();
+        let backup_naming = BackupNaming::new(config_path, &BackupPolicy::default()).unwrap();
+
+        let keep_3 = BackupPolicy { keep: 3, ..BackupPolicy::default() };
+        let mut backup_paths = Vec::new();
+        for generation in 1..=4 {
+            config_fs.write(config_path, format!("generation {generation}").as_bytes()).await.unwrap();
+            let backup_path = backup_config_file_in(&config_fs, config_path, &keep_3).await.unwrap()
+                .expect("An existing config file should have been backed up");
+            assert!(!config_fs.exists(config_path).await, "The config file should have been moved to the backup");
+            backup_paths.push(backup_path);
+            let backups = list_config_file_backups(&config_fs, config_path, &backup_naming).await.unwrap();
+            assert_eq!(backups, backup_paths[backup_paths.len().saturating_sub(3)..], "Unexpected backups after {generation} rewrites");
+        }
+        assert_eq!(config_fs.contents(&backup_paths[3]).as_deref(), Some("generation 4"), "The most recent backup doesn't hold the last config");
+        assert!(!config_fs.exists(&backup_paths[0]).await, "The oldest backup should have been pruned");
+        assert_eq!(backup_config_file_in(&config_fs, config_path, &keep_3).await.unwrap(), None, "There should be nothing to back up");
+    }
+
+    #[tokio::test]
+    async fn ron_line_comment_docs() {
+        let config_path = temp_config_path("ron");
+        let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+        let save_options = SaveOptions { comment_style: Some(crate::CommentStyle::LinePrefix("// ".to_string())), ..SaveOptions::default() };
+        save_to_file_with_options(&config, "I am\nthe docs", &config_path, &save_options).await.unwrap();
+        let txt_config = std::fs::read_to_string(&config_path).unwrap();
+        assert!(txt_config.ends_with("\n// I am\n// the docs"), "The docs should have been rendered as RON line comments: '{txt_config}'");
+        assert!(!txt_config.contains("/*"), "No block comment should have been used: '{txt_config}'");
+        assert_eq!(load_from_file(&config_path).await.unwrap(), Some(config), "The file should still load");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[tokio::test]
+    async fn forward_compatible_loading() {
+
+        /// A strict config, as seen by the older binaries
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct StrictConfig {
+            port: u16,
+            log: LogConfig,
+        }
+        impl Default for StrictConfig {
+            fn default() -> Self {
+                Self { port: 8080, log: LogConfig { sink: Some(Dummy::StdError) } }
+            }
+        }
+        impl OgreRootConfig for StrictConfig {}
+
+        let drifted_configs = [
+            ("ron", "(port: 9090, threads: 4, log: (sink: Some(stdout), colors: true))", "(log: ())", "(port: 9090"),
+            ("yaml", "port: 9090\nthreads: 4\nlog:\n  sink: stdout\n  colors: true\n", "log: {}\n", "port: [9090\n"),
+        ];
+        for (extension, newer_txt, older_txt, broken_txt) in drifted_configs {
+            let config_path = temp_config_path(extension);
+            std::fs::write(&config_path, newer_txt).unwrap();
+            assert!(load_from_file::<StrictConfig>(&config_path).await.is_err(), "Unknown {extension} fields should fail strict loads");
+            let config = load_forward_compatible::<StrictConfig>(&config_path).await
+                .unwrap_or_else(|err| panic!("Unknown {extension} fields should have been ignored: {err}"));
+            assert_eq!(config, Some(StrictConfig { port: 9090, log: LogConfig { sink: Some(Dummy::StdOut) } }), "Wrong config loaded from the newer {extension} file");
+
+            std::fs::write(&config_path, older_txt).unwrap();
+            assert!(load_from_file::<StrictConfig>(&config_path).await.is_err(), "Missing {extension} fields should fail strict loads");
+            let config = load_forward_compatible::<StrictConfig>(&config_path).await
+                .unwrap_or_else(|err| panic!("Missing {extension} fields should have taken their defaults: {err}"));
+            assert_eq!(config, Some(StrictConfig::default()), "Wrong config loaded from the older {extension} file");
+
+            std::fs::write(&config_path, broken_txt).unwrap();
+            let result = load_forward_compatible::<StrictConfig>(&config_path).await;
+            assert!(result.is_err(), "Syntax errors in {extension} files should still be reported. Got {result:?}");
+            _ = std::fs::remove_file(&config_path);
+        }
+    }
+
+    #[tokio::test]
+    async fn type_provided_docs() {
+
+        /// A config documenting itself -- as a derive macro would do
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct SelfDocumentedConfig {
+            workers: u8,
+        }
+        impl OgreRootConfig for SelfDocumentedConfig {
+            fn docs() -> Option<&'static str> {
+                Some("workers: how many threads serve the requests")
+            }
+        }
+
+        let config_path = temp_config_path("yaml");
+        save_to_file(&SelfDocumentedConfig { workers: 4 }, "", &config_path).await.unwrap();
+        let txt_config = std::fs::read_to_string(&config_path).unwrap();
+        assert!(txt_config.contains("# workers: how many threads serve the requests"), "The type's docs should have been used: '{txt_config}'");
+        assert_eq!(load_existing::<SelfDocumentedConfig>(&config_path).await.unwrap(), SelfDocumentedConfig { workers: 4 }, "The documented config didn't load back");
+
+        save_to_file(&SelfDocumentedConfig { workers: 4 }, "explicit docs", &config_path).await.unwrap();
+        let txt_config = std::fs::read_to_string(&config_path).unwrap();
+        assert!(txt_config.contains("# explicit docs") && !txt_config.contains("threads"), "Explicit docs should take precedence: '{txt_config}'");
+
+        std::fs::remove_file(&config_path).unwrap();
+        load_or_create_default::<SelfDocumentedConfig>(&config_path, "").await.unwrap();
+        assert!(std::fs::read_to_string(&config_path).unwrap().contains("threads serve"), "Default files should also get the type's docs");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[test]
+    fn enum_values_in_docs() {
+        assert!(DOCS.contains("pub sink: Option<Dummy>,    // possible values: null, stdout, stderror"),
+                "The possible values of the enum fields are missing from the docs:\n{}", DOCS.as_str());
+    }
+
+    #[test]
+    fn docs_urls() {
+        static CONFIGS_DIR_SRC: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/src/testkit/");
+        let docs = documented_config_models_with_docs_url(&CONFIGS_DIR_SRC, "https://docs.example.com/");
+        assert!(docs.contains("pub log_sub_config: LogConfig,    // see https://docs.example.com/#log_sub_config"),
+                "The docs URL of the `log_sub_config` section is missing:\n{docs}");
+        assert!(docs.contains("pub sink: Option<Dummy>,    // possible values: null, stdout, stderror\n"),
+                "Non-section fields shouldn't be linked:\n{docs}");
+        assert!(!DOCS.contains("https://"), "No URLs should be present if no docs URL was given:\n{}", DOCS.as_str());
+    }
+
+    #[test]
+    fn docs_computed_once() {
+        static CONFIGS_DIR_SRC: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/src/testkit/");
+        let computations = std::sync::atomic::AtomicUsize::new(0);
+        let compute = || {
+            computations.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            computed_config_models_docs(&CONFIGS_DIR_SRC, Some("https://docs_computed_once.example.com/"))
+        };
+        let docs = cached_docs(&CONFIGS_DIR_SRC, Some("https://docs_computed_once.example.com/"), compute);
+        let cached = cached_docs(&CONFIGS_DIR_SRC, Some("https://docs_computed_once.example.com/"), compute);
+        assert_eq!(cached, docs, "The cached docs differ from the computed ones");
+        assert_eq!(computations.load(std::sync::atomic::Ordering::Relaxed), 1, "The docs should have been computed only once");
+        assert_eq!(documented_config_models_with_docs_url(&CONFIGS_DIR_SRC, "https://docs_computed_once.example.com/"), docs,
+                   "The public API should have used the cached docs");
+        assert_ne!(documented_config_models(&CONFIGS_DIR_SRC), docs, "Docs for other URLs should be cached apart");
+    }
+
+    #[test]
+    fn enum_renaming_rules() {
+        let test = |rule, expected| assert_eq!(renamed_variant("StdOut", Some(rule)), expected, "Wrong `rename_all = \"{rule}\"`");
+        test("lowercase", "stdout");
+        test("UPPERCASE", "STDOUT");
+        test("PascalCase", "StdOut");
+        test("camelCase", "stdOut");
+        test("snake_case", "std_out");
+        test("SCREAMING_SNAKE_CASE", "STD_OUT");
+        test("kebab-case", "std-out");
+        test("SCREAMING-KEBAB-CASE", "STD-OUT");
+    }
+
+    #[test]
+    fn reader_loader() {
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct RoutesConfig {
+            routes: Vec<LogConfig>,
+        }
+        impl OgreRootConfig for RoutesConfig {}
+
+        let config = RoutesConfig { routes: (0..50_000).map(|i| LogConfig { sink: [None, Some(Dummy::StdOut), Some(Dummy::StdError)][i % 3].clone() }).collect() };
+        for format in [SerdeFormat::Ron, SerdeFormat::Yaml] {
+            let config_path = temp_config_path(&format!("{format:?}").to_lowercase());
+            std::fs::write(&config_path, config_to_string(&config, format, "").unwrap()).unwrap();
+            let reader = std::io::BufReader::new(std::fs::File::open(&config_path).unwrap());
+            let loaded_config: RoutesConfig = load_from_reader(reader, format)
+                .unwrap_or_else(|err| panic!("The large {format:?} config should have been read: {err}"));
+            assert_eq!(loaded_config, config, "The {format:?} config read back differs");
+        }
+
+        let result = load_from_reader::<RoutesConfig>("routes: [".as_bytes(), SerdeFormat::Yaml);
+        assert!(result.as_ref().is_err_and(crate::Error::is_parsing_error), "Malformed configs should be reported as parsing errors. Got {result:?}");
+    }
+
     #[test]
     fn ron_with_docs() {
         let default_config = AppRootConfig::default();
@@ -0,0 +1,90 @@
+//! Structural diffs between configs -- over their generic value trees, so any config type may be compared
+
+use std::fmt::{Display, Formatter};
+use serde_json::Value;
+use crate::logic::generic_value_logic::{changed_paths, field_path_of};
+use crate::{FieldChange, OgreRootConfig};
+
+/// The fields that differ from `before` to `after`, ordered by their paths: nested structs are descended into -- so each change is
+/// reported at its deepest field --, while sequences (& values that became or ceased to be structs, like `Option`al sections)
+/// change as a whole. Configs that can't be represented generically compare as `Null`s
+pub fn diff_configs<RootConfigType: OgreRootConfig>(before: &RootConfigType, after: &RootConfigType) -> Vec<FieldChange> {
+    diff_value_trees(&serde_json::to_value(before).unwrap_or_default(), &serde_json::to_value(after).unwrap_or_default())
+}
+
+/// The logic behind [diff_configs()], for already serialized configs
+pub(crate) fn diff_value_trees(before: &Value, after: &Value) -> Vec<FieldChange> {
+    changed_paths(before, after).into_iter()
+        .map(|pointer| FieldChange {
+            path: field_path_of(&pointer),
+            before: before.pointer(&pointer).cloned().unwrap_or_default(),
+            after: after.pointer(&pointer).cloned().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Renders the change like `log_sub_config.sink: "stderror" -> "stdout"`
+impl Display for FieldChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {}", if self.path.is_empty() { "<the whole config>" } else { &self.path }, self.before, self.after)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct TlsConfig {
+        cert: String,
+        key: String,
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct LogConfig {
+        level: u8,
+        file: Option<String>,
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct ServiceConfig {
+        name: String,
+        hosts: Vec<String>,
+        log: LogConfig,
+        tls: Option<TlsConfig>,
+    }
+    impl OgreRootConfig for ServiceConfig {}
+
+    #[test]
+    fn nested_and_optional_changes() {
+        let before = ServiceConfig {
+            name: "api".to_string(),
+            hosts: vec!["a".to_string(), "b".to_string()],
+            log: LogConfig { level: 1, file: None },
+            tls: None,
+        };
+        assert_eq!(diff_configs(&before, &before), [], "Equal configs have no changes");
+
+        let after = ServiceConfig {
+            name: "api".to_string(),
+            hosts: vec!["a".to_string(), "c".to_string()],
+            log: LogConfig { level: 3, file: Some("/var/log/api.log".to_string()) },
+            tls: Some(TlsConfig { cert: "api.crt".to_string(), key: "api.key".to_string() }),
+        };
+        let changes = diff_configs(&before, &after);
+        assert_eq!(changes, [
+            FieldChange { path: "hosts".to_string(), before: json!(["a", "b"]), after: json!(["a", "c"]) },
+            FieldChange { path: "log.file".to_string(), before: json!(null), after: json!("/var/log/api.log") },
+            FieldChange { path: "log.level".to_string(), before: json!(1), after: json!(3) },
+            FieldChange { path: "tls".to_string(), before: json!(null), after: json!({"cert": "api.crt", "key": "api.key"}) },
+        ], "Wrong changes -- which should be ordered by their paths");
+        assert_eq!(changes[2].to_string(), "log.level: 1 -> 3", "Wrong rendering");
+
+        let renewed = ServiceConfig { tls: Some(TlsConfig { cert: "renewed.crt".to_string(), ..after.tls.clone().unwrap() }), ..after.clone() };
+        assert_eq!(diff_configs(&after, &renewed), [FieldChange { path: "tls.cert".to_string(), before: json!("api.crt"), after: json!("renewed.crt") }],
+                   "Present optional sections should be descended into");
+    }
+}
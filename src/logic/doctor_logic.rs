@@ -0,0 +1,120 @@
+//! Checking config files for common problems -- underpinning commands like `--doctor`
+
+use std::fmt::Debug;
+use std::path::Path;
+use crate::{load_with_warnings, Diagnostic, LoadOptions, OgreRootConfig, Severity};
+
+/// Checks the config file at `config_file_path` for common problems, as a `--doctor` command would:
+/// it must exist, be readable & parse into `RootConfigType` -- with no duplicate fields --, while unknown & deprecated fields
+/// (see [OgreRootConfig::deprecated_fields()]) are reported as warnings, along with the other [crate::LoadWarnings].
+/// An empty list means no problems were found. See [doctor_with_options()] for also checking the config against its JSON Schema
+pub async fn doctor<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+) -> Vec<Diagnostic> {
+    doctor_with_options::<RootConfigType>(config_file_path, &LoadOptions::default()).await
+}
+
+/// Same as [doctor()], but loading the config file as specified by `load_options` -- which may hold the JSON Schema
+/// the config must conform to (see the `schema` feature)
+pub async fn doctor_with_options<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    load_options: &LoadOptions,
+) -> Vec<Diagnostic> {
+    match load_with_warnings::<RootConfigType>(&config_file_path, load_options).await {
+        Ok(Some((_, warnings))) => warnings.into_iter()
+            .map(|warning| Diagnostic { severity: Severity::Warning, field_path: warning.field_path, message: warning.message })
+            .collect(),
+        Ok(None) => vec![Diagnostic {
+            severity: Severity::Error,
+            field_path: String::new(),
+            message: format!("the config file {config_file_path:?} doesn't exist"),
+        }],
+        Err(err) => vec![load_error_diagnostic(&config_file_path, err)],
+    }
+}
+
+/// Describes the `err` loading the config file at `config_file_path` -- telling parsing errors apart from the ones reading it
+fn load_error_diagnostic(config_file_path: impl AsRef<Path> + Debug, err: crate::Error) -> Diagnostic {
+    let cause = match &err {
+        crate::Error::LoadingConfig { cause, .. } => cause.downcast_ref::<crate::Error>(),
+        err => Some(err),
+    };
+    let field_path = match cause {
+        Some(crate::Error::DuplicateKey { key, .. }) => key.clone(),
+        Some(crate::Error::MissingRequiredField { field, .. }) => field.clone(),
+        _ => String::new(),
+    };
+    let message = match cause {
+        Some(crate::Error::DuplicateKey { key, line, .. }) => format!("the field `{key}` is duplicated (at line {line}) in the config file {config_file_path:?}"),
+        Some(parsing_err) if parsing_err.is_parsing_error() => format!("the config file {config_file_path:?} doesn't parse: {parsing_err}"),
+        _ => match &err {
+            crate::Error::LoadingConfig { cause, .. } if cause.downcast_ref::<std::io::Error>().is_some() =>
+                format!("the config file {config_file_path:?} couldn't be read: {cause}"),
+            err => format!("the config file {config_file_path:?} couldn't be loaded: {err}"),
+        },
+    };
+    Diagnostic { severity: Severity::Error, field_path, message }
+}
+
+
+#[cfg(all(test, feature = "yaml"))]
+mod tests {
+    use super::*;
+    use crate::DeprecatedField;
+    use crate::testkit::temp_config_path;
+
+    /// A config that went through some evolution
+    #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    struct ServiceConfig {
+        name: String,
+        port: u16,
+        log_file: Option<String>,
+    }
+    impl OgreRootConfig for ServiceConfig {
+        fn deprecated_fields() -> &'static [DeprecatedField] {
+            &[DeprecatedField { path: "log_file", replacement: "log to stderr instead" }]
+        }
+    }
+
+    #[tokio::test]
+    async fn diagnostics() {
+        let config_path = temp_config_path("yaml");
+        let diagnose = || doctor::<ServiceConfig>(&config_path);
+        let reported = |diagnostics: &[Diagnostic]| diagnostics.iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.field_path.clone()))
+            .collect::<Vec<_>>();
+
+        let diagnostics = diagnose().await;
+        assert_eq!(reported(&diagnostics), [(Severity::Error, String::new())], "A missing config file should have been reported");
+        assert!(diagnostics[0].message.contains("doesn't exist"), "Wrong message: '{}'", diagnostics[0].message);
+
+        std::fs::write(&config_path, "name: api\nport: 8080\n").unwrap();
+        assert_eq!(diagnose().await, [], "A healthy config file should have no diagnostics");
+
+        std::fs::write(&config_path, "name: api\nport: 8080\nlog_file: /var/log/api.log\nhost: localhost\n").unwrap();
+        let diagnostics = diagnose().await;
+        assert_eq!(reported(&diagnostics), [(Severity::Warning, "log_file".to_string()), (Severity::Warning, "host".to_string())],
+                   "The deprecated & unknown fields should have been reported as warnings");
+        assert!(diagnostics[0].message.contains("log to stderr instead"), "The replacement should have been suggested: '{}'", diagnostics[0].message);
+
+        std::fs::write(&config_path, "name: api\nport: 8080\nname: web\n").unwrap();
+        let diagnostics = diagnose().await;
+        assert_eq!(reported(&diagnostics), [(Severity::Error, "name".to_string())], "The duplicate field should have been reported");
+        assert!(diagnostics[0].message.contains("duplicated (at line 3)"), "Wrong message: '{}'", diagnostics[0].message);
+
+        std::fs::write(&config_path, "name: api\nport: eighty\n").unwrap();
+        let diagnostics = diagnose().await;
+        assert_eq!(reported(&diagnostics), [(Severity::Error, String::new())], "The unparseable config file should have been reported");
+        assert!(diagnostics[0].message.contains("doesn't parse"), "Wrong message: '{}'", diagnostics[0].message);
+        _ = std::fs::remove_file(&config_path);
+
+        // a directory can't be read as a file
+        let config_dir = temp_config_path("yaml");
+        _ = std::fs::create_dir(&config_dir);
+        let diagnostics = doctor::<ServiceConfig>(&config_dir).await;
+        assert_eq!(reported(&diagnostics), [(Severity::Error, String::new())], "The unreadable config file should have been reported");
+        assert!(diagnostics[0].message.contains("couldn't be read"), "Wrong message: '{}'", diagnostics[0].message);
+        _ = std::fs::remove_dir(&config_dir);
+    }
+}
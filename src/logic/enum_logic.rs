@@ -0,0 +1,148 @@
+//! Support for enums used both in the config files & in the command line,
+//! keeping their `clap` & `serde` spellings aligned
+
+use std::fmt::Debug;
+use clap::builder::PossibleValue;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Declares an enum usable both in configs & in the CLI, deriving `serde::Serialize` & `serde::Deserialize` with the `rename_all`
+/// attribute for the single case convention given -- one of "lowercase", "UPPERCASE", "PascalCase", "camelCase", "snake_case",
+/// "SCREAMING_SNAKE_CASE" or "kebab-case" -- along with a `clap::ValueEnum` impl whose possible values are spelled the same
+/// (the variants' doc comments being their help). Only unit variants are allowed.
+/// Both are used through this crate's re-exports, so programs needn't depend on `serde` or `clap` directly.
+/// Use it like this:
+/// ```nocompile
+///   ogre_config_meld::config_enum! {
+///       #[rename_all = "lowercase"]
+///       /// Where the log messages go to
+///       #[derive(Clone, Debug, PartialEq)]
+///       pub enum Sink { Null, StdOut, StdError }
+///   }
+/// ```
+/// See also [assert_enum_spellings_match()].
+#[macro_export]
+macro_rules! config_enum {
+    (#[rename_all = $case:literal] $(#[$meta:meta])* $vis:vis enum $name:ident { $($(#[$($variant_attr:tt)*])* $variant:ident),* $(,)? }) => {
+        $(#[$meta])*
+        #[derive($crate::serde::Serialize, $crate::serde::Deserialize)]
+        #[serde(crate = "ogre_config_meld::serde", rename_all = $case)]
+        $vis enum $name { $($(#[$($variant_attr)*])* $variant),* }
+
+        impl $crate::clap::ValueEnum for $name {
+            fn value_variants<'a>() -> &'a [Self] {
+                &[$($name::$variant),*]
+            }
+
+            fn to_possible_value(&self) -> ::std::option::Option<$crate::clap::builder::PossibleValue> {
+                let doc_lines: &[::std::option::Option<&str>] = match self {
+                    $($name::$variant => &[$($crate::config_enum!(@doc $($variant_attr)*)),*]),*
+                };
+                ::std::option::Option::Some($crate::config_enum_possible_value(self, doc_lines))
+            }
+        }
+    };
+    (@doc doc = $doc_line:literal) => { ::std::option::Option::Some($doc_line) };
+    (@doc $($attr:tt)*) => { ::std::option::Option::None };
+}
+
+/// The `clap` possible value of the unit enum `variant`, spelled as in the config files & having its `doc_lines` (if any) as help
+/// -- for the `clap::ValueEnum` impls of [config_enum!()]
+#[doc(hidden)]
+pub fn config_enum_possible_value<EnumType: Serialize + Debug>(variant: &EnumType, doc_lines: &[Option<&str>]) -> PossibleValue {
+    let name = match serde_json::to_value(variant) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => panic!("`{variant:?}` isn't serialized as a string: only unit variants are allowed in `config_enum!()`"),
+    };
+    let help = doc_lines.iter().flatten().map(|doc_line| doc_line.trim()).collect::<Vec<_>>().join(" ");
+    let possible_value = PossibleValue::new(name);
+    if help.is_empty() { possible_value } else { possible_value.help(help) }
+}
+
+/// Asserts that each variant of `EnumType` is spelled the same way in the config files (`serde`) and in the
+/// command line (`clap`), panicking otherwise -- so applications may guarantee, in their tests, that values
+/// written in the config are accepted in the CLI & vice-versa.
+/// See also [config_enum!()].
+pub fn assert_enum_spellings_match<EnumType: ValueEnum + Serialize + Debug>() {
+    for variant in EnumType::value_variants() {
+        let Some(cli_value) = variant.to_possible_value() else {
+            continue    // skipped in the CLI
+        };
+        let config_value = serde_json::to_value(variant)
+            .unwrap_or_else(|err| panic!("`{variant:?}` couldn't be serialized: {err}"));
+        assert_eq!(config_value.as_str(), Some(cli_value.get_name()),
+                   "The config file & command line spellings differ for `{variant:?}`");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    use clap::Parser;
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    use serde::Deserialize;
+
+    config_enum! {
+        #[rename_all = "kebab-case"]
+        /// The log level to use
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum LogLevel {
+            Error,
+            /// Problems that were worked around
+            Warning,
+            DebugDetails,
+        }
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct LogLevelConfig {
+        level: LogLevel,
+    }
+
+    #[derive(clap::Parser, Debug)]
+    struct LogLevelCmdLineOptions {
+        #[clap(long)]
+        level: LogLevel,
+    }
+
+    #[test]
+    fn spellings_match() {
+        assert_enum_spellings_match::<LogLevel>();
+        assert_enum_spellings_match::<crate::testkit::Dummy>();
+    }
+
+    #[test]
+    #[should_panic(expected = "spellings differ")]
+    fn spellings_mismatch() {
+        #[derive(clap::ValueEnum, Clone, Debug, Serialize)]
+        enum Mismatched { StdOut }
+        assert_enum_spellings_match::<Mismatched>();
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn round_trips() {
+        let cmdline_options = LogLevelCmdLineOptions::parse_from(["test", "--level", "debug-details"]);
+        assert_eq!(cmdline_options.level, LogLevel::DebugDetails, "CLI parsing failed");
+
+        let expected_config = LogLevelConfig { level: cmdline_options.level };
+        let ron_config = ron::to_string(&expected_config).unwrap();
+        assert!(ron_config.contains("debug-details"), "Unexpected RON spelling: {ron_config}");
+        assert_eq!(ron::from_str::<LogLevelConfig>(&ron_config).unwrap(), expected_config, "RON round-trip failed");
+        let yaml_config = serde_yaml::to_string(&expected_config).unwrap();
+        assert_eq!(yaml_config, "level: debug-details\n", "Unexpected YAML spelling");
+        assert_eq!(serde_yaml::from_str::<LogLevelConfig>(&yaml_config).unwrap(), expected_config, "YAML round-trip failed");
+    }
+
+    #[test]
+    fn possible_values_in_help() {
+        let help = LogLevelCmdLineOptions::command().render_help().to_string();
+        assert!(help.contains("[possible values: error, warning, debug-details]"), "The possible values are missing from the help: {help}");
+        let long_help = LogLevelCmdLineOptions::command().render_long_help().to_string();
+        assert!(long_help.lines().any(|line| line.trim_start().starts_with("- warning:") && line.ends_with("Problems that were worked around")),
+                "The variant docs are missing from the long help: {long_help}");
+    }
+}
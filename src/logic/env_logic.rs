@@ -0,0 +1,79 @@
+//! Rendering of configs as shell environment variable exports -- for deployment scripts to `source` them
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Flattens `config` into `export <PREFIX>_<FIELD>=<value>` lines, one per leaf field, for shell scripts to `source`.
+/// Names are upper cased, with the fields of nested sections joined by `__` -- as in `APP_LOG_SUB_CONFIG__SINK`, for a
+/// `prefix` of `APP` (an empty `prefix` leaves just the field names). Strings are single quoted; numbers & bools go bare;
+/// lists go as quoted JSON; fields holding no value (like `None`s) are left out. Lines are sorted by the field names.
+///
+/// Configs that can't be represented generically (like maps with non-string keys) are reported as [crate::Error::SavingConfig].
+pub fn to_env_exports<ConfigType: Serialize>(config: &ConfigType, prefix: &str) -> Result<String, crate::Error> {
+    let generic_config = serde_json::to_value(config)
+        .map_err(|err| crate::Error::SavingConfig {
+            message: "Error representing the config as environment variable exports".to_string(),
+            cause: Box::new(err),
+        })?;
+    let mut exports = String::new();
+    push_exports(&mut exports, &prefix.to_uppercase(), "_", &generic_config);
+    Ok(exports)
+}
+
+/// Appends the export lines of `value` -- whose variable name is `name` -- to `exports`, recursing into sections.
+/// `separator` goes between `name` and the names of the section's fields
+fn push_exports(exports: &mut String, name: &str, separator: &str, value: &Value) {
+    let rendered_value = match value {
+        Value::Null => return,
+        Value::Object(fields) => {
+            for (field_name, field_value) in fields {
+                let field_name = field_name.to_uppercase();
+                let field_var_name = if name.is_empty() { field_name } else { format!("{name}{separator}{field_name}") };
+                push_exports(exports, &field_var_name, "__", field_value);
+            }
+            return
+        },
+        Value::Bool(_) | Value::Number(_) => value.to_string(),
+        Value::String(text) => shell_quoted(text),
+        Value::Array(_) => shell_quoted(&value.to_string()),
+    };
+    exports.push_str(&format!("export {name}={rendered_value}\n"));
+}
+
+/// Single quotes `text` for POSIX shells -- where nothing is special inside single quotes, except for the quote itself
+fn shell_quoted(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::*;
+
+    #[test]
+    fn app_root_config_exports() {
+        let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+        assert_eq!(to_env_exports(&config, "app").unwrap(), "export APP_LOG_SUB_CONFIG__SINK='stdout'\n", "Wrong exports");
+        assert_eq!(to_env_exports(&config, "").unwrap(), "export LOG_SUB_CONFIG__SINK='stdout'\n", "Wrong exports without a prefix");
+        assert_eq!(to_env_exports(&AppRootConfig::default(), "APP").unwrap(), "", "Fields holding no value should be left out");
+    }
+
+    #[test]
+    fn value_rendering() {
+        #[derive(Serialize)]
+        struct Section { retries: u32, verbose: bool }
+        #[derive(Serialize)]
+        struct Config { name: String, hosts: Vec<String>, section: Section }
+        let config = Config {
+            name: "it's $HOME".to_string(),
+            hosts: vec!["a".to_string(), "b".to_string()],
+            section: Section { retries: 3, verbose: true },
+        };
+        assert_eq!(to_env_exports(&config, "APP").unwrap(),
+                   "export APP_HOSTS='[\"a\",\"b\"]'\n\
+                    export APP_NAME='it'\\''s $HOME'\n\
+                    export APP_SECTION__RETRIES=3\n\
+                    export APP_SECTION__VERBOSE=true\n",
+                   "Wrong rendering of the values");
+    }
+}
@@ -0,0 +1,86 @@
+//! The real filesystem behind the [ConfigFs] abstraction -- see [RealFs]
+
+use std::path::{Path, PathBuf};
+use crate::logic::config_logic::restore_file_metadata;
+use crate::{ConfigFs, FileMetadata, RealFs};
+use encryptable_tokio_fs::fs;
+
+impl ConfigFs for RealFs {
+    async fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        fs::read(path).await
+    }
+
+    async fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+        fs::read_to_string(path).await
+    }
+
+    async fn write(&self, path: &Path, contents: &[u8]) -> std::io::Result<()> {
+        fs::write(path, contents).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        fs::rename(from, to).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        fs::copy(from, to).await
+            .map(|_| ())
+    }
+
+    async fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+        fs::remove_file(path).await
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        fs::create_dir_all(path).await
+    }
+
+    async fn metadata(&self, path: &Path) -> std::io::Result<FileMetadata> {
+        fs::metadata(path).await
+            .map(file_metadata)
+    }
+
+    async fn symlink_metadata(&self, path: &Path) -> std::io::Result<FileMetadata> {
+        fs::symlink_metadata(path).await
+            .map(file_metadata)
+    }
+
+    async fn exists(&self, path: &Path) -> bool {
+        fs::try_exists(path).await.unwrap_or(false)
+    }
+
+    async fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        let mut dir_entries = fs::read_dir(path).await?;
+        let mut entry_paths = Vec::new();
+        while let Some(dir_entry) = dir_entries.next_entry().await? {
+            entry_paths.push(dir_entry.path());
+        }
+        Ok(entry_paths)
+    }
+
+    async fn read_link(&self, path: &Path) -> std::io::Result<PathBuf> {
+        fs::read_link(path).await
+    }
+
+    async fn sync(&self, path: &Path) -> std::io::Result<()> {
+        // files must be opened for writing to be fsynced on some platforms, while directories can't be
+        let is_dir = fs::metadata(path).await?.is_dir();
+        fs::OpenOptions::new().read(is_dir).write(!is_dir).open(path).await?
+            .sync_all().await
+    }
+
+    async fn restore_metadata(&self, path: &Path, original: &FileMetadata) -> std::io::Result<()> {
+        match &original.os_metadata {
+            Some(original_metadata) => restore_file_metadata(path, original_metadata).await,
+            None => Ok(()),
+        }
+    }
+}
+
+fn file_metadata(metadata: std::fs::Metadata) -> FileMetadata {
+    FileMetadata {
+        is_file: metadata.is_file(),
+        is_symlink: metadata.file_type().is_symlink(),
+        os_metadata: Some(metadata),
+    }
+}
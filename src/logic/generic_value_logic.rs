@@ -0,0 +1,476 @@
+//! Format-agnostic (generic) representation of config files, as `serde_json::Value`s -- for reading single values
+//! without knowing the config types.
+//!
+//! Enums follow `serde_json`'s conventions: unit variants are strings & other variants are single-entry objects.
+//! Generic RON values (`ron::Value`) can't be used, as they lose the enum variant names.
+
+#[cfg(feature = "ron")]
+use crate::logic::layout_logic::{indentation_at, FieldInsertion, ValueSpans};
+use crate::SerdeFormat;
+#[cfg(feature = "ron")]
+use std::ops::Range;
+
+/// Parses `txt_config`, in the given `format`, into its generic representation
+pub(crate) fn generic_from_txt(txt_config: &str, format: SerdeFormat) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+    match format {
+        #[cfg(feature = "ron")]
+        SerdeFormat::Ron => generic_from_ron(txt_config).map_err(Into::into),
+        #[cfg(feature = "yaml")]
+        SerdeFormat::Yaml => serde_yaml::from_str(txt_config).map(generic_from_yaml).map_err(Into::into),
+    }
+}
+
+/// Converts a `serde_yaml::Value` into its generic representation -- tagged values (`!Variant value`) become `{"Variant": value}`
+#[cfg(feature = "yaml")]
+pub(crate) fn generic_from_yaml(yaml_value: serde_yaml::Value) -> serde_json::Value {
+    use serde_yaml::Value;
+    match yaml_value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(b),
+        Value::Number(n) => serde_json::to_value(&n).unwrap_or(serde_json::Value::Null),
+        Value::String(s) => serde_json::Value::String(s),
+        Value::Sequence(seq) => serde_json::Value::Array(seq.into_iter().map(generic_from_yaml).collect()),
+        Value::Mapping(map) => serde_json::Value::Object(map.into_iter()
+            .map(|(key, value)| (generic_key(generic_from_yaml(key)), generic_from_yaml(value)))
+            .collect()),
+        Value::Tagged(tagged) => {
+            let variant = tagged.tag.to_string().trim_start_matches('!').to_string();
+            serde_json::Value::Object([(variant, generic_from_yaml(tagged.value))].into_iter().collect())
+        },
+    }
+}
+
+/// Parses a RON config text into its generic representation.
+/// Named structs have their names dropped, while newtype & tuple variants become single-entry objects.
+#[cfg(feature = "ron")]
+pub(crate) fn generic_from_ron(txt_config: &str) -> Result<serde_json::Value, String> {
+    ron_with_spans(txt_config).map(|(value, _)| value)
+}
+
+/// Same as [generic_from_ron()], but also locating where the fields of the structs & maps are in `txt_config`
+#[cfg(feature = "ron")]
+pub(crate) fn ron_with_spans(txt_config: &str) -> Result<(serde_json::Value, ValueSpans), String> {
+    let mut parser = RonParser { src: txt_config, pos: 0, pointer: String::new(), spans: ValueSpans::default() };
+    parser.skip_extensions();
+    let value = parser.value()?;
+    parser.skip_ws();
+    match parser.peek() {
+        None => Ok((value, parser.spans)),
+        Some(c) => Err(parser.error(&format!("unexpected trailing character '{c}'"))),
+    }
+}
+
+/// Returns the JSON pointers to the values that differ from `old` to `new` -- down to the leaves of the nested objects
+pub(crate) fn changed_paths(old: &serde_json::Value, new: &serde_json::Value) -> Vec<String> {
+    fn collect(old: Option<&serde_json::Value>, new: Option<&serde_json::Value>, path: &str, changed: &mut Vec<String>) {
+        match (old, new) {
+            (Some(serde_json::Value::Object(old)), Some(serde_json::Value::Object(new))) => {
+                let mut keys = old.keys().chain(new.keys().filter(|key| !old.contains_key(*key))).collect::<Vec<_>>();
+                keys.sort();
+                for key in keys {
+                    collect(old.get(key), new.get(key), &child_pointer(path, key), changed);
+                }
+            },
+            (old, new) if old != new => changed.push(path.to_string()),
+            _ => (),
+        }
+    }
+    let mut changed = Vec::new();
+    collect(Some(old), Some(new), "", &mut changed);
+    changed
+}
+
+/// The dotted field path for the JSON `pointer` -- like `log_sub_config.sink` for `/log_sub_config/sink`
+pub(crate) fn field_path_of(pointer: &str) -> String {
+    pointer.split('/')
+        .skip(1)
+        .map(|key| key.replace("~1", "/").replace("~0", "~"))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The JSON pointer to the `key` of the object at `pointer`
+pub(crate) fn child_pointer(pointer: &str, key: &str) -> String {
+    format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Object keys must be strings: non-string keys are represented by their JSON text
+fn generic_key(key: serde_json::Value) -> String {
+    match key {
+        serde_json::Value::String(key) => key,
+        key => key.to_string(),
+    }
+}
+
+/// A minimal recursive descent parser for RON values, delegating scalars to `ron` itself
+#[cfg(feature = "ron")]
+struct RonParser<'a> {
+    src: &'a str,
+    pos: usize,
+    /// The JSON pointer of the object whose fields are being parsed
+    pointer: String,
+    spans: ValueSpans,
+}
+
+#[cfg(feature = "ron")]
+impl<'a> RonParser<'a> {
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn error(&self, message: &str) -> String {
+        let line = self.src[..self.pos].matches('\n').count() + 1;
+        format!("RON parsing error at line {line}: {message}")
+    }
+
+    /// Skips `#![enable(...)]` extension attributes
+    fn skip_extensions(&mut self) {
+        loop {
+            self.skip_ws();
+            if !self.rest().starts_with("#!") {
+                break;
+            }
+            self.pos += self.rest().find('\n').unwrap_or(self.rest().len());
+        }
+    }
+
+    /// Skips whitespaces & (nested) comments
+    fn skip_ws(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                let mut depth = 0;
+                let mut chars = trimmed.char_indices().peekable();
+                let mut end = trimmed.len();
+                while let Some((i, c)) = chars.next() {
+                    match (c, chars.peek().map(|(_, next)| *next)) {
+                        ('/', Some('*')) => { depth += 1; chars.next(); },
+                        ('*', Some('/')) => {
+                            depth -= 1;
+                            chars.next();
+                            if depth == 0 {
+                                end = i + 2;
+                                break;
+                            }
+                        },
+                        _ => (),
+                    }
+                }
+                self.pos += end;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn consume(&mut self, c: char) -> bool {
+        self.skip_ws();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.consume(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{c}'")))
+        }
+    }
+
+    /// Parses an identifier, including raw ones (`r#name`), returning its name
+    fn identifier(&mut self) -> Option<&'a str> {
+        self.skip_ws();
+        let rest = self.rest();
+        let (prefix_len, is_ident_char): (usize, fn(char) -> bool) = if rest.starts_with("r#") {
+            (2, |c| c.is_ascii_alphanumeric() || "_-.+".contains(c))
+        } else {
+            (0, |c| c.is_ascii_alphanumeric() || c == '_')
+        };
+        let name = &rest[prefix_len..];
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            return None;
+        }
+        let len = name.find(|c| !is_ident_char(c)).unwrap_or(name.len());
+        let start = self.pos + prefix_len;
+        self.pos = start + len;
+        Some(&self.src[start..start + len])
+    }
+
+    /// Tells if a raw string (`r"..."`, `r#"..."#`, ...) is ahead -- as opposed to a raw identifier (`r#name`)
+    fn is_raw_string_ahead(&self) -> bool {
+        self.rest().strip_prefix('r')
+            .is_some_and(|after_r| after_r.trim_start_matches('#').starts_with('"'))
+    }
+
+    /// Tells if an identifier followed by ':' is ahead -- meaning struct fields
+    fn is_field_ahead(&mut self) -> bool {
+        let pos = self.pos;
+        let is_field = self.identifier().is_some() && self.consume(':');
+        self.pos = pos;
+        is_field
+    }
+
+    fn value(&mut self) -> Result<serde_json::Value, String> {
+        self.skip_ws();
+        match self.peek() {
+            None => Err(self.error("unexpected end of input")),
+            Some('[') => {
+                self.pos += 1;
+                Ok(serde_json::Value::Array(self.values_until(']')?))
+            },
+            Some('{') => {
+                let open_pos = self.pos;
+                self.pos += 1;
+                let mut map = serde_json::Map::new();
+                let mut last_field = None;
+                while !self.consume('}') {
+                    self.skip_ws();
+                    let field_start = self.pos;
+                    let key = generic_key(self.value()?);
+                    self.expect(':')?;
+                    let value = self.field_value(&key, field_start)?;
+                    map.insert(key, value);
+                    last_field = Some(field_start..self.pos);
+                    if !self.consume(',') {
+                        self.expect('}')?;
+                        break;
+                    }
+                }
+                self.record_insertion(open_pos, last_field);
+                Ok(serde_json::Value::Object(map))
+            },
+            Some('(') => self.parenthesized(None),
+            Some('"' | '\'') => self.scalar(),
+            Some('r') if self.is_raw_string_ahead() => self.scalar(),
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let pos = self.pos;
+                let ident = self.identifier().unwrap_or_default().to_string();
+                match ident.as_str() {
+                    "true" => Ok(serde_json::Value::Bool(true)),
+                    "false" => Ok(serde_json::Value::Bool(false)),
+                    "None" => Ok(serde_json::Value::Null),
+                    "Some" => {
+                        self.expect('(')?;
+                        let value = self.value()?;
+                        self.consume(',');
+                        self.expect(')')?;
+                        Ok(value)
+                    },
+                    "inf" | "inff32" | "inff64" | "NaN" | "NaNf32" | "NaNf64" => {
+                        self.pos = pos;
+                        self.scalar()
+                    },
+                    _ if self.consume('(') => {
+                        self.pos -= 1;
+                        self.parenthesized(Some(ident))
+                    },
+                    _ => Ok(serde_json::Value::String(ident)),
+                }
+            },
+            Some(_) => self.scalar(),
+        }
+    }
+
+    /// Parses `(...)` -- structs, tuples & the unit -- given the optional preceding name
+    fn parenthesized(&mut self, name: Option<String>) -> Result<serde_json::Value, String> {
+        self.expect('(')?;
+        let open_pos = self.pos - 1;
+        if self.is_field_ahead() {
+            let mut fields = serde_json::Map::new();
+            let mut last_field = None;
+            while !self.consume(')') {
+                self.skip_ws();
+                let field_start = self.pos;
+                let field = self.identifier().ok_or_else(|| self.error("expected a field name"))?.to_string();
+                self.expect(':')?;
+                let value = self.field_value(&field, field_start)?;
+                fields.insert(field, value);
+                last_field = Some(field_start..self.pos);
+                if !self.consume(',') {
+                    self.expect(')')?;
+                    break;
+                }
+            }
+            self.record_insertion(open_pos, last_field);
+            return Ok(serde_json::Value::Object(fields));
+        }
+        let elements_pointer = match &name {
+            Some(variant) => child_pointer(&self.pointer, variant),
+            None => self.pointer.clone(),
+        };
+        let outer_pointer = std::mem::replace(&mut self.pointer, elements_pointer);
+        let elements = self.values_until(')');
+        self.pointer = outer_pointer;
+        let mut elements = elements?;
+        let value = match elements.len() {
+            0 => serde_json::Value::Null,
+            1 if name.is_some() => elements.remove(0),
+            _ => serde_json::Value::Array(elements),
+        };
+        Ok(match name {
+            Some(variant) => serde_json::Value::Object([(variant, value)].into_iter().collect()),
+            None => value,
+        })
+    }
+
+    /// Parses the value of the field `key` -- just after its `:`, with the field starting at `field_start` -- recording where they are
+    fn field_value(&mut self, key: &str, field_start: usize) -> Result<serde_json::Value, String> {
+        let value_start = self.pos;
+        let value = self.value_at(child_pointer(&self.pointer, key))?;
+        let field_pointer = child_pointer(&self.pointer, key);
+        self.spans.values.insert(field_pointer.clone(), value_start..self.pos);
+        self.spans.fields.insert(field_pointer, field_start..self.pos);
+        Ok(value)
+    }
+
+    /// Parses a value, having `pointer` as the pointer of its fields' object
+    fn value_at(&mut self, pointer: String) -> Result<serde_json::Value, String> {
+        let outer_pointer = std::mem::replace(&mut self.pointer, pointer);
+        let value = self.value();
+        self.pointer = outer_pointer;
+        value
+    }
+
+    /// Records where new fields may be added to the object opened at `open_pos` (& just closed), after its `last_field`:
+    /// in its own line -- with the same indentation as the last field -- or inline, if that is how the object is written.
+    /// Empty objects get no insertion point
+    fn record_insertion(&mut self, open_pos: usize, last_field: Option<Range<usize>>) {
+        let Some(Range { start: last_field_start, end: last_value_end }) = last_field else {
+            return
+        };
+        let has_trailing_comma = self.src[last_value_end..].trim_start().starts_with(',');
+        let is_inline = !self.src[open_pos..last_field_start].contains('\n');
+        let insertion = match (is_inline, has_trailing_comma) {
+            (true, _) => FieldInsertion { position: last_value_end, prefix: ", ".to_string(), suffix: String::new() },
+            (false, true) => FieldInsertion {
+                position: last_value_end + self.src[last_value_end..].find(',').unwrap_or(0) + 1,
+                prefix: format!("\n{}", indentation_at(self.src, last_field_start)),
+                suffix: ",".to_string(),
+            },
+            (false, false) => FieldInsertion { position: last_value_end, prefix: format!(",\n{}", indentation_at(self.src, last_field_start)), suffix: String::new() },
+        };
+        self.spans.insertions.insert(self.pointer.clone(), insertion);
+    }
+
+    /// Parses comma separated values up to the `closing` char (consumed)
+    fn values_until(&mut self, closing: char) -> Result<Vec<serde_json::Value>, String> {
+        let mut values = Vec::new();
+        while !self.consume(closing) {
+            values.push(self.value_at(child_pointer(&self.pointer, &values.len().to_string()))?);
+            if !self.consume(',') {
+                self.expect(closing)?;
+                break;
+            }
+        }
+        Ok(values)
+    }
+
+    /// Delegates the parsing of strings, chars & numbers to `ron`
+    fn scalar(&mut self) -> Result<serde_json::Value, String> {
+        let rest = self.rest();
+        let len = if let Some(quoted) = rest.strip_prefix('"').or_else(|| rest.strip_prefix('\'')) {
+            let quote = rest.chars().next().unwrap_or('"');
+            let mut escaped = false;
+            quoted.char_indices()
+                .find(|&(_, c)| {
+                    let is_end = c == quote && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    is_end
+                })
+                .map(|(i, _)| i + 2)
+                .ok_or_else(|| self.error("unterminated string"))?
+        } else if let Some(raw) = rest.strip_prefix('r') {
+            let hashes = raw.len() - raw.trim_start_matches('#').len();
+            let terminator = format!("\"{}", "#".repeat(hashes));
+            raw[hashes + 1..].find(&terminator)
+                .map(|i| 1 + hashes + 1 + i + terminator.len())
+                .ok_or_else(|| self.error("unterminated raw string"))?
+        } else {
+            rest.find(|c: char| !(c.is_ascii_alphanumeric() || "_.+-".contains(c))).unwrap_or(rest.len())
+        };
+        let token = &rest[..len];
+        let value = ron::from_str::<serde_json::Value>(token)
+            .map_err(|err| self.error(&format!("invalid value '{token}': {err}")))?;
+        self.pos += len;
+        Ok(value)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn ron_values() {
+        let txt_config = r##"#![enable(implicit_some)]
+            // comments are ignored /* even with nested markers */
+            (
+                /* block /* nested */ comments too */
+                name: "a \"quoted\" name",
+                raw: r#"raw "string""#,
+                char: 'c',
+                numbers: [1, -2.5, 0x10, 1_000],
+                flags: (true, false),
+                unit: (),
+                nothing: None,
+                something: Some(Some(3)),
+                sink: stdout,
+                r#kebab-field: r#kebab-variant,
+                newtype: Variant(7),
+                tuple: Variant(1, 2),
+                named: Named(a: 1,),
+                map: {"key": [], 2: Some(Empty)},
+            )"##;
+        let generic_config = generic_from_ron(txt_config).unwrap_or_else(|err| panic!("Parsing failed: {err}"));
+        assert_eq!(generic_config, json!({
+            "name": "a \"quoted\" name",
+            "raw": "raw \"string\"",
+            "char": "c",
+            "numbers": [1, -2.5, 16, 1000],
+            "flags": [true, false],
+            "unit": null,
+            "nothing": null,
+            "something": 3,
+            "sink": "stdout",
+            "kebab-field": "kebab-variant",
+            "newtype": {"Variant": 7},
+            "tuple": {"Variant": [1, 2]},
+            "named": {"a": 1},
+            "map": {"key": [], "2": "Empty"},
+        }), "Wrong generic representation");
+
+        assert!(generic_from_ron("(a: 1").is_err(), "Unterminated structs should be reported");
+        assert!(generic_from_ron("(a: 1) (b: 2)").is_err(), "Trailing contents should be reported");
+    }
+
+    #[test]
+    fn changed_paths_test() {
+        let old = json!({"log": {"sink": "stdout", "level": 1}, "name": "a", "gone": true});
+        let new = json!({"log": {"sink": "stderr", "level": 1}, "name": "a", "added/key": [1]});
+        assert_eq!(changed_paths(&old, &new), ["/added~1key", "/gone", "/log/sink"], "Wrong changed paths");
+        assert!(changed_paths(&old, &old).is_empty(), "Equal values have no changes");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_values() {
+        let yaml_value: serde_yaml::Value = serde_yaml::from_str("sink: stdout\nnewtype: !Variant 7\nlist: [1, 2.5]\n").unwrap();
+        assert_eq!(generic_from_yaml(yaml_value), json!({"sink": "stdout", "newtype": {"Variant": 7}, "list": [1, 2.5]}), "Wrong generic representation");
+    }
+}
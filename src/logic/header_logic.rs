@@ -0,0 +1,125 @@
+//! The machine-readable header of the config files regenerated with the effective config -- see [RewriteHeader]
+
+use crate::logic::serde::commented_out;
+use crate::{RewriteHeader, SerdeFormat};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// The title of the header's banner line, by which it is found back in the config files
+const HEADER_TITLE: &str = "REWRITE HEADER";
+
+impl RewriteHeader {
+    /// The version of the header's layout written by this crate
+    pub const VERSION: u32 = 1;
+
+    /// A header for a config file written right now by the program at `program_version`, where the command line
+    /// changed the fields at `changed_fields` -- see [RewriteHeader::backup] & [RewriteHeader::source] for the rest
+    pub(crate) fn now(program_version: Option<String>, changed_fields: Vec<String>) -> Self {
+        Self {
+            header_version: Self::VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            program_version,
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            hostname: hostname(),
+            backup: None,
+            backup_failed: false,
+            source: None,
+            changed_fields,
+        }
+    }
+
+    /// Renders the header as a comment of the given `format` -- in the style of its tail docs (see [SerdeFormat::comment_style()]),
+    /// holding a `name: <JSON value>` line per field, like:
+    /// ```nocompile
+    ///   /*
+    ///   ///////////////////////// REWRITE HEADER //////////////////////////
+    ///   header_version: 1
+    ///   ...
+    ///   changed_fields: ["log_sub_config.sink"]
+    ///   */
+    pub fn render(&self, format: SerdeFormat) -> String {
+        let path = |path: &Option<PathBuf>| path.as_ref().map(|path| path.to_string_lossy().to_string());
+        let fields = [
+            ("header_version", Value::from(self.header_version)),
+            ("crate_version", Value::from(self.crate_version.as_str())),
+            ("program_version", Value::from(self.program_version.clone())),
+            ("timestamp", Value::from(self.timestamp.as_str())),
+            ("hostname", Value::from(self.hostname.clone())),
+            ("backup", Value::from(path(&self.backup))),
+            ("backup_failed", Value::from(self.backup_failed)),
+            ("source", Value::from(path(&self.source))),
+            ("changed_fields", Value::from(self.changed_fields.clone())),
+        ];
+        let body = fields.iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut rendered = commented_out(HEADER_TITLE, &body, &format.comment_style());
+        if !rendered.ends_with('\n') {
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// Parses back the header rendered by [RewriteHeader::render()] out of the text of a config file -- returning `None`
+    /// if it has no header or if it is malformed. Unknown fields -- from newer layouts -- are ignored
+    pub fn parse(config_txt: &str) -> Option<Self> {
+        let lines = config_txt.lines()
+            .skip_while(|line| !line.contains(&format!(" {HEADER_TITLE} ")))
+            .skip(1);
+        let mut fields = serde_json::Map::new();
+        // the header ends at the first line not holding a field -- like `*/` or the blank line before the config
+        for line in lines {
+            let line = line.trim_start();
+            let line = line.strip_prefix('#').or_else(|| line.strip_prefix("//")).unwrap_or(line).trim();
+            let Some((name, value)) = line.split_once(": ")
+                .filter(|(name, _)| name.chars().all(|c| c.is_ascii_lowercase() || c == '_')) else { break };
+            fields.insert(name.to_string(), serde_json::from_str(value).ok()?);
+        }
+        let string = |name: &str| fields.get(name).and_then(Value::as_str).map(str::to_string);
+        Some(Self {
+            header_version: fields.get("header_version")?.as_u64()?.try_into().ok()?,
+            crate_version: string("crate_version")?,
+            program_version: string("program_version"),
+            timestamp: string("timestamp")?,
+            hostname: string("hostname"),
+            backup: string("backup").map(PathBuf::from),
+            backup_failed: fields.get("backup_failed").and_then(Value::as_bool).unwrap_or_default(),
+            source: string("source").map(PathBuf::from),
+            changed_fields: fields.get("changed_fields")?.as_array()?.iter()
+                .map(|path| path.as_str().map(str::to_string))
+                .collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// The name of the host we are running on -- as told by the environment or, on Unix, by `/etc/hostname`
+fn hostname() -> Option<String> {
+    ["HOSTNAME", "COMPUTERNAME"].iter()
+        .find_map(|var| std::env::var(var).ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+}
+
+
+#[cfg(all(test, feature = "ron", feature = "yaml"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_and_parse() {
+        let header = RewriteHeader {
+            program_version: Some("2.0.1".to_string()),
+            backup: Some(PathBuf::from("/etc/app/app.config.ron.bak-20240501")),
+            ..RewriteHeader::now(None, vec!["log_sub_config.sink".to_string(), "workers[0].name".to_string()])
+        };
+        for format in [SerdeFormat::Ron, SerdeFormat::Yaml] {
+            let rendered = header.render(format);
+            assert_eq!(RewriteHeader::parse(&rendered).as_ref(), Some(&header), "The {format:?} header didn't parse back: '{rendered}'");
+        }
+        assert!(header.render(SerdeFormat::Ron).starts_with("/*\n"), "RON headers should be block comments");
+        assert!(header.render(SerdeFormat::Yaml).lines().all(|line| line.starts_with('#')), "YAML headers should be commented lines");
+        assert_eq!(RewriteHeader::parse("log_sub_config:\n  sink: null\n"), None, "Files without headers should have none parsed");
+    }
+}
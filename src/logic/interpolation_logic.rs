@@ -0,0 +1,397 @@
+//! Environment variable interpolation (`${VAR}` / `$VAR`) inside the string values of the configs,
+//! done while deserializing -- so it works for any format and never touches keys nor field names.
+//! The [ValuePath] of the value refused by the deserialization is also tracked, for the formats not reporting it
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::fmt::Formatter;
+use serde::de::{self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use crate::EnvInterpolation;
+
+/// Expands the environment variables referenced in `value` as `${VAR}` or `$VAR`, according to `policy`.
+/// `$$` is the escape for a literal `$`.
+/// Returns the name of the first undefined variable as the error, if `policy` mandates it.
+pub(crate) fn interpolate_env_vars(value: &str, policy: EnvInterpolation) -> Result<Cow<'_, str>, String> {
+    if policy == EnvInterpolation::Disabled || !value.contains('$') {
+        return Ok(Cow::Borrowed(value));
+    }
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut interpolated = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(dollar_pos) = rest.find('$') {
+        interpolated.push_str(&rest[..dollar_pos]);
+        let after_dollar = &rest[dollar_pos + 1..];
+        let (var_name, reference, remaining) = if let Some(escaped) = after_dollar.strip_prefix('$') {
+            interpolated.push('$');
+            rest = escaped;
+            continue;
+        } else if let Some(braced) = after_dollar.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], &rest[dollar_pos..dollar_pos + end + 3], &braced[end + 1..]),
+                None => ("", &rest[dollar_pos..dollar_pos + 1], after_dollar),
+            }
+        } else {
+            let end = after_dollar.find(|c| !is_name_char(c)).unwrap_or(after_dollar.len());
+            (&after_dollar[..end], &rest[dollar_pos..dollar_pos + end + 1], &after_dollar[end..])
+        };
+        let is_valid_name = var_name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') && var_name.chars().all(is_name_char);
+        match std::env::var(var_name) {
+            Ok(var_value) if is_valid_name => interpolated.push_str(&var_value),
+            _ if is_valid_name && policy == EnvInterpolation::FailOnUndefined => return Err(var_name.to_string()),
+            _ => interpolated.push_str(reference),
+        }
+        rest = remaining;
+    }
+    interpolated.push_str(rest);
+    Ok(Cow::Owned(interpolated))
+}
+
+/// The keys (& sequence indexes) leading to the first value refused while deserializing -- see [ValuePath::refused()]
+#[derive(Clone, Default)]
+pub(crate) struct ValuePath(Rc<RefCell<ValuePathState>>);
+
+#[derive(Default)]
+struct ValuePathState {
+    /// the keys & indexes of the values currently being deserialized
+    segments: Vec<String>,
+    /// the key just read, for the value about to be deserialized
+    pending_key: Option<String>,
+    /// the `.` separated `segments` of the value refused, as of the error
+    refused: Option<String>,
+}
+
+impl ValuePath {
+    /// The `.` separated keys & indexes leading to the value whose deserialization failed -- like `log_sub_config.sink` or `servers.0.port`
+    /// -- or `None` if no nested value was refused
+    #[cfg(any(test, feature = "ron"))]
+    pub(crate) fn refused(&self) -> Option<String> {
+        self.0.borrow().refused.clone()
+    }
+
+    fn record_key(&self, key: &str) {
+        self.0.borrow_mut().pending_key = Some(key.to_string());
+    }
+
+    fn take_pending_key(&self) -> String {
+        self.0.borrow_mut().pending_key.take().unwrap_or_else(|| "?".to_string())
+    }
+
+    /// Deserializes a nested value with `deserialize`, keeping track of the `segment` leading to it
+    fn nested<T, E>(&self, segment: String, deserialize: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        self.0.borrow_mut().segments.push(segment);
+        let result = deserialize();
+        let mut state = self.0.borrow_mut();
+        match result {
+            // the innermost value is the first to fail
+            Err(_) if state.refused.is_none() => state.refused = Some(state.segments.join(".")),
+            Err(_) => (),
+            // errors recovered from (like in untagged enums) are forgotten
+            Ok(_) => state.refused = None,
+        }
+        state.segments.pop();
+        result
+    }
+}
+
+/// A [Deserializer] wrapper that applies [interpolate_env_vars()] to every string value
+struct Interpolating<D> {
+    inner: D,
+    policy: EnvInterpolation,
+    path: ValuePath,
+    /// if the strings are map keys, recorded into `path` rather than interpolated
+    key: bool,
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+                self.inner.$method(InterpolatingVisitor { inner: visitor, policy: self.policy, path: self.path, key: self.key })
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for Interpolating<D> {
+    type Error = D::Error;
+
+    forward_deserialize!(
+        deserialize_any, deserialize_bool, deserialize_i8, deserialize_i16, deserialize_i32, deserialize_i64, deserialize_i128,
+        deserialize_u8, deserialize_u16, deserialize_u32, deserialize_u64, deserialize_u128, deserialize_f32, deserialize_f64,
+        deserialize_char, deserialize_str, deserialize_string, deserialize_bytes, deserialize_byte_buf, deserialize_option,
+        deserialize_unit, deserialize_seq, deserialize_map, deserialize_identifier, deserialize_ignored_any,
+    );
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, D::Error> {
+        self.inner.deserialize_unit_struct(name, InterpolatingVisitor { inner: visitor, policy: self.policy, path: self.path, key: self.key })
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, D::Error> {
+        self.inner.deserialize_newtype_struct(name, InterpolatingVisitor { inner: visitor, policy: self.policy, path: self.path, key: self.key })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, D::Error> {
+        self.inner.deserialize_tuple(len, InterpolatingVisitor { inner: visitor, policy: self.policy, path: self.path, key: self.key })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, D::Error> {
+        self.inner.deserialize_tuple_struct(name, len, InterpolatingVisitor { inner: visitor, policy: self.policy, path: self.path, key: self.key })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, D::Error> {
+        self.inner.deserialize_struct(name, fields, InterpolatingVisitor { inner: visitor, policy: self.policy, path: self.path, key: self.key })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, D::Error> {
+        self.inner.deserialize_enum(name, variants, InterpolatingVisitor { inner: visitor, policy: self.policy, path: self.path, key: self.key })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// Returns a [DeserializeSeed] for `T` that applies [interpolate_env_vars()] to every string value
+pub(crate) fn interpolating_seed<'de, T: serde::Deserialize<'de>>(policy: EnvInterpolation) -> impl DeserializeSeed<'de, Value = T> {
+    interpolating_seed_tracking(policy, ValuePath::default())
+}
+
+/// Same as [interpolating_seed()], also keeping track, in `path`, of the value refused if the deserialization fails
+pub(crate) fn interpolating_seed_tracking<'de, T: serde::Deserialize<'de>>(policy: EnvInterpolation, path: ValuePath) -> impl DeserializeSeed<'de, Value = T> {
+    InterpolatingSeed { inner: std::marker::PhantomData::<T>, policy, path, key: false }
+}
+
+/// Wraps [DeserializeSeed]s for values (or for the map `key`s recorded in `path`), so nested values are also interpolated
+struct InterpolatingSeed<S> {
+    inner: S,
+    policy: EnvInterpolation,
+    path: ValuePath,
+    key: bool,
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for InterpolatingSeed<S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.inner.deserialize(Interpolating { inner: deserializer, policy: self.policy, path: self.path, key: self.key })
+    }
+}
+
+/// Intercepts the strings given to the wrapped [Visitor], also wrapping any nested accessors
+struct InterpolatingVisitor<V> {
+    inner: V,
+    policy: EnvInterpolation,
+    path: ValuePath,
+    key: bool,
+}
+
+macro_rules! forward_visit {
+    ($($method:ident: $type:ty),* $(,)?) => {
+        $(
+            fn $method<E: de::Error>(self, v: $type) -> Result<Self::Value, E> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for InterpolatingVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    forward_visit!(
+        visit_bool: bool, visit_i8: i8, visit_i16: i16, visit_i32: i32, visit_i64: i64, visit_i128: i128,
+        visit_u8: u8, visit_u16: u16, visit_u32: u32, visit_u64: u64, visit_u128: u128, visit_f32: f32, visit_f64: f64,
+        visit_char: char, visit_bytes: &[u8], visit_borrowed_bytes: &'de [u8], visit_byte_buf: Vec<u8>,
+    );
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        if self.key {
+            self.path.record_key(v);
+            return self.inner.visit_str(v)
+        }
+        match interpolate_env_vars(v, self.policy).map_err(undefined_var_error)? {
+            Cow::Borrowed(v) => self.inner.visit_str(v),
+            Cow::Owned(v) => self.inner.visit_string(v),
+        }
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        if self.key {
+            self.path.record_key(v);
+            return self.inner.visit_borrowed_str(v)
+        }
+        match interpolate_env_vars(v, self.policy).map_err(undefined_var_error)? {
+            Cow::Borrowed(v) => self.inner.visit_borrowed_str(v),
+            Cow::Owned(v) => self.inner.visit_string(v),
+        }
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        if self.key {
+            self.path.record_key(&v);
+            return self.inner.visit_string(v)
+        }
+        match interpolate_env_vars(&v, self.policy).map_err(undefined_var_error)? {
+            Cow::Borrowed(_) => self.inner.visit_string(v),
+            Cow::Owned(interpolated) => self.inner.visit_string(interpolated),
+        }
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.inner.visit_some(Interpolating { inner: deserializer, policy: self.policy, path: self.path, key: false })
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.inner.visit_newtype_struct(Interpolating { inner: deserializer, policy: self.policy, path: self.path, key: false })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        self.inner.visit_seq(InterpolatingAccess { inner: seq, policy: self.policy, path: self.path, index: 0 })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        self.inner.visit_map(InterpolatingAccess { inner: map, policy: self.policy, path: self.path, index: 0 })
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        self.inner.visit_enum(InterpolatingAccess { inner: data, policy: self.policy, path: self.path, index: 0 })
+    }
+}
+
+fn undefined_var_error<E: de::Error>(var_name: String) -> E {
+    E::custom(format!("undefined environment variable `{var_name}` referenced in the config"))
+}
+
+/// Wraps seq, map & enum accessors, interpolating values -- but not map keys nor enum variant names
+struct InterpolatingAccess<A> {
+    inner: A,
+    policy: EnvInterpolation,
+    path: ValuePath,
+    /// of the next sequence element
+    index: usize,
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for InterpolatingAccess<A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, A::Error> {
+        let seed = InterpolatingSeed { inner: seed, policy: self.policy, path: self.path.clone(), key: false };
+        self.index += 1;
+        self.path.nested((self.index - 1).to_string(), || self.inner.next_element_seed(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for InterpolatingAccess<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error> {
+        self.inner.next_key_seed(InterpolatingSeed { inner: seed, policy: EnvInterpolation::Disabled, path: self.path.clone(), key: true })
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        let seed = InterpolatingSeed { inner: seed, policy: self.policy, path: self.path.clone(), key: false };
+        self.path.nested(self.path.take_pending_key(), || self.inner.next_value_seed(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: EnumAccess<'de>> EnumAccess<'de> for InterpolatingAccess<A> {
+    type Error = A::Error;
+    type Variant = InterpolatingAccess<A::Variant>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant), A::Error> {
+        let (policy, path) = (self.policy, self.path);
+        self.inner.variant_seed(seed)
+            .map(|(value, variant)| (value, InterpolatingAccess { inner: variant, policy, path, index: 0 }))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for InterpolatingAccess<A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, A::Error> {
+        self.inner.newtype_variant_seed(InterpolatingSeed { inner: seed, policy: self.policy, path: self.path, key: false })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        self.inner.tuple_variant(len, InterpolatingVisitor { inner: visitor, policy: self.policy, path: self.path, key: false })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, A::Error> {
+        self.inner.struct_variant(fields, InterpolatingVisitor { inner: visitor, policy: self.policy, path: self.path, key: false })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolation() {
+        std::env::set_var("OGRE_CONFIG_MELD_TEST_HOST", "db.example.com");
+        let test = |value, policy, expected: Result<&str, &str>| {
+            let observed = interpolate_env_vars(value, policy);
+            assert_eq!(observed.as_deref().map_err(String::as_str), expected, "Wrong interpolation for '{value}' with {policy:?}");
+        };
+        // defined
+        test("${OGRE_CONFIG_MELD_TEST_HOST}:5432", EnvInterpolation::KeepUndefined, Ok("db.example.com:5432"));
+        test("postgres://$OGRE_CONFIG_MELD_TEST_HOST/db", EnvInterpolation::FailOnUndefined, Ok("postgres://db.example.com/db"));
+        // undefined
+        test("${OGRE_CONFIG_MELD_TEST_UNDEFINED}:5432", EnvInterpolation::KeepUndefined, Ok("${OGRE_CONFIG_MELD_TEST_UNDEFINED}:5432"));
+        test("$OGRE_CONFIG_MELD_TEST_UNDEFINED", EnvInterpolation::FailOnUndefined, Err("OGRE_CONFIG_MELD_TEST_UNDEFINED"));
+        // escaped
+        test("price: $$5 @ $${OGRE_CONFIG_MELD_TEST_HOST}", EnvInterpolation::FailOnUndefined, Ok("price: $5 @ ${OGRE_CONFIG_MELD_TEST_HOST}"));
+        // not references
+        test("5$ ${unclosed", EnvInterpolation::FailOnUndefined, Ok("5$ ${unclosed"));
+        // disabled
+        test("${OGRE_CONFIG_MELD_TEST_HOST}", EnvInterpolation::Disabled, Ok("${OGRE_CONFIG_MELD_TEST_HOST}"));
+    }
+
+    #[test]
+    fn refused_value_path() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Server {
+            _port: u16,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        struct Servers {
+            _servers: Vec<Server>,
+        }
+        let deserialize = |json: &str| {
+            let path = ValuePath::default();
+            let result = interpolating_seed_tracking::<Servers>(EnvInterpolation::Disabled, path.clone())
+                .deserialize(&mut serde_json::Deserializer::from_str(json));
+            (result, path.refused())
+        };
+        let (result, refused) = deserialize(r#"{"_servers": [{"_port": 80}, {"_port": 443}]}"#);
+        assert!(result.is_ok(), "Deserialization failed: {result:?}");
+        assert_eq!(refused, None, "No value should have been refused");
+        let (result, refused) = deserialize(r#"{"_servers": [{"_port": 80}, {"_port": "https"}]}"#);
+        assert!(result.is_err(), "The port should have been refused");
+        assert_eq!(refused.as_deref(), Some("_servers.1._port"), "Wrong path for the refused value");
+    }
+}
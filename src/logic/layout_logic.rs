@@ -0,0 +1,373 @@
+//! Rewriting of config files preserving their layout -- comments, blank lines & the ordering of the fields --
+//! by editing just the changed values, in place, on the original text. See [crate::RewriteStyle::PreserveLayout]
+
+use std::collections::HashMap;
+use std::ops::Range;
+#[cfg(feature = "yaml")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "yaml")]
+use regex::Regex;
+use crate::logic::generic_value_logic::{changed_paths, field_path_of};
+#[cfg(feature = "yaml")]
+use crate::logic::generic_value_logic::child_pointer;
+#[cfg(feature = "ron")]
+use crate::logic::generic_value_logic::ron_with_spans;
+#[cfg(feature = "yaml")]
+use crate::logic::generic_value_logic::generic_from_yaml;
+use crate::SerdeFormat;
+
+/// Where the fields of the objects are in a config text -- all keyed by the JSON pointers of the fields' values
+#[derive(Debug, Default)]
+pub(crate) struct ValueSpans {
+    /// The values, from just after the `:` following the field name -- so they may be replaced
+    pub(crate) values: HashMap<String, Range<usize>>,
+    /// The whole fields: name & value -- so they may be copied into other texts
+    pub(crate) fields: HashMap<String, Range<usize>>,
+    /// Where new fields may be added to the objects -- keyed by the objects' pointers
+    pub(crate) insertions: HashMap<String, FieldInsertion>,
+}
+
+/// How to add a new field to an object of a config text -- see [ValueSpans::insertions]
+#[derive(Debug)]
+pub(crate) struct FieldInsertion {
+    pub(crate) position: usize,
+    /// What goes before the new field -- like `,\n    ` -- which must end with the field's indentation, if it goes in its own line
+    pub(crate) prefix: String,
+    /// What goes after the new field -- like a trailing `,`
+    pub(crate) suffix: String,
+}
+
+/// Edits `original_txt` -- the text of a config file, in `format` -- to hold the values of `effective_txt` (the effective config,
+/// serialized in the same `format`) by replacing just the values that differ & adding the missing fields after the last field of
+/// their objects. Comments, blank lines & the ordering of the fields are kept, except inside the replaced values.
+/// Fields missing from `effective_txt` are left alone. Returns `None` if any change can't be applied that way
+/// -- like when the root itself is replaced or when the texts can't be parsed.
+pub(crate) fn preserving_layout(original_txt: &str, effective_txt: &str, format: SerdeFormat) -> Option<String> {
+    let (original, original_spans) = located(original_txt, format)?;
+    let (effective, effective_spans) = located(effective_txt, format)?;
+    let mut edits: Vec<(String, Range<usize>, String)> = Vec::new();
+    for changed_pointer in changed_paths(&original, &effective) {
+        let mut pointer = changed_pointer.as_str();
+        // changes that can't be located are applied to their closest located ancestor
+        let edit = loop {
+            let (parent, _) = pointer.rsplit_once('/')?;
+            match (original.pointer(pointer), effective.pointer(pointer)) {
+                (Some(_), Some(_)) => if let (Some(original_range), Some(effective_range)) = (original_spans.values.get(pointer), effective_spans.values.get(pointer)) {
+                    let replacement = reindented(&effective_txt[effective_range.clone()], &indentation_at(effective_txt, effective_range.start),
+                                                 &indentation_at(original_txt, original_range.start));
+                    break Some((original_range.clone(), replacement))
+                },
+                (None, Some(_)) => if let (Some(insertion), Some(field_range)) = (original_spans.insertions.get(parent), effective_spans.fields.get(pointer)) {
+                    let indentation = match insertion.prefix.rsplit_once('\n') {
+                        Some((_, indentation)) => indentation.to_string(),
+                        None => indentation_at(original_txt, insertion.position),
+                    };
+                    let field = reindented(&effective_txt[field_range.clone()], &indentation_at(effective_txt, field_range.start), &indentation);
+                    break Some((insertion.position..insertion.position, format!("{}{field}{}", insertion.prefix, insertion.suffix)))
+                },
+                // fields the effective config doesn't have were ignored when loading, so they may stay
+                _ => break None,
+            }
+            pointer = parent;
+        };
+        if let Some((range, replacement)) = edit {
+            edits.push((pointer.to_string(), range, replacement));
+        }
+    }
+    // edits of ancestors supersede the ones of their descendants
+    let edited_pointers = edits.iter().map(|(pointer, ..)| pointer.clone()).collect::<Vec<_>>();
+    edits.retain(|(pointer, ..)| !edited_pointers.iter().any(|edited| pointer.starts_with(&format!("{edited}/"))));
+    edits.dedup_by(|(pointer, ..), (previous_pointer, ..)| pointer == previous_pointer);
+    // applied from the end, so the ranges stay valid -- & insertions at the same position keep their order
+    let mut ordered_edits = edits.into_iter().enumerate().collect::<Vec<_>>();
+    ordered_edits.sort_by_key(|(i, (_, range, _))| (std::cmp::Reverse(range.start), std::cmp::Reverse(*i)));
+    let mut edited_txt = original_txt.to_string();
+    let mut applied_start = usize::MAX;
+    for (_, (_, range, replacement)) in ordered_edits {
+        if range.end > applied_start || (range.end == applied_start && !range.is_empty()) {
+            return None
+        }
+        applied_start = range.start;
+        edited_txt.replace_range(range, &replacement);
+    }
+    Some(edited_txt)
+}
+
+/// The generic representation of `txt_config` along with the spans of its fields
+pub(crate) fn located(txt_config: &str, format: SerdeFormat) -> Option<(serde_json::Value, ValueSpans)> {
+    match format {
+        #[cfg(feature = "ron")]
+        SerdeFormat::Ron => ron_with_spans(txt_config).ok(),
+        #[cfg(feature = "yaml")]
+        SerdeFormat::Yaml => {
+            let value = serde_yaml::from_str(txt_config).ok().map(generic_from_yaml)?;
+            Some((value, yaml_spans(txt_config)))
+        },
+    }
+}
+
+/// Locates the fields of `txt_config` -- a config text in `format` -- for editor integrations, like highlighting or jumping to them:
+/// returns the dotted path of each field (like `log_sub_config.sink`) along with the byte range of the whole field -- name & value --
+/// in the text, ordered by their positions. Fields inside sequences & inline YAML values are located along with their parents only.
+/// Texts that can't be parsed have no fields located
+pub fn field_spans(txt_config: &str, format: SerdeFormat) -> Vec<(String, Range<usize>)> {
+    let Some((_, spans)) = located(txt_config, format) else {
+        return Vec::new()
+    };
+    let mut field_spans = spans.fields.into_iter()
+        .map(|(pointer, range)| (field_path_of(&pointer), range))
+        .collect::<Vec<_>>();
+    field_spans.sort_by_key(|(_, range)| (range.start, std::cmp::Reverse(range.end)));
+    field_spans
+}
+
+/// The whitespaces at the start of the line holding the `position` in `txt`
+pub(crate) fn indentation_at(txt: &str, position: usize) -> String {
+    let line_start = txt[..position].rfind('\n').map_or(0, |i| i + 1);
+    txt[line_start..].chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+}
+
+/// Moves the lines of `txt` (but the first) from the `from` indentation to the `to` one
+fn reindented(txt: &str, from: &str, to: &str) -> String {
+    txt.split('\n')
+        .enumerate()
+        .map(|(i, line)| match line.strip_prefix(from) {
+            Some(unindented) if i > 0 => format!("{to}{unindented}"),
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Locates the fields of the block mappings of the YAML `txt_config`, based on the indentation of their lines.
+/// Values written inline (flow collections, tagged values, ...) are located as a whole, while sequences & multi-line scalars
+/// span all their lines -- with nothing located inside them
+#[cfg(feature = "yaml")]
+fn yaml_spans(txt_config: &str) -> ValueSpans {
+    static FIELD: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^( *)("(?:[^"\\]|\\.)*"|'(?:[^']|'')*'|[^ \t#'"{\[?|>!&*%@`-][^#]*?|-[^ \t#][^#]*?)[ \t]*:(?:[ \t]|$)"#).expect("Bad Regex"));
+
+    /// A field (or the root) whose lines are being located
+    struct Block {
+        indentation: isize,
+        pointer: String,
+        /// Where the field starts & where its value starts -- just after the `:`
+        field_start: usize,
+        value_start: usize,
+        /// The end of the block's contents: its inline value or its last line with contents
+        end: usize,
+        /// The indentation of the block's fields, if it is a mapping
+        fields_indentation: Option<usize>,
+        has_inline_value: bool,
+        /// Blocks whose lines aren't fields, like sequences or inline values possibly continued in the following lines
+        is_opaque: bool,
+    }
+
+    fn close(block: Block, spans: &mut ValueSpans) {
+        if block.indentation >= 0 {
+            spans.values.insert(block.pointer.clone(), block.value_start..block.end);
+            spans.fields.insert(block.pointer.clone(), block.field_start..block.end);
+        }
+        if let Some(fields_indentation) = block.fields_indentation {
+            spans.insertions.insert(block.pointer, FieldInsertion {
+                position: block.end,
+                prefix: format!("\n{}", " ".repeat(fields_indentation)),
+                suffix: String::new(),
+            });
+        }
+    }
+
+    let mut spans = ValueSpans::default();
+    let mut blocks = vec![Block { indentation: -1, pointer: String::new(), field_start: 0, value_start: 0, end: 0, fields_indentation: None, has_inline_value: false, is_opaque: false }];
+    let mut line_start = 0;
+    for line in txt_config.split('\n') {
+        let (current_line_start, line_end) = (line_start, line_start + line.len());
+        line_start = line_end + 1;
+        let contents = line.trim();
+        if contents.is_empty() || contents.starts_with('#') || (line.starts_with("---") || line.starts_with("...")) {
+            continue
+        }
+        let indentation = (line.len() - line.trim_start_matches(' ').len()) as isize;
+        let is_sequence_item = contents == "-" || contents.starts_with("- ");
+        while let Some(block) = blocks.pop() {
+            // sequences may be indented as their field
+            let continues = block.indentation < indentation
+                || (block.indentation == indentation && is_sequence_item && block.fields_indentation.is_none() && !block.has_inline_value);
+            if continues {
+                blocks.push(block);
+                break
+            }
+            close(block, &mut spans);
+        }
+        let Some(block) = blocks.last_mut() else {
+            return spans
+        };
+        let field = FIELD.captures(line).filter(|_| !block.is_opaque && !is_sequence_item);
+        let Some(field) = field else {
+            // lines that aren't fields make the block opaque -- like sequences & continued values
+            if block.fields_indentation.is_none() {
+                block.is_opaque = true;
+            }
+            blocks.iter_mut().for_each(|block| block.end = line_end);
+            continue
+        };
+        if block.is_opaque || block.fields_indentation.is_some_and(|fields_indentation| fields_indentation as isize != indentation) {
+            // inconsistent indentation: nothing else is located
+            return ValueSpans::default()
+        }
+        block.fields_indentation = Some(indentation as usize);
+        let key = field.get(2).map_or("", |key| key.as_str());
+        let key = if key.starts_with(['"', '\'']) { serde_yaml::from_str::<String>(key).unwrap_or_else(|_| key.to_string()) } else { key.to_string() };
+        let pointer = child_pointer(&block.pointer, &key);
+        let value_start = current_line_start + field.get(0).map_or(0, |field| field.end());
+        // the `:` is right before the matched whitespace, if any
+        let value_start = if txt_config[..value_start].ends_with(':') { value_start } else { value_start - 1 };
+        let inline_value = inline_yaml_value(&txt_config[value_start..line_end]);
+        blocks.iter_mut().for_each(|block| block.end = line_end);
+        blocks.push(Block {
+            indentation,
+            pointer,
+            field_start: current_line_start + indentation as usize,
+            value_start,
+            end: value_start + inline_value.end,
+            fields_indentation: None,
+            has_inline_value: !inline_value.is_empty(),
+            is_opaque: !inline_value.is_empty(),
+        });
+    }
+    while let Some(block) = blocks.pop() {
+        close(block, &mut spans);
+    }
+    spans
+}
+
+/// The range of the value (including its leading spaces) in `after_colon` -- the rest of the line after a field's `:` --
+/// excluding any trailing comment & spaces. Empty if there is no value in the line
+#[cfg(feature = "yaml")]
+fn inline_yaml_value(after_colon: &str) -> Range<usize> {
+    let leading_spaces = after_colon.len() - after_colon.trim_start().len();
+    let value = &after_colon[leading_spaces..];
+    let quote_end = match value.chars().next() {
+        Some('\'') => value[1..].find('\'').map(|i| i + 2),
+        Some('"') => {
+            let mut escaped = false;
+            value[1..].char_indices()
+                .find(|&(_, c)| {
+                    let is_end = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    is_end
+                })
+                .map(|(i, _)| i + 2)
+        },
+        _ => None,
+    }.unwrap_or(0);
+    let comment_start = value[quote_end..].find(" #").map_or(value.len(), |i| quote_end + i);
+    let value_len = value[..comment_start].trim_end().len();
+    if value_len == 0 || value.starts_with('#') {
+        0..0
+    } else {
+        0..leading_spaces + value_len
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_edits() {
+        let original_txt = "\
+# the app's config
+log:   # logging
+  sink: stdout    # where the logs go
+
+  # how much gets logged
+  level: 3
+list:
+- 1
+- 2
+name: 'app' # quoted
+";
+        let effective_txt = "log:\n  sink: stderr\n  level: 3\n  colors: true\nlist:\n- 1\n- 3\nname: app\nadded:\n  a: 1\n";
+        let edited_txt = preserving_layout(original_txt, effective_txt, SerdeFormat::Yaml).expect("The layout should have been preserved");
+        assert_eq!(edited_txt, "\
+# the app's config
+log:   # logging
+  sink: stderr    # where the logs go
+
+  # how much gets logged
+  level: 3
+  colors: true
+list:
+- 1
+- 3
+name: 'app' # quoted
+added:
+  a: 1
+", "Only the changed values should have been edited");
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn field_locations() {
+        let yaml_txt = "# the config\nlog:\n  sink: stdout   # where to\n  level: 3\nname: app\n";
+        let spans = field_spans(yaml_txt, SerdeFormat::Yaml);
+        let field_paths = spans.iter().map(|(field_path, _)| field_path.as_str()).collect::<Vec<_>>();
+        assert_eq!(field_paths, ["log", "log.sink", "log.level", "name"], "Wrong YAML fields located");
+        let (_, sink_range) = &spans[1];
+        assert_eq!(&yaml_txt[sink_range.clone()], "sink: stdout", "Wrong span for the YAML `log.sink` field");
+
+        let ron_txt = "(\n    log: (sink: Some(stdout), level: 3),\n    name: \"app\",\n)";
+        let spans = field_spans(ron_txt, SerdeFormat::Ron);
+        let located = spans.iter().map(|(field_path, range)| (field_path.as_str(), &ron_txt[range.clone()])).collect::<Vec<_>>();
+        assert_eq!(located, [("log", "log: (sink: Some(stdout), level: 3)"), ("log.sink", "sink: Some(stdout)"), ("log.level", "level: 3"), ("name", "name: \"app\"")],
+                   "Wrong RON fields located");
+
+        assert!(field_spans("log: [unterminated", SerdeFormat::Yaml).is_empty(), "Unparseable texts should have no fields located");
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn ron_edits() {
+        let original_txt = "\
+// the app's config
+(
+    log: (   // logging
+        sink: Some(stdout),   // where the logs go
+        /* how much gets logged */
+        level: 3,
+    ),
+    inline: (a: 1, b: \"x\"),
+    name: \"app\"
+)
+";
+        let effective_txt = "(\n    log: (\n        sink: Some(stderr),\n        level: 3,\n        colors: true,\n    ),\n    inline: (\n        a: 2,\n        b: \"x\",\n        c: None,\n    ),\n    name: \"app\",\n    added: (\n        a: 1,\n    ),\n)";
+        let edited_txt = preserving_layout(original_txt, effective_txt, SerdeFormat::Ron).expect("The layout should have been preserved");
+        assert_eq!(edited_txt, "\
+// the app's config
+(
+    log: (   // logging
+        sink: Some(stderr),   // where the logs go
+        /* how much gets logged */
+        level: 3,
+        colors: true,
+    ),
+    inline: (a: 2, b: \"x\", c: None),
+    name: \"app\",
+    added: (
+        a: 1,
+    )
+)
+", "Only the changed values should have been edited");
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn unlocatable_changes() {
+        assert_eq!(preserving_layout("(a: 1)", "[1]", SerdeFormat::Ron), None, "Replacing the root can't preserve the layout");
+        assert_eq!(preserving_layout("a: 1\nb: [1,\n  2]\n", "a: 2\nb:\n- 1\n- 2\n", SerdeFormat::Yaml).as_deref(), Some("a: 2\nb: [1,\n  2]\n"),
+                   "Unchanged multi-line values should be kept");
+        assert_eq!(preserving_layout("a: 1\n  b: 1\n", "a: 2\n", SerdeFormat::Yaml), None, "Unparseable texts can't have their layout preserved");
+    }
+}
@@ -0,0 +1,171 @@
+//! The merging of command line options into configs -- field by field (see [MergeField]) or by overlaying their serialized values
+
+use serde::Serialize;
+use serde_json::Value;
+use crate::{MergeField, OgreRootConfig};
+
+impl<T> MergeField<T> for Option<T> {
+    fn merge_into(self, config_field: &mut T) {
+        if let Some(value) = self {
+            *config_field = value;
+        }
+    }
+}
+
+impl<T> MergeField<Option<T>> for Option<T> {
+    fn merge_into(self, config_field: &mut Option<T>) {
+        if self.is_some() {
+            *config_field = self;
+        }
+    }
+}
+
+impl MergeField<bool> for bool {
+    fn merge_into(self, config_field: &mut bool) {
+        *config_field |= self;
+    }
+}
+
+impl<T> MergeField<Vec<T>> for Vec<T> {
+    fn merge_into(self, config_field: &mut Vec<T>) {
+        if !self.is_empty() {
+            *config_field = self;
+        }
+    }
+}
+
+/// Overlays the serialized `cmdline_options` on the `config` -- see [crate::CmdLineAndConfigIntegration::default_merge_with_config()].
+/// Each option goes to the dotted config path given for it in `cli_to_config_paths` or, otherwise, to the config field of the same name
+pub(crate) fn overlay_cmdline_options<RootConfigType: OgreRootConfig>(
+    cmdline_options: &impl Serialize,
+    config: RootConfigType,
+    cli_to_config_paths: &[(&str, &str)],
+) -> Result<RootConfigType, crate::Error> {
+    let overlay_err = |reason: String| crate::Error::CliParsing {
+        rendered_help: format!("error: the command line options couldn't be merged into the config: {reason}\n"),
+        exit_hint: 2,
+    };
+    let options = match serde_json::to_value(cmdline_options) {
+        Ok(Value::Object(options)) => options,
+        Ok(_) => return Err(overlay_err("the options aren't a struct".to_string())),
+        Err(err) => return Err(overlay_err(format!("the options can't be represented generically: {err}"))),
+    };
+    let mut generic_config = serde_json::to_value(&config)
+        .map_err(|err| overlay_err(format!("the config can't be represented generically: {err}")))?;
+    for (option_name, option_value) in options.into_iter().filter(|(_, option_value)| !holds_no_value(option_value)) {
+        let config_path = cli_to_config_paths.iter()
+            .find(|(cli_field_name, _)| *cli_field_name == option_name)
+            .map_or(option_name.as_str(), |(_, config_path)| config_path);
+        if let Some(config_field) = config_field_at(&mut generic_config, config_path) {
+            overlay(config_field, option_value);
+        }
+    }
+    serde_json::from_value(generic_config)
+        .map_err(|err| overlay_err(format!("the given values don't fit the config: {err}")))
+}
+
+/// The config field at the dotted `config_path` -- if there is one. Sections holding no value are created along the way
+fn config_field_at<'a>(generic_config: &'a mut Value, config_path: &str) -> Option<&'a mut Value> {
+    let mut field = generic_config;
+    for field_name in config_path.split('.') {
+        let is_new_section = field.is_null();
+        if is_new_section {
+            *field = Value::Object(serde_json::Map::new());
+        }
+        let Value::Object(section) = field else {
+            return None
+        };
+        if is_new_section {
+            section.insert(field_name.to_string(), Value::Null);
+        }
+        field = section.get_mut(field_name)?;
+    }
+    Some(field)
+}
+
+/// Overlays the `option_value` on the `config_field` -- sections field by field -- unless it holds no value
+fn overlay(config_field: &mut Value, option_value: Value) {
+    match (config_field, option_value) {
+        (_, option_value) if holds_no_value(&option_value) => (),
+        (Value::Object(config_section), Value::Object(option_section)) => {
+            for (field_name, option_value) in option_section {
+                if let Some(config_field) = config_section.get_mut(&field_name) {
+                    overlay(config_field, option_value);
+                }
+            }
+        },
+        (config_field, option_value) => *config_field = option_value,
+    }
+}
+
+/// Tells if the `option_value` is of an option that wasn't given -- `None`, `false` or an empty list
+fn holds_no_value(option_value: &Value) -> bool {
+    match option_value {
+        Value::Null | Value::Bool(false) => true,
+        Value::Array(elements) => elements.is_empty(),
+        _ => false,
+    }
+}
+
+
+#[cfg(all(test, feature = "yaml"))]
+mod tests {
+    use super::*;
+    use crate::CmdLineAndConfigIntegration;
+    use clap::Parser;
+    use serde::Deserialize;
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct LogSection {
+        level: u8,
+        sink: String,
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct ServiceConfig {
+        name: String,
+        port: u16,
+        log: LogSection,
+    }
+    impl OgreRootConfig for ServiceConfig {}
+
+    #[derive(Parser, Debug, Serialize)]
+    struct ServiceOptions {
+        #[clap(long)]
+        config_file: Option<String>,
+        #[clap(long)]
+        write_effective_config: bool,
+        #[clap(long)]
+        log_level: Option<u8>,
+        #[clap(long)]
+        output: Option<String>,
+        #[clap(long)]
+        port: Option<u16>,
+    }
+    impl CmdLineAndConfigIntegration<ServiceConfig> for ServiceOptions {
+        fn config_file_path(&self) -> Option<&str> { self.config_file.as_deref() }
+        fn should_write_effective_config(&self) -> bool { self.write_effective_config }
+        fn should_show_effective_config(&self) -> bool { false }
+        fn cli_to_config_paths() -> &'static [(&'static str, &'static str)] {
+            &[("log_level", "log.level"), ("output", "log.sink")]
+        }
+        fn merge_with_config(self, config: ServiceConfig) -> Result<ServiceConfig, crate::Error> {
+            self.default_merge_with_config(config)
+        }
+    }
+
+    #[test]
+    fn default_merge_with_config() {
+        let config = || ServiceConfig { name: "api".to_string(), port: 80, log: LogSection { level: 1, sink: "stderr".to_string() } };
+        let merge = |args: &[&str]| ServiceOptions::parse_from(args).merge_with_config(config()).expect("The options should have been merged");
+
+        assert_eq!(merge(&["test", "--config-file", "service.yaml", "--write-effective-config"]), config(),
+                   "Options not given -- or not in the config -- shouldn't change it");
+        assert_eq!(merge(&["test", "--log-level", "3", "--output", "stdout", "--port", "8080"]),
+                   ServiceConfig { name: "api".to_string(), port: 8080, log: LogSection { level: 3, sink: "stdout".to_string() } },
+                   "The options should have been merged at their mapped paths -- or into the fields of the same names");
+        assert_eq!(merge(&["test", "--output", "stdout"]),
+                   ServiceConfig { log: LogSection { sink: "stdout".to_string(), ..config().log }, ..config() },
+                   "Only the given option should have overridden the config");
+    }
+}
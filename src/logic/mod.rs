@@ -1,7 +1,94 @@
 mod cli_logic;
 pub use cli_logic::*;
+#[cfg(feature = "test-util")]
+pub(crate) use cli_logic::load_and_merge_configs_traced_for;
+
+#[cfg(feature = "async")]
+mod builder_logic;
 
 mod config_logic;
 pub use config_logic::*;
 
+#[cfg(feature = "async")]
+mod fs_logic;
+
 mod serde;
+pub use serde::{SerdeFormat, supported_extensions};
+
+#[cfg(feature = "async")]
+mod subcommand_logic;
+#[cfg(feature = "async")]
+pub use subcommand_logic::*;
+
+mod enum_logic;
+pub use enum_logic::*;
+
+mod interpolation_logic;
+
+mod compat_logic;
+
+mod header_logic;
+
+mod overrides_logic;
+pub use overrides_logic::*;
+
+mod env_logic;
+pub use env_logic::*;
+
+// the loading with warnings is only done by the async API, while the warnings found while running are emitted by both
+#[cfg_attr(not(feature = "async"), allow(dead_code))]
+mod warnings_logic;
+pub use warnings_logic::*;
+
+#[cfg(feature = "async")]
+mod doctor_logic;
+#[cfg(feature = "async")]
+pub use doctor_logic::*;
+
+mod diff_logic;
+pub use diff_logic::*;
+
+mod sparse_logic;
+
+#[cfg(feature = "yaml")]
+mod yaml_keys_logic;
+
+mod generic_value_logic;
+
+// its layout-preserving rewrites are only done by the async API
+#[cfg_attr(not(feature = "async"), allow(dead_code, unused_imports))]
+mod layout_logic;
+pub use layout_logic::field_spans;
+
+mod provenance_logic;
+pub(crate) use provenance_logic::ProvenanceTracer;
+
+mod secrets_logic;
+pub(crate) use secrets_logic::{config_with_secrets, secret_reading_error, keeping_secret_refs, secret_refs_in, SecretRefsSource, SECRET_REF_MARKER};
+
+mod time_logic;
+
+pub mod serde_helpers;
+
+mod merge_logic;
+pub(crate) use merge_logic::overlay_cmdline_options;
+
+#[cfg(feature = "schema")]
+mod schema_logic;
+#[cfg(feature = "schema")]
+pub use schema_logic::*;
+
+#[cfg(feature = "remote")]
+mod remote_logic;
+#[cfg(feature = "remote")]
+pub use remote_logic::*;
+
+#[cfg(feature = "watch")]
+mod watch_logic;
+#[cfg(feature = "watch")]
+pub use watch_logic::*;
+
+#[cfg(all(unix, feature = "sighup"))]
+mod sighup_logic;
+#[cfg(all(unix, feature = "sighup"))]
+pub use sighup_logic::*;
@@ -0,0 +1,139 @@
+//! Generic overrides of single config fields, given as `key=value` pairs -- like in `--set log_sub_config.sink=stdout`
+
+use crate::OgreRootConfig;
+
+/// Applies the `key=value` `overrides` to `config`, in order: keys are the `.` separated field names of the nested config sections
+/// (like `log_sub_config.sink`) and values are coerced to the types of the fields -- so bools, numbers, enum variants & strings
+/// may be given bare. Fields that hold no value (like `None`s) take JSON values (as `[1, 2]`) or, failing that, strings.
+///
+/// Invalid overrides are reported as [crate::Error::CliParsing], as they come from the command line.
+/// See [crate::CmdLineAndConfigIntegration::config_overrides()].
+pub fn apply_config_overrides<RootConfigType: OgreRootConfig>(
+    config: RootConfigType,
+    overrides: &[impl AsRef<str>],
+) -> Result<RootConfigType, crate::Error> {
+    apply_overrides_reporting(config, overrides, |config_override, reason| crate::Error::CliParsing {
+        rendered_help: format!("error: invalid value '{config_override}' for '--set <KEY=VALUE>': {reason}\n"),
+        exit_hint: 2,
+    })
+}
+
+/// The logic behind [apply_config_overrides()], reporting invalid overrides through `override_err`
+/// -- called with the offending override (empty if it can't be told) & the reason
+pub(crate) fn apply_overrides_reporting<RootConfigType: OgreRootConfig>(
+    config: RootConfigType,
+    overrides: &[impl AsRef<str>],
+    override_err: impl Fn(&str, String) -> crate::Error,
+) -> Result<RootConfigType, crate::Error> {
+    if overrides.is_empty() {
+        return Ok(config);
+    }
+    let mut generic_config = serde_json::to_value(&config)
+        .map_err(|err| override_err("", format!("the config can't be represented generically: {err}")))?;
+    for config_override in overrides {
+        let config_override = config_override.as_ref();
+        let Some((key, value)) = config_override.split_once('=') else {
+            return Err(override_err(config_override, "expected the 'key=value' form".to_string()));
+        };
+        let slot = field_at(&mut generic_config, key.trim())
+            .map_err(|reason| override_err(config_override, reason))?;
+        *slot = coerced_value(slot, value)
+            .map_err(|reason| override_err(config_override, reason))?;
+        serde_json::from_value::<RootConfigType>(generic_config.clone())
+            .map_err(|err| override_err(config_override, format!("the value doesn't fit the config: {err}")))?;
+    }
+    serde_json::from_value(generic_config)
+        .map_err(|err| override_err("", format!("the overridden values don't fit the config: {err}")))
+}
+
+/// Tells if `generic_config` has a field at the `.` separated `key` -- or may have it, inside sections holding no value
+#[cfg(feature = "async")]
+pub(crate) fn has_field(generic_config: &serde_json::Value, key: &str) -> bool {
+    field_at(&mut generic_config.clone(), key).is_ok()
+}
+
+/// Navigates to the field at the `.` separated `key` -- sections that hold no value are created along the way
+fn field_at<'a>(generic_config: &'a mut serde_json::Value, key: &str) -> Result<&'a mut serde_json::Value, String> {
+    let mut field = generic_config;
+    let mut path = Vec::new();
+    for name in key.split('.') {
+        path.push(name);
+        if field.is_null() {
+            *field = serde_json::Value::Object(serde_json::Map::new());
+        }
+        field = match field {
+            serde_json::Value::Object(section) if section.contains_key(name) || section.is_empty() =>
+                section.entry(name).or_insert(serde_json::Value::Null),
+            serde_json::Value::Object(_) => return Err(format!("there is no '{}' field in the config", path.join("."))),
+            _ => return Err(format!("'{}' is not a config section", path[..path.len() - 1].join("."))),
+        };
+    }
+    Ok(field)
+}
+
+/// Converts the textual `value` to the type of the `current` value of the field
+fn coerced_value(current: &serde_json::Value, value: &str) -> Result<serde_json::Value, String> {
+    let value = value.trim();
+    match current {
+        serde_json::Value::String(_) => Ok(serde_json::Value::String(value.to_string())),
+        serde_json::Value::Bool(_) => value.parse::<bool>()
+            .map(serde_json::Value::Bool)
+            .map_err(|_| format!("'{value}' is not a bool -- use 'true' or 'false'")),
+        serde_json::Value::Number(_) => serde_json::from_str::<serde_json::Number>(value)
+            .map(serde_json::Value::Number)
+            .map_err(|_| format!("'{value}' is not a number")),
+        _ => Ok(serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()))),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    enum Sink { #[default] Stdout, Stderr }
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct LogConfig { sink: Sink, level: u8, colored: bool, prefix: String, file: Option<String> }
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct ServiceConfig { name: String, log: LogConfig, ports: Vec<u16> }
+    impl OgreRootConfig for ServiceConfig {}
+
+    #[test]
+    fn nested_overrides() {
+        let config = apply_config_overrides(ServiceConfig::default(), &[
+            "name=api",
+            "log.sink=Stderr",
+            "log.level=3",
+            "log.colored=true",
+            "log.prefix=42",
+            "log.file=/var/log/api.log",
+            "ports=[80, 443]",
+            "log.level = 4",
+        ]).expect("Applying the overrides failed");
+        assert_eq!(config, ServiceConfig {
+            name: "api".to_string(),
+            log: LogConfig { sink: Sink::Stderr, level: 4, colored: true, prefix: "42".to_string(), file: Some("/var/log/api.log".to_string()) },
+            ports: vec![80, 443],
+        }, "Wrong overridden config");
+
+        for (invalid_override, reason) in [
+            ("log.sink", "expected the 'key=value' form"),
+            ("log.verbose=true", "there is no 'log.verbose' field"),
+            ("name.first=a", "'name' is not a config section"),
+            ("log.colored=yes", "is not a bool"),
+            ("log.level=many", "is not a number"),
+            ("log.level=300", "doesn't fit the config"),
+            ("log.sink=Syslog", "doesn't fit the config"),
+        ] {
+            match apply_config_overrides(ServiceConfig::default(), &[invalid_override]) {
+                Err(crate::Error::CliParsing { rendered_help, .. }) =>
+                    assert!(rendered_help.contains(reason), "Wrong error for '{invalid_override}': '{rendered_help}'"),
+                result => panic!("'{invalid_override}' should have been rejected. Got {result:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Tracking of where each value of the effective config came from -- by diffing the value trees between the stages of the meld pipeline
+
+use std::path::Path;
+use clap::{Arg, ArgMatches, Command, CommandFactory};
+use clap::parser::ValueSource;
+use serde::Serialize;
+use serde_json::Value;
+#[cfg(feature = "async")]
+use crate::logic::builder_logic::env_var_name;
+use crate::logic::generic_value_logic::{changed_paths, field_path_of};
+use crate::logic::layout_logic::located;
+use crate::{config_to_string, Provenance, SerdeFormat, Source};
+
+impl Provenance {
+    /// Where the value of the field at the dotted `field_path` (like `log_sub_config.sink`) came from.
+    /// Paths inside tracked values (like the elements of lists) get the source of the whole value
+    pub fn source_of(&self, field_path: &str) -> Option<&Source> {
+        let mut field_path = field_path;
+        loop {
+            if let Some(source) = self.0.get(field_path) {
+                return Some(source)
+            }
+            field_path = &field_path[..field_path.rfind('.')?];
+        }
+    }
+
+    /// The tracked fields, in the order of their paths
+    pub fn iter(&self) -> impl Iterator<Item=(&str, &Source)> {
+        self.0.iter().map(|(field_path, source)| (field_path.as_str(), source))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Attributes the values that changed from the `old` to the `new` config to the source given by `source_of`
+    /// -- called with the dotted path of each changed field
+    fn record(&mut self, old: &Value, new: &Value, source_of: impl Fn(&str) -> Source) {
+        for pointer in changed_paths(old, new) {
+            let field_path = field_path_of(&pointer);
+            self.0.retain(|tracked_path, _| !is_within(tracked_path, &field_path));
+            let source = source_of(&field_path);
+            self.insert_leaves(&field_path, new.pointer(&pointer).unwrap_or(&Value::Null), &source);
+        }
+    }
+
+    /// Attributes all the leaves of the `value` at `field_path` to `source`
+    fn insert_leaves(&mut self, field_path: &str, value: &Value, source: &Source) {
+        match value {
+            Value::Object(fields) if !fields.is_empty() => fields.iter()
+                .for_each(|(field_name, field_value)| self.insert_leaves(&child_field_path(field_path, field_name), field_value, source)),
+            _ if field_path.is_empty() => (),
+            _ => {
+                self.0.insert(field_path.to_string(), source.clone());
+            },
+        }
+    }
+}
+
+/// Tracks the provenance of the values along the meld pipeline: created with the defaults of the config type,
+/// it is to be informed of each stage's outcome
+pub(crate) struct ProvenanceTracer<'a> {
+    /// The parsed command line, for telling values given in the command line from the ones given by environment variables
+    arg_matches: Option<&'a ArgMatches>,
+    /// The config, as of the last stage
+    previous: Value,
+    pub(crate) provenance: Provenance,
+}
+
+impl<'a> ProvenanceTracer<'a> {
+
+    pub(crate) fn new<RootConfigType: Serialize + Default>(arg_matches: Option<&'a ArgMatches>) -> Self {
+        let mut tracer = Self { arg_matches, previous: Value::Null, provenance: Provenance::default() };
+        tracer.stage(&RootConfigType::default(), |_| Source::Default);
+        tracer
+    }
+
+    /// The values of `config` that changed since the last stage came from the config file at `config_file_path`
+    pub(crate) fn loaded(&mut self, config: &impl Serialize, config_file_path: &Path) {
+        self.stage(config, |_| Source::ConfigFile(config_file_path.to_path_buf()))
+    }
+
+    /// The values of `config` that changed since the last stage came from the given command line `flag`
+    pub(crate) fn flagged(&mut self, config: &impl Serialize, flag: &str) {
+        self.stage(config, |_| Source::CliFlag(flag.to_string()))
+    }
+
+    /// The values of `config` that changed since the last stage came from the environment variables with the given `prefix`
+    /// -- see [crate::ConfigMeld::env_prefix()]
+    #[cfg(feature = "async")]
+    pub(crate) fn env_overlaid(&mut self, config: &impl Serialize, prefix: &str) {
+        self.stage(config, |field_path| Source::EnvVar(env_var_name(prefix, field_path)))
+    }
+
+    /// The values of `config` that changed since the last stage came from config overrides
+    pub(crate) fn overridden(&mut self, config: &impl Serialize) {
+        self.stage(config, |_| Source::Override)
+    }
+
+    /// The values of `config` that changed since the last stage came from merging in the `CmdLineOptionsType` options.
+    /// As the merge is opaque, each value is attributed to the option merged into its field, as told by `cli_to_config_paths`
+    /// (see [crate::CmdLineAndConfigIntegration::cli_to_config_paths()]) -- telling if it was given by an environment variable bound to it --
+    /// or to `<command line>` if there is no such option
+    pub(crate) fn merged<CmdLineOptionsType: CommandFactory>(&mut self, config: &impl Serialize, cli_to_config_paths: &[(&str, &str)]) {
+        let command = CmdLineOptionsType::command();
+        let arg_matches = self.arg_matches;
+        self.stage(config, |field_path| match option_arg_for(&command, field_path, cli_to_config_paths) {
+            Some(arg) => cmdline_source(arg, arg_matches),
+            None => Source::CliFlag("<command line>".to_string()),
+        })
+    }
+
+    fn stage(&mut self, config: &impl Serialize, source_of: impl Fn(&str) -> Source) {
+        let Ok(current) = serde_json::to_value(config) else {
+            return
+        };
+        self.provenance.record(&self.previous, &current, source_of);
+        self.previous = current;
+    }
+}
+
+/// The command line option merged into the config field at the dotted `field_path`: the one `cli_to_config_paths` maps to it -- or to
+/// the section holding it, which gets the options (flattened into the `command`) named after its fields -- or, for root fields, the one
+/// named after it
+fn option_arg_for<'a>(command: &'a Command, field_path: &str, cli_to_config_paths: &[(&str, &str)]) -> Option<&'a Arg> {
+    let option_named = |option_name: &str| command.get_arguments().find(|arg| arg.get_id() == option_name);
+    cli_to_config_paths.iter()
+        .find_map(|(option_name, config_path)| match field_path.strip_prefix(config_path)? {
+            "" => option_named(option_name),
+            field_path_in_section => option_named(field_path_in_section.strip_prefix('.')?),
+        })
+        .or_else(|| option_named(field_path))
+}
+
+/// Where the value of the command line `arg` came from -- as told by the `arg_matches`, if available
+fn cmdline_source(arg: &Arg, arg_matches: Option<&ArgMatches>) -> Source {
+    let env_var = arg.get_env().map(|env_var| env_var.to_string_lossy().to_string());
+    match (arg_matches.and_then(|arg_matches| arg_matches.value_source(arg.get_id().as_str())), env_var) {
+        (Some(ValueSource::EnvVariable), Some(env_var)) => Source::EnvVar(env_var),
+        _ => Source::CliFlag(match (arg.get_long(), arg.get_short()) {
+            (Some(long), _) => format!("--{long}"),
+            (None, Some(short)) => format!("-{short}"),
+            (None, None) => arg.get_id().to_string(),
+        }),
+    }
+}
+
+/// Renders `effective_config` in YAML (or in RON, if the `yaml` feature is disabled), with each value annotated with its source,
+/// as given by `provenance`. Returns `None` if the config can't be rendered that way
+pub(crate) fn annotated_effective_config(effective_config: &impl crate::OgreRootConfig, provenance: &Provenance) -> Option<String> {
+    #[cfg(feature = "yaml")]
+    let (format, comment_prefix) = (SerdeFormat::Yaml, "#");
+    #[cfg(not(feature = "yaml"))]
+    let (format, comment_prefix) = (SerdeFormat::Ron, "//");
+    let txt_config = config_to_string(effective_config, format, "").ok()?;
+    let (_, spans) = located(&txt_config, format)?;
+    let mut annotations = provenance.iter()
+        .filter_map(|(field_path, source)| {
+            let value_span = spans.values.get(&pointer_of(field_path))?;
+            let line_end = txt_config[value_span.start..].find('\n').map_or(txt_config.len(), |i| value_span.start + i);
+            Some((line_end, source_description(source)))
+        })
+        .collect::<Vec<_>>();
+    annotations.sort_by_key(|(line_end, _)| std::cmp::Reverse(*line_end));
+    let mut annotated_txt = txt_config;
+    for (line_end, description) in annotations {
+        annotated_txt.insert_str(line_end, &format!("    {comment_prefix} from {description}"));
+    }
+    Some(annotated_txt)
+}
+
+/// The user facing description of `source`
+fn source_description(source: &Source) -> String {
+    match source {
+        Source::Default => "the defaults".to_string(),
+        Source::ConfigFile(config_file_path) => format!("the config file {config_file_path:?}"),
+        Source::EnvVar(env_var) => format!("the environment variable {env_var}"),
+        Source::CliFlag(flag) => format!("the command line option {flag}"),
+        Source::Override => "a config override".to_string(),
+    }
+}
+
+/// The JSON pointer for the dotted `field_path` -- the inverse of [field_path_of()]
+fn pointer_of(field_path: &str) -> String {
+    field_path.split('.')
+        .map(|key| format!("/{}", key.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// The dotted path of `field_name` inside the field at `field_path`
+fn child_field_path(field_path: &str, field_name: &str) -> String {
+    if field_path.is_empty() { field_name.to_string() } else { format!("{field_path}.{field_name}") }
+}
+
+/// Tells if `field_path` is `ancestor_path` itself or one of its fields (at any depth)
+fn is_within(field_path: &str, ancestor_path: &str) -> bool {
+    ancestor_path.is_empty() || field_path.strip_prefix(ancestor_path).is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
@@ -0,0 +1,343 @@
+//! Loading of configs served by config servers, through http(s) -- behind the `remote` feature
+
+use crate::logic::config_logic::post_loaded;
+use crate::logic::serde::SerdeFormat;
+use crate::logic::warnings_logic::emit_warning;
+use crate::{config_from_str, LoadWarningKind, OgreRootConfig};
+use encryptable_tokio_fs::fs;
+use reqwest::header::{HeaderName, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use std::fmt::Debug;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::Duration;
+
+/// How configs are fetched from config servers -- see [load_from_url_with_options()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteOptions {
+    /// The format of the served configs. Defaults to `None`, where it is inferred from the URL's path extension
+    /// (`.ron`, `.yaml` or `.yml`) or, lacking one, from the `Content-Type` the config server answers with
+    pub format: Option<SerdeFormat>,
+    /// How long to wait for the whole request to complete. Defaults to 30s.
+    pub timeout: Duration,
+}
+
+impl Default for RemoteOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The HTTP validators of a fetched config -- allowing later polls to be answered with `304 Not Modified`,
+/// without transferring the config again. See [load_from_url_if_modified()]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RemoteValidators {
+    /// The `ETag` the config was served with -- sent back as `If-None-Match`
+    pub etag: Option<String>,
+    /// The `Last-Modified` date the config was served with -- sent back as `If-Modified-Since`
+    pub last_modified: Option<String>,
+}
+
+/// The outcome of [load_from_url_if_modified()]
+#[derive(Debug, PartialEq)]
+pub enum RemoteFetch<RootConfigType> {
+    /// The config changed (or there were no validators to check against): here it is, along with its new validators
+    Modified {
+        config: RootConfigType,
+        validators: RemoteValidators,
+    },
+    /// The config server told the config didn't change since the given validators were obtained
+    NotModified,
+}
+
+/// Fetches the configuration served at `url` (with a GET request), parsing it in the given `format`.
+/// Network & HTTP failures (including non-success statuses) are reported as [crate::Error::RemoteConfig].
+/// See also [load_from_url_with_options()] & [load_from_url_with_cache()].
+pub async fn load_from_url<RootConfigType: OgreRootConfig>(
+    url: &str,
+    format: SerdeFormat,
+) -> Result<RootConfigType, crate::Error> {
+    load_from_url_with_options(url, &RemoteOptions { format: Some(format), ..RemoteOptions::default() }).await
+}
+
+/// Similar to [load_from_url()], but fetching as specified by `options` -- which allows the format to be inferred.
+/// Configs whose format can't be inferred are reported as [crate::Error::UnsupportedConfigFileFormat]
+pub async fn load_from_url_with_options<RootConfigType: OgreRootConfig>(
+    url: &str,
+    options: &RemoteOptions,
+) -> Result<RootConfigType, crate::Error> {
+    load_from_url_reporting_format(url, options).await
+        .map(|(config, _)| config)
+}
+
+/// The logic behind [load_from_url_with_options()], also telling the format the config was parsed in
+pub(crate) async fn load_from_url_reporting_format<RootConfigType: OgreRootConfig>(
+    url: &str,
+    options: &RemoteOptions,
+) -> Result<(RootConfigType, SerdeFormat), crate::Error> {
+    let fetched_config = fetch_config_unconditionally(url, options).await?;
+    let config = deserialize_remote_config(&fetched_config.txt_config, fetched_config.format, url)?;
+    Ok((config, fetched_config.format))
+}
+
+/// Similar to [load_from_url_with_options()], but issuing a conditional request with the `validators` of a previous fetch,
+/// so polling for changes is cheap: [RemoteFetch::NotModified] is returned if the config server says the config didn't change.
+/// Pass `RemoteValidators::default()` on the first fetch.
+pub async fn load_from_url_if_modified<RootConfigType: OgreRootConfig>(
+    url: &str,
+    options: &RemoteOptions,
+    validators: &RemoteValidators,
+) -> Result<RemoteFetch<RootConfigType>, crate::Error> {
+    match fetch_config(url, options, validators).await? {
+        Some(fetched_config) => Ok(RemoteFetch::Modified {
+            config: deserialize_remote_config(&fetched_config.txt_config, fetched_config.format, url)?,
+            validators: fetched_config.validators,
+        }),
+        None => Ok(RemoteFetch::NotModified),
+    }
+}
+
+/// Similar to [load_from_url()], but keeping a local copy of the fetched config at `cache_path`, used as a fallback
+/// when the config can't be fetched -- so the program may still start when the config server is unreachable.
+/// Only configs that parse are cached. With no cached copy, fetching failures are reported as in [load_from_url()].
+pub async fn load_from_url_with_cache<RootConfigType: OgreRootConfig>(
+    url: &str,
+    cache_path: impl AsRef<Path> + Debug,
+    format: SerdeFormat,
+) -> Result<RootConfigType, crate::Error> {
+    match fetch_config_unconditionally(url, &RemoteOptions { format: Some(format), ..RemoteOptions::default() }).await {
+        Ok(FetchedConfig { txt_config, .. }) => {
+            let config = deserialize_remote_config(&txt_config, format, url)?;
+            if let Err(err) = fs::write(&cache_path, &txt_config).await {
+                emit_warning(LoadWarningKind::FailedPersistence, "", format!("the config fetched from '{url}' couldn't be cached at {cache_path:?}: {err}"));
+            }
+            Ok(config)
+        },
+        Err(remote_err) => {
+            let txt_config = match fs::read_to_string(&cache_path).await {
+                Ok(txt_config) => txt_config,
+                Err(err) if err.kind() == ErrorKind::NotFound => return Err(remote_err),
+                Err(err) => return Err(crate::Error::LoadingConfig {
+                    message: format!("Error loading the cached config from {cache_path:?}, after failing to fetch it: {remote_err}"),
+                    cause: Box::new(err),
+                }),
+            };
+            emit_warning(LoadWarningKind::Fallback, "", format!("falling back to the cached config at {cache_path:?}, as it couldn't be fetched: {remote_err}"));
+            config_from_str(&txt_config, format)
+                .map_err(|err| crate::Error::LoadingConfig {
+                    message: format!("Error deserializing the cached config from {cache_path:?}"),
+                    cause: Box::new(err),
+                })
+                .and_then(|config| post_loaded(config, Path::new(url), format))
+        },
+    }
+}
+
+/// A config text fetched by [fetch_config()]
+struct FetchedConfig {
+    txt_config: String,
+    format: SerdeFormat,
+    validators: RemoteValidators,
+}
+
+/// GETs the config served at `url`, inferring its format if `options` don't specify it.
+/// Returns `None` if the config server answers the conditional request -- issued if there are any `validators` -- with
+/// `304 Not Modified`
+async fn fetch_config(url: &str, options: &RemoteOptions, validators: &RemoteValidators) -> Result<Option<FetchedConfig>, crate::Error> {
+    let remote_err = |message: &str, err: reqwest::Error| crate::Error::RemoteConfig {
+        url: url.to_string(),
+        message: message.to_string(),
+        cause: Box::new(err),
+    };
+    let client = reqwest::Client::builder()
+        .timeout(options.timeout)
+        .build()
+        .map_err(|err| remote_err("Error setting up the http client", err))?;
+    let mut request = client.get(url);
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    let response = request.send().await
+        .map_err(|err| remote_err("Error requesting the config", err))?;
+    let is_conditional = validators.etag.is_some() || validators.last_modified.is_some();
+    if is_conditional && response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None)
+    }
+    let response = response.error_for_status()
+        .map_err(|err| remote_err("The config server refused the request", err))?;
+
+    let header = |name: HeaderName| response.headers().get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let validators = RemoteValidators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+    let content_type = header(CONTENT_TYPE);
+    let format = options.format
+        .or_else(|| format_for_url(url))
+        .or_else(|| content_type.as_deref().and_then(format_for_content_type))
+        .ok_or_else(|| crate::Error::UnsupportedConfigFileFormat {
+            message: format!("`cli-config`: Couldn't infer the format of the config served at '{url}': its path has no '.ron', '.yaml' nor '.yml' extension \
+                              and its Content-Type ({}) is neither RON nor YAML. Specify it with `RemoteOptions::format`",
+                             content_type.as_deref().unwrap_or("absent")),
+        })?;
+    let txt_config = response.text().await
+        .map_err(|err| remote_err("Error receiving the config", err))?;
+    Ok(Some(FetchedConfig { txt_config, format, validators }))
+}
+
+/// Similar to [fetch_config()], but without validators -- so the config is always transferred
+async fn fetch_config_unconditionally(url: &str, options: &RemoteOptions) -> Result<FetchedConfig, crate::Error> {
+    fetch_config(url, options, &RemoteValidators::default()).await?
+        .ok_or_else(|| crate::Error::RemoteConfig {
+            url: url.to_string(),
+            message: "The config server answered an unconditional request with '304 Not Modified'".to_string(),
+            cause: "no config was received".into(),
+        })
+}
+
+/// The format implied by the extension of the `url`'s path, if any
+fn format_for_url(url: &str) -> Option<SerdeFormat> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let (_, extension) = url.path().rsplit_once('.')?;
+    match extension.to_ascii_lowercase().as_str() {
+        #[cfg(feature = "ron")]
+        "ron" => Some(SerdeFormat::Ron),
+        #[cfg(feature = "yaml")]
+        "yaml" | "yml" => Some(SerdeFormat::Yaml),
+        _ => None,
+    }
+}
+
+/// The format implied by the `content_type` header value, if any
+fn format_for_content_type(content_type: &str) -> Option<SerdeFormat> {
+    let mime_type = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    match mime_type.as_str() {
+        #[cfg(feature = "ron")]
+        "application/ron" | "application/x-ron" | "text/ron" | "text/x-ron" => Some(SerdeFormat::Ron),
+        #[cfg(feature = "yaml")]
+        "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => Some(SerdeFormat::Yaml),
+        _ => None,
+    }
+}
+
+fn deserialize_remote_config<RootConfigType: OgreRootConfig>(
+    txt_config: &str,
+    format: SerdeFormat,
+    url: &str,
+) -> Result<RootConfigType, crate::Error> {
+    config_from_str(txt_config, format)
+        .map_err(|err| crate::Error::LoadingConfig {
+            message: format!("Error deserializing config after fetching it from '{url}'"),
+            cause: Box::new(err),
+        })
+        .and_then(|config| post_loaded(config, Path::new(url), format))
+}
+
+#[cfg(all(test, feature = "ron"))]
+mod tests {
+    use super::*;
+    use crate::testkit::*;
+    use crate::test_commons::http_fixtures::*;
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[tokio::test]
+    async fn load_from_url_test() {
+        let base_url = mock_config_server(&[("/app.config.ron", "text/plain", "(log_sub_config: (sink: Some(stdout)))")]).await;
+
+        let config: AppRootConfig = load_from_url(&format!("{base_url}/app.config.ron"), SerdeFormat::Ron).await
+            .expect("Loading the served config failed");
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdOut), "Wrong config fetched");
+
+        let result = load_from_url::<AppRootConfig>(&format!("{base_url}/missing.ron"), SerdeFormat::Ron).await;
+        assert!(matches!(result, Err(crate::Error::RemoteConfig { .. })), "HTTP failures should be reported as remote config errors. Got {result:?}");
+
+        let result = load_from_url::<AppRootConfig>(&format!("{base_url}/app.config.ron"), SerdeFormat::Yaml).await;
+        assert!(result.is_err_and(|err| err.is_parsing_error()), "Contents in the wrong format should be reported as parsing errors");
+    }
+
+    #[tokio::test]
+    async fn load_from_url_with_cache_test() {
+        let base_url = mock_config_server(&[("/app.config.ron", "text/plain", "(log_sub_config: (sink: Some(stderror)))")]).await;
+        let cache_path = temp_config_path("ron");
+
+        let result = load_from_url_with_cache::<AppRootConfig>(&format!("{base_url}/missing.ron"), &cache_path, SerdeFormat::Ron).await;
+        assert!(matches!(result, Err(crate::Error::RemoteConfig { .. })), "With nothing cached, the fetching failure should be reported. Got {result:?}");
+
+        // successful fetches are cached
+        let config: AppRootConfig = load_from_url_with_cache(&format!("{base_url}/app.config.ron"), &cache_path, SerdeFormat::Ron).await
+            .expect("Loading the served config failed");
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdError), "Wrong config fetched");
+        assert_eq!(std::fs::read_to_string(&cache_path).unwrap(), "(log_sub_config: (sink: Some(stderror)))", "The fetched config should have been cached");
+
+        // failures fall back to the cache
+        let cached_config: AppRootConfig = load_from_url_with_cache(&format!("{base_url}/missing.ron"), &cache_path, SerdeFormat::Ron).await
+            .expect("The cached config should have been used");
+        assert_eq!(cached_config, config, "The cached config should have been loaded");
+        _ = std::fs::remove_file(&cache_path);
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[tokio::test]
+    async fn format_inference() {
+        let base_url = mock_config_server(&[
+            ("/app.config.yaml", "text/plain", "log_sub_config:\n  sink: stdout\n"),
+            ("/configs/app", "application/yaml; charset=utf-8", "log_sub_config:\n  sink: stderror\n"),
+            ("/configs/app-ron", "application/ron", "(log_sub_config: (sink: Some(stdout)))"),
+            ("/configs/page", "text/html", "<html></html>"),
+        ]).await;
+        let options = RemoteOptions::default();
+
+        let config: AppRootConfig = load_from_url_with_options(&format!("{base_url}/app.config.yaml"), &options).await
+            .expect("The format should have been inferred from the URL's extension");
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdOut), "Wrong config fetched");
+
+        let config: AppRootConfig = load_from_url_with_options(&format!("{base_url}/configs/app"), &options).await
+            .expect("The format should have been inferred from the Content-Type");
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdError), "Wrong config fetched");
+
+        let config: AppRootConfig = load_from_url_with_options(&format!("{base_url}/configs/app-ron"), &options).await
+            .expect("The format should have been inferred from the RON Content-Type");
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdOut), "Wrong config fetched");
+
+        let result = load_from_url_with_options::<AppRootConfig>(&format!("{base_url}/configs/page"), &options).await;
+        assert!(matches!(result, Err(crate::Error::UnsupportedConfigFileFormat { .. })), "Unknown Content-Types should be reported as unsupported formats. Got {result:?}");
+
+        let result = load_from_url_with_options::<AppRootConfig>(&format!("{base_url}/missing"), &options).await;
+        assert!(matches!(result, Err(crate::Error::RemoteConfig { .. })), "HTTP failures should be reported as remote config errors. Got {result:?}");
+
+        // a server that never answers
+        let silent_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_url = format!("http://{}/app.config.yaml", silent_listener.local_addr().unwrap());
+        let result = load_from_url_with_options::<AppRootConfig>(&silent_url, &RemoteOptions { timeout: Duration::from_millis(100), ..options }).await;
+        assert!(matches!(result, Err(crate::Error::RemoteConfig { .. })), "Timeouts should be reported as remote config errors. Got {result:?}");
+    }
+
+    #[tokio::test]
+    async fn conditional_fetches() {
+        let base_url = mock_config_server(&[("/app.config.ron", "application/ron", "(log_sub_config: (sink: Some(stdout)))")]).await;
+        let url = format!("{base_url}/app.config.ron");
+        let options = RemoteOptions::default();
+
+        let RemoteFetch::Modified { config, validators } = load_from_url_if_modified::<AppRootConfig>(&url, &options, &RemoteValidators::default()).await
+            .expect("Loading the served config failed") else { panic!("Without validators, the config should always be fetched") };
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdOut), "Wrong config fetched");
+        assert_eq!(validators, RemoteValidators { etag: Some(MOCK_ETAG.to_string()), last_modified: Some(MOCK_LAST_MODIFIED.to_string()) }, "Wrong validators");
+
+        let fetch = load_from_url_if_modified::<AppRootConfig>(&url, &options, &validators).await
+            .expect("Polling the served config failed");
+        assert_eq!(fetch, RemoteFetch::NotModified, "The unchanged config shouldn't have been transferred again");
+
+        let only_date = RemoteValidators { etag: None, ..validators };
+        let fetch = load_from_url_if_modified::<AppRootConfig>(&url, &options, &only_date).await
+            .expect("Polling the served config failed");
+        assert_eq!(fetch, RemoteFetch::NotModified, "`If-Modified-Since` should have been sent");
+    }
+}
@@ -0,0 +1,121 @@
+//! JSON Schema support for the configs -- behind the `schema` feature:
+//! schemas are generated from the config types & config files may be validated against them at load time
+
+use serde::de::DeserializeSeed;
+use crate::logic::interpolation_logic::interpolating_seed;
+use crate::EnvInterpolation;
+
+/// Generates the JSON Schema for `RootConfigType` -- which should `#[derive(schemars::JsonSchema)]`.
+/// Give it to [crate::LoadOptions::schema] for loaded config files to be validated against it.
+pub fn config_json_schema<RootConfigType: schemars::JsonSchema>() -> serde_json::Value {
+    schemars::schema_for!(RootConfigType).to_value()
+}
+
+/// Validates the generic representation of a config (see `generic_value_logic`) against the JSON `schema`, once its environment
+/// variable references are expanded as per `env_interpolation` -- so the values are validated just as the config will hold them.
+/// The first violation found is reported as [crate::Error::SchemaViolation], while undefined variables are left for the typed
+/// deserialization to report
+pub(crate) fn validate_interpolated_against_schema(
+    schema: &serde_json::Value,
+    generic_config: serde_json::Value,
+    env_interpolation: EnvInterpolation,
+) -> Result<(), crate::Error> {
+    match interpolating_seed::<serde_json::Value>(env_interpolation).deserialize(generic_config) {
+        Ok(interpolated_config) => validate_against_schema(schema, &interpolated_config),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Validates the generic representation of a config (see `generic_value_logic`) against the JSON `schema`,
+/// reporting the first violation found as [crate::Error::SchemaViolation]
+fn validate_against_schema(schema: &serde_json::Value, generic_config: &serde_json::Value) -> Result<(), crate::Error> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|err| crate::Error::LoadingConfig {
+            message: "The JSON Schema given in the load options is invalid".to_string(),
+            cause: err.to_string().into(),
+        })?;
+    let first_violation = validator.iter_errors(generic_config).next()
+        .map(|violation| crate::Error::SchemaViolation {
+            path: violation.instance_path().to_string(),
+            message: violation.to_string(),
+        });
+    first_violation.map_or(Ok(()), Err)
+}
+
+
+#[cfg(all(test, feature = "ron", feature = "yaml"))]
+mod tests {
+    use super::*;
+    use crate::{load_from_file_with_options, EnvInterpolation, LoadOptions, OgreRootConfig};
+    use crate::testkit::temp_config_dir;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+    #[serde(default)]
+    struct ServerConfig {
+        name: String,
+        port: u16,
+    }
+    impl OgreRootConfig for ServerConfig {}
+
+    #[tokio::test]
+    async fn schema_violations() {
+        let load_options = LoadOptions { schema: Some(config_json_schema::<ServerConfig>()), ..LoadOptions::default() };
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        for (file_name, valid_config, type_mismatched_config) in [
+            ("app.config.ron", r#"(name: "api", port: 8080)"#, r#"(name: "api", port: "eighty")"#),
+            ("app.config.yaml", "name: api\nport: 8080\n", "name: api\nport: eighty\n"),
+        ] {
+            let config_path = config_dir.join(file_name);
+            std::fs::write(&config_path, valid_config).unwrap();
+            let loaded_config: Option<ServerConfig> = load_from_file_with_options(&config_path, &load_options).await
+                .unwrap_or_else(|err| panic!("The valid {file_name} should have passed the schema validation: {err}"));
+            assert_eq!(loaded_config, Some(ServerConfig { name: "api".to_string(), port: 8080 }), "Wrong config loaded from {file_name}");
+
+            std::fs::write(&config_path, type_mismatched_config).unwrap();
+            let result = load_from_file_with_options::<ServerConfig>(&config_path, &load_options).await;
+            let violation = match &result {
+                Err(crate::Error::LoadingConfig { cause, .. }) => cause.downcast_ref::<crate::Error>(),
+                _ => None,
+            };
+            match violation {
+                Some(crate::Error::SchemaViolation { path, message }) => {
+                    assert_eq!(path, "/port", "Wrong path for the violation in {file_name}");
+                    assert!(message.contains("eighty"), "The offending value should be in the message for {file_name}: '{message}'");
+                },
+                _ => panic!("A schema violation should have been reported for {file_name}. Got {result:?}"),
+            }
+            assert!(result.is_err_and(|err| err.is_parsing_error()), "Schema violations are parsing errors");
+            _ = std::fs::remove_file(&config_path);
+        }
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[tokio::test]
+    async fn schema_validation_after_interpolation() {
+        std::env::set_var("OGRE_CONFIG_MELD_TEST_SCHEMA_NAME", "api");
+        let schema = serde_json::json!({"type": "object", "properties": {"name": {"type": "string", "pattern": "^[a-z]+$"}}});
+        let load_options = LoadOptions { schema: Some(schema), env_interpolation: EnvInterpolation::FailOnUndefined, ..LoadOptions::default() };
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        for (file_name, interpolated_config, violating_config) in [
+            ("app.config.ron", r#"(name: "${OGRE_CONFIG_MELD_TEST_SCHEMA_NAME}")"#, r#"(name: "${OGRE_CONFIG_MELD_TEST_SCHEMA_NAME}-2")"#),
+            ("app.config.yaml", "name: ${OGRE_CONFIG_MELD_TEST_SCHEMA_NAME}\n", "name: ${OGRE_CONFIG_MELD_TEST_SCHEMA_NAME}-2\n"),
+        ] {
+            let config_path = config_dir.join(file_name);
+            std::fs::write(&config_path, interpolated_config).unwrap();
+            let loaded_config: Option<ServerConfig> = load_from_file_with_options(&config_path, &load_options).await
+                .unwrap_or_else(|err| panic!("The interpolated value in {file_name} should have been validated, rather than the reference: {err}"));
+            assert_eq!(loaded_config.map(|config| config.name).as_deref(), Some("api"), "Wrong name loaded from {file_name}");
+
+            std::fs::write(&config_path, violating_config).unwrap();
+            let result = load_from_file_with_options::<ServerConfig>(&config_path, &load_options).await;
+            assert!(matches!(&result, Err(crate::Error::LoadingConfig { cause, .. })
+                             if matches!(cause.downcast_ref::<crate::Error>(), Some(crate::Error::SchemaViolation { message, .. }) if message.contains("api-2"))),
+                    "The interpolated value in {file_name} should have been reported as violating the schema. Got {result:?}");
+            _ = std::fs::remove_file(&config_path);
+        }
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+}
@@ -0,0 +1,304 @@
+//! Secrets kept out of the config files: values given as `{ secret_ref: "<path>" }` are replaced, at load time,
+//! by the contents of the referenced files -- as a pass over the generic representation of the config (see [generic_value_logic]).
+//! Rewrites of the config files -- & the effective configs written elsewhere -- get the references back, so secrets never reach them
+//!
+//! [generic_value_logic]: crate::logic::generic_value_logic
+
+use crate::logic::config_logic::join_relative;
+use crate::logic::compat_logic::forward_compatible_config;
+use crate::logic::generic_value_logic::generic_from_txt;
+use crate::logic::interpolation_logic::interpolating_seed;
+use crate::logic::layout_logic::located;
+#[cfg(feature = "schema")]
+use crate::logic::schema_logic::validate_interpolated_against_schema;
+#[cfg(feature = "async")]
+use crate::config_from_str_with_options;
+use crate::{LoadOptions, OgreRootConfig, SerdeFormat};
+#[cfg(feature = "async")]
+use encryptable_tokio_fs::fs;
+use serde::de::DeserializeSeed;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// The key of the single-entry objects referencing secret files
+pub(crate) const SECRET_REF_MARKER: &str = "secret_ref";
+
+/// Parses `txt_config`, in the given `format`, replacing the `{ secret_ref: "<path>" }` values by the contents of the referenced
+/// files -- with relative paths resolved against `base_dir` (the config file's directory) and a single trailing newline trimmed.
+/// Configs without secret references are parsed as usual (see [config_from_str_with_options()]).
+#[cfg(feature = "async")]
+pub(crate) async fn config_from_str_with_secret_refs<RootConfigType: OgreRootConfig>(
+    txt_config: &str,
+    format: SerdeFormat,
+    load_options: &LoadOptions,
+    base_dir: &Path,
+) -> Result<RootConfigType, crate::Error> {
+    let Some((generic_config, secret_refs)) = secret_refs_in(txt_config, format, base_dir) else {
+        return config_from_str_with_options(txt_config, format, load_options)
+    };
+    let mut secrets = Vec::with_capacity(secret_refs.len());
+    for (pointer, secret_path) in secret_refs {
+        let secret = fs::read_to_string(&secret_path).await
+            .map_err(|err| secret_reading_error(&secret_path, &pointer, err))?;
+        secrets.push((pointer, secret));
+    }
+    config_with_secrets(generic_config, secrets, format, load_options)
+}
+
+/// The generic representation of `txt_config`, along with the JSON pointers to its secret references & the paths of the referenced files
+/// -- relative to `base_dir`. `None` if there are no references or if the config can't be parsed -- so the regular parsing reports it precisely.
+/// Shared by the async & blocking loads, which differ just on reading the secrets
+pub(crate) fn secret_refs_in(txt_config: &str, format: SerdeFormat, base_dir: &Path) -> Option<(Value, Vec<(String, PathBuf)>)> {
+    let generic_config = generic_from_txt(txt_config, format).ok()?;
+    let secret_refs = secret_ref_pointers(&generic_config).into_iter()
+        .map(|(pointer, secret_path)| (pointer, join_relative(base_dir, Path::new(&secret_path))))
+        .collect::<Vec<_>>();
+    (!secret_refs.is_empty()).then_some((generic_config, secret_refs))
+}
+
+/// The error for failing to read the secret file at `secret_path`, referenced by the config at the JSON `pointer`
+pub(crate) fn secret_reading_error(secret_path: &Path, pointer: &str, err: std::io::Error) -> crate::Error {
+    crate::Error::LoadingConfig {
+        message: format!("Error reading the secret file {secret_path:?}, referenced by the config at '{pointer}'"),
+        cause: Box::new(err),
+    }
+}
+
+/// Deserializes the `generic_config` after replacing the secret references at the given JSON pointers by their `secrets`
+/// -- see [secret_refs_in()]
+pub(crate) fn config_with_secrets<RootConfigType: OgreRootConfig>(
+    mut generic_config: Value,
+    secrets: Vec<(String, String)>,
+    format: SerdeFormat,
+    load_options: &LoadOptions,
+) -> Result<RootConfigType, crate::Error> {
+    for (pointer, mut secret) in secrets {
+        if secret.ends_with('\n') {
+            secret.pop();
+            if secret.ends_with('\r') {
+                secret.pop();
+            }
+        }
+        if let Some(value) = generic_config.pointer_mut(&pointer) {
+            *value = Value::String(secret);
+        }
+    }
+    #[cfg(feature = "schema")]
+    if let Some(schema) = &load_options.schema {
+        validate_interpolated_against_schema(schema, generic_config.clone(), load_options.env_interpolation)?;
+    }
+    if let Some(config) = load_options.forward_compatible
+        .then(|| forward_compatible_config(generic_config.clone(), load_options.env_interpolation).ok())
+        .flatten() {
+        return Ok(config)
+    }
+    interpolating_seed(load_options.env_interpolation)
+        .deserialize(generic_config)
+        .map_err(|err| match format {
+            #[cfg(feature = "ron")]
+            SerdeFormat::Ron => crate::Error::Ron {
+                message: "RON deserialization error, after resolving the secret references".to_string(),
+                cause: ron::Error::Message(err.to_string()),
+            },
+            #[cfg(feature = "yaml")]
+            SerdeFormat::Yaml => crate::Error::Yaml {
+                message: "YAML deserialization error, after resolving the secret references".to_string(),
+                cause: serde::de::Error::custom(err),
+            },
+        })
+}
+
+/// The config file a config was loaded from -- whose secret references are to be kept by the files that config is written to
+/// (see [keeping_secret_refs()]): its `txt`, as loaded, in its `format`, from its `path`
+pub(crate) struct SecretRefsSource<'a> {
+    pub(crate) txt: &'a str,
+    pub(crate) format: SerdeFormat,
+    pub(crate) path: &'a Path,
+}
+
+/// Puts the secret references of the `source` file back into `txt_config` -- the config loaded from it, serialized in `format` for
+/// `config_file_path` --, so the secrets resolved at load time are never written in plaintext. References at (or inside) the `replaced`
+/// pointer, if any, are dropped: their values were explicitly replaced. Every write of a loaded config -- to its own file or elsewhere --
+/// goes through here, so none skips the references. See [secret_refs_to_keep()] & [with_secret_refs()]
+pub(crate) fn keeping_secret_refs(
+    txt_config: String,
+    format: SerdeFormat,
+    config_file_path: &Path,
+    source: Option<SecretRefsSource>,
+    replaced: Option<&str>,
+) -> Result<String, crate::Error> {
+    let Some(source) = source else {
+        return Ok(txt_config)
+    };
+    let secret_refs = secret_refs_to_keep(source.txt, source.format, source.path.parent().unwrap_or(Path::new("")),
+                                          config_file_path.parent().unwrap_or(Path::new(""))).into_iter()
+        .filter(|(pointer, _)| replaced.is_none_or(|replaced| pointer != replaced && !pointer.starts_with(&format!("{replaced}/"))))
+        .collect::<Vec<_>>();
+    with_secret_refs(txt_config, format, &secret_refs, config_file_path)
+}
+
+/// The secret references of `original_txt` -- the text, in `format`, of the config file in `from_dir` -- to be kept by the rewrites
+/// of that config: their JSON pointers along with the referenced paths, as written -- or, for rewrites to another directory (`to_dir`),
+/// along with the paths resolved against `from_dir`, so they keep referencing the same files. See [with_secret_refs()]
+fn secret_refs_to_keep(original_txt: &str, format: SerdeFormat, from_dir: &Path, to_dir: &Path) -> Vec<(String, String)> {
+    if !original_txt.contains(SECRET_REF_MARKER) {
+        return Vec::new()
+    }
+    let Ok(generic_config) = generic_from_txt(original_txt, format) else {
+        return Vec::new()
+    };
+    secret_ref_pointers(&generic_config).into_iter()
+        .map(|(pointer, secret_path)| match Path::new(&secret_path).is_absolute() || from_dir == to_dir {
+            true => (pointer, secret_path),
+            false => (pointer, join_relative(from_dir, Path::new(&secret_path)).to_string_lossy().to_string()),
+        })
+        .collect()
+}
+
+/// Puts the `secret_refs` (see [secret_refs_to_keep()]) back into `txt_config` -- a config text in `format`, to be written to
+/// `config_file_path` -- replacing the secrets resolved at load time by their `{ secret_ref: "<path>" }` objects, so they are never
+/// written in plaintext. Values missing from `txt_config` are left missing, while the ones that can't be located
+/// -- like inside sequences -- fail the write with [crate::Error::SavingConfig]
+fn with_secret_refs(txt_config: String, format: SerdeFormat, secret_refs: &[(String, String)], config_file_path: &Path) -> Result<String, crate::Error> {
+    if secret_refs.is_empty() {
+        return Ok(txt_config)
+    }
+    let unlocatable = |pointer: &str| crate::Error::SavingConfig {
+        message: format!("Error writing the config file {config_file_path:?}: its value at '{pointer}' comes from a secret file,                           but it can't be written back as a `{SECRET_REF_MARKER}` -- refusing to write the secret in plaintext"),
+        cause: "secret references are only kept for the fields of objects".into(),
+    };
+    let Some((generic_config, spans)) = located(&txt_config, format) else {
+        return Err(unlocatable(&secret_refs[0].0))
+    };
+    let mut edits = Vec::with_capacity(secret_refs.len());
+    for (pointer, secret_path) in secret_refs {
+        if generic_config.pointer(pointer).is_none() {
+            continue
+        }
+        let range = spans.values.get(pointer).ok_or_else(|| unlocatable(pointer))?;
+        let value_txt = &txt_config[range.clone()];
+        let leading_space = match &value_txt[..value_txt.len() - value_txt.trim_start().len()] {
+            "" => " ",
+            leading_space => leading_space,
+        };
+        let trailing_space = &value_txt[value_txt.trim_end().len()..];
+        let quoted_path = serde_json::to_string(secret_path).expect("Strings are always serializable");
+        let secret_ref = match format {
+            #[cfg(feature = "ron")]
+            SerdeFormat::Ron => format!("({SECRET_REF_MARKER}: {quoted_path})"),
+            #[cfg(feature = "yaml")]
+            SerdeFormat::Yaml => format!("{{ {SECRET_REF_MARKER}: {quoted_path} }}"),
+        };
+        edits.push((range.clone(), format!("{leading_space}{secret_ref}{trailing_space}")));
+    }
+    // applied from the end, so the ranges stay valid
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut edited_txt = txt_config;
+    for (range, replacement) in edits {
+        edited_txt.replace_range(range, &replacement);
+    }
+    Ok(edited_txt)
+}
+
+/// Returns the JSON pointers to the secret references in `generic_config`, along with the referenced paths
+fn secret_ref_pointers(generic_config: &Value) -> Vec<(String, String)> {
+    fn collect(value: &Value, pointer: &str, secret_refs: &mut Vec<(String, String)>) {
+        match value {
+            Value::Object(fields) => match fields.get(SECRET_REF_MARKER) {
+                Some(Value::String(secret_path)) if fields.len() == 1 => secret_refs.push((pointer.to_string(), secret_path.clone())),
+                _ => fields.iter()
+                    .for_each(|(key, value)| collect(value, &format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1")), secret_refs)),
+            },
+            Value::Array(elements) => elements.iter()
+                .enumerate()
+                .for_each(|(i, element)| collect(element, &format!("{pointer}/{i}"), secret_refs)),
+            _ => (),
+        }
+    }
+    let mut secret_refs = Vec::new();
+    collect(generic_config, "", &mut secret_refs);
+    secret_refs
+}
+
+#[cfg(all(test, feature = "async", feature = "ron", feature = "yaml"))]
+mod tests {
+    use super::*;
+    use crate::load_existing;
+    use crate::testkit::{temp_config_dir, Dummy};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct DbConfig {
+        user: String,
+        password: String,
+        sink: Option<Dummy>,
+    }
+    impl OgreRootConfig for DbConfig {}
+
+    #[tokio::test]
+    async fn secret_files() {
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(config_dir.join("secrets")).unwrap();
+        std::fs::write(config_dir.join("secrets/db_password"), "s3cr3t\n").unwrap();
+        let expected_config = DbConfig { user: "admin".to_string(), password: "s3cr3t".to_string(), sink: Some(Dummy::StdOut) };
+
+        std::fs::write(config_dir.join("db.yaml"), "user: admin\npassword:\n  secret_ref: secrets/db_password\nsink: stdout\n").unwrap();
+        let config: DbConfig = load_existing(config_dir.join("db.yaml")).await
+            .expect("Loading the YAML config referencing a secret file failed");
+        assert_eq!(config, expected_config, "The YAML secret wasn't loaded from its file");
+
+        std::fs::write(config_dir.join("db.ron"), r#"(user: "admin", password: (secret_ref: "secrets/db_password"), sink: Some(stdout))"#).unwrap();
+        let config: DbConfig = load_existing(config_dir.join("db.ron")).await
+            .expect("Loading the RON config referencing a secret file failed");
+        assert_eq!(config, expected_config, "The RON secret wasn't loaded from its file");
+
+        std::fs::write(config_dir.join("missing.yaml"), "user: admin\npassword: { secret_ref: secrets/missing }\n").unwrap();
+        let result = load_existing::<DbConfig>(config_dir.join("missing.yaml")).await;
+        assert!(result.as_ref().is_err_and(|err| err.to_string().contains("secrets/missing")), "Missing secret files should be reported. Got {result:?}");
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[tokio::test]
+    async fn secret_refs_kept_on_saves() {
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(config_dir.join("secrets")).unwrap();
+        std::fs::write(config_dir.join("secrets/db_password"), "s3cr3t\n").unwrap();
+        for (file_name, config_txt) in [
+            ("db.yaml", "user: admin\npassword:\n  secret_ref: secrets/db_password\nsink: stdout\n"),
+            ("db.ron", r#"(user: "admin", password: (secret_ref: "secrets/db_password"), sink: Some(stdout))"#),
+        ] {
+            let config_path = config_dir.join(file_name);
+            std::fs::write(&config_path, config_txt).unwrap();
+            let mut loaded_config = crate::load_or_create_default_traced::<DbConfig>(&config_path, "").await.unwrap();
+            assert_eq!(loaded_config.config.password, "s3cr3t", "The secret should have been loaded");
+            loaded_config.config.user = "root".to_string();
+            loaded_config.save_to_file("").await.unwrap();
+            let saved_txt = std::fs::read_to_string(&config_path).unwrap();
+            assert!(!saved_txt.contains("s3cr3t") && saved_txt.contains("secrets/db_password"), "The secret reference should have been kept in '{file_name}': '{saved_txt}'");
+            let config: DbConfig = load_existing(&config_path).await.unwrap();
+            assert_eq!((config.user.as_str(), config.password.as_str()), ("root", "s3cr3t"), "The saved '{file_name}' should load back the same");
+        }
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+
+    #[test]
+    fn secret_refs_written_back() {
+        let config_dir = Path::new("/etc/app");
+        let other_dir = Path::new("/tmp/elsewhere");
+        let original_txt = "user: admin\npassword: { secret_ref: secrets/db_password }\n";
+        let secret_refs = secret_refs_to_keep(original_txt, SerdeFormat::Yaml, config_dir, config_dir);
+        assert_eq!(secret_refs, vec![("/password".to_string(), "secrets/db_password".to_string())], "Wrong secret references");
+        let rebased_secret_refs = secret_refs_to_keep(original_txt, SerdeFormat::Yaml, config_dir, other_dir);
+        assert_eq!(rebased_secret_refs[0].1, "/etc/app/secrets/db_password", "Secret references written elsewhere should still point to the same files");
+
+        let txt_config = with_secret_refs("user: root\npassword: s3cr3t\n".to_string(), SerdeFormat::Yaml, &secret_refs, Path::new("db.yaml")).unwrap();
+        assert_eq!(txt_config, "user: root\npassword: { secret_ref: \"secrets/db_password\" }\n", "Wrong YAML secret reference");
+        let txt_config = with_secret_refs("(\n    user: \"root\",\n    password: \"s3cr3t\",\n)".to_string(), SerdeFormat::Ron, &secret_refs, Path::new("db.ron")).unwrap();
+        assert!(txt_config.contains("password: (secret_ref: \"secrets/db_password\"),") && !txt_config.contains("s3cr3t"), "Wrong RON secret reference: '{txt_config}'");
+
+        let listed_secret_refs = vec![("/passwords/0".to_string(), "secrets/db_password".to_string())];
+        let result = with_secret_refs("passwords:\n- s3cr3t\n".to_string(), SerdeFormat::Yaml, &listed_secret_refs, Path::new("db.yaml"));
+        assert!(matches!(&result, Err(crate::Error::SavingConfig { message, .. }) if message.contains("/passwords/0")),
+                "Secrets that can't be written back as references should fail the write, rather than being written in plaintext. Got {result:?}");
+    }
+}
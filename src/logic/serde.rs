@@ -1,10 +1,30 @@
 //! SERializer & DEserializer operations for the configs,
-//! able to load & write RON and YAML files
+//! able to load & write RON and YAML files -- each behind its own cargo feature, `ron` & `yaml`
 
-use crate::{Error, OgreRootConfig};
+use crate::logic::compat_logic::forward_compatible_config;
+#[cfg(feature = "ron")]
+use crate::logic::generic_value_logic::generic_from_ron;
+#[cfg(feature = "yaml")]
+use crate::logic::generic_value_logic::generic_from_yaml;
+#[cfg(feature = "yaml")]
+use crate::logic::interpolation_logic::interpolating_seed;
+#[cfg(feature = "ron")]
+use crate::logic::interpolation_logic::{interpolating_seed_tracking, ValuePath};
+#[cfg(feature = "schema")]
+use crate::logic::schema_logic::validate_interpolated_against_schema;
+use crate::logic::sparse_logic::{Sparse, SparseRules};
+#[cfg(feature = "yaml")]
+use crate::logic::yaml_keys_logic::{yaml_documents, DuplicateKey};
+use crate::{CommentStyle, Error, LoadOptions, OgreRootConfig, SaveOptions};
+#[cfg(feature = "yaml")]
+use crate::{DuplicateKeys, YamlMultiDocuments};
 use once_cell::sync::Lazy;
 use regex::Regex;
+#[cfg(feature = "ron")]
 use ron::ser::{to_string_pretty, PrettyConfig};
+#[cfg(feature = "yaml")]
+use serde::de::DeserializeSeed;
+use std::str::FromStr;
 
 pub trait ConfigSerde {
     fn serialize_config(
@@ -19,16 +39,100 @@ pub trait ConfigSerde {
     ) -> Result<RootConfigType, crate::Error>;
 }
 
-/// Supported config file formats
+/// Supported config file formats -- each one available through its cargo feature: `ron` & `yaml`, both enabled by default
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SerdeFormat {
+    #[cfg(feature = "ron")]
     Ron,
+    #[cfg(feature = "yaml")]
     Yaml,
 }
 
+impl SerdeFormat {
+    /// The format implied by a config file's `file_extension` -- including the dot, like in `.ron`
+    pub fn for_file_extension(file_extension: &str) -> Result<Self, crate::Error> {
+        match file_extension {
+            #[cfg(feature = "ron")]
+            ".ron" => Ok(SerdeFormat::Ron),
+            #[cfg(feature = "yaml")]
+            ".yaml" | ".yml" => Ok(SerdeFormat::Yaml),
+            #[cfg(not(feature = "ron"))]
+            ".ron" => Err(disabled_format("RON", "ron")),
+            #[cfg(not(feature = "yaml"))]
+            ".yaml" | ".yml" => Err(disabled_format("YAML", "yaml")),
+            _ => Err(crate::Error::UnsupportedConfigFileFormat { message: format!("`cli-config`: Unsupported config file extension: '{file_extension}'. Supported extensions are {}", listed_extensions()) })
+        }
+    }
+
+    /// The format's own way of commenting out docs: `/* */` blocks for RON & `# ` prefixed lines for YAML
+    pub(crate) fn comment_style(self) -> CommentStyle {
+        match self {
+            #[cfg(feature = "ron")]
+            SerdeFormat::Ron => CommentStyle::Block { open: "/*".to_string(), close: "*/".to_string() },
+            #[cfg(feature = "yaml")]
+            SerdeFormat::Yaml => CommentStyle::LinePrefix("# ".to_string()),
+        }
+    }
+}
+
+/// Parses format names -- `ron`, `yaml` or `yml`, in any case
+impl FromStr for SerdeFormat {
+    type Err = crate::Error;
+
+    fn from_str(format_name: &str) -> Result<Self, Self::Err> {
+        match format_name.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "ron")]
+            "ron" => Ok(SerdeFormat::Ron),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Ok(SerdeFormat::Yaml),
+            #[cfg(not(feature = "ron"))]
+            "ron" => Err(disabled_format("RON", "ron")),
+            #[cfg(not(feature = "yaml"))]
+            "yaml" | "yml" => Err(disabled_format("YAML", "yaml")),
+            _ => Err(crate::Error::UnsupportedConfigFileFormat { message: format!("`cli-config`: Unsupported config format: '{format_name}'. Supported formats are 'ron' and 'yaml'") })
+        }
+    }
+}
+
+/// The config file extensions -- including the dot -- of the formats compiled into this build, as accepted by
+/// [SerdeFormat::for_file_extension()]: `.ron`, `.yaml` & `.yml` with the default features
+pub fn supported_extensions() -> &'static [&'static str] {
+    &[
+        #[cfg(feature = "ron")]
+        ".ron",
+        #[cfg(feature = "yaml")]
+        ".yaml",
+        #[cfg(feature = "yaml")]
+        ".yml",
+    ]
+}
+
+/// [supported_extensions()] for error messages, like `'.ron', '.yaml' and '.yml'`
+fn listed_extensions() -> String {
+    let quoted = supported_extensions().iter()
+        .map(|extension| format!("'{extension}'"))
+        .collect::<Vec<_>>();
+    match quoted.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {last}", rest.join(", ")),
+        None => String::new(),
+    }
+}
+
+/// The error for a known format -- named `format_name` -- whose cargo `feature` was disabled in this build
+#[cfg(not(all(feature = "ron", feature = "yaml")))]
+fn disabled_format(format_name: &str, feature: &str) -> crate::Error {
+    crate::Error::UnsupportedConfigFileFormat {
+        message: format!("`cli-config`: {format_name} config files aren't supported by this build -- recompile with the `{feature}` feature of `ogre-config-meld`"),
+    }
+}
+
 /// Automatically selects between [RonSerde] and [YamlSerde]
 pub struct AutomaticSerde {
     format: SerdeFormat,
+    #[cfg(feature = "ron")]
     ron_serde: RonSerde,
+    #[cfg(feature = "yaml")]
     yaml_serde: YamlSerde,
 }
 
@@ -36,20 +140,31 @@ impl AutomaticSerde {
     pub fn new(format: SerdeFormat) -> Self {
         Self {
             format,
-            ron_serde: RonSerde {},
-            yaml_serde: YamlSerde {},
+            #[cfg(feature = "ron")]
+            ron_serde: RonSerde::default(),
+            #[cfg(feature = "yaml")]
+            yaml_serde: YamlSerde::default(),
         }
     }
 
-    pub fn for_file_extension(file_extension: &str) -> Result<Self, crate::Error> {
-        let format = match file_extension {
-            ".ron" => Ok(SerdeFormat::Ron),
-            ".yaml" => Ok(SerdeFormat::Yaml),
-            ".yml" => Ok(SerdeFormat::Yaml),
-            _ => Err(crate::Error::UnsupportedConfigFileFormat { message: format!("`cli-config`: Unsupported config file extension: '{file_extension}'. Supported extensions are '.ron', '.yaml' and '.yml'") })
-        }?;
-        Ok(Self::new(format))
+    /// Applies the given `load_options` to the underlying serdes
+    pub fn with_load_options(mut self, load_options: &LoadOptions) -> Self {
+        #[cfg(feature = "ron")]
+        { self.ron_serde.load_options = load_options.clone(); }
+        #[cfg(feature = "yaml")]
+        { self.yaml_serde.load_options = load_options.clone(); }
+        self
+    }
+
+    /// Applies the given `save_options` to the underlying serdes
+    pub fn with_save_options(mut self, save_options: &SaveOptions) -> Self {
+        #[cfg(feature = "ron")]
+        { self.ron_serde.save_options = save_options.clone(); }
+        #[cfg(feature = "yaml")]
+        { self.yaml_serde.save_options = save_options.clone(); }
+        self
     }
+
 }
 
 impl ConfigSerde for AutomaticSerde {
@@ -59,7 +174,9 @@ impl ConfigSerde for AutomaticSerde {
         tail_comment: &str,
     ) -> Result<String, Error> {
         match self.format {
+            #[cfg(feature = "ron")]
             SerdeFormat::Ron => self.ron_serde.serialize_config(config, tail_comment),
+            #[cfg(feature = "yaml")]
             SerdeFormat::Yaml => self.yaml_serde.serialize_config(config, tail_comment),
         }
     }
@@ -69,32 +186,43 @@ impl ConfigSerde for AutomaticSerde {
         txt_config: &str,
     ) -> Result<RootConfigType, Error> {
         match self.format {
+            #[cfg(feature = "ron")]
             SerdeFormat::Ron => self.ron_serde.deserialize_config(txt_config),
+            #[cfg(feature = "yaml")]
             SerdeFormat::Yaml => self.yaml_serde.deserialize_config(txt_config),
         }
     }
 }
 
-struct RonSerde {}
+#[cfg(feature = "ron")]
+#[derive(Default)]
+struct RonSerde {
+    load_options: LoadOptions,
+    save_options: SaveOptions,
+}
+#[cfg(feature = "ron")]
 impl ConfigSerde for RonSerde {
     fn serialize_config(
         &self,
         config: &impl OgreRootConfig,
         tail_comment: &str,
     ) -> Result<String, crate::Error> {
-        to_string_pretty(&config, PrettyConfig::default())
+        let sparse_rules = SparseRules { sparse: self.save_options.sparse, omit_none: self.save_options.omit_none };
+        let defaults = (sparse_rules.sparse || sparse_rules.omit_none).then(|| defaults_for_sparse(config)).transpose()?;
+        let txt_config = if sparse_rules.sparse || sparse_rules.omit_none {
+            to_string_pretty(&Sparse::new(config, defaults.as_ref(), sparse_rules), PrettyConfig::default())
+        } else {
+            to_string_pretty(&config, PrettyConfig::default())
+        };
+        txt_config
             .map_err(|err| crate::Error::Ron {
                 message: format!("RON serialization Error for config '{config:?}'"),
                 cause: err,
             })
             .map(|mut txt_config| {
                 if !tail_comment.is_empty() {
-                    txt_config.push_str("\n\n/*\n");
-                    txt_config.push_str(
-                        "///////////////////////////// DOCS //////////////////////////////\n",
-                    );
-                    txt_config.push_str(tail_comment);
-                    txt_config.push_str("\n*/\n");
+                    let block = SerdeFormat::Ron.comment_style();
+                    txt_config.push_str(&render_tail_comment(tail_comment, self.save_options.comment_style.as_ref().unwrap_or(&block)));
                 }
                 txt_config
             })
@@ -104,37 +232,67 @@ impl ConfigSerde for RonSerde {
         &self,
         txt_config: &str,
     ) -> Result<RootConfigType, crate::Error> {
+        // unparseable texts are left for the typed deserialization to report
+        #[cfg(feature = "schema")]
+        if let (Some(schema), Ok(generic_config)) = (&self.load_options.schema, generic_from_ron(txt_config)) {
+            validate_interpolated_against_schema(schema, generic_config, self.load_options.env_interpolation)?;
+        }
+        // structural drift is tolerated, while other errors are left for the regular deserialization to report precisely
+        if let Some(config) = self.load_options.forward_compatible
+            .then(|| generic_from_ron(txt_config).ok())
+            .flatten()
+            .and_then(|generic_config| forward_compatible_config(generic_config, self.load_options.env_interpolation).ok()) {
+            return Ok(config)
+        }
+        let value_path = ValuePath::default();
         ron::Options::default()
-            .from_str(txt_config)
-            .map_err(|err| crate::Error::Ron {
-                message: format!("RON deserialization error for config text '{txt_config}'"),
-                cause: err.into(),
+            .from_str_seed(txt_config, interpolating_seed_tracking(self.load_options.env_interpolation, value_path.clone()))
+            .map_err(|err| match err.code {
+                ron::Error::MissingStructField { field, .. } => crate::Error::MissingRequiredField {
+                    field: field.to_string(),
+                    message: format!("RON deserialization error: {err} -- consider adding `#[serde(default)]` to the config struct. Config text: '{txt_config}'"),
+                },
+                // unlike YAML's, RON errors don't tell the offending field
+                _ => crate::Error::Ron {
+                    message: match value_path.refused() {
+                        Some(field) => format!("RON deserialization error for the field `{field}` of config text '{txt_config}'"),
+                        None => format!("RON deserialization error for config text '{txt_config}'"),
+                    },
+                    cause: err.into(),
+                },
             })
     }
 }
 
-struct YamlSerde {}
+#[cfg(feature = "yaml")]
+#[derive(Default)]
+struct YamlSerde {
+    load_options: LoadOptions,
+    save_options: SaveOptions,
+}
+#[cfg(feature = "yaml")]
 impl ConfigSerde for YamlSerde {
     fn serialize_config(
         &self,
         config: &impl OgreRootConfig,
         tail_comment: &str,
     ) -> Result<String, crate::Error> {
-        static REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("(?m)^").expect("Bad Regex"));
-
-        serde_yaml::to_string(config)
+        let sparse_rules = SparseRules { sparse: self.save_options.sparse, omit_none: self.save_options.omit_none };
+        let defaults = (sparse_rules.sparse || sparse_rules.omit_none).then(|| defaults_for_sparse(config)).transpose()?;
+        let txt_config = if sparse_rules.sparse || sparse_rules.omit_none {
+            serde_yaml::to_string(&Sparse::new(config, defaults.as_ref(), sparse_rules))
+        } else {
+            serde_yaml::to_string(config)
+        };
+        txt_config
             .map_err(|err| crate::Error::Yaml {
                 message: format!("YAML serialization error for config '{config:?}'"),
                 cause: err,
             })
             .map(|mut txt_config| {
                 if !tail_comment.is_empty() {
-                    let tail_comment = REGEX.replace_all(tail_comment, "# ");
-                    txt_config.push('\n');
-                    txt_config.push_str(
-                        "############################# DOCS ##############################\n",
-                    );
-                    txt_config.push_str(&tail_comment);
+                    let line_prefix = SerdeFormat::Yaml.comment_style();
+                    txt_config.push_str(&render_tail_comment(tail_comment, self.save_options.comment_style.as_ref().unwrap_or(&line_prefix)));
                 }
                 txt_config
             })
@@ -144,23 +302,294 @@ impl ConfigSerde for YamlSerde {
         &self,
         txt_config: &str,
     ) -> Result<RootConfigType, crate::Error> {
-        serde_yaml::from_str(txt_config).map_err(|err| crate::Error::Yaml {
+        let yaml_err = |err: serde_yaml::Error| crate::Error::Yaml {
             message: format!("YAML deserialization error for config text '{txt_config}'"),
             cause: err,
+        };
+        let deserialization_err = |err: serde_yaml::Error, document: &serde_yaml::Value| match self.missing_required_field::<RootConfigType>(document) {
+            Some(field_path) => crate::Error::MissingRequiredField {
+                field: field_path.rsplit('.').next().unwrap_or_default().to_string(),
+                message: format!("YAML deserialization error: the field `{field_path}` is missing & has no default -- consider adding `#[serde(default)]` to the config struct. \
+                                  Config text: '{txt_config}'"),
+            },
+            None => yaml_err(err),
+        };
+        let refuse_duplicate_keys = self.load_options.duplicate_keys == DuplicateKeys::Refuse;
+        let documents = yaml_documents(txt_config, refuse_duplicate_keys)
+            .map_err(|err| match (tab_indented_line(txt_config), err) {
+                // tabs in the indentation are a common cause of cryptic parsing errors in hand-edited files
+                (Some(line), err) => crate::Error::YamlIndentation {
+                    line,
+                    message: format!("YAML parsing error: line {line} is indented with tabs -- only spaces are allowed for indentation in YAML. The parser reported: {}",
+                                     err.map_or_else(|err| err.to_string(), |duplicate_key| format!("duplicate key `{}`", duplicate_key.key))),
+                },
+                (None, Ok(DuplicateKey { key, line })) => crate::Error::DuplicateKey {
+                    message: format!("YAML parsing error: the key `{key}` appears more than once at line {line} -- only one of its values would be taken"),
+                    key,
+                    line,
+                },
+                (None, Err(err)) => yaml_err(err),
+            })?;
+        let seed = interpolating_seed(self.load_options.env_interpolation);
+        if documents.len() <= 1 {
+            #[cfg(feature = "schema")]
+            if let Some(schema) = &self.load_options.schema {
+                let document = documents.first().cloned().unwrap_or_default();
+                validate_interpolated_against_schema(schema, generic_from_yaml(document), self.load_options.env_interpolation)?;
+            }
+            if let Some(config) = self.forward_compatible_config(documents.first()) {
+                return Ok(config)
+            }
+            let document = documents.into_iter().next().unwrap_or_default();
+            // the text is deserialized again for the errors to be located -- unless it may have repeated keys, which the config types refuse
+            return match refuse_duplicate_keys {
+                true => seed.deserialize(serde_yaml::Deserializer::from_str(txt_config)),
+                false => seed.deserialize(document.clone()),
+            }.map_err(|err| deserialization_err(err, &document));
+        }
+        let document = match self.load_options.yaml_multi_documents {
+            YamlMultiDocuments::Forbid => return Err(crate::Error::MultipleYamlDocuments {
+                message: format!("The YAML config has {} `---` separated documents, but only one is allowed. Config text: '{txt_config}'", documents.len()),
+            }),
+            YamlMultiDocuments::FirstOnly => documents.into_iter().next().expect("at least two documents are present"),
+            YamlMultiDocuments::MergeAll => documents.into_iter().reduce(merge_yaml_values).expect("at least two documents are present"),
+        };
+        #[cfg(feature = "schema")]
+        if let Some(schema) = &self.load_options.schema {
+            validate_interpolated_against_schema(schema, generic_from_yaml(document.clone()), self.load_options.env_interpolation)?;
+        }
+        if let Some(config) = self.forward_compatible_config(Some(&document)) {
+            return Ok(config)
+        }
+        seed.deserialize(document.clone()).map_err(|err| deserialization_err(err, &document))
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl YamlSerde {
+    /// The config from the YAML `document` (if any), as per [LoadOptions::forward_compatible] -- `None` if not requested or
+    /// if it can't be deserialized even so, leaving the error for the regular deserialization to report precisely
+    fn forward_compatible_config<RootConfigType: OgreRootConfig>(&self, document: Option<&serde_yaml::Value>) -> Option<RootConfigType> {
+        document
+            .filter(|_| self.load_options.forward_compatible)
+            .and_then(|document| forward_compatible_config(generic_from_yaml(document.clone()), self.load_options.env_interpolation).ok())
+    }
+
+    /// The dotted path of the field missing from the YAML `document` that failed its deserialization into `RootConfigType` -- `None` if the
+    /// failure isn't due to a missing field. Found structurally: the fields of the model (`RootConfigType::default()`) missing from the
+    /// `document` are filled in with their defaults, telling the one whose absence alone fails the deserialization
+    fn missing_required_field<RootConfigType: OgreRootConfig>(&self, document: &serde_yaml::Value) -> Option<String> {
+        let model = serde_yaml::to_value(RootConfigType::default()).ok()?;
+        let mut missing_fields = vec![];
+        missing_field_paths(&model, document, &mut vec![], &mut missing_fields);
+        let deserializes_filling_in = |filled_fields: &mut dyn Iterator<Item=&Vec<&serde_yaml::Value>>| {
+            let mut filled_document = document.clone();
+            for field_path in filled_fields {
+                fill_in_from(&model, &mut filled_document, field_path);
+            }
+            interpolating_seed::<RootConfigType>(self.load_options.env_interpolation).deserialize(filled_document).is_ok()
+        };
+        if missing_fields.is_empty() || !deserializes_filling_in(&mut missing_fields.iter()) {
+            return None
+        }
+        missing_fields.iter().enumerate()
+            .find(|&(i, _)| !deserializes_filling_in(&mut missing_fields.iter().enumerate().filter(|&(j, _)| j != i).map(|(_, field_path)| field_path)))
+            .map(|(_, field_path)| field_path.iter()
+                .map(|key| key.as_str().map_or_else(|| format!("{key:?}"), str::to_string))
+                .collect::<Vec<_>>()
+                .join("."))
+    }
+}
+
+/// Gathers into `missing_fields` the paths (as keys) of the fields of the `model` missing from the `document` -- only the outermost of them.
+/// Fields are looked for in the mappings present in both
+#[cfg(feature = "yaml")]
+fn missing_field_paths<'a>(model: &'a serde_yaml::Value, document: &serde_yaml::Value, path: &mut Vec<&'a serde_yaml::Value>, missing_fields: &mut Vec<Vec<&'a serde_yaml::Value>>) {
+    let (serde_yaml::Value::Mapping(model_fields), serde_yaml::Value::Mapping(document_fields)) = (model, document) else {
+        return
+    };
+    for (key, model_value) in model_fields {
+        path.push(key);
+        match document_fields.get(key) {
+            Some(document_value) => missing_field_paths(model_value, document_value, path, missing_fields),
+            None => missing_fields.push(path.clone()),
+        }
+        path.pop();
+    }
+}
+
+/// Copies the field at `field_path` (as keys) from the `model` into the `document`, where it is missing
+#[cfg(feature = "yaml")]
+fn fill_in_from(model: &serde_yaml::Value, document: &mut serde_yaml::Value, field_path: &[&serde_yaml::Value]) {
+    let Some((key, parent_path)) = field_path.split_last() else {
+        return
+    };
+    let (Some(model_value), Some(serde_yaml::Value::Mapping(document_fields))) = (
+        field_path.iter().try_fold(model, |value, key| value.get(key)),
+        parent_path.iter().try_fold(document, |value, key| value.get_mut(key)),
+    ) else {
+        return
+    };
+    document_fields.insert((*key).clone(), model_value.clone());
+}
+
+/// Comments out the `tail_comment` in the given style, under a "DOCS" banner, for it to be appended to the serialized config
+fn render_tail_comment(tail_comment: &str, comment_style: &CommentStyle) -> String {
+    match comment_style {
+        CommentStyle::Block { .. } => format!("\n\n{}", commented_out("DOCS", tail_comment, comment_style)),
+        CommentStyle::LinePrefix(_) => format!("\n{}", commented_out("DOCS", tail_comment, comment_style)),
+    }
+}
+
+/// Comments out `txt` in the given `comment_style`, preceded by a banner line with the `title`.
+/// Block comments end with a new line, while prefixed lines end as `txt` does
+pub(crate) fn commented_out(title: &str, txt: &str, comment_style: &CommentStyle) -> String {
+    static LINE_STARTS: Lazy<Regex> = Lazy::new(|| Regex::new("(?m)^").expect("Bad Regex"));
+    // the title is centered in a 65 chars wide banner
+    let fills = 63_usize.saturating_sub(title.len());
+    let banner = |fill: char| format!("{} {title} {}", fill.to_string().repeat(fills / 2), fill.to_string().repeat(fills - fills / 2));
+    match comment_style {
+        CommentStyle::Block { open, close } => format!("{open}\n{}\n{txt}\n{close}\n", banner('/')),
+        CommentStyle::LinePrefix(prefix) => {
+            // markers made of a single repeated char (`#`, `//`, `--`, ...) are also used as the banner's fill
+            let marker = prefix.trim_end();
+            let banner = match marker.chars().next() {
+                Some(fill) if marker.chars().all(|c| c == fill) => banner(fill),
+                _ => format!("{prefix}{}", banner('=')),
+            };
+            format!("{banner}\n{}", LINE_STARTS.replace_all(txt, regex::NoExpand(prefix)))
+        },
+    }
+}
+
+/// The tail docs commented out by [render_tail_comment()] at the end of `txt_config`, in the `format`'s own comment style
+/// -- see [SerdeFormat::comment_style()]. `None` if there are none, like when they were removed or given in another style
+#[cfg(feature = "async")]
+pub(crate) fn tail_docs_of(txt_config: &str, format: SerdeFormat) -> Option<String> {
+    let comment_style = format.comment_style();
+    let commented_out_nothing = commented_out("DOCS", "", &comment_style);
+    let banner = commented_out_nothing.lines().find(|line| line.contains(" DOCS "))?;
+    let docs_start = txt_config.rfind(&format!("{banner}\n"))? + banner.len() + 1;
+    let commented_out_docs = &txt_config[docs_start..];
+    let tail_docs = match &comment_style {
+        CommentStyle::Block { close, .. } => commented_out_docs.trim_end()
+            .strip_suffix(close.as_str())
+            .map(|docs| docs.strip_suffix('\n').unwrap_or(docs).to_string())?,
+        // editors may have trimmed the prefix of empty lines
+        CommentStyle::LinePrefix(prefix) => commented_out_docs.lines()
+            .map(|line| line.strip_prefix(prefix.as_str()).or_else(|| (line == prefix.trim_end()).then_some("")))
+            .collect::<Option<Vec<_>>>()?
+            .join("\n"),
+    };
+    (!tail_docs.trim().is_empty()).then_some(tail_docs)
+}
+
+/// Returns the (1-based) number of the first line of `txt_config` having tabs in its indentation, if any
+#[cfg(feature = "yaml")]
+fn tab_indented_line(txt_config: &str) -> Option<usize> {
+    txt_config.lines()
+        .position(|line| {
+            let content = line.trim_start_matches([' ', '\t']);
+            !content.is_empty() && line[..line.len() - content.len()].contains('\t')
         })
+        .map(|index| index + 1)
+}
+
+/// Deep-merges `overlay` into `base`: mappings are merged key by key, any other values are replaced
+#[cfg(feature = "yaml")]
+fn merge_yaml_values(base: serde_yaml::Value, overlay: serde_yaml::Value) -> serde_yaml::Value {
+    match (base, overlay) {
+        (serde_yaml::Value::Mapping(mut base), serde_yaml::Value::Mapping(overlay)) => {
+            for (key, overlay_value) in overlay {
+                let merged_value = match base.remove(&key) {
+                    Some(base_value) => merge_yaml_values(base_value, overlay_value),
+                    None => overlay_value,
+                };
+                base.insert(key, merged_value);
+            }
+            serde_yaml::Value::Mapping(base)
+        },
+        (_, overlay) => overlay,
     }
 }
 
+/// The `Default` counterpart of `config`, as needed by [Sparse] to tell which fields may be left out
+fn defaults_for_sparse<RootConfigType: OgreRootConfig>(config: &RootConfigType) -> Result<serde_json::Value, crate::Error> {
+    serde_json::to_value(RootConfigType::default())
+        .map_err(|err| crate::Error::SavingConfig {
+            message: format!("Error computing the default values to leave out when sparsely serializing config '{config:?}'"),
+            cause: Box::new(err),
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_commons::config_models::*;
+    use crate::testkit::*;
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    use crate::EnvInterpolation;
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    use serde::Deserialize;
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn tail_docs_extraction() {
+        let formats = [
+            #[cfg(feature = "ron")]
+            SerdeFormat::Ron,
+            #[cfg(feature = "yaml")]
+            SerdeFormat::Yaml,
+        ];
+        for format in formats {
+            let txt_config = crate::config_to_string(&AppRootConfig::default(), format, "I am\n\nthe docs").unwrap();
+            assert_eq!(tail_docs_of(&txt_config, format).as_deref(), Some("I am\n\nthe docs"), "The {format:?} docs weren't extracted from '{txt_config}'");
+            let undocumented_txt_config = crate::config_to_string(&AppRootConfig::default(), format, "").unwrap();
+            assert_eq!(tail_docs_of(&undocumented_txt_config, format), None, "No {format:?} docs should have been found in '{undocumented_txt_config}'");
+        }
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn serde_format_names() {
+        assert_eq!("ron".parse::<SerdeFormat>().unwrap(), SerdeFormat::Ron, "Wrong format parsed");
+        assert_eq!("YAML".parse::<SerdeFormat>().unwrap(), SerdeFormat::Yaml, "Format names should be case insensitive");
+        assert_eq!("yml".parse::<SerdeFormat>().unwrap(), SerdeFormat::Yaml, "Wrong format parsed");
+        let result = "toml".parse::<SerdeFormat>();
+        assert!(matches!(result, Err(crate::Error::UnsupportedConfigFileFormat { .. })), "Unknown formats should be reported. Got {result:?}");
+        assert_eq!(SerdeFormat::for_file_extension(".yml").unwrap(), SerdeFormat::Yaml, "Wrong format for the extension");
+    }
 
+    #[test]
+    fn extensions_listing() {
+        for extension in supported_extensions() {
+            assert!(SerdeFormat::for_file_extension(extension).is_ok(), "Listed extension '{extension}' should be accepted");
+        }
+        #[cfg(all(feature = "ron", feature = "yaml"))]
+        {
+            assert!(supported_extensions().contains(&".ron") && supported_extensions().contains(&".yaml"), "The default formats should be listed. Got {:?}", supported_extensions());
+            assert_eq!(listed_extensions(), "'.ron', '.yaml' and '.yml'", "Wrong listing for error messages");
+        }
+    }
+
+    #[cfg(not(all(feature = "ron", feature = "yaml")))]
+    #[test]
+    fn disabled_formats() {
+        let (disabled_format, disabled_extension, feature) = if cfg!(feature = "ron") { ("yaml", ".yml", "`yaml`") } else { ("ron", ".ron", "`ron`") };
+        for result in [disabled_format.parse::<SerdeFormat>(), SerdeFormat::for_file_extension(disabled_extension)] {
+            assert!(matches!(&result, Err(crate::Error::UnsupportedConfigFileFormat { message }) if message.contains(feature)),
+                    "Formats disabled at compile time should be reported with a hint of the feature enabling them. Got {result:?}");
+        }
+        let result = SerdeFormat::for_file_extension(".toml");
+        assert!(matches!(&result, Err(crate::Error::UnsupportedConfigFileFormat { message }) if !message.contains(disabled_extension)),
+                "Only the enabled formats should be listed as supported. Got {result:?}");
+    }
+
+    #[cfg(feature = "ron")]
     #[test]
     fn ron_serde() {
         let test = |tail_docs| {
             let expected_config = AppRootConfig::default();
-            let ron_serde = RonSerde {};
+            let ron_serde = RonSerde::default();
             let config_txt = ron_serde
                 .serialize_config(&expected_config, tail_docs)
                 .unwrap();
@@ -177,11 +606,12 @@ mod tests {
         test("I\nhave\nmultiline\ntail docs");
     }
 
+    #[cfg(feature = "yaml")]
     #[test]
     fn yaml_serde() {
         let test = |tail_docs| {
             let expected_config = AppRootConfig::default();
-            let yaml_serde = YamlSerde {};
+            let yaml_serde = YamlSerde::default();
             let config_txt = yaml_serde
                 .serialize_config(&expected_config, tail_docs)
                 .unwrap();
@@ -198,11 +628,343 @@ mod tests {
         test("I\nhave\nmultiline\ntail docs");
     }
 
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn comment_styles() {
+        let config = AppRootConfig::default();
+        let save_options = |comment_style| SaveOptions { comment_style, ..SaveOptions::default() };
+        let ron = |comment_style| RonSerde { save_options: save_options(comment_style), ..RonSerde::default() }
+            .serialize_config(&config, "tail\ndocs").unwrap();
+        let yaml = |comment_style| YamlSerde { save_options: save_options(comment_style), ..YamlSerde::default() }
+            .serialize_config(&config, "tail\ndocs").unwrap();
+
+        // per-format defaults
+        assert!(ron(None).ends_with("\n\n/*\n///////////////////////////// DOCS //////////////////////////////\ntail\ndocs\n*/\n"), "Wrong default RON style: '{}'", ron(None));
+        assert!(yaml(None).ends_with("\n############################# DOCS ##############################\n# tail\n# docs"), "Wrong default YAML style: '{}'", yaml(None));
+
+        // custom styles
+        let ron_lines = ron(Some(CommentStyle::LinePrefix("// ".to_string())));
+        assert!(ron_lines.ends_with("\n///////////////////////////// DOCS //////////////////////////////\n// tail\n// docs"), "Wrong line prefixed RON: '{ron_lines}'");
+        let reloaded_config: AppRootConfig = RonSerde::default().deserialize_config(&ron_lines).unwrap();
+        assert_eq!(reloaded_config, config, "RON with line comments should load back");
+        let yaml_block = yaml(Some(CommentStyle::Block { open: "<<'DOCS'".to_string(), close: "DOCS".to_string() }));
+        assert!(yaml_block.ends_with("\n\n<<'DOCS'\n///////////////////////////// DOCS //////////////////////////////\ntail\ndocs\nDOCS\n"), "Wrong block YAML: '{yaml_block}'");
+        let yaml_rem = yaml(Some(CommentStyle::LinePrefix("REM $1 ".to_string())));
+        assert!(yaml_rem.ends_with("\nREM $1 ============================= DOCS ==============================\nREM $1 tail\nREM $1 docs"), "Wrong prefixed YAML: '{yaml_rem}'");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_multi_documents() {
+        let txt_config = "log_sub_config:\n  sink: stdout\n---\nlog_sub_config:\n  sink: stderror\n";
+        let yaml_serde = |yaml_multi_documents| YamlSerde { load_options: LoadOptions { yaml_multi_documents, ..LoadOptions::default() }, ..YamlSerde::default() };
+
+        let result: Result<AppRootConfig, _> = yaml_serde(YamlMultiDocuments::Forbid).deserialize_config(txt_config);
+        assert!(matches!(result, Err(crate::Error::MultipleYamlDocuments { .. })), "Multiple documents should have been refused. Got {result:?}");
+
+        let first_only: AppRootConfig = yaml_serde(YamlMultiDocuments::FirstOnly).deserialize_config(txt_config).unwrap();
+        assert_eq!(first_only.log_sub_config.sink, Some(Dummy::StdOut), "Only the first document should have been loaded");
+
+        let merged: AppRootConfig = yaml_serde(YamlMultiDocuments::MergeAll).deserialize_config(txt_config).unwrap();
+        assert_eq!(merged.log_sub_config.sink, Some(Dummy::StdError), "The last document should have overridden the first");
+
+        // single documents are unaffected by the policy
+        let single: AppRootConfig = yaml_serde(YamlMultiDocuments::Forbid).deserialize_config("---\nlog_sub_config:\n  sink: null\n").unwrap();
+        assert_eq!(single, AppRootConfig::default(), "A single document should have been loaded");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_duplicate_keys() {
+        let yaml_serde = YamlSerde::default();
+        let result = yaml_serde.deserialize_config::<AppRootConfig>("log_sub_config:\n  sink: stdout\n  sink: null\n");
+        assert!(matches!(&result, Err(crate::Error::DuplicateKey { key, line: 3, .. }) if key == "log_sub_config.sink"),
+                "The duplicated nested key should have been reported. Got {result:?}");
+        assert!(result.is_err_and(|err| err.is_parsing_error()), "Duplicate keys are parsing errors");
+
+        let result = yaml_serde.deserialize_config::<AppRootConfig>("log_sub_config: {}\nlog_sub_config: {}\n");
+        assert!(matches!(&result, Err(crate::Error::DuplicateKey { key, line: 2, .. }) if key == "log_sub_config"),
+                "The duplicated top level key should have been reported. Got {result:?}");
+
+        // keys holding the key separator or not being strings are told apart structurally
+        let result = yaml_serde.deserialize_config::<AppRootConfig>("log_sub_config:\n  sink: null\nother:\n  \"a: b\": 1\n  a: 2\n  \"1\": x\n  1: y\n  1: z\n");
+        assert!(matches!(&result, Err(crate::Error::DuplicateKey { key, line: 8, .. }) if key == "other.1"),
+                "The duplicated numeric key should have been reported. Got {result:?}");
+
+        let lenient_serde = YamlSerde { load_options: LoadOptions { duplicate_keys: DuplicateKeys::LastWins, ..LoadOptions::default() }, ..YamlSerde::default() };
+        let config: AppRootConfig = lenient_serde.deserialize_config("log_sub_config:\n  sink: stdout\n  sink: stderror\n")
+            .expect("Duplicate keys should be tolerated in the lenient mode");
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdError), "The last value of the duplicated key should have been taken");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_indentation() {
+        let yaml_serde = YamlSerde::default();
+        let result: Result<AppRootConfig, _> = yaml_serde.deserialize_config("log_sub_config:\n  sink: stdout\nother:\n\tsink: null\n");
+        match &result {
+            Err(crate::Error::YamlIndentation { line, message }) => {
+                assert_eq!(*line, 4, "Wrong tab-indented line reported");
+                assert!(message.contains("indented with tabs"), "Unexpected error message: '{message}'");
+            },
+            _ => panic!("Tab indentation should have been reported. Got {result:?}"),
+        }
+        assert!(result.is_err_and(|err| err.is_parsing_error()), "Tab indentation is a parsing error");
+        let result: Result<AppRootConfig, _> = yaml_serde.deserialize_config("log_sub_config:\n  sink: stdout   \n");
+        assert_eq!(result.unwrap().log_sub_config.sink, Some(Dummy::StdOut), "Trailing spaces should be accepted");
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn env_interpolation() {
+        #[derive(Debug, Default, PartialEq, serde::Serialize, Deserialize)]
+        struct DbConfig {
+            url: String,
+            labels: Vec<String>,
+        }
+        impl OgreRootConfig for DbConfig {}
+
+        std::env::set_var("OGRE_CONFIG_MELD_TEST_DB_HOST", "db.example.com");
+        let load_options = |env_interpolation| LoadOptions { env_interpolation, ..LoadOptions::default() };
+        let test = |file_extension, txt_config: &str| {
+            let serde = AutomaticSerde::new(SerdeFormat::for_file_extension(file_extension).unwrap());
+            let interpolated: DbConfig = serde.with_load_options(&load_options(EnvInterpolation::KeepUndefined)).deserialize_config(txt_config).unwrap();
+            assert_eq!(interpolated, DbConfig {
+                url: "db.example.com:5432".to_string(),
+                labels: vec!["$OGRE_CONFIG_MELD_TEST_UNDEFINED".to_string(), "$OGRE_CONFIG_MELD_TEST_DB_HOST".to_string()],
+            }, "Wrong {file_extension} interpolation");
+
+            let serde = AutomaticSerde::new(SerdeFormat::for_file_extension(file_extension).unwrap());
+            let result: Result<DbConfig, _> = serde.with_load_options(&load_options(EnvInterpolation::FailOnUndefined)).deserialize_config(txt_config);
+            let error_message = format!("{result:?}");
+            assert!(result.is_err() && error_message.contains("OGRE_CONFIG_MELD_TEST_UNDEFINED"),
+                    "Undefined variables should have failed the {file_extension} loading. Got {error_message}");
+
+            let serde = AutomaticSerde::new(SerdeFormat::for_file_extension(file_extension).unwrap());
+            let untouched: DbConfig = serde.deserialize_config(txt_config).unwrap();
+            assert_eq!(untouched.url, "${OGRE_CONFIG_MELD_TEST_DB_HOST}:5432", "Interpolation should be disabled by default");
+        };
+        test(".ron", r#"(url: "${OGRE_CONFIG_MELD_TEST_DB_HOST}:5432", labels: ["$OGRE_CONFIG_MELD_TEST_UNDEFINED", "$$OGRE_CONFIG_MELD_TEST_DB_HOST"])"#);
+        test(".yaml", "url: ${OGRE_CONFIG_MELD_TEST_DB_HOST}:5432\nlabels:\n- $OGRE_CONFIG_MELD_TEST_UNDEFINED\n- $$OGRE_CONFIG_MELD_TEST_DB_HOST\n");
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn missing_fields() {
+        /// A config struct, as it was in version 1 of a hypothetical program
+        #[derive(Debug, Default, PartialEq, serde::Serialize, Deserialize)]
+        struct ConfigV1 {
+            name: String,
+        }
+        impl OgreRootConfig for ConfigV1 {}
+
+        /// Version 2 added a new field & follows the recommendation of using `#[serde(default)]`
+        #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+        #[serde(default)]
+        struct ConfigV2 {
+            name: String,
+            retries: u32,
+        }
+        impl Default for ConfigV2 {
+            fn default() -> Self {
+                Self { name: "unnamed".to_string(), retries: 3 }
+            }
+        }
+        impl OgreRootConfig for ConfigV2 {}
+
+        /// Version 2, if the recommendation wasn't followed
+        #[derive(Debug, Default, PartialEq, serde::Serialize, Deserialize)]
+        struct ConfigV2WithoutDefaults {
+            name: String,
+            retries: u32,
+        }
+        impl OgreRootConfig for ConfigV2WithoutDefaults {}
+
+        let test = |file_extension| {
+            let serde = AutomaticSerde::new(SerdeFormat::for_file_extension(file_extension).unwrap());
+            let old_config_txt = serde.serialize_config(&ConfigV1 { name: "old".to_string() }, "").unwrap();
+
+            let new_config: ConfigV2 = serde.deserialize_config(&old_config_txt).unwrap();
+            assert_eq!(new_config, ConfigV2 { name: "old".to_string(), retries: 3 }, "The {file_extension} missing field should have been defaulted");
+
+            let result: Result<ConfigV2WithoutDefaults, _> = serde.deserialize_config(&old_config_txt);
+            match result {
+                Err(crate::Error::MissingRequiredField { field, .. }) => assert_eq!(field, "retries", "Wrong {file_extension} missing field reported"),
+                _ => panic!("The {file_extension} missing field should have been reported. Got {result:?}"),
+            }
+        };
+        test(".ron");
+        test(".yaml");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_missing_fields_detected_structurally() {
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct AppConfig {
+            name: String,
+            #[serde(default)]
+            log: LogConfig,
+            db: DbConfig,
+        }
+        impl OgreRootConfig for AppConfig {}
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct LogConfig {
+            level: String,
+        }
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct DbConfig {
+            url: String,
+            #[serde(default)]
+            pool_size: u32,
+            #[serde(deserialize_with = "refusing_deserializer")]
+            #[serde(default)]
+            replica: String,
+        }
+        /// Refuses values with an error message looking like the one for missing fields
+        fn refusing_deserializer<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+            let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+            match value.as_str() {
+                "refused" => Err(serde::de::Error::custom("missing field `url`")),
+                _ => Ok(value),
+            }
+        }
+
+        let serde = AutomaticSerde::new(SerdeFormat::Yaml);
+        let missing_field = |txt_config| match serde.deserialize_config::<AppConfig>(txt_config) {
+            Err(crate::Error::MissingRequiredField { field, message }) => Some((field, message)),
+            _ => None,
+        };
+        // fields with defaults are not reported, even if missing along with the required ones
+        let (field, message) = missing_field("name: app
+db:
+  pool_size: 4
+").expect("The nested missing field should have been reported");
+        assert_eq!(field, "url", "Wrong missing field reported");
+        assert!(message.contains("`db.url`"), "The path of the missing field should have been reported. Got: {message}");
+        let (field, _) = missing_field("name: app
+").expect("The missing section should have been reported");
+        assert_eq!(field, "db", "Wrong missing section reported");
+        // errors merely mentioning missing fields aren't taken for them
+        let result = serde.deserialize_config::<AppConfig>("name: app
+db:
+  url: db:5432
+  replica: refused
+");
+        assert!(matches!(result, Err(crate::Error::Yaml { .. })), "Only missing fields should be reported as such. Got {result:?}");
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn sparse() {
+        #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+        #[serde(default)]
+        struct SparseConfig {
+            name: String,
+            retries: u32,
+            log: LogConfig,
+        }
+        impl Default for SparseConfig {
+            fn default() -> Self {
+                Self { name: "unnamed".to_string(), retries: 3, log: LogConfig::default() }
+            }
+        }
+        impl OgreRootConfig for SparseConfig {}
+
+        let test = |file_extension| {
+            let serde = AutomaticSerde::new(SerdeFormat::for_file_extension(file_extension).unwrap())
+                .with_save_options(&SaveOptions { sparse: true, ..SaveOptions::default() });
+
+            let config = SparseConfig { retries: 5, log: LogConfig { sink: Some(Dummy::StdOut) }, ..SparseConfig::default() };
+            let config_txt = serde.serialize_config(&config, "").unwrap();
+            assert!(!config_txt.contains("name"), "Default {file_extension} fields should have been left out: '{config_txt}'");
+            assert!(config_txt.contains("retries") && config_txt.contains("stdout"), "Changed {file_extension} fields should have been written: '{config_txt}'");
+            let reloaded_config: SparseConfig = serde.deserialize_config(&config_txt).unwrap();
+            assert_eq!(reloaded_config, config, "The sparse {file_extension} config didn't round-trip");
+
+            let default_config_txt = serde.serialize_config(&SparseConfig::default(), "").unwrap();
+            assert!(!default_config_txt.contains("retries") && !default_config_txt.contains("log"), "No {file_extension} fields should have been written for the defaults: '{default_config_txt}'");
+            let reloaded_config: SparseConfig = serde.deserialize_config(&default_config_txt).unwrap();
+            assert_eq!(reloaded_config, SparseConfig::default(), "The sparse {file_extension} defaults didn't round-trip");
+        };
+        test(".ron");
+        test(".yaml");
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn omit_none() {
+        #[derive(Debug, Default, PartialEq, serde::Serialize, Deserialize)]
+        struct OptionalConfig {
+            proxy: Option<String>,
+            retries: u32,
+            log: LogConfig,
+        }
+        impl OgreRootConfig for OptionalConfig {}
+
+        let test = |file_extension| {
+            let serde = AutomaticSerde::new(SerdeFormat::for_file_extension(file_extension).unwrap())
+                .with_save_options(&SaveOptions { omit_none: true, ..SaveOptions::default() });
+
+            let config = OptionalConfig { retries: 0, ..OptionalConfig::default() };
+            let config_txt = serde.serialize_config(&config, "").unwrap();
+            assert!(!config_txt.contains("proxy") && !config_txt.contains("sink"), "{file_extension} fields holding `None` should have been left out: '{config_txt}'");
+            assert!(config_txt.contains("retries") && config_txt.contains("log"), "{file_extension} fields holding values should have been written, even defaults: '{config_txt}'");
+            let reloaded_config: OptionalConfig = serde.deserialize_config(&config_txt).unwrap();
+            assert_eq!(reloaded_config, config, "The {file_extension} config without `None`s didn't round-trip");
+
+            let config = OptionalConfig { proxy: Some("socks5://localhost".to_string()), log: LogConfig { sink: Some(Dummy::StdOut) }, ..OptionalConfig::default() };
+            let config_txt = serde.serialize_config(&config, "").unwrap();
+            assert!(config_txt.contains("proxy") && config_txt.contains("sink"), "{file_extension} fields holding `Some` should have been written: '{config_txt}'");
+            let reloaded_config: OptionalConfig = serde.deserialize_config(&config_txt).unwrap();
+            assert_eq!(reloaded_config, config, "The {file_extension} config with `Some`s didn't round-trip");
+
+            let config_txt = AutomaticSerde::new(SerdeFormat::for_file_extension(file_extension).unwrap())
+                .serialize_config(&OptionalConfig::default(), "").unwrap();
+            assert!(config_txt.contains("proxy"), "By default, {file_extension} fields holding `None` should be written: '{config_txt}'");
+        };
+        test(".ron");
+        test(".yaml");
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[test]
+    fn omit_none_with_some_defaults() {
+        #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+        #[serde(default)]
+        struct ProxiedConfig {
+            proxy: Option<String>,
+            fallback: Option<String>,
+        }
+        impl Default for ProxiedConfig {
+            fn default() -> Self {
+                Self { proxy: Some("socks5://localhost".to_string()), fallback: None }
+            }
+        }
+        impl OgreRootConfig for ProxiedConfig {}
+
+        let test = |file_extension, save_options: SaveOptions| {
+            let serde = AutomaticSerde::new(SerdeFormat::for_file_extension(file_extension).unwrap())
+                .with_save_options(&save_options);
+            let config = ProxiedConfig { proxy: None, fallback: None };
+            let config_txt = serde.serialize_config(&config, "").unwrap();
+            assert!(config_txt.contains("proxy"), "{file_extension} `None`s overriding `Some` defaults should have been written: '{config_txt}'");
+            assert!(!config_txt.contains("fallback"), "{file_extension} `None`s defaulting to `None` should have been left out: '{config_txt}'");
+            let reloaded_config: ProxiedConfig = serde.deserialize_config(&config_txt).unwrap();
+            assert_eq!(reloaded_config, config, "The {file_extension} config without `None`s didn't round-trip");
+        };
+        for file_extension in [".ron", ".yaml"] {
+            test(file_extension, SaveOptions { omit_none: true, ..SaveOptions::default() });
+            test(file_extension, SaveOptions { omit_none: true, sparse: true, ..SaveOptions::default() });
+        }
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
     #[test]
     fn automatic_serde() {
         // unsupported extension
         let expected_error_message = "`cli-config`: Unsupported config file extension: '.unsupported.file.extension'. Supported extensions are '.ron', '.yaml' and '.yml'";
-        let result = AutomaticSerde::for_file_extension(".unsupported.file.extension");
+        let result = SerdeFormat::for_file_extension(".unsupported.file.extension");
         assert!(
             result.is_err(),
             "Passing an unsupported config file extension should result in an error"
@@ -220,7 +982,7 @@ mod tests {
         // supported extensions
         let test = |file_extension| {
             let expected_config = AppRootConfig::default();
-            let serde = AutomaticSerde::for_file_extension(file_extension).unwrap();
+            let serde = AutomaticSerde::new(SerdeFormat::for_file_extension(file_extension).unwrap());
             let config_txt = serde.serialize_config(&expected_config, "").unwrap();
             let deserialized_config: AppRootConfig = serde.deserialize_config(&config_txt).unwrap();
             println!("{file_extension}:\n{config_txt}");
@@ -0,0 +1,262 @@
+//! `u64` byte counts written like `"512MiB"` or `"1.5GB"`, for fields like `max_cache_size`. Also works for `Option<u64>` fields,
+//! which should be `#[serde(default)]` for them to be omittable:
+//! ```nocompile
+//!   pub struct CacheConfig {
+//!       #[serde(with = "ogre_config_meld::serde_helpers::byte_size")]
+//!       pub max_cache_size: u64,
+//!       #[serde(default, with = "ogre_config_meld::serde_helpers::byte_size")]
+//!       pub max_entry_size: Option<u64>,
+//!   }
+//! ```
+//! Both the SI (`kB`, `MB`, `GB`, ... -- powers of 1000) & the binary units (`KiB`, `MiB`, `GiB`, ... -- powers of 1024) are accepted,
+//! in any case, up to exabytes. Plain numbers -- as strings or not -- are bytes, while fractions must amount to whole bytes
+//! (`"1.5KiB"` is fine, `"1.5B"` isn't) & values beyond `u64::MAX` are refused. Writing picks the largest unit the value is
+//! an exact multiple of -- like `"512MiB"` or `"1500MB"` -- for readable default files.
+//! See [parse()] for taking byte sizes from the command line.
+
+use std::fmt::Formatter;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Visitor;
+
+/// The units byte sizes may be written in, along with how many bytes they are -- from the largest
+const UNITS: [(&str, u64); 13] = [
+    ("EiB", 1 << 60), ("EB", 1_000_000_000_000_000_000),
+    ("PiB", 1 << 50), ("PB", 1_000_000_000_000_000),
+    ("TiB", 1 << 40), ("TB", 1_000_000_000_000),
+    ("GiB", 1 << 30), ("GB", 1_000_000_000),
+    ("MiB", 1 << 20), ("MB", 1_000_000),
+    ("KiB", 1 << 10), ("kB", 1_000),
+    ("B", 1),
+];
+
+/// Parses the byte size written in `txt` -- like `"512MiB"`, `"1.5 GB"` or `"4096"` -- into bytes, as described in the module docs.
+/// Fit for `clap`'s `value_parser`, so byte sizes may be given in the command line the same way as in config files
+pub fn parse(txt: &str) -> Result<u64, String> {
+    let txt = txt.trim();
+    let number_len = txt.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(txt.len());
+    let (number, unit) = (&txt[..number_len], txt[number_len..].trim());
+    let invalid = |reason: &str| format!("invalid byte size '{txt}': {reason} -- expected something like \"4096\", \"512MiB\" or \"1.5GB\"");
+    let multiplier = match unit {
+        "" => 1,
+        unit => UNITS.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(unit))
+            .map(|&(_, multiplier)| multiplier)
+            .ok_or_else(|| invalid(&format!("unknown unit \"{unit}\"")))?,
+    };
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if integer.is_empty() && fraction.is_empty() || fraction.contains('.') {
+        return Err(invalid("not a number"))
+    }
+    let overflow = || invalid("it exceeds the largest supported size (16EiB, minus a byte)");
+    let digits_value = |digits: &str| match digits {
+        "" => Some(0_u128),
+        digits => digits.parse::<u128>().ok(),
+    };
+    // the fraction is scaled by the unit before dividing, so exact values (like 1.5KiB) don't lose precision
+    let fraction_scale = 10_u128.checked_pow(fraction.len() as u32).ok_or_else(overflow)?;
+    let integer_bytes = digits_value(integer).ok_or_else(overflow)?.checked_mul(multiplier as u128).ok_or_else(overflow)?;
+    let scaled_fraction_bytes = digits_value(fraction).ok_or_else(overflow)?.checked_mul(multiplier as u128).ok_or_else(overflow)?;
+    if !scaled_fraction_bytes.is_multiple_of(fraction_scale) {
+        return Err(invalid("it isn't a whole number of bytes"))
+    }
+    let bytes = integer_bytes.checked_add(scaled_fraction_bytes / fraction_scale).ok_or_else(overflow)?;
+    u64::try_from(bytes).map_err(|_| overflow())
+}
+
+/// Writes the given number of `bytes` in the largest unit it is an exact multiple of -- like `"512MiB"`
+pub fn format(bytes: u64) -> String {
+    let (unit, multiplier) = UNITS.iter()
+        .filter(|&&(_, multiplier)| bytes != 0 && bytes.is_multiple_of(multiplier))
+        .max_by_key(|&&(_, multiplier)| multiplier)
+        .copied()
+        .unwrap_or(("B", 1));
+    format!("{}{unit}", bytes / multiplier)
+}
+
+/// The field types this module works with: `u64` & `Option<u64>`
+pub trait ByteSizeField: Sized {
+    #[doc(hidden)]
+    fn serialize_human<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+    #[doc(hidden)]
+    fn deserialize_human<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+impl ByteSizeField for u64 {
+    fn serialize_human<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ByteSize(*self).serialize(serializer)
+    }
+
+    fn deserialize_human<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ByteSize::deserialize(deserializer)
+            .map(|byte_size| byte_size.0)
+    }
+}
+
+impl ByteSizeField for Option<u64> {
+    fn serialize_human<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.map(ByteSize).serialize(serializer)
+    }
+
+    fn deserialize_human<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<ByteSize>::deserialize(deserializer)
+            .map(|byte_size| byte_size.map(|byte_size| byte_size.0))
+    }
+}
+
+/// Writes the byte size in the largest exact unit -- see [format()]
+pub fn serialize<S: Serializer>(bytes: &impl ByteSizeField, serializer: S) -> Result<S::Ok, S::Error> {
+    bytes.serialize_human(serializer)
+}
+
+/// Reads byte sizes written as described in the module docs -- see [parse()]
+pub fn deserialize<'de, T: ByteSizeField, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    T::deserialize_human(deserializer)
+}
+
+/// A number of bytes, (de)serialized in the human form
+struct ByteSize(u64);
+
+impl Serialize for ByteSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format(self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ByteSizeVisitor)
+    }
+}
+
+/// Takes byte sizes written as strings -- or as plain numbers of bytes
+struct ByteSizeVisitor;
+
+impl Visitor<'_> for ByteSizeVisitor {
+    type Value = ByteSize;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("a byte size, like 4096, \"512MiB\" or \"1.5GB\"")
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, bytes: u64) -> Result<Self::Value, E> {
+        Ok(ByteSize(bytes))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, bytes: i64) -> Result<Self::Value, E> {
+        u64::try_from(bytes)
+            .map(ByteSize)
+            .map_err(|_| E::custom(format!("invalid byte size {bytes}: it can't be negative")))
+    }
+
+    fn visit_str<E: serde::de::Error>(self, txt: &str) -> Result<Self::Value, E> {
+        parse(txt).map(ByteSize).map_err(E::custom)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config_from_str, config_to_string, effective_config_from_parts, CmdLineAndConfigIntegration, OgreRootConfig, SerdeFormat};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct CacheConfig {
+        #[serde(with = "crate::serde_helpers::byte_size")]
+        max_cache_size: u64,
+        #[serde(default, with = "crate::serde_helpers::byte_size")]
+        max_entry_size: Option<u64>,
+    }
+    impl OgreRootConfig for CacheConfig {}
+
+    /// Command line options taking byte sizes the same way as the config files
+    #[derive(clap::Parser, Debug)]
+    struct CacheCliOptions {
+        #[clap(long, value_parser = parse)]
+        max_cache_size: Option<u64>,
+    }
+    impl CmdLineAndConfigIntegration<CacheConfig> for CacheCliOptions {
+        fn config_file_path(&self) -> Option<&str> { None }
+        fn should_write_effective_config(&self) -> bool { false }
+        fn should_show_effective_config(&self) -> bool { false }
+        fn merge_with_config(self, mut config: CacheConfig) -> Result<CacheConfig, crate::Error> {
+            if let Some(max_cache_size) = self.max_cache_size {
+                config.max_cache_size = max_cache_size;
+            }
+            Ok(config)
+        }
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse("512MiB"), Ok(512 << 20), "Binary units weren't parsed");
+        assert_eq!(parse("1.5GB"), Ok(1_500_000_000), "Fractional SI units weren't parsed");
+        assert_eq!(parse("1.5 kib"), Ok(1536), "Units should be case insensitive & may be spaced");
+        assert_eq!(parse("4096"), Ok(4096), "Plain numbers should be bytes");
+        assert_eq!(parse("16EiB").map_err(|err| err.contains("exceeds")), Err(true), "Sizes beyond u64 should be refused as such");
+        assert_eq!(parse("18446744073709551615B"), Ok(u64::MAX), "The largest size should be accepted");
+        assert_eq!(parse("18446744073709551616").map_err(|err| err.contains("exceeds")), Err(true), "Plain numbers beyond u64 should be refused as such");
+        assert_eq!(parse("1.5").map_err(|err| err.contains("whole number of bytes")), Err(true), "Fractional bytes should be refused as such");
+        assert_eq!(parse("12 parsecs").map_err(|err| err.contains("unknown unit")), Err(true), "Unknown units should be refused as such");
+        assert!(parse("MiB").is_err() && parse("1.2.3KB").is_err() && parse("").is_err(), "Malformed numbers should be refused");
+
+        assert_eq!(format(512 << 20), "512MiB", "The largest exact binary unit should have been used");
+        assert_eq!(format(1_500_000_000), "1500MB", "The largest exact SI unit should have been used");
+        assert_eq!(format(1536), "1536B", "Bytes should be used when no unit is exact");
+        assert_eq!(format(0), "0B", "Zero should be in bytes");
+        assert_eq!(format(u64::MAX), "18446744073709551615B", "The largest size should be written in bytes");
+    }
+
+    #[test]
+    fn round_trips() {
+        let formats = [
+            #[cfg(feature = "ron")]
+            SerdeFormat::Ron,
+            #[cfg(feature = "yaml")]
+            SerdeFormat::Yaml,
+        ];
+        for format in formats {
+            for config in [
+                CacheConfig { max_cache_size: 512 << 20, max_entry_size: Some(1_500_000) },
+                CacheConfig { max_cache_size: 1536, max_entry_size: None },
+            ] {
+                let txt_config = config_to_string(&config, format, "").unwrap();
+                assert_eq!(config_from_str::<CacheConfig>(&txt_config, format).unwrap(), config, "The {format:?} config didn't load back the same from '{txt_config}'");
+            }
+            let txt_config = config_to_string(&CacheConfig { max_cache_size: 512 << 20, max_entry_size: Some(1_500_000) }, format, "").unwrap();
+            assert!(txt_config.contains("512MiB") && txt_config.contains("1500kB"), "The {format:?} sizes should be in the compact form: '{txt_config}'");
+        }
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn ron_byte_sizes() {
+        let config = config_from_str::<CacheConfig>(r#"(max_cache_size: "1.5GB", max_entry_size: Some(4096))"#, SerdeFormat::Ron).unwrap();
+        assert_eq!(config, CacheConfig { max_cache_size: 1_500_000_000, max_entry_size: Some(4096) }, "Wrong sizes parsed");
+        let config = config_from_str::<CacheConfig>("(max_cache_size: 4096)", SerdeFormat::Ron).unwrap();
+        assert_eq!(config.max_entry_size, None, "The optional size should be omittable");
+        let err = config_from_str::<CacheConfig>(r#"(max_cache_size: "lots")"#, SerdeFormat::Ron).expect_err("Invalid sizes should be refused");
+        assert!(format!("{err:?}").contains("invalid byte size 'lots'"), "The offending value should be reported. Got {err:?}");
+        let err = config_from_str::<CacheConfig>("(max_cache_size: -1)", SerdeFormat::Ron).expect_err("Negative sizes should be refused");
+        assert!(format!("{err:?}").contains("can't be negative"), "The negative size should be reported as such. Got {err:?}");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_byte_sizes() {
+        let config = config_from_str::<CacheConfig>("max_cache_size: 512MiB\nmax_entry_size: 64 KiB\n", SerdeFormat::Yaml).unwrap();
+        assert_eq!(config, CacheConfig { max_cache_size: 512 << 20, max_entry_size: Some(64 << 10) }, "Wrong sizes parsed");
+        let config = config_from_str::<CacheConfig>("max_cache_size: 4096\nmax_entry_size: null\n", SerdeFormat::Yaml).unwrap();
+        assert_eq!(config, CacheConfig { max_cache_size: 4096, max_entry_size: None }, "Plain numbers should be bytes & the optional size should take nulls");
+        let err = config_from_str::<CacheConfig>("max_cache_size: 20EB\n", SerdeFormat::Yaml).expect_err("Overflowing sizes should be refused");
+        let message = err.to_string();
+        assert!(message.contains("exceeds") && message.contains("max_cache_size"), "The overflow & the field should be reported. Got {message}");
+    }
+
+    #[test]
+    fn cli_merge() {
+        let config = effective_config_from_parts::<CacheCliOptions, _>(["app", "--max-cache-size", "2GiB"], CacheConfig { max_cache_size: 4096, max_entry_size: None }).unwrap();
+        assert_eq!(config.max_cache_size, 2 << 30, "The byte size from the command line wasn't merged");
+        let result = effective_config_from_parts::<CacheCliOptions, _>(["app", "--max-cache-size", "2 parsecs"], CacheConfig::default());
+        assert!(matches!(&result, Err(crate::Error::CliParsing { rendered_help, .. }) if rendered_help.contains("unknown unit")), "The invalid size should have been refused. Got {result:?}");
+    }
+}
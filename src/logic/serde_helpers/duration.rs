@@ -0,0 +1,125 @@
+//! [std::time::Duration] fields written like `"30s"` or `"2m 30s"` -- the same way as [crate::HumanDuration] -- instead of the
+//! `secs` & `nanos` maps serde gives by default (which also differ between the formats). Also works for `Option<Duration>` fields,
+//! which should be `#[serde(default)]` for them to be omittable:
+//! ```nocompile
+//!   pub struct ServerConfig {
+//!       #[serde(with = "ogre_config_meld::serde_helpers::duration")]
+//!       pub timeout: std::time::Duration,
+//!       #[serde(default, with = "ogre_config_meld::serde_helpers::duration")]
+//!       pub retry_backoff: Option<std::time::Duration>,
+//!   }
+//! ```
+//! Invalid values are reported along with what was expected & the offending field.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The field types this module works with: [std::time::Duration] & `Option<std::time::Duration>`
+pub trait DurationField: Sized {
+    #[doc(hidden)]
+    fn serialize_human<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+    #[doc(hidden)]
+    fn deserialize_human<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+impl DurationField for std::time::Duration {
+    fn serialize_human<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::HumanDuration(*self).serialize(serializer)
+    }
+
+    fn deserialize_human<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        crate::HumanDuration::deserialize(deserializer)
+            .map(Into::into)
+    }
+}
+
+impl DurationField for Option<std::time::Duration> {
+    fn serialize_human<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.map(crate::HumanDuration).serialize(serializer)
+    }
+
+    fn deserialize_human<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<crate::HumanDuration>::deserialize(deserializer)
+            .map(|duration| duration.map(Into::into))
+    }
+}
+
+/// Writes the `duration` in its compact human form, like `"2m 30s"`
+pub fn serialize<S: Serializer>(duration: &impl DurationField, serializer: S) -> Result<S::Ok, S::Error> {
+    duration.serialize_human(serializer)
+}
+
+/// Reads durations written in any combination of the units accepted by [crate::HumanDuration], like `"150s"` or `"2m 30s"`
+pub fn deserialize<'de, T: DurationField, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    T::deserialize_human(deserializer)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use serde::{Deserialize, Serialize};
+    use crate::{config_from_str, config_to_string, OgreRootConfig, SerdeFormat};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct RetriesConfig {
+        #[serde(with = "crate::serde_helpers::duration")]
+        timeout: Duration,
+        #[serde(default, with = "crate::serde_helpers::duration")]
+        retry_backoff: Option<Duration>,
+    }
+    impl OgreRootConfig for RetriesConfig {}
+
+    const FORMATS: &[SerdeFormat] = &[
+        #[cfg(feature = "ron")]
+        SerdeFormat::Ron,
+        #[cfg(feature = "yaml")]
+        SerdeFormat::Yaml,
+    ];
+
+    #[test]
+    fn round_trips() {
+        for &format in FORMATS {
+            for config in [
+                RetriesConfig { timeout: Duration::from_secs(30), retry_backoff: Some(Duration::from_secs(150)) },
+                RetriesConfig { timeout: Duration::from_millis(250), retry_backoff: None },
+            ] {
+                let txt_config = config_to_string(&config, format, "").unwrap();
+                assert!(!txt_config.contains("secs") && !txt_config.contains("nanos"), "The {format:?} durations should be in the human form: '{txt_config}'");
+                assert_eq!(config_from_str::<RetriesConfig>(&txt_config, format).unwrap(), config, "The {format:?} config didn't load back the same from '{txt_config}'");
+            }
+            let txt_config = config_to_string(&RetriesConfig { timeout: Duration::from_secs(30), retry_backoff: Some(Duration::from_secs(150)) }, format, "").unwrap();
+            assert!(txt_config.contains("30s") && txt_config.contains("2m 30s"), "The {format:?} durations should be in the compact form: '{txt_config}'");
+        }
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn ron_durations() {
+        let config = config_from_str::<RetriesConfig>(r#"(timeout: "2m 30s", retry_backoff: Some("1h"))"#, SerdeFormat::Ron).unwrap();
+        assert_eq!(config, RetriesConfig { timeout: Duration::from_secs(150), retry_backoff: Some(Duration::from_secs(3600)) }, "Wrong durations parsed");
+        let config = config_from_str::<RetriesConfig>(r#"(timeout: "30s")"#, SerdeFormat::Ron).unwrap();
+        assert_eq!(config.retry_backoff, None, "The optional duration should be omittable");
+
+        let err = config_from_str::<RetriesConfig>("(\n  timeout: \"30 parsecs\",\n)", SerdeFormat::Ron).expect_err("Invalid durations should be refused");
+        let message = format!("{err:?}");
+        assert!(message.contains("30 parsecs") && message.contains("expected something like"), "The offending value & a hint should be reported. Got {message}");
+        assert!(message.contains("field `timeout`"), "The offending field should be reported. Got {message}");
+        let err = config_from_str::<RetriesConfig>(r#"(timeout: "30s", retry_backoff: Some("soon"))"#, SerdeFormat::Ron).expect_err("Invalid durations should be refused");
+        let message = err.to_string();
+        assert!(message.contains("soon") && message.contains("field `retry_backoff`"), "The offending value & field should be reported. Got {message}");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_durations() {
+        let config = config_from_str::<RetriesConfig>("timeout: 2m 30s\nretry_backoff: 1h\n", SerdeFormat::Yaml).unwrap();
+        assert_eq!(config, RetriesConfig { timeout: Duration::from_secs(150), retry_backoff: Some(Duration::from_secs(3600)) }, "Wrong durations parsed");
+        let config = config_from_str::<RetriesConfig>("timeout: 30s\nretry_backoff: null\n", SerdeFormat::Yaml).unwrap();
+        assert_eq!(config.retry_backoff, None, "The optional duration should take nulls");
+
+        let err = config_from_str::<RetriesConfig>("timeout: 30s\nretry_backoff: soon\n", SerdeFormat::Yaml).expect_err("Invalid durations should be refused");
+        let message = err.to_string();
+        assert!(message.contains("soon") && message.contains("retry_backoff"), "The offending value & field should be reported. Got {message}");
+        assert!(config_from_str::<RetriesConfig>("retry_backoff: 1h\n", SerdeFormat::Yaml).is_err(), "The required duration shouldn't be omittable");
+    }
+}
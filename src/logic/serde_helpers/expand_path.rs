@@ -0,0 +1,352 @@
+//! [ExpandedPath] fields, written like `"~/myapp/data"` or `"${DATA_ROOT}/myapp"` -- expanded when loaded, so apps don't end up
+//! creating directories literally named `~`. Use `Option<ExpandedPath>` for optional paths, which should be `#[serde(default)]`
+//! for them to be omittable:
+//! ```nocompile
+//!   pub struct StorageConfig {
+//!       pub data_dir: ExpandedPath,
+//!       #[serde(default)]
+//!       pub cache_dir: Option<ExpandedPath>,
+//!   }
+//!   impl OgreRootConfig for StorageConfig {
+//!       fn post_load(&mut self, context: &LoadContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//!           self.data_dir.resolve_relative_to_config_dir(context);
+//!           if let Some(cache_dir) = &mut self.cache_dir {
+//!               cache_dir.resolve_relative_to_config_dir(context);
+//!           }
+//!           Ok(())
+//!       }
+//!   }
+//! ```
+//! See [expand()] for the accepted forms. Each path keeps the form it was written in, so rewrites of the config files -- like the
+//! ones from `--write-effective-config` -- keep `"~/myapp/data"` rather than baking in the machine's absolute paths.
+//! Paths set after loading (see [ExpandedPath::new()]) are written as they are.
+
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+use std::path::{is_separator, Path, PathBuf};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::logic::interpolation_logic::interpolate_env_vars;
+use crate::{EnvInterpolation, LoadContext};
+
+/// Expands the path written in `txt`: a leading `~` becomes the current user's home directory & `~user`, the home of
+/// `user`, while `${VAR}` / `$VAR` references become the values of the environment variables (`$$` standing for a literal
+/// `$`). Undefined variables & unknown users are refused. Fit for `clap`'s `value_parser`, so paths may be given in the
+/// command line the same way as in config files.
+///
+/// `~user` is only supported on Unix, for the users listed in `/etc/passwd` -- the ones known through NSS modules,
+/// like LDAP users, aren't
+pub fn expand(txt: &str) -> Result<PathBuf, String> {
+    let invalid = |reason: String| format!("invalid path '{txt}': {reason}");
+    let (home_dir, rest) = match txt.strip_prefix('~') {
+        Some(after_tilde) => {
+            let user_len = after_tilde.find(is_separator).unwrap_or(after_tilde.len());
+            let (user, rest) = after_tilde.split_at(user_len);
+            let home_dir = home_dir_of(user)
+                .ok_or_else(|| invalid(match user {
+                    "" => "the home directory is unknown".to_string(),
+                    user => format!("the home directory of user '{user}' is unknown"),
+                }))?;
+            (Some(home_dir), rest.trim_start_matches(is_separator))
+        },
+        None => (None, txt),
+    };
+    let rest = interpolate_env_vars(rest, EnvInterpolation::FailOnUndefined)
+        .map_err(|var_name| invalid(format!("the environment variable `{var_name}` isn't defined")))?;
+    Ok(match home_dir {
+        Some(home_dir) if rest.is_empty() => home_dir,
+        Some(home_dir) => home_dir.join(&*rest),
+        None => PathBuf::from(&*rest),
+    })
+}
+
+/// The home directory of `user` -- or of the current user, if empty. See [expand()] for the supported users
+fn home_dir_of(user: &str) -> Option<PathBuf> {
+    if user.is_empty() {
+        return dirs::home_dir()
+    }
+    #[cfg(unix)]
+    return std::fs::read_to_string("/etc/passwd").ok()
+        .and_then(|passwd| passwd.lines()
+            .map(|entry| entry.split(':').collect::<Vec<_>>())
+            .find(|fields| fields.len() >= 6 && fields[0] == user)
+            .map(|fields| PathBuf::from(fields[5])));
+    #[cfg(not(unix))]
+    None
+}
+
+/// A path expanded when loaded -- see the module docs -- along with the form it was written in, for writing it back the same way.
+/// Derefs to the expanded [Path]. Equality only takes the expanded paths into account
+#[derive(Clone, Debug, Default)]
+pub struct ExpandedPath {
+    path: PathBuf,
+    /// How the path was written in the config file, if not as it is
+    original: Option<String>,
+    /// The config directory a relative `original` was resolved against -- see [ExpandedPath::resolve_relative_to_config_dir()]
+    anchor_dir: Option<PathBuf>,
+}
+
+impl ExpandedPath {
+
+    /// A path set by the program -- which is written as it is
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), original: None, anchor_dir: None }
+    }
+
+    /// The expanded path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How the path was written in the config file -- `None` if it was already written as it is (or wasn't loaded from a file)
+    pub fn original(&self) -> Option<&str> {
+        self.original.as_deref()
+    }
+
+    /// Anchors the path, if relative -- as loaded from the config file in `context` -- to the file's directory, rather than to
+    /// the process' current directory. Meant for [crate::OgreRootConfig::post_load()], as shown in the module docs.
+    /// Empty & absolute paths are left untouched, as well as all paths of configs not loaded from files -- see [LoadContext::config_dir()].
+    /// Resolved paths are still written back relative into the same directory -- but absolute elsewhere, so they keep pointing to
+    /// the same place
+    pub fn resolve_relative_to_config_dir(&mut self, context: &LoadContext) {
+        let Some(config_dir) = context.config_dir() else {
+            return
+        };
+        if self.path.as_os_str().is_empty() || self.path.is_absolute() {
+            return
+        }
+        if self.original.is_none() {
+            self.original = self.path.to_str().map(str::to_string);
+        }
+        self.path = config_dir.join(&self.path);
+        self.anchor_dir = Some(config_dir.to_path_buf());
+    }
+
+    /// The form to write the path in, into files in the `destination_dir` -- if known
+    fn written_form(&self, destination_dir: Option<&Path>) -> Option<&str> {
+        match &self.anchor_dir {
+            Some(anchor_dir) if destination_dir != Some(anchor_dir.as_path()) => None,
+            _ => self.original.as_deref(),
+        }
+    }
+}
+
+impl Deref for ExpandedPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for ExpandedPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl From<PathBuf> for ExpandedPath {
+    fn from(path: PathBuf) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<&str> for ExpandedPath {
+    fn from(path: &str) -> Self {
+        Self::new(path)
+    }
+}
+
+impl PartialEq for ExpandedPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for ExpandedPath {}
+
+impl Display for ExpandedPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.path.display().fmt(f)
+    }
+}
+
+impl Serialize for ExpandedPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let destination_dir = DESTINATION_DIR.with_borrow(Clone::clone);
+        match self.written_form(destination_dir.as_deref()) {
+            Some(original) => serializer.serialize_str(original),
+            None => self.path.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ExpandedPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let original = String::deserialize(deserializer)?;
+        let path = expand(&original).map_err(serde::de::Error::custom)?;
+        let original = (path.as_os_str() != original.as_str()).then_some(original);
+        Ok(Self { path, original, anchor_dir: None })
+    }
+}
+
+thread_local! {
+    /// The directory of the config file being serialized in this thread, if any -- see [writing_into()]
+    static DESTINATION_DIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Runs `serialize` -- serializing a config to be written to a file in `destination_dir` -- letting the [ExpandedPath]s
+/// know where they go, for the relative ones to be written relative only into the directory they were resolved against
+pub(crate) fn writing_into<R>(destination_dir: &Path, serialize: impl FnOnce() -> R) -> R {
+
+    /// Restores the previous destination, even on panics
+    struct Restore(Option<PathBuf>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            DESTINATION_DIR.set(self.0.take());
+        }
+    }
+
+    let _restore = Restore(DESTINATION_DIR.replace(Some(destination_dir.to_path_buf())));
+    serialize()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use serde::{Deserialize, Serialize};
+    use super::{expand, writing_into, ExpandedPath};
+    use crate::{config_from_str, config_to_string, LoadContext, OgreRootConfig, SerdeFormat};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct StorageConfig {
+        data_dir: ExpandedPath,
+        #[serde(default)]
+        cache_dir: Option<ExpandedPath>,
+        logs_dir: ExpandedPath,
+    }
+    impl OgreRootConfig for StorageConfig {
+        fn post_load(&mut self, context: &LoadContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.data_dir.resolve_relative_to_config_dir(context);
+            if let Some(cache_dir) = &mut self.cache_dir {
+                cache_dir.resolve_relative_to_config_dir(context);
+            }
+            self.logs_dir.resolve_relative_to_config_dir(context);
+            Ok(())
+        }
+    }
+
+    fn home_dir() -> PathBuf {
+        dirs::home_dir().expect("The tests need a home directory")
+    }
+
+    #[test]
+    fn expansions() {
+        std::env::set_var("OGRE_CONFIG_MELD_TEST_PATHS_ROOT", "/srv/apps");
+        assert_eq!(expand("~").unwrap(), home_dir(), "A lone tilde should be the home directory");
+        assert_eq!(expand("~/myapp/data").unwrap(), home_dir().join("myapp/data"), "Wrong home relative path");
+        assert_eq!(expand("${OGRE_CONFIG_MELD_TEST_PATHS_ROOT}/myapp").unwrap(), PathBuf::from("/srv/apps/myapp"), "Wrong braced variable expansion");
+        assert_eq!(expand("$OGRE_CONFIG_MELD_TEST_PATHS_ROOT/myapp").unwrap(), PathBuf::from("/srv/apps/myapp"), "Wrong variable expansion");
+        assert_eq!(expand("~/$$myapp").unwrap(), home_dir().join("$myapp"), "`$$` should stand for a literal `$`");
+        assert_eq!(expand("data/~").unwrap(), PathBuf::from("data/~"), "Only leading tildes should be expanded");
+        assert_eq!(expand("/var/lib/myapp").unwrap(), PathBuf::from("/var/lib/myapp"), "Plain paths should be kept");
+
+        let result = expand("${OGRE_CONFIG_MELD_TEST_PATHS_UNDEFINED}/myapp");
+        assert!(matches!(&result, Err(message) if message.contains("OGRE_CONFIG_MELD_TEST_PATHS_UNDEFINED")), "Undefined variables should be reported. Got {result:?}");
+        let result = expand("~ogre-config-meld-no-such-user/data");
+        assert!(matches!(&result, Err(message) if message.contains("ogre-config-meld-no-such-user")), "Unknown users should be reported. Got {result:?}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn user_homes() {
+        let root_home = expand("~root").expect("The home of `root` should be known");
+        assert!(root_home.is_absolute(), "Wrong home for `root`: {root_home:?}");
+        assert_eq!(expand("~root/myapp").unwrap(), root_home.join("myapp"), "Wrong user home relative path");
+    }
+
+    /// The text of a [StorageConfig] for each format -- with a home relative, an environment relative & a relative path
+    fn storage_config_txt(format: SerdeFormat) -> &'static str {
+        match format {
+            #[cfg(feature = "ron")]
+            SerdeFormat::Ron => r#"(data_dir: "~/ogre-config-meld-data", cache_dir: Some("${OGRE_CONFIG_MELD_TEST_PATHS_CACHE}/myapp"), logs_dir: "logs")"#,
+            #[cfg(feature = "yaml")]
+            SerdeFormat::Yaml => "data_dir: ~/ogre-config-meld-data\ncache_dir: ${OGRE_CONFIG_MELD_TEST_PATHS_CACHE}/myapp\nlogs_dir: logs\n",
+        }
+    }
+
+    const FORMATS: &[SerdeFormat] = &[
+        #[cfg(feature = "ron")]
+        SerdeFormat::Ron,
+        #[cfg(feature = "yaml")]
+        SerdeFormat::Yaml,
+    ];
+
+    /// The [StorageConfig] of [storage_config_txt()], as loaded from a file in `/etc/myapp`
+    fn loaded_storage_config(format: SerdeFormat) -> StorageConfig {
+        std::env::set_var("OGRE_CONFIG_MELD_TEST_PATHS_CACHE", "/var/cache");
+        let mut config = config_from_str::<StorageConfig>(storage_config_txt(format), format).unwrap();
+        config.post_load(&LoadContext { path: PathBuf::from("/etc/myapp/myapp.config"), format }).unwrap();
+        config
+    }
+
+    #[test]
+    fn relative_resolution() {
+        for &format in FORMATS {
+            let config = config_from_str::<StorageConfig>(storage_config_txt(format), format).unwrap();
+            assert_eq!(config.logs_dir.path(), Path::new("logs"), "Relative paths should only be resolved by the hook");
+            assert_eq!(loaded_storage_config(format), StorageConfig {
+                data_dir: home_dir().join("ogre-config-meld-data").into(),
+                cache_dir: Some(PathBuf::from("/var/cache/myapp").into()),
+                logs_dir: "/etc/myapp/logs".into(),
+            }, "Wrong {format:?} paths");
+        }
+    }
+
+    #[test]
+    fn original_forms_rewritten() {
+        for &format in FORMATS {
+            let mut config = loaded_storage_config(format);
+            let txt_config = writing_into(Path::new("/etc/myapp"), || config_to_string(&config, format, "")).unwrap();
+            for original in ["~/ogre-config-meld-data", "${OGRE_CONFIG_MELD_TEST_PATHS_CACHE}/myapp", "logs"] {
+                assert!(txt_config.contains(original), "The {format:?} rewrite should have kept '{original}': '{txt_config}'");
+            }
+            assert!(!txt_config.contains("/etc/myapp"), "The {format:?} rewrite shouldn't have the resolved paths: '{txt_config}'");
+
+            let txt_config = writing_into(Path::new("/tmp/elsewhere"), || config_to_string(&config, format, "")).unwrap();
+            assert!(txt_config.contains("/etc/myapp/logs") && txt_config.contains("~/ogre-config-meld-data"),
+                    "Relative paths should be written absolute into other directories -- unlike the others: '{txt_config}'");
+
+            config.logs_dir = ExpandedPath::new("/var/log/myapp");
+            config.data_dir = ExpandedPath::new(home_dir().join("ogre-config-meld-data"));
+            let txt_config = writing_into(Path::new("/etc/myapp"), || config_to_string(&config, format, "")).unwrap();
+            assert!(txt_config.contains("/var/log/myapp") && !txt_config.contains("~/ogre-config-meld-data"),
+                    "Paths set after loading should be written as they are -- even if another one was written in another form: '{txt_config}'");
+        }
+    }
+
+    #[cfg(all(feature = "async", feature = "yaml"))]
+    #[tokio::test]
+    async fn original_forms_saved() {
+        use crate::testkit::temp_config_dir;
+        std::env::set_var("OGRE_CONFIG_MELD_TEST_PATHS_CACHE", "/var/cache");
+        let config_dir = temp_config_dir();
+        std::fs::create_dir_all(config_dir.join("elsewhere")).unwrap();
+        let config_path = config_dir.join("storage.yaml");
+        std::fs::write(&config_path, storage_config_txt(SerdeFormat::Yaml)).unwrap();
+        let config: StorageConfig = crate::load_from_file(&config_path).await.unwrap().unwrap();
+        assert_eq!(config.logs_dir.path(), config_dir.join("logs"), "The relative path should have been resolved against the config dir");
+
+        crate::save_to_file(&config, "", &config_path).await.unwrap();
+        let saved_txt = std::fs::read_to_string(&config_path).unwrap();
+        assert!(saved_txt.contains("logs_dir: logs") && saved_txt.contains("~/ogre-config-meld-data"), "The original forms should have been saved: '{saved_txt}'");
+        let elsewhere_path = config_dir.join("elsewhere/storage.yaml");
+        crate::save_to_file(&config, "", &elsewhere_path).await.unwrap();
+        let saved_txt = std::fs::read_to_string(&elsewhere_path).unwrap();
+        assert!(saved_txt.contains(&*config_dir.join("logs").to_string_lossy()), "Relative paths should be saved absolute into other dirs: '{saved_txt}'");
+        let elsewhere_config: StorageConfig = crate::load_from_file(&elsewhere_path).await.unwrap().unwrap();
+        assert_eq!(elsewhere_config, config, "The config saved elsewhere should load back the same");
+        _ = std::fs::remove_dir_all(&config_dir);
+    }
+}
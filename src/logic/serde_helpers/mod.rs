@@ -0,0 +1,10 @@
+//! Helpers for `#[serde(with = "...")]`, (de)serializing the standard types of config fields in a human-friendly way
+//! -- for configs that would rather keep them than use this crate's own wrappers, like [crate::Timestamp] --
+//! along with field types needing more than that, like [expand_path::ExpandedPath]
+
+#[cfg(feature = "humantime")]
+pub mod duration;
+
+pub mod byte_size;
+
+pub mod expand_path;
@@ -0,0 +1,117 @@
+//! The classic Unix contract for daemons -- `kill -HUP` makes them re-read their configs --
+//! behind the `sighup` feature
+
+use crate::logic::warnings_logic::emit_warning;
+use crate::{diff_configs, load_from_file_with_options, ConfigSubscription, LoadOptions, LoadWarningKind, OgreRootConfig};
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+/// Loads the (existing) config file at `config_file_path`, reloading it whenever the process receives a SIGHUP:
+/// the returned [ConfigSubscription] gets every new config that loads & merges successfully -- a summary of the changed fields
+/// is given to the warnings sink (see [crate::set_warnings_sink()]), as is the error of failed reloads, which keep the previous config.
+///
+/// `merge` is applied to every loaded config, so command line options keep their precedence over the reloaded file,
+/// like this:
+/// ```nocompile
+///   let configs = reload_on_sighup(&config_file_path, &LoadOptions::default(),
+///                                  move |config| merge_cmdline_args_with_configs(cmdline_options.clone(), config)).await?;
+/// ```
+/// Must be called within a `tokio` runtime. Reloading stops when all subscriptions are dropped (and another signal arrives).
+pub async fn reload_on_sighup<RootConfigType: OgreRootConfig + Send + Sync + 'static>(
+    config_file_path: impl AsRef<Path> + Debug,
+    load_options: &LoadOptions,
+    merge: impl Fn(RootConfigType) -> Result<RootConfigType, crate::Error> + Send + Sync + 'static,
+) -> Result<ConfigSubscription<RootConfigType>, crate::Error> {
+    let config_file_path = config_file_path.as_ref().to_path_buf();
+    let config = load_and_merge(&config_file_path, load_options, &merge).await?;
+    let mut hangups = signal(SignalKind::hangup())
+        .map_err(|err| crate::Error::Io {
+            message: format!("Error installing the SIGHUP handler for reloading the config file {config_file_path:?}"),
+            cause: err,
+        })?;
+    let (config_sender, config_subscription) = watch::channel(Arc::new(config));
+    let load_options = load_options.clone();
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            let reloaded_config = match load_and_merge(&config_file_path, &load_options, &merge).await {
+                Ok(reloaded_config) => reloaded_config,
+                Err(err) => {
+                    emit_warning(LoadWarningKind::FailedReload, "", format!("SIGHUP: the config file {config_file_path:?} couldn't be reloaded -- keeping the previous config: {err}"));
+                    continue;
+                },
+            };
+            let changed_fields = diff_configs(&**config_sender.borrow(), &reloaded_config).into_iter()
+                .map(|field_change| field_change.path)
+                .collect::<Vec<_>>()
+                .join(", ");
+            emit_warning(LoadWarningKind::Reloaded, "", format!("SIGHUP: reloaded the config file {config_file_path:?} -- changed fields: [{changed_fields}]"));
+            if config_sender.send(Arc::new(reloaded_config)).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(config_subscription)
+}
+
+async fn load_and_merge<RootConfigType: OgreRootConfig>(
+    config_file_path: &Path,
+    load_options: &LoadOptions,
+    merge: &impl Fn(RootConfigType) -> Result<RootConfigType, crate::Error>,
+) -> Result<RootConfigType, crate::Error> {
+    let config = load_from_file_with_options(config_file_path, load_options).await?
+        .ok_or_else(|| crate::Error::ConfigFileNotFound {
+            path: config_file_path.to_path_buf(),
+            hint: "only existing config files may be reloaded".to_string(),
+        })?;
+    merge(config)
+}
+
+
+#[cfg(all(test, feature = "ron"))]
+mod tests {
+    use super::*;
+    use crate::test_commons::warnings_fixtures::{capture_warnings, captured_warnings};
+    use crate::testkit::*;
+    use crate::save_to_file;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn reload_on_sighup_test() {
+        capture_warnings();
+        let config_path = temp_config_path("ron");
+        let config = |sink| AppRootConfig { log_sub_config: LogConfig { sink: Some(sink) } };
+        save_to_file(&config(Dummy::Null), "", &config_path).await.unwrap();
+        // the command line took precedence over the sink: the merge must be kept in reloads
+        let merge = |mut config: AppRootConfig| {
+            config.log_sub_config.sink = Some(Dummy::StdError);
+            Ok(config)
+        };
+        let mut subscription = reload_on_sighup::<AppRootConfig>(&config_path, &LoadOptions::default(), merge).await
+            .expect("Reloading on SIGHUP couldn't be set up");
+        assert_eq!(**subscription.borrow_and_update(), config(Dummy::StdError), "The initial config should have been merged");
+
+        async fn hangup_and_wait(subscription: &mut ConfigSubscription<AppRootConfig>) -> Result<(), tokio::time::error::Elapsed> {
+            let status = std::process::Command::new("kill").args(["-HUP", &std::process::id().to_string()]).status().unwrap();
+            assert!(status.success(), "SIGHUP couldn't be sent");
+            tokio::time::timeout(Duration::from_secs(1), subscription.changed()).await
+                .map(|changed| changed.expect("The reloader is gone"))
+        }
+
+        // broken files keep the previous config
+        std::fs::write(&config_path, "(log_sub_config: (").unwrap();
+        assert!(hangup_and_wait(&mut subscription).await.is_err(), "Broken configs shouldn't be published");
+        assert_eq!(**subscription.borrow(), config(Dummy::StdError), "The previous config should have been kept");
+
+        save_to_file(&AppRootConfig::default(), "", &config_path).await.unwrap();
+        hangup_and_wait(&mut subscription).await.expect("Timed out waiting for the reloaded config");
+        assert_eq!(**subscription.borrow_and_update(), config(Dummy::StdError), "The merge should have been reapplied to the reloaded config");
+        let reported = captured_warnings(&format!("{config_path:?}")).into_iter()
+            .map(|warning| warning.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(reported, [LoadWarningKind::FailedReload, LoadWarningKind::Reloaded], "Both reloads should have been given to the warnings sink");
+        _ = std::fs::remove_file(&config_path);
+    }
+}
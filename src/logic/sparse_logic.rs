@@ -0,0 +1,164 @@
+//! Sparse serialization of the configs: struct fields equal to their `Default` values -- or holding `None`s -- are left out,
+//! done while serializing -- so it works for any format. Loading such files relies on `#[serde(default)]`
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// A [Serialize] wrapper that skips the struct fields of `value` (recursively) that, if `sparse`, are equal to the ones in `defaults`
+/// -- the latter being the generic (JSON) representation of the `Default` value, used just for the comparisons -- and, if `omit_none`,
+/// the ones holding `None`s. `None`s are only left out where the default is also `None` (or unknown, like inside sequences):
+/// a missing field would load back as its default, so a `None` overriding a `Some` default is kept
+pub(crate) struct Sparse<'a, T: ?Sized> {
+    value: &'a T,
+    defaults: Option<&'a serde_json::Value>,
+    rules: SparseRules,
+}
+
+/// Which fields [Sparse] leaves out
+#[derive(Clone, Copy)]
+pub(crate) struct SparseRules {
+    /// The ones equal to their defaults
+    pub(crate) sparse: bool,
+    /// The ones holding `None`s, where the default is `None` as well
+    pub(crate) omit_none: bool,
+}
+
+impl<'a, T: ?Sized> Sparse<'a, T> {
+    pub(crate) fn new(value: &'a T, defaults: Option<&'a serde_json::Value>, rules: SparseRules) -> Self {
+        Self { value, defaults, rules }
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for Sparse<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(SparseSerializer { inner: serializer, defaults: self.defaults, rules: self.rules })
+    }
+}
+
+/// A [Serializer] wrapper that only intercepts structs -- see [Sparse]
+struct SparseSerializer<'a, S> {
+    inner: S,
+    defaults: Option<&'a serde_json::Value>,
+    rules: SparseRules,
+}
+
+macro_rules! forward_serialize {
+    ($($method:ident($type:ty)),* $(,)?) => {
+        $(
+            fn $method(self, v: $type) -> Result<Self::Ok, Self::Error> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'a, S: Serializer> Serializer for SparseSerializer<'a, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = S::SerializeSeq;
+    type SerializeTuple = S::SerializeTuple;
+    type SerializeTupleStruct = S::SerializeTupleStruct;
+    type SerializeTupleVariant = S::SerializeTupleVariant;
+    type SerializeMap = S::SerializeMap;
+    type SerializeStruct = SparseStruct<'a, S::SerializeStruct>;
+    type SerializeStructVariant = S::SerializeStructVariant;
+
+    forward_serialize!(
+        serialize_bool(bool), serialize_char(char), serialize_str(&str), serialize_bytes(&[u8]),
+        serialize_i8(i8), serialize_i16(i16), serialize_i32(i32), serialize_i64(i64), serialize_i128(i128),
+        serialize_u8(u8), serialize_u16(u16), serialize_u32(u32), serialize_u64(u64), serialize_u128(u128),
+        serialize_f32(f32), serialize_f64(f64),
+    );
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_none()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_some(value)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, variant_index: u32, variant: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, name: &'static str, variant_index: u32, variant: &'static str, value: &T) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_newtype_variant(name, variant_index, variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        self.inner.serialize_seq(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.inner.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.inner.serialize_tuple_struct(name, len)
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.inner.serialize_tuple_variant(name, variant_index, variant, len)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        self.inner.serialize_map(len)
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(SparseStruct { inner: self.inner.serialize_struct(name, len)?, defaults: self.defaults, rules: self.rules })
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.inner.serialize_struct_variant(name, variant_index, variant, len)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// Skips the fields equal to their defaults or holding `None`s -- see [Sparse]
+struct SparseStruct<'a, S> {
+    inner: S,
+    defaults: Option<&'a serde_json::Value>,
+    rules: SparseRules,
+}
+
+impl<S: SerializeStruct> SerializeStruct for SparseStruct<'_, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error> {
+        let field_defaults = self.defaults.and_then(|defaults| defaults.get(key));
+        let SparseRules { sparse, omit_none } = self.rules;
+        // `None`s are the only field values represented as null -- besides the rare unit ones
+        let is_omitted = ((sparse && field_defaults.is_some()) || omit_none) && serde_json::to_value(value)
+            .is_ok_and(|field_value| (sparse && Some(&field_value) == field_defaults)
+                                     || (omit_none && field_value.is_null() && field_defaults.is_none_or(serde_json::Value::is_null)));
+        match is_omitted {
+            true => self.inner.skip_field(key),
+            false => self.inner.serialize_field(key, &Sparse { value, defaults: field_defaults, rules: self.rules }),
+        }
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
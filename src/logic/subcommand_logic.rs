@@ -0,0 +1,194 @@
+//! A `config` subcommand group applications may attach to their own CLI,
+//! offering common operations over the program's config file
+
+use std::fmt::Debug;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use crate::logic::config_logic::{serialize_for_file, tail_docs_for};
+use crate::{convert_config, load_from_file, reset_config_file, MeldOptions, OgreRootConfig, SaveOptions};
+
+/// Operations over the program's config file, to be used as a subcommand -- like this:
+/// ```nocompile
+///   #[derive(clap::Subcommand)]
+///   enum MyAppCommands {
+///       #[clap(subcommand)]
+///       Config(ogre_config_meld::ConfigSubcommand),
+///       // ...
+///   }
+/// ```
+/// then delegated to [handle_config_subcommand()]
+#[derive(clap::Subcommand, Clone, Debug, PartialEq)]
+pub enum ConfigSubcommand {
+    /// Shows the configuration in effect, as loaded from the config file -- or the defaults, if the file doesn't exist
+    Show,
+    /// Shows the path of the config file in use
+    Path,
+    /// Shows the default configuration, along with its docs
+    Default,
+    /// Regenerates the config file with the default values & docs, backing up the existing one
+    Reset,
+    /// Converts a config file into the format implied by the output's extension -- like `config convert app.config.ron app.config.yaml`
+    Convert {
+        input: PathBuf,
+        output: PathBuf,
+    },
+}
+
+/// Executes the given `config_subcommand` over the config file at `config_file_path`
+/// (see [crate::get_config_file_path()]), writing the outcome to stdout.
+/// Returns the configuration the subcommand operated on -- `None` for [ConfigSubcommand::Path] & [ConfigSubcommand::Convert].
+pub async fn handle_config_subcommand<RootConfigType: OgreRootConfig>(
+    config_subcommand: &ConfigSubcommand,
+    config_file_path: impl AsRef<Path> + Debug,
+    tail_docs: &str,
+) -> Result<Option<RootConfigType>, crate::Error> {
+    handle_config_subcommand_into(config_subcommand, config_file_path, tail_docs_for::<RootConfigType>(tail_docs), &mut io::stdout()).await
+}
+
+/// The logic behind [handle_config_subcommand()], writing the outcome to `out`
+async fn handle_config_subcommand_into<RootConfigType: OgreRootConfig>(
+    config_subcommand: &ConfigSubcommand,
+    config_file_path: impl AsRef<Path> + Debug,
+    tail_docs: &str,
+    out: &mut impl Write,
+) -> Result<Option<RootConfigType>, crate::Error> {
+    let output_err = |err| crate::Error::Io {
+        message: format!("Error outputting the results of the `config {config_subcommand:?}` subcommand"),
+        cause: err,
+    };
+    match config_subcommand {
+        ConfigSubcommand::Show => {
+            let config = load_from_file(&config_file_path).await?
+                .unwrap_or_default();
+            let txt_config = serialize_for_file(&config, "", &config_file_path, &SaveOptions::default())?;
+            writeln!(out, "{txt_config}").map_err(output_err)?;
+            Ok(Some(config))
+        },
+        ConfigSubcommand::Path => {
+            writeln!(out, "{}", config_file_path.as_ref().display()).map_err(output_err)?;
+            Ok(None)
+        },
+        ConfigSubcommand::Default => {
+            let default_config = RootConfigType::default();
+            let txt_config = serialize_for_file(&default_config, tail_docs, &config_file_path, &SaveOptions::default())?;
+            writeln!(out, "{txt_config}").map_err(output_err)?;
+            Ok(Some(default_config))
+        },
+        ConfigSubcommand::Reset => {
+            let backup_config_file_path = reset_config_file::<RootConfigType>(&config_file_path, tail_docs, &MeldOptions::default().backup_policy).await?;
+            write_reset_report(out, config_file_path.as_ref(), backup_config_file_path.as_deref()).map_err(output_err)?;
+            Ok(Some(RootConfigType::default()))
+        },
+        ConfigSubcommand::Convert { input, output } => {
+            convert_config::<RootConfigType>(input, output, tail_docs).await?;
+            writeln!(out, "Config file {} converted to {}", input.display(), output.display()).map_err(output_err)?;
+            Ok(None)
+        },
+    }
+}
+
+/// Tells the user the outcome of [reset_config_file()]
+pub(crate) fn write_reset_report(out: &mut impl Write, config_file_path: &Path, backup_config_file_path: Option<&Path>) -> io::Result<()> {
+    match backup_config_file_path {
+        Some(backup_config_file_path) => writeln!(out, "Config file {} reset to the defaults. The previous one was backed up to {}",
+                                                  config_file_path.display(), backup_config_file_path.display()),
+        None => writeln!(out, "Config file {} didn't exist and was created with the defaults", config_file_path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::*;
+    use crate::save_to_file;
+    #[cfg(feature = "ron")]
+    use crate::config_file_backups;
+    use clap::Parser;
+
+    /// An application having the `config` subcommand group attached
+    #[derive(clap::Parser, Debug)]
+    struct AppCmdLineOptions {
+        #[clap(subcommand)]
+        command: AppCommand,
+    }
+    #[derive(clap::Subcommand, Debug)]
+    enum AppCommand {
+        #[clap(subcommand)]
+        Config(ConfigSubcommand),
+    }
+
+    /// Runs the `config` subcommand parsed from `args`, returning the outcome & the output
+    async fn run(args: &[&str], config_file_path: &Path) -> (Option<AppRootConfig>, String) {
+        let AppCommand::Config(config_subcommand) = AppCmdLineOptions::parse_from(args).command;
+        let mut out = Vec::new();
+        let config = handle_config_subcommand_into(&config_subcommand, config_file_path, "I am the docs", &mut out).await.unwrap();
+        (config, String::from_utf8(out).unwrap())
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn show() {
+        let config_path = temp_config_path("yaml");
+        let expected_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdError) } };
+        save_to_file(&expected_config, "", &config_path).await.unwrap();
+        let (observed_config, output) = run(&["app", "config", "show"], &config_path).await;
+        assert_eq!(observed_config, Some(expected_config), "Wrong config shown");
+        assert!(output.contains("sink: stderror"), "The config wasn't shown. Output: '{output}'");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn path() {
+        let config_path = temp_config_path("ron");
+        let (observed_config, output) = run(&["app", "config", "path"], &config_path).await;
+        assert_eq!(observed_config, None, "No config should be returned for `config path`");
+        assert_eq!(output.trim(), config_path.to_string_lossy(), "Wrong path shown");
+        assert!(!config_path.exists(), "`config path` should not create the config file");
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn default() {
+        let config_path = temp_config_path("ron");
+        let (observed_config, output) = run(&["app", "config", "default"], &config_path).await;
+        assert_eq!(observed_config, Some(AppRootConfig::default()), "Wrong default config");
+        assert!(output.contains("log_sub_config"), "The default config wasn't shown. Output: '{output}'");
+        assert!(output.contains("I am the docs"), "The docs weren't shown. Output: '{output}'");
+        assert!(!config_path.exists(), "`config default` should not create the config file");
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn reset() {
+        let config_path = temp_config_path("ron");
+        let old_config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+        save_to_file(&old_config, "", &config_path).await.unwrap();
+        let old_config_txt = std::fs::read_to_string(&config_path).unwrap();
+
+        let (observed_config, output) = run(&["app", "config", "reset"], &config_path).await;
+        let backup_path = config_file_backups(&config_path).await.unwrap().pop().expect("The previous config file should have been backed up");
+        assert_eq!(observed_config, Some(AppRootConfig::default()), "Wrong reset config");
+        assert!(output.contains(&*backup_path.to_string_lossy()), "The backup path wasn't shown. Output: '{output}'");
+        assert_eq!(std::fs::read_to_string(&backup_path).unwrap(), old_config_txt, "The backup doesn't hold the previous config");
+        let reset_config: AppRootConfig = load_from_file(&config_path).await.unwrap().unwrap();
+        assert_eq!(reset_config, AppRootConfig::default(), "The config file wasn't reset to the defaults");
+        _ = std::fs::remove_file(&config_path);
+        config_file_backups(&config_path).await.unwrap().iter().for_each(|backup_path| _ = std::fs::remove_file(backup_path));
+    }
+
+    #[cfg(all(feature = "ron", feature = "yaml"))]
+    #[tokio::test]
+    async fn convert() {
+        let (ron_path, yaml_path) = (temp_config_path("ron"), temp_config_path("yaml"));
+        let config = AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } };
+        save_to_file(&config, "", &ron_path).await.unwrap();
+        let (observed_config, output) = run(&["app", "config", "convert", &ron_path.to_string_lossy(), &yaml_path.to_string_lossy()], &ron_path).await;
+        assert_eq!(observed_config, None, "No config should be returned for `config convert`");
+        assert!(output.contains("converted to"), "The conversion wasn't reported. Output: '{output}'");
+        assert_eq!(load_from_file(&yaml_path).await.unwrap(), Some(config), "The YAML file doesn't hold the converted config");
+        assert!(std::fs::read_to_string(&yaml_path).unwrap().contains("# I am the docs"), "The docs should end the converted file");
+        _ = std::fs::remove_file(&ron_path);
+        _ = std::fs::remove_file(&yaml_path);
+    }
+}
@@ -0,0 +1,202 @@
+//! The human-friendly (de)serialization of the [HumanDuration] & [Timestamp] config field types
+
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Visitor;
+#[cfg(feature = "humantime")]
+use crate::HumanDuration;
+use crate::Timestamp;
+
+#[cfg(feature = "humantime")]
+impl FromStr for HumanDuration {
+    type Err = String;
+
+    fn from_str(txt: &str) -> Result<Self, Self::Err> {
+        humantime::parse_duration(txt.trim())
+            .map(HumanDuration)
+            .map_err(|err| format!("invalid duration '{txt}': {err} -- expected something like \"90s\", \"5m\" or \"1h 30m\""))
+    }
+}
+
+#[cfg(feature = "humantime")]
+impl Display for HumanDuration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", humantime::format_duration(self.0))
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+
+    fn from_str(txt: &str) -> Result<Self, Self::Err> {
+        chrono::DateTime::parse_from_rfc3339(txt.trim())
+            .map(Timestamp)
+            .map_err(|err| format!("invalid timestamp '{txt}': {err} -- expected an ISO 8601 one, like \"2024-03-15T10:30:00Z\""))
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+    }
+}
+
+#[cfg(feature = "humantime")]
+impl Deref for HumanDuration {
+    type Target = std::time::Duration;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Deref for Timestamp {
+    type Target = chrono::DateTime<chrono::FixedOffset>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "humantime")]
+impl From<std::time::Duration> for HumanDuration {
+    fn from(duration: std::time::Duration) -> Self {
+        HumanDuration(duration)
+    }
+}
+
+#[cfg(feature = "humantime")]
+impl From<HumanDuration> for std::time::Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0
+    }
+}
+
+impl<Tz: chrono::TimeZone> From<chrono::DateTime<Tz>> for Timestamp {
+    fn from(date_time: chrono::DateTime<Tz>) -> Self {
+        Timestamp(date_time.fixed_offset())
+    }
+}
+
+impl From<Timestamp> for chrono::DateTime<chrono::FixedOffset> {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+#[cfg(feature = "humantime")]
+impl Serialize for HumanDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "humantime")]
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor::<HumanDuration>::new("a duration, like \"90s\" or \"1h 30m\""))
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor::<Timestamp>::new("an ISO 8601 timestamp, like \"2024-03-15T10:30:00Z\""))
+    }
+}
+
+/// Deserializes `T` from the strings it parses from, reporting parsing failures as custom errors
+struct FromStrVisitor<T> {
+    expecting: &'static str,
+    _type: std::marker::PhantomData<T>,
+}
+
+impl<T> FromStrVisitor<T> {
+    fn new(expecting: &'static str) -> Self {
+        Self { expecting, _type: std::marker::PhantomData }
+    }
+}
+
+impl<T: FromStr<Err=String>> Visitor<'_> for FromStrVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, txt: &str) -> Result<Self::Value, E> {
+        txt.parse().map_err(E::custom)
+    }
+}
+
+#[cfg(all(feature = "schema", feature = "humantime"))]
+impl schemars::JsonSchema for HumanDuration {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "HumanDuration".into()
+    }
+
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({ "type": "string", "description": "A duration, like \"90s\", \"5m\" or \"1h 30m\"" })
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Timestamp {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Timestamp".into()
+    }
+
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({ "type": "string", "format": "date-time" })
+    }
+}
+
+
+#[cfg(all(test, feature = "ron", feature = "yaml", feature = "humantime"))]
+mod tests {
+    use super::*;
+    use crate::{config_from_str, config_to_string, OgreRootConfig, SerdeFormat};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct TimedConfig {
+        idle_timeout: HumanDuration,
+        valid_until: Option<Timestamp>,
+    }
+    impl OgreRootConfig for TimedConfig {}
+
+    #[test]
+    fn round_trips() {
+        let expected_config = TimedConfig {
+            idle_timeout: HumanDuration(std::time::Duration::from_secs(90)),
+            valid_until: Some("2024-03-15T10:30:00-03:00".parse().unwrap()),
+        };
+        for (format, txt_config) in [
+            (SerdeFormat::Ron, r#"(idle_timeout: "90s", valid_until: Some("2024-03-15T10:30:00-03:00"))"#),
+            (SerdeFormat::Yaml, "idle_timeout: 90s\nvalid_until: 2024-03-15T10:30:00-03:00\n"),
+        ] {
+            let config = config_from_str::<TimedConfig>(txt_config, format).unwrap_or_else(|err| panic!("{format:?} config not loaded: {err}"));
+            assert_eq!(config, expected_config, "Wrong {format:?} config loaded");
+            let written_txt = config_to_string(&config, format, "").unwrap();
+            assert!(written_txt.contains("1m 30s") && written_txt.contains("2024-03-15T10:30:00-03:00"), "Unexpected {format:?} representation: '{written_txt}'");
+            assert_eq!(config_from_str::<TimedConfig>(&written_txt, format).unwrap(), expected_config, "The written {format:?} config didn't load back the same");
+        }
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!("1h 30m".parse::<HumanDuration>(), Ok(HumanDuration(std::time::Duration::from_secs(5400))), "Combined units weren't parsed");
+        assert_eq!("250ms".parse::<HumanDuration>().map(|duration| duration.as_millis()), Ok(250), "Sub-second units weren't parsed");
+        assert!("90".parse::<HumanDuration>().is_err_and(|err| err.contains("90s")), "Durations without units should be refused with a hint");
+        assert_eq!("2024-03-15T13:30:00Z".parse::<Timestamp>().map(|timestamp| timestamp.to_string()), Ok("2024-03-15T13:30:00Z".to_string()), "UTC timestamps should keep the 'Z'");
+        assert!("15/03/2024".parse::<Timestamp>().is_err(), "Non ISO 8601 timestamps should be refused");
+        let err = config_from_str::<TimedConfig>("idle_timeout: forever\n", SerdeFormat::Yaml).expect_err("Invalid durations should be refused");
+        assert!(err.to_string().contains("forever"), "The offending value should be reported. Got {err}");
+    }
+}
@@ -0,0 +1,288 @@
+//! Non-fatal findings while loading config files -- gathered into a single [LoadWarnings] list, so apps may report them together --
+//! & the ones found while running, given to the sink set with [set_warnings_sink()]
+
+#[cfg(feature = "async")]
+use std::fmt::Debug;
+#[cfg(feature = "async")]
+use std::path::Path;
+use std::sync::{PoisonError, RwLock};
+use serde::Serialize;
+use serde_json::Value;
+#[cfg(feature = "async")]
+use crate::logic::config_logic::load_text_and_config_from_file;
+#[cfg(feature = "ron")]
+use crate::logic::generic_value_logic::generic_from_ron;
+#[cfg(feature = "yaml")]
+use crate::logic::generic_value_logic::generic_from_yaml;
+use crate::logic::interpolation_logic::interpolate_env_vars;
+use crate::{DeprecatedField, EnvInterpolation, LoadOptions, LoadWarning, LoadWarningKind, LoadWarnings, OgreRootConfig, SerdeFormat};
+#[cfg(feature = "yaml")]
+use crate::YamlMultiDocuments;
+
+/// Same as [crate::load_from_file_with_options()], but also returning the [LoadWarnings] found along the way:
+/// fields unknown to `RootConfigType` (whose values were ignored), YAML documents ignored as per [YamlMultiDocuments::FirstOnly]
+/// references to undefined environment variables kept as per [EnvInterpolation::KeepUndefined]
+/// & the [OgreRootConfig::deprecated_fields()] present in the file.
+/// Returns `Ok(None)` if the file doesn't exist.
+#[cfg(feature = "async")]
+pub async fn load_with_warnings<RootConfigType: OgreRootConfig>(
+    config_file_path: impl AsRef<Path> + Debug,
+    load_options: &LoadOptions,
+) -> Result<Option<(RootConfigType, LoadWarnings)>, crate::Error> {
+    Ok(load_text_and_config_from_file(config_file_path, load_options).await?
+        .map(|(txt_config, format, config)| {
+            let warnings = collect_load_warnings(&txt_config, format, load_options, &config, RootConfigType::deprecated_fields());
+            (config, warnings)
+        }))
+}
+
+/// Receives the [LoadWarning]s found while running -- see [set_warnings_sink()]
+type WarningsSink = Box<dyn Fn(&LoadWarning) + Send + Sync>;
+
+static WARNINGS_SINK: RwLock<Option<WarningsSink>> = RwLock::new(None);
+
+/// Sets where the [LoadWarning]s found while running go -- like config files that couldn't be created or reloaded, as told
+/// by their [LoadWarningKind]s -- so apps may route them into their own logs. Without a sink, they are printed to stderr.
+/// The sink is process-wide: setting another one replaces it
+pub fn set_warnings_sink(sink: impl Fn(&LoadWarning) + Send + Sync + 'static) {
+    *WARNINGS_SINK.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(sink));
+}
+
+/// Gives a warning found while running to the sink set with [set_warnings_sink()] -- or prints it to stderr, if there is none
+pub(crate) fn emit_warning(kind: LoadWarningKind, field_path: &str, message: String) {
+    let warning = LoadWarning { kind, field_path: field_path.to_string(), message };
+    match WARNINGS_SINK.read().unwrap_or_else(PoisonError::into_inner).as_ref() {
+        Some(sink) => sink(&warning),
+        None if warning.kind.is_notice() => eprintln!("NOTICE: {}", warning.message),
+        None => eprintln!("WARNING: {}", warning.message),
+    }
+}
+
+impl LoadWarnings {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&LoadWarning> {
+        self.0.iter()
+    }
+
+    /// The warnings of the given `kind` only
+    pub fn of_kind(&self, kind: LoadWarningKind) -> impl Iterator<Item=&LoadWarning> {
+        self.0.iter().filter(move |warning| warning.kind == kind)
+    }
+
+    fn push(&mut self, kind: LoadWarningKind, field_path: &str, message: String) {
+        self.0.push(LoadWarning { kind, field_path: field_path.to_string(), message });
+    }
+}
+
+impl LoadWarningKind {
+    /// Tells if warnings of this kind are just informative, rather than about something going wrong
+    pub fn is_notice(&self) -> bool {
+        matches!(self, LoadWarningKind::Reloaded | LoadWarningKind::RewriteSkipped)
+    }
+}
+
+impl IntoIterator for LoadWarnings {
+    type Item = LoadWarning;
+    type IntoIter = std::vec::IntoIter<LoadWarning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// The dotted paths of the fields in the `txt_config` the `config` was loaded from that are unknown to its types -- see [LoadWarningKind::UnknownField]
+pub(crate) fn unknown_fields<RootConfigType: OgreRootConfig>(txt_config: &str, format: SerdeFormat, config: &RootConfigType) -> Vec<String> {
+    collect_load_warnings(txt_config, format, &LoadOptions::default(), config, RootConfigType::deprecated_fields())
+        .of_kind(LoadWarningKind::UnknownField)
+        .map(|warning| warning.field_path.clone())
+        .collect()
+}
+
+/// Inspects the `txt_config` the `config` was successfully loaded from, gathering the [LoadWarnings] of the load.
+/// The warnings are based on the generic representation of the text, so values that can't be represented that way are not inspected
+fn collect_load_warnings(txt_config: &str, format: SerdeFormat, load_options: &LoadOptions, config: &impl Serialize, deprecated_fields: &[DeprecatedField]) -> LoadWarnings {
+    let mut warnings = LoadWarnings::default();
+    let documents: Vec<Value> = match format {
+        #[cfg(feature = "ron")]
+        SerdeFormat::Ron => generic_from_ron(txt_config).into_iter().collect(),
+        #[cfg(feature = "yaml")]
+        SerdeFormat::Yaml => {
+            // repeated keys were either refused or taken as the last of their values
+            let mut documents = crate::logic::yaml_keys_logic::yaml_documents(txt_config, false)
+                .map(|documents| documents.into_iter().map(generic_from_yaml).collect::<Vec<_>>())
+                .unwrap_or_default();
+            if documents.len() > 1 && load_options.yaml_multi_documents == YamlMultiDocuments::FirstOnly {
+                warnings.push(LoadWarningKind::IgnoredYamlDocuments, "",
+                              format!("{} `---` separated document(s) after the first were ignored", documents.len() - 1));
+                documents.truncate(1);
+            }
+            documents
+        },
+    };
+    let Ok(loaded_config) = serde_json::to_value(config) else {
+        return warnings
+    };
+    for document in &documents {
+        for deprecated_field in deprecated_fields {
+            let pointer = format!("/{}", deprecated_field.path.replace('~', "~0").replace('/', "~1").replace('.', "/"));
+            if document.pointer(&pointer).is_some_and(|value| !value.is_null()) {
+                warnings.push(LoadWarningKind::DeprecatedField, deprecated_field.path,
+                              format!("the field `{}` is deprecated: {}", deprecated_field.path, deprecated_field.replacement));
+            }
+        }
+        let mut unknown_fields = LoadWarnings::default();
+        push_unknown_fields(&mut unknown_fields, "", document, &loaded_config);
+        // deprecated fields (or their parents) may no longer be in the config types -- having been reported already
+        warnings.0.extend(unknown_fields.into_iter()
+            .filter(|unknown_field| !deprecated_fields.iter().any(|deprecated_field| deprecated_field.path == unknown_field.field_path
+                                                                                     || deprecated_field.path.starts_with(&format!("{}.", unknown_field.field_path)))));
+        if load_options.env_interpolation == EnvInterpolation::KeepUndefined {
+            push_undefined_env_vars(&mut warnings, "", document);
+        }
+    }
+    warnings
+}
+
+/// Warns about the fields of `document` (as read from the file) that are missing from the `loaded` config -- as they were ignored.
+/// Fields holding no value are not reported, as they may be omitted when serializing the loaded config
+fn push_unknown_fields(warnings: &mut LoadWarnings, path: &str, document: &Value, loaded: &Value) {
+    match (document, loaded) {
+        (Value::Object(document_fields), Value::Object(loaded_fields)) => {
+            for (field_name, document_value) in document_fields {
+                let field_path = field_path(path, field_name);
+                match loaded_fields.get(field_name) {
+                    Some(loaded_value) => push_unknown_fields(warnings, &field_path, document_value, loaded_value),
+                    None if document_value.is_null() => (),
+                    None => warnings.push(LoadWarningKind::UnknownField, &field_path,
+                                          format!("the field `{field_path}` is unknown to the config types -- its value was ignored")),
+                }
+            }
+        },
+        (Value::Array(document_elements), Value::Array(loaded_elements)) => {
+            for (i, (document_element, loaded_element)) in document_elements.iter().zip(loaded_elements).enumerate() {
+                push_unknown_fields(warnings, &field_path(path, &i.to_string()), document_element, loaded_element);
+            }
+        },
+        _ => (),
+    }
+}
+
+/// Warns about the string values of `document` referencing undefined environment variables -- the first one of each value
+fn push_undefined_env_vars(warnings: &mut LoadWarnings, path: &str, document: &Value) {
+    match document {
+        Value::String(text) => {
+            if let Err(var_name) = interpolate_env_vars(text, EnvInterpolation::FailOnUndefined) {
+                warnings.push(LoadWarningKind::UndefinedEnvVar, path,
+                              format!("the value of `{path}` references the undefined environment variable `{var_name}` -- it was kept as-is"));
+            }
+        },
+        Value::Object(fields) => fields.iter()
+            .for_each(|(field_name, value)| push_undefined_env_vars(warnings, &field_path(path, field_name), value)),
+        Value::Array(elements) => elements.iter().enumerate()
+            .for_each(|(i, element)| push_undefined_env_vars(warnings, &field_path(path, &i.to_string()), element)),
+        _ => (),
+    }
+}
+
+/// The dotted path of `field_name` inside the field at `path`
+fn field_path(path: &str, field_name: &str) -> String {
+    if path.is_empty() { field_name.to_string() } else { format!("{path}.{field_name}") }
+}
+
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use super::*;
+    use crate::test_commons::warnings_fixtures::{capture_warnings, captured_warnings};
+    use crate::testkit::*;
+
+    #[test]
+    fn warnings_sink() {
+        capture_warnings();
+        emit_warning(LoadWarningKind::FailedReload, "log_sub_config", "the config file `warnings_sink` couldn't be reloaded".to_string());
+        let warnings = captured_warnings("`warnings_sink`");
+        assert_eq!(warnings, [LoadWarning { kind: LoadWarningKind::FailedReload, field_path: "log_sub_config".to_string(), message: "the config file `warnings_sink` couldn't be reloaded".to_string() }],
+                   "The warning should have been given to the sink");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn warnings_of_all_kinds() {
+        let config_path = temp_config_path("yaml");
+        std::fs::write(&config_path, "log_sub_config:\n  sink: stdout\n  colors: true\nrotation: ${CLI_CONFIG_UNDEFINED_VAR}\n---\nlog_sub_config:\n  sink: null\n").unwrap();
+        let mut load_options = LoadOptions { yaml_multi_documents: YamlMultiDocuments::FirstOnly, ..LoadOptions::default() };
+        load_options.env_interpolation = EnvInterpolation::KeepUndefined;
+        let (config, warnings) = load_with_warnings::<AppRootConfig>(&config_path, &load_options).await
+            .expect("Loading the config failed")
+            .expect("The config file should exist");
+        assert_eq!(config, AppRootConfig { log_sub_config: LogConfig { sink: Some(Dummy::StdOut) } }, "Wrong config loaded");
+        let mut field_paths = warnings.of_kind(LoadWarningKind::UnknownField).map(|warning| warning.field_path.as_str()).collect::<Vec<_>>();
+        field_paths.sort();
+        assert_eq!(field_paths, ["log_sub_config.colors", "rotation"], "Wrong unknown fields reported");
+        assert_eq!(warnings.of_kind(LoadWarningKind::IgnoredYamlDocuments).count(), 1, "The ignored document should have been reported");
+        let undefined_var = warnings.of_kind(LoadWarningKind::UndefinedEnvVar).next()
+            .expect("The undefined environment variable should have been reported");
+        assert_eq!(undefined_var.field_path, "rotation", "Wrong path for the undefined environment variable");
+        assert!(undefined_var.message.contains("CLI_CONFIG_UNDEFINED_VAR"), "The variable name should be in the message. Got {}", undefined_var.message);
+        assert_eq!(warnings.len(), 4, "Unexpected warnings: {warnings:?}");
+
+        // clean loads have no warnings
+        std::fs::write(&config_path, "log_sub_config:\n  sink: stdout\n").unwrap();
+        let (_, warnings) = load_with_warnings::<AppRootConfig>(&config_path, &load_options).await.unwrap().unwrap();
+        assert!(warnings.is_empty(), "No warnings were expected. Got {warnings:?}");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(feature = "ron")]
+    #[tokio::test]
+    async fn ron_unknown_fields() {
+        let config_path = temp_config_path("ron");
+        std::fs::write(&config_path, "(log_sub_config: (sink: Some(stderror), level: 3), verbose: None)").unwrap();
+        let (_, warnings) = load_with_warnings::<AppRootConfig>(&config_path, &LoadOptions::default()).await.unwrap().unwrap();
+        let field_paths = warnings.iter().map(|warning| (warning.kind, warning.field_path.as_str())).collect::<Vec<_>>();
+        assert_eq!(field_paths, [(LoadWarningKind::UnknownField, "log_sub_config.level")], "Only the unknown field holding a value should be reported");
+        _ = std::fs::remove_file(&config_path);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn deprecated_fields() {
+
+        /// A config that went through some evolution
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(default)]
+        struct EvolvedConfig {
+            sink: Option<String>,
+            log_file: Option<String>,
+        }
+        impl OgreRootConfig for EvolvedConfig {
+            fn deprecated_fields() -> &'static [DeprecatedField] {
+                &[
+                    DeprecatedField { path: "log_file", replacement: "use `sink` instead" },
+                    DeprecatedField { path: "legacy.verbose", replacement: "use the `--verbose` command line option instead" },
+                ]
+            }
+        }
+
+        let config_path = temp_config_path("yaml");
+        std::fs::write(&config_path, "log_file: /var/log/app.log\nlegacy:\n  verbose: true\n").unwrap();
+        let (config, warnings) = load_with_warnings::<EvolvedConfig>(&config_path, &LoadOptions::default()).await.unwrap().unwrap();
+        assert_eq!(config.log_file.as_deref(), Some("/var/log/app.log"), "Deprecated fields should still be loaded");
+        let reported = warnings.iter().map(|warning| (warning.kind, warning.field_path.as_str())).collect::<Vec<_>>();
+        assert_eq!(reported, [(LoadWarningKind::DeprecatedField, "log_file"), (LoadWarningKind::DeprecatedField, "legacy.verbose")],
+                   "Removed deprecated fields shouldn't also be reported as unknown");
+        assert!(warnings.iter().next().is_some_and(|warning| warning.message.contains("use `sink` instead")), "The replacement should have been suggested. Got {warnings:?}");
+
+        std::fs::write(&config_path, "sink: /var/log/app.log\n").unwrap();
+        let (_, warnings) = load_with_warnings::<EvolvedConfig>(&config_path, &LoadOptions::default()).await.unwrap().unwrap();
+        assert!(warnings.is_empty(), "Configs without deprecated fields should have no warnings. Got {warnings:?}");
+        _ = std::fs::remove_file(&config_path);
+    }
+}
@@ -0,0 +1,167 @@
+//! Hot reloading of config files -- behind the `watch` feature:
+//! changes to the config file are picked up (and parsed) as they happen, without restarting the program
+
+use crate::logic::warnings_logic::emit_warning;
+use crate::{load_from_file_with_options, ConfigSubscription, LoadOptions, LoadWarningKind, OgreRootConfig};
+use notify::Watcher;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+/// Options for [watch_config()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchOptions {
+    /// Changes are only reloaded after the file is left alone for this long -- so the bursts of writes editors do
+    /// (or partially written files) don't cause several reloads. Defaults to 200ms.
+    pub debounce: Duration,
+    /// How the config file is (re)loaded
+    pub load_options: LoadOptions,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(200),
+            load_options: LoadOptions::default(),
+        }
+    }
+}
+
+/// Keeps watching the config file while alive -- see [watch_config()]
+pub struct WatchHandle {
+    _watcher: notify::RecommendedWatcher,
+    reloader: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.reloader.abort();
+    }
+}
+
+/// Loads the (existing) config file at `config_file_path` and keeps watching it for changes, for as long as the returned
+/// [WatchHandle] is kept: the [ConfigSubscription] gets every new config that parses -- on failures, the last good config is kept
+/// & a warning is issued. The file's directory is watched, rather than the file itself, so the atomic renames editors
+/// (and [crate::SaveOptions::durable]) do are picked up.
+///
+/// Must be called within a `tokio` runtime.
+pub async fn watch_config<RootConfigType: OgreRootConfig + Send + Sync + 'static>(
+    config_file_path: impl AsRef<Path> + Debug,
+    watch_options: &WatchOptions,
+) -> Result<(ConfigSubscription<RootConfigType>, WatchHandle), crate::Error> {
+    let config_file_path = config_file_path.as_ref().to_path_buf();
+    let config: RootConfigType = load_from_file_with_options(&config_file_path, &watch_options.load_options).await?
+        .ok_or_else(|| crate::Error::ConfigFileNotFound {
+            path: config_file_path.clone(),
+            hint: "only existing config files may be watched".to_string(),
+        })?;
+    let (config_sender, config_subscription) = watch::channel(Arc::new(config));
+
+    let config_file_name = config_file_path.file_name().map(|file_name| file_name.to_os_string());
+    let (events_sender, events_receiver) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let concerns_config_file = event.is_ok_and(|event| event.paths.iter()
+            .any(|path| path.file_name().map(|file_name| file_name.to_os_string()) == config_file_name));
+        if concerns_config_file {
+            _ = events_sender.send(());
+        }
+    }).map_err(|err| watching_err(&config_file_path, err))?;
+    let watched_dir = match config_file_path.parent() {
+        Some(parent_dir) if !parent_dir.as_os_str().is_empty() => parent_dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    watcher.watch(&watched_dir, notify::RecursiveMode::NonRecursive)
+        .map_err(|err| watching_err(&config_file_path, err))?;
+
+    let reloader = tokio::spawn(reload_on_changes(config_file_path, watch_options.clone(), events_receiver, config_sender));
+    Ok((config_subscription, WatchHandle { _watcher: watcher, reloader }))
+}
+
+fn watching_err(config_file_path: &Path, err: notify::Error) -> crate::Error {
+    crate::Error::LoadingConfig {
+        message: format!("Error watching the config file {config_file_path:?} for changes"),
+        cause: Box::new(err),
+    }
+}
+
+/// Reloads the config file after each (debounced) burst of `change_events`, publishing it to `config_sender`
+/// -- until there are no more subscribers
+async fn reload_on_changes<RootConfigType: OgreRootConfig>(
+    config_file_path: PathBuf,
+    watch_options: WatchOptions,
+    mut change_events: mpsc::UnboundedReceiver<()>,
+    config_sender: watch::Sender<Arc<RootConfigType>>,
+) {
+    while change_events.recv().await.is_some() {
+        // debounce: wait for the changes to settle
+        loop {
+            match tokio::time::timeout(watch_options.debounce, change_events.recv()).await {
+                Ok(Some(())) => continue,
+                Ok(None) => return,
+                Err(_elapsed) => break,
+            }
+        }
+        match load_from_file_with_options::<RootConfigType>(&config_file_path, &watch_options.load_options).await {
+            Ok(Some(config)) => {
+                if config_sender.send(Arc::new(config)).is_err() {
+                    return;
+                }
+            },
+            Ok(None) => emit_warning(LoadWarningKind::FailedReload, "", format!("the watched config file {config_file_path:?} is gone -- keeping the last good config")),
+            Err(err) => emit_warning(LoadWarningKind::FailedReload, "", format!("the watched config file {config_file_path:?} couldn't be reloaded -- keeping the last good config: {err}")),
+        }
+    }
+}
+
+
+#[cfg(all(test, feature = "yaml"))]
+mod tests {
+    use super::*;
+    use crate::test_commons::warnings_fixtures::{capture_warnings, captured_warnings};
+    use crate::testkit::*;
+    use crate::{save_to_file, save_to_file_with_options, SaveOptions};
+
+    #[tokio::test]
+    async fn watch_config_test() {
+        capture_warnings();
+        let base_dir = temp_config_dir();
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let config_path = base_dir.join("app.config.yaml");
+        let config = |sink| AppRootConfig { log_sub_config: LogConfig { sink: Some(sink) } };
+        save_to_file(&config(Dummy::Null), "", &config_path).await.unwrap();
+        let watch_options = WatchOptions { debounce: Duration::from_millis(50), ..WatchOptions::default() };
+        let (mut subscription, _handle) = watch_config::<AppRootConfig>(&config_path, &watch_options).await
+            .expect("Watching the config failed");
+        assert_eq!(**subscription.borrow_and_update(), config(Dummy::Null), "Wrong initial config");
+
+        async fn next_config(subscription: &mut ConfigSubscription<AppRootConfig>) -> Arc<AppRootConfig> {
+            tokio::time::timeout(Duration::from_secs(5), subscription.changed()).await
+                .expect("Timed out waiting for the reloaded config")
+                .expect("The watcher is gone");
+            subscription.borrow_and_update().clone()
+        }
+
+        // in-place writes
+        save_to_file(&config(Dummy::StdOut), "", &config_path).await.unwrap();
+        assert_eq!(*next_config(&mut subscription).await, config(Dummy::StdOut), "In-place changes weren't reloaded");
+
+        // atomic renames
+        let durable = SaveOptions { durable: true, ..SaveOptions::default() };
+        save_to_file_with_options(&config(Dummy::StdError), "", &config_path, &durable).await.unwrap();
+        assert_eq!(*next_config(&mut subscription).await, config(Dummy::StdError), "Atomically renamed changes weren't reloaded");
+
+        // broken files keep the last good config
+        std::fs::write(&config_path, "log_sub_config: [unterminated").unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(!subscription.has_changed().unwrap(), "Broken configs shouldn't be published");
+        assert_eq!(**subscription.borrow(), config(Dummy::StdError), "The last good config should have been kept");
+        let warnings = captured_warnings(&format!("{config_path:?}"));
+        assert!(!warnings.is_empty() && warnings.iter().all(|warning| warning.kind == LoadWarningKind::FailedReload),
+                "The failed reload should have been warned about through the sink: {warnings:?}");
+        save_to_file(&config(Dummy::Null), "", &config_path).await.unwrap();
+        assert_eq!(*next_config(&mut subscription).await, config(Dummy::Null), "Fixed configs should be reloaded");
+        _ = std::fs::remove_dir_all(&base_dir);
+    }
+}
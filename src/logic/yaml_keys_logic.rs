@@ -0,0 +1,155 @@
+//! Structural detection of the keys repeated in YAML mappings -- which `serde_yaml` refuses with just an error message --
+//! done while parsing the documents into values, so the ones taking the last of the repeated values may still be loaded.
+//! See [crate::DuplicateKeys]
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::Formatter;
+use serde::de::{self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Mapping, Value};
+
+/// The first key found repeated in a mapping, given with its parent keys, `.` separated (like `log_sub_config.sink`),
+/// along with the (1-based) line of its repetition
+pub(crate) struct DuplicateKey {
+    pub(crate) key: String,
+    pub(crate) line: usize,
+}
+
+/// Parses the `---` separated documents of `txt_config` into values, taking the last of the values of repeated keys -- unless `refuse_duplicates`,
+/// where the first repeated key found is reported instead. Errors not due to repeated keys are given as-is, for the regular reporting
+pub(crate) fn yaml_documents(txt_config: &str, refuse_duplicates: bool) -> Result<Vec<Value>, Result<DuplicateKey, serde_yaml::Error>> {
+    let duplicate_key = RefCell::new(None);
+    let mut documents = vec![];
+    for document in serde_yaml::Deserializer::from_str(txt_config) {
+        let seed = KeysScanner { path: String::new(), refuse_duplicates, duplicate_key: &duplicate_key };
+        match seed.deserialize(document) {
+            Ok(value) => documents.push(value),
+            Err(err) => return Err(match duplicate_key.take() {
+                Some(key) => {
+                    // the error is located at the mapping holding the key
+                    let line = err.location()
+                        .map_or(0, |location| duplicate_key_line(txt_config, location.line(), location.column(), key.rsplit('.').next().unwrap_or_default()));
+                    Ok(DuplicateKey { key, line })
+                },
+                None => Err(err),
+            }),
+        }
+    }
+    Ok(documents)
+}
+
+/// The (1-based) line of the second occurrence of `key` in the block mapping starting at `mapping_line` & `mapping_column`
+/// -- or `mapping_line` itself, if it can't be found (like in flow mappings)
+fn duplicate_key_line(txt_config: &str, mapping_line: usize, mapping_column: usize, key: &str) -> usize {
+    let indentation = mapping_column.saturating_sub(1);
+    txt_config.lines()
+        .enumerate()
+        .skip(mapping_line.saturating_sub(1))
+        .filter(|(_, line)| line.get(..indentation).is_some_and(|prefix| prefix.chars().all(|c| c == ' ' || c == '-')))
+        .filter(|(_, line)| line[indentation..].strip_prefix(key).is_some_and(|rest| rest.trim_start().starts_with(':')))
+        .nth(1)
+        .map_or(mapping_line, |(i, _)| i + 1)
+}
+
+/// Builds the [Value] of a YAML node, keeping track of the `path` of its keys to report the `duplicate_key`, if `refuse_duplicates`
+struct KeysScanner<'a> {
+    path: String,
+    refuse_duplicates: bool,
+    duplicate_key: &'a RefCell<Option<String>>,
+}
+
+impl KeysScanner<'_> {
+    fn nested(&self, key: &str) -> Self {
+        let path = match self.path.is_empty() {
+            true => key.to_string(),
+            false => format!("{}.{key}", self.path),
+        };
+        KeysScanner { path, refuse_duplicates: self.refuse_duplicates, duplicate_key: self.duplicate_key }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for KeysScanner<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeysScanner<'_> {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("any YAML value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut elements = vec![];
+        while let Some(element) = seq.next_element_seed(self.nested(&elements.len().to_string()))? {
+            elements.push(element);
+        }
+        Ok(Value::Sequence(elements))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut mapping = Mapping::new();
+        let mut seen_keys = HashSet::new();
+        while let Some(key) = map.next_key_seed(self.nested("?"))? {
+            let key_path = match &key {
+                Value::String(key) => key.clone(),
+                key => serde_yaml::to_string(key).map_or_else(|_| format!("{key:?}"), |key| key.trim_end().to_string()),
+            };
+            if !seen_keys.insert(key.clone()) && self.refuse_duplicates {
+                *self.duplicate_key.borrow_mut() = Some(self.nested(&key_path).path);
+                return Err(de::Error::custom(format!("duplicate entry with key {key_path:?}")))
+            }
+            let value = map.next_value_seed(self.nested(&key_path))?;
+            mapping.insert(key, value);
+        }
+        Ok(Value::Mapping(mapping))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Value, A::Error> {
+        // the way `serde_yaml` gives tagged values, like `!Tag value`
+        let (tag, variant) = data.variant::<String>()?;
+        let value = variant.newtype_variant_seed(self)?;
+        Ok(Value::Tagged(Box::new(TaggedValue { tag: Tag::new(tag), value })))
+    }
+}
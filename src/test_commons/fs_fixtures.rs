@@ -0,0 +1,54 @@
+//! Filesystem situations for the tests
+
+use std::path::PathBuf;
+
+/// Creates the read-only (`chmod 555`) temp dir `name`, with the given files in it -- panicking if the permissions wouldn't
+/// be enforced, like when running as root, so the tests using it never pass without exercising anything.
+/// Those tests are, so, `#[ignore]`d -- to be run with `cargo test -- --ignored` as a regular user
+#[cfg(unix)]
+pub fn read_only_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join(name);
+    remove_read_only_dir(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file_name, contents) in files {
+        std::fs::write(dir.join(file_name), contents).unwrap();
+    }
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+    if std::fs::write(dir.join("probe"), "").is_ok() {
+        remove_read_only_dir(&dir);
+        panic!("Read-only dirs aren't enforced for this user (like for root): this test can't be run");
+    }
+    dir
+}
+
+/// Removes the dir created by [read_only_dir()]
+#[cfg(unix)]
+pub fn remove_read_only_dir(dir: &std::path::Path) {
+    use std::os::unix::fs::PermissionsExt;
+    _ = std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o755));
+    _ = std::fs::remove_dir_all(dir);
+}
+
+/// The longest file name the filesystem of `dir` accepts -- found by creating (& removing) files in it, up to 4096 chars long.
+/// `None` if there is no such limit
+#[cfg(feature = "async")]
+pub fn max_file_name_len(dir: &std::path::Path) -> Option<usize> {
+    let fits = |len: usize| {
+        let probe_path = dir.join(format!("{:x<len$}", "cli-config-name-probe-"));
+        let fits = std::fs::write(&probe_path, "").is_ok();
+        _ = std::fs::remove_file(&probe_path);
+        fits
+    };
+    const MAX_PROBED_LEN: usize = 4096;
+    if fits(MAX_PROBED_LEN) {
+        return None
+    }
+    // binary search for the longest fitting length
+    let (mut fitting, mut exceeding) = (0, MAX_PROBED_LEN);
+    while exceeding - fitting > 1 {
+        let len = (fitting + exceeding) / 2;
+        if fits(len) { fitting = len } else { exceeding = len }
+    }
+    Some(fitting)
+}
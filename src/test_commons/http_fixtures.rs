@@ -0,0 +1,41 @@
+//! A tiny HTTP server for testing the loading of remote configs
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// The `ETag` of every config served by [mock_config_server()]
+pub const MOCK_ETAG: &str = "\"v1\"";
+/// The `Last-Modified` date of every config served by [mock_config_server()]
+pub const MOCK_LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+/// Serves the given `(path, content_type, body)` routes -- 404ing any other paths -- returning the server's base url.
+/// Requests bearing [MOCK_ETAG] or [MOCK_LAST_MODIFIED] as validators are answered with `304 Not Modified`
+pub async fn mock_config_server(routes: &'static [(&'static str, &'static str, &'static str)]) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                match stream.read(&mut buffer).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buffer[..n]),
+                }
+            }
+            let request = String::from_utf8_lossy(&request).to_lowercase();
+            let not_modified = request.contains(&format!("\r\nif-none-match: {MOCK_ETAG}\r\n"))
+                || request.contains(&format!("\r\nif-modified-since: {}\r\n", MOCK_LAST_MODIFIED.to_lowercase()));
+            let route = routes.iter()
+                .find(|(path, _, _)| request.starts_with(&format!("get {} ", path.to_lowercase())));
+            let response = match route {
+                Some(_) if not_modified => "HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+                Some((_, content_type, body)) => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nETag: {MOCK_ETAG}\r\nLast-Modified: {MOCK_LAST_MODIFIED}\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len()),
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            };
+            _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+    format!("http://{address}")
+}
@@ -1 +1,6 @@
-pub mod config_models;
+#[cfg(feature = "ron")]
+pub mod fs_fixtures;
+#[cfg(all(feature = "remote", feature = "ron"))]
+pub mod http_fixtures;
+#[cfg(feature = "async")]
+pub mod warnings_fixtures;
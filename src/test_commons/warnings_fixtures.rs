@@ -0,0 +1,25 @@
+//! Capturing of the warnings emitted while running -- see [crate::set_warnings_sink()]
+
+use std::sync::{Mutex, Once, PoisonError};
+use crate::{set_warnings_sink, LoadWarning};
+
+static CAPTURED_WARNINGS: Mutex<Vec<LoadWarning>> = Mutex::new(Vec::new());
+
+/// Sets the sink capturing the warnings emitted while running (by any test) -- also printing them, as it would happen without it.
+/// See [captured_warnings()]
+pub fn capture_warnings() {
+    static SINK: Once = Once::new();
+    SINK.call_once(|| set_warnings_sink(|warning| {
+        eprintln!("{}: {}", if warning.kind.is_notice() { "NOTICE" } else { "WARNING" }, warning.message);
+        CAPTURED_WARNINGS.lock().unwrap_or_else(PoisonError::into_inner).push(warning.clone());
+    }));
+}
+
+/// The warnings captured since [capture_warnings()] whose messages mention `needle` -- like the config file of the test,
+/// as tests run in parallel
+pub fn captured_warnings(needle: &str) -> Vec<LoadWarning> {
+    CAPTURED_WARNINGS.lock().unwrap_or_else(PoisonError::into_inner).iter()
+        .filter(|warning| warning.message.contains(needle))
+        .cloned()
+        .collect()
+}
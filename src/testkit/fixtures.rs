@@ -0,0 +1,225 @@
+//! Toy config & command line models -- the ones this crate's own tests use -- along with helpers for the usual test scaffolding
+
+use std::ffi::OsString;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use serde::{Deserialize, Serialize};
+use crate::{config_from_str, config_to_string, ApplyVerbosity, CmdLineAndConfigIntegration, OgreRootConfig, SerdeFormat, VerbosityArgs};
+
+/// A root config, containing a sub-config
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppRootConfig {
+    pub log_sub_config: LogConfig,
+}
+impl OgreRootConfig for AppRootConfig {}
+
+/// Specifies what the application should do with its log messages -- also usable in the command line
+#[derive(clap::Args, Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    #[clap(long)]
+    pub sink: Option<Dummy>,
+}
+
+/// Where the log messages go -- spelled `null`, `stdout` & `stderror` in config files & in the command line
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
+pub enum Dummy {
+    Null,
+    StdOut,
+    StdError,
+}
+
+/// Command line options for [AppRootConfig], with all the options of [CmdLineAndConfigIntegration] bound to flags
+/// named after them (like `--write-effective-config`), `--set KEY=VALUE` overrides & `-v` / `-q` setting the log sink
+#[derive(clap::Parser, Clone, Debug, Default)]
+#[command(version)]
+pub struct SampleCliOptions {
+    #[clap(long, short = 'c')]
+    pub config_file: Option<String>,
+
+    #[clap(long)]
+    pub write_effective_config: bool,
+
+    #[clap(long)]
+    pub show_effective_config: bool,
+
+    #[clap(long)]
+    pub reset_config: bool,
+
+    #[clap(long)]
+    pub help_config: bool,
+
+    #[clap(long)]
+    pub recover_config: bool,
+
+    #[clap(long)]
+    pub require_existing_config: bool,
+
+    #[clap(long)]
+    pub allow_create_at_explicit_path: bool,
+
+    #[clap(long)]
+    pub debug_config_paths: bool,
+
+    #[clap(long = "set", value_name = "KEY=VALUE")]
+    pub set: Vec<String>,
+
+    #[clap(flatten)]
+    pub log: LogConfig,
+
+    #[clap(flatten)]
+    pub verbosity: VerbosityArgs,
+}
+
+impl CmdLineAndConfigIntegration<AppRootConfig> for SampleCliOptions {
+    fn config_file_path(&self) -> Option<&str> {
+        self.config_file.as_deref()
+    }
+
+    fn should_write_effective_config(&self) -> bool {
+        self.write_effective_config
+    }
+
+    fn should_show_effective_config(&self) -> bool {
+        self.show_effective_config
+    }
+
+    fn should_reset_config(&self) -> bool {
+        self.reset_config
+    }
+
+    fn should_print_config_help(&self) -> bool {
+        self.help_config
+    }
+
+    fn should_recover_config(&self) -> bool {
+        self.recover_config
+    }
+
+    fn require_existing(&self) -> bool {
+        self.require_existing_config
+    }
+
+    fn allow_create_at_explicit_path(&self) -> bool {
+        self.allow_create_at_explicit_path
+    }
+
+    fn should_debug_config_paths(&self) -> bool {
+        self.debug_config_paths
+    }
+
+    fn config_overrides(&self) -> &[String] {
+        &self.set
+    }
+
+    fn verbosity_mapping(&self) -> Option<&dyn ApplyVerbosity<AppRootConfig>> {
+        Some(self)
+    }
+
+    fn merge_with_config(self, mut config: AppRootConfig) -> Result<AppRootConfig, crate::Error> {
+        if let Some(sink) = self.log.sink {
+            config.log_sub_config.sink = Some(sink);
+        }
+        Ok(config)
+    }
+
+    fn cli_to_config_paths() -> &'static [(&'static str, &'static str)] {
+        &[("log", "log_sub_config")]
+    }
+}
+
+impl ApplyVerbosity<AppRootConfig> for SampleCliOptions {
+    fn verbosity_args(&self) -> &VerbosityArgs {
+        &self.verbosity
+    }
+
+    fn apply_verbosity(&self, level: i8, mut config: AppRootConfig) -> AppRootConfig {
+        config.log_sub_config.sink = Some(match level {
+            ..=-1 => Dummy::Null,
+            1 => Dummy::StdError,
+            _ => Dummy::StdOut,
+        });
+        config
+    }
+}
+
+/// A fresh path for a config file with the given `extension` (like `yaml` or `ron.gz`) in the temp dir -- nothing is created there.
+/// Each call gets a unique name, so tests may run in parallel. See [crate::testkit::TempConfig] for files removed automatically
+pub fn temp_config_path(extension: &str) -> PathBuf {
+    unique_temp_path(&format!(".{extension}"))
+}
+
+/// A fresh path for a dir in the temp dir -- for tests needing several config files, or other files beside them.
+/// Like [temp_config_path()], nothing is created there & each call gets a unique name
+pub fn temp_config_dir() -> PathBuf {
+    unique_temp_path("")
+}
+
+fn unique_temp_path(suffix: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!("ogre-config-meld-testkit-{}-{}{suffix}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)))
+}
+
+/// Parses the synthetic command line `args` (whose first element is the program name) into `CmdLineOptionsType` & hands the
+/// options to `f`, returning its outcome. Panics with the rendered command line error if `args` don't parse
+pub fn with_args<CmdLineOptionsType: clap::Parser, R>(
+    args: impl IntoIterator<Item = impl Into<OsString> + Clone>,
+    f: impl FnOnce(CmdLineOptionsType) -> R,
+) -> R {
+    let cmdline_options = CmdLineOptionsType::try_parse_from(args)
+        .unwrap_or_else(|err| panic!("`testkit`: the synthetic command line didn't parse: {}", err.render()));
+    f(cmdline_options)
+}
+
+/// Asserts that `config` is written & read back unchanged through every config file format enabled in this build
+/// -- panicking with the offending format & text otherwise
+pub fn assert_round_trips<RootConfigType: OgreRootConfig + PartialEq + Debug>(config: &RootConfigType) {
+    let formats = [
+        #[cfg(feature = "ron")]
+        SerdeFormat::Ron,
+        #[cfg(feature = "yaml")]
+        SerdeFormat::Yaml,
+    ];
+    for format in formats {
+        let txt_config = config_to_string(config, format, "")
+            .unwrap_or_else(|err| panic!("`testkit`: the config couldn't be written in {format:?}: {err}"));
+        let read_config = config_from_str::<RootConfigType>(&txt_config, format)
+            .unwrap_or_else(|err| panic!("`testkit`: the config written in {format:?} couldn't be read back: {err}\n{txt_config}"));
+        assert_eq!(&read_config, config, "`testkit`: the config changed on the {format:?} round-trip through:\n{txt_config}");
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaffolding() {
+        let config_path = temp_config_path("yaml.gz");
+        assert!(config_path.to_string_lossy().ends_with(".yaml.gz"), "The extension wasn't used: {config_path:?}");
+        assert_ne!(temp_config_path("yaml.gz"), config_path, "Each temp config path should be unique");
+        assert!(!config_path.exists(), "Nothing should have been created");
+        let config_dir = temp_config_dir();
+        assert!(config_dir.extension().is_none() && !config_dir.exists(), "A fresh, extensionless dir path was expected: {config_dir:?}");
+        assert_ne!(temp_config_dir(), config_dir, "Each temp config dir should be unique");
+
+        let config = with_args(["test", "--sink", "stderror", "-c", "app.config.ron"], |cmdline_options: SampleCliOptions| {
+            assert_eq!(cmdline_options.config_file_path(), Some("app.config.ron"), "The synthetic command line wasn't parsed");
+            cmdline_options.merge_with_config(AppRootConfig::default()).unwrap()
+        });
+        assert_eq!(config.log_sub_config.sink, Some(Dummy::StdError), "The options weren't handed over");
+        assert_round_trips(&config);
+        assert_round_trips(&AppRootConfig::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "didn't parse")]
+    fn bad_synthetic_args() {
+        with_args(["test", "--no-such-option"], |_: SampleCliOptions| ());
+    }
+}
@@ -0,0 +1,296 @@
+//! An in-memory [ConfigFs], for exercising the save, backup & rewrite flows without touching the disk
+//! -- & for simulating failures at any of their steps
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::logic::{followed_symlink, too_many_symlinks, MAX_SYMLINKS};
+use crate::{ConfigFs, FileMetadata};
+
+/// The operations of a [ConfigFs] -- for [MemoryFs::fail_on()] & [MemoryFs::fail_on_nth()] to make them fail
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum FsOperation {
+    /// [ConfigFs::read()] & [ConfigFs::read_to_string()]
+    Read,
+    Write,
+    Rename,
+    Copy,
+    RemoveFile,
+    CreateDir,
+    /// [ConfigFs::metadata()], [ConfigFs::symlink_metadata()] & [ConfigFs::exists()] -- which reports nothing as existing, when failing
+    Metadata,
+    ReadDir,
+    ReadLink,
+    Sync,
+    RestoreMetadata,
+}
+
+/// A [ConfigFs] keeping its files in memory -- starting empty, except for the root directory.
+/// Like the real thing, files may only be created in existing directories. Any operation may be set to fail
+/// with [MemoryFs::fail_on()] -- or just one of its calls, with [MemoryFs::fail_on_nth()] -- so tests can tell
+/// what is left behind when, say, a rewrite fails midway:
+/// ```nocompile
+/// let config_fs = MemoryFs::new().with_file("/etc/app.config.ron", "()");
+/// config_fs.fail_on(FsOperation::Write, std::io::ErrorKind::StorageFull);
+/// ```
+#[derive(Debug)]
+pub(crate) struct MemoryFs {
+    state: Mutex<MemoryFsState>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryFsState {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    symlinks: BTreeMap<PathBuf, PathBuf>,
+    dirs: BTreeSet<PathBuf>,
+    failures: BTreeMap<FsOperation, ErrorKind>,
+    /// how many more calls of each operation succeed before the one failing -- see [MemoryFs::fail_on_nth()]
+    nth_failures: BTreeMap<FsOperation, (usize, ErrorKind)>,
+}
+
+impl Default for MemoryFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryFs {
+
+    pub(crate) fn new() -> Self {
+        let state = MemoryFsState { dirs: BTreeSet::from([PathBuf::from("/"), PathBuf::new()]), ..MemoryFsState::default() };
+        Self { state: Mutex::new(state) }
+    }
+
+    /// Adds the file at `path` holding `contents` -- along with its parent directories
+    pub(crate) fn with_file(self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.add_parent_dirs(path.as_ref());
+            state.files.insert(path.as_ref().to_path_buf(), contents.as_ref().to_vec());
+        }
+        self
+    }
+
+    /// Adds the symlink at `link_path`, pointing to `target` -- relative targets being taken relative to the link's directory
+    pub(crate) fn with_symlink(self, link_path: impl AsRef<Path>, target: impl AsRef<Path>) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.add_parent_dirs(link_path.as_ref());
+            state.symlinks.insert(link_path.as_ref().to_path_buf(), target.as_ref().to_path_buf());
+        }
+        self
+    }
+
+    /// Makes every subsequent `operation` fail with an error of the given `kind` -- until [MemoryFs::heal()] is called
+    pub(crate) fn fail_on(&self, operation: FsOperation, kind: ErrorKind) {
+        self.state.lock().unwrap().failures.insert(operation, kind);
+    }
+
+    /// Makes just the `n`th (1-based) subsequent call of `operation` fail with an error of the given `kind` -- like the write of
+    /// the contents, among the several writes of a save
+    pub(crate) fn fail_on_nth(&self, operation: FsOperation, n: usize, kind: ErrorKind) {
+        self.state.lock().unwrap().nth_failures.insert(operation, (n.saturating_sub(1), kind));
+    }
+
+    /// Undoes all [MemoryFs::fail_on()] & [MemoryFs::fail_on_nth()] calls
+    pub(crate) fn heal(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures.clear();
+        state.nth_failures.clear();
+    }
+
+    /// The contents of the file at `path` -- following symlinks -- as text, or `None` if there is no such file
+    pub(crate) fn contents(&self, path: impl AsRef<Path>) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        let resolved_path = state.resolve(path.as_ref()).ok()?;
+        state.files.get(&resolved_path)
+            .map(|contents| String::from_utf8_lossy(contents).to_string())
+    }
+
+    /// The paths of all the files -- not including symlinks nor directories -- in order
+    pub(crate) fn file_paths(&self) -> Vec<PathBuf> {
+        self.state.lock().unwrap().files.keys().cloned().collect()
+    }
+
+    /// Fails with the error set by [MemoryFs::fail_on()] or [MemoryFs::fail_on_nth()] for `operation`, if it is due
+    /// -- otherwise, runs `op` over the state
+    fn operate<T>(&self, operation: FsOperation, op: impl FnOnce(&mut MemoryFsState) -> std::io::Result<T>) -> std::io::Result<T> {
+        let mut state = self.state.lock().unwrap();
+        let nth_failure = match state.nth_failures.get_mut(&operation) {
+            Some((0, kind)) => Some(*kind),
+            Some((remaining, _)) => {
+                *remaining -= 1;
+                None
+            },
+            None => None,
+        };
+        if nth_failure.is_some() {
+            state.nth_failures.remove(&operation);
+        }
+        match state.failures.get(&operation).copied().or(nth_failure) {
+            Some(kind) => Err(std::io::Error::new(kind, format!("`MemoryFs`: simulated {operation:?} failure"))),
+            None => op(&mut state),
+        }
+    }
+}
+
+impl MemoryFsState {
+
+    fn add_parent_dirs(&mut self, path: &Path) {
+        self.dirs.extend(path.ancestors().skip(1).map(Path::to_path_buf));
+    }
+
+    /// Follows the symlinks at `path`, returning the path of the final (possibly missing) target
+    fn resolve(&self, path: &Path) -> std::io::Result<PathBuf> {
+        let mut resolved_path = path.to_path_buf();
+        for _ in 0..MAX_SYMLINKS {
+            match self.symlinks.get(&resolved_path) {
+                Some(target) => resolved_path = followed_symlink(&resolved_path, target.clone()),
+                None => return Ok(resolved_path),
+            }
+        }
+        Err(too_many_symlinks(path))
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        self.files.get(&self.resolve(path)?)
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn write(&mut self, path: &Path, contents: &[u8]) -> std::io::Result<()> {
+        let resolved_path = self.resolve(path)?;
+        let parent_dir = resolved_path.parent().unwrap_or(Path::new(""));
+        if !self.dirs.contains(parent_dir) {
+            return Err(not_found(parent_dir))
+        }
+        self.files.insert(resolved_path, contents.to_vec());
+        Ok(())
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> std::io::Result<FileMetadata> {
+        let metadata = |is_file, is_symlink| FileMetadata { is_file, is_symlink, os_metadata: None };
+        if self.symlinks.contains_key(path) {
+            Ok(metadata(false, true))
+        } else if self.files.contains_key(path) {
+            Ok(metadata(true, false))
+        } else if self.dirs.contains(path) {
+            Ok(metadata(false, false))
+        } else {
+            Err(not_found(path))
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.resolve(path)
+            .is_ok_and(|resolved_path| self.files.contains_key(&resolved_path) || self.dirs.contains(&resolved_path))
+    }
+}
+
+fn not_found(path: &Path) -> std::io::Error {
+    std::io::Error::new(ErrorKind::NotFound, format!("`MemoryFs`: there is nothing at {path:?}"))
+}
+
+impl ConfigFs for MemoryFs {
+    async fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        self.operate(FsOperation::Read, |state| state.read(path))
+    }
+
+    async fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+        self.operate(FsOperation::Read, |state| String::from_utf8(state.read(path)?)
+            .map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err)))
+    }
+
+    async fn write(&self, path: &Path, contents: &[u8]) -> std::io::Result<()> {
+        self.operate(FsOperation::Write, |state| state.write(path, contents))
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        self.operate(FsOperation::Rename, |state| {
+            if let Some(target) = state.symlinks.remove(from) {
+                state.symlinks.insert(to.to_path_buf(), target);
+                return Ok(())
+            }
+            let contents = state.files.remove(from).ok_or_else(|| not_found(from))?;
+            // like the real thing, symlinks at `to` are replaced rather than written through
+            state.symlinks.remove(to);
+            state.write(to, &contents)
+                .inspect_err(|_| _ = state.files.insert(from.to_path_buf(), contents.clone()))
+        })
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        self.operate(FsOperation::Copy, |state| {
+            let contents = state.read(from)?;
+            state.write(to, &contents)
+        })
+    }
+
+    async fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+        self.operate(FsOperation::RemoveFile, |state| {
+            let was_symlink = state.symlinks.remove(path).is_some();
+            match state.files.remove(path) {
+                Some(_) => Ok(()),
+                None if was_symlink => Ok(()),
+                None => Err(not_found(path)),
+            }
+        })
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        self.operate(FsOperation::CreateDir, |state| {
+            state.add_parent_dirs(path);
+            state.dirs.insert(path.to_path_buf());
+            Ok(())
+        })
+    }
+
+    async fn metadata(&self, path: &Path) -> std::io::Result<FileMetadata> {
+        self.operate(FsOperation::Metadata, |state| state.symlink_metadata(&state.resolve(path)?))
+    }
+
+    async fn symlink_metadata(&self, path: &Path) -> std::io::Result<FileMetadata> {
+        self.operate(FsOperation::Metadata, |state| state.symlink_metadata(path))
+    }
+
+    async fn exists(&self, path: &Path) -> bool {
+        self.operate(FsOperation::Metadata, |state| Ok(state.exists(path)))
+            .unwrap_or(false)
+    }
+
+    async fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        self.operate(FsOperation::ReadDir, |state| {
+            if !state.dirs.contains(path) {
+                return Err(not_found(path))
+            }
+            let entry_paths = state.files.keys()
+                .chain(state.symlinks.keys())
+                .chain(state.dirs.iter())
+                .filter(|entry_path| entry_path.parent() == Some(path))
+                .cloned()
+                .collect();
+            Ok(entry_paths)
+        })
+    }
+
+    async fn read_link(&self, path: &Path) -> std::io::Result<PathBuf> {
+        self.operate(FsOperation::ReadLink, |state| state.symlinks.get(path)
+            .cloned()
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, format!("`MemoryFs`: {path:?} is not a symlink"))))
+    }
+
+    async fn sync(&self, path: &Path) -> std::io::Result<()> {
+        self.operate(FsOperation::Sync, |state| match state.exists(path) {
+            true => Ok(()),
+            false => Err(not_found(path)),
+        })
+    }
+
+    async fn restore_metadata(&self, path: &Path, _original: &FileMetadata) -> std::io::Result<()> {
+        self.operate(FsOperation::RestoreMetadata, |state| match state.exists(path) {
+            true => Ok(()),
+            false => Err(not_found(path)),
+        })
+    }
+}
@@ -0,0 +1,145 @@
+//! Helpers for testing the config integration of applications -- with temporary config files & synthetic command lines,
+//! instead of the real ones -- plus toy config & command line models (see [AppRootConfig] & [SampleCliOptions]) for the
+//! scaffolding every application would otherwise rewrite. This is what this crate uses for its own tests. Enabled by the `test-util` feature:
+//! ```nocompile
+//!   #[tokio::test]
+//!   async fn sink_from_the_command_line() {
+//!       let config = with_temp_config("log:\n  sink: stderr\n", SerdeFormat::Yaml, |config_file| async move {
+//!           run_cli::<MyCmdLineOptions, MyRootConfig>(["myapp", "--sink", "stdout"], &config_file).await
+//!       }).await.unwrap();
+//!       assert_eq!(config.log.sink, Sink::StdOut);
+//!   }
+
+mod fixtures;
+pub use fixtures::*;
+
+// the in-memory filesystem, for this crate's own tests -- see [crate::ConfigFs]. Not all of it is used by every feature combination
+#[cfg(all(test, feature = "async"))]
+#[allow(dead_code)]
+mod memory_fs;
+#[cfg(all(test, feature = "async"))]
+#[allow(unused_imports)]
+pub(crate) use memory_fs::*;
+
+#[cfg(feature = "async")]
+use std::ffi::OsString;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "async")]
+use crate::logic::load_and_merge_configs_traced_for;
+#[cfg(feature = "async")]
+use crate::{CmdLineAndConfigIntegration, OgreRootConfig};
+use crate::SerdeFormat;
+
+/// A config file with the given contents in the temp dir, removed when dropped -- along with its backups, lock & other siblings
+/// created by this crate (like `<name>.bak-<timestamp>`). Each instance gets a unique name, so tests may run in parallel
+#[derive(Debug)]
+pub struct TempConfig {
+    path: PathBuf,
+}
+
+impl TempConfig {
+
+    /// Writes `content` to a new temporary config file, with the extension for `format`
+    pub fn new(content: &str, format: SerdeFormat) -> Self {
+        let extension = match format {
+            #[cfg(feature = "ron")]
+            SerdeFormat::Ron => "ron",
+            #[cfg(feature = "yaml")]
+            SerdeFormat::Yaml => "yaml",
+        };
+        let path = temp_config_path(extension);
+        std::fs::write(&path, content)
+            .unwrap_or_else(|err| panic!("`testkit`: couldn't write the temporary config file {path:?}: {err}"));
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The current contents of the config file -- for checking rewrites
+    pub fn contents(&self) -> String {
+        std::fs::read_to_string(&self.path)
+            .unwrap_or_else(|err| panic!("`testkit`: couldn't read the temporary config file {:?}: {err}", self.path))
+    }
+}
+
+impl Drop for TempConfig {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.path);
+        let (Some(dir), Some(file_name)) = (self.path.parent(), self.path.file_name()) else {
+            return
+        };
+        let siblings_prefix = format!("{}.", file_name.to_string_lossy());
+        for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&siblings_prefix) {
+                _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+/// Runs `f` with the path of a temporary config file holding `content` -- in the given `format` -- which is removed afterwards
+/// (even if `f` panics). See [TempConfig]
+#[cfg(feature = "async")]
+pub async fn with_temp_config<R, Fut: Future<Output = R>>(
+    content: &str,
+    format: SerdeFormat,
+    f: impl FnOnce(PathBuf) -> Fut,
+) -> R {
+    let temp_config = TempConfig::new(content, format);
+    f(temp_config.path().to_path_buf()).await
+}
+
+/// Runs the whole load & merge pipeline of [crate::parse_cmdline_and_merge_with_loaded_configs()] as if the program was
+/// called with the synthetic command line `args` (whose first element is the program name) & `config_file_path`
+/// were the config file it resolves to -- so `args` don't need to specify it. Options like `--write-effective-config`
+/// act on that file. Returns the effective config -- or the errors the program would get.
+#[cfg(feature = "async")]
+pub async fn run_cli<
+    CmdLineOptionsType: clap::Parser + CmdLineAndConfigIntegration<RootConfigType>,
+    RootConfigType: OgreRootConfig,
+>(
+    args: impl IntoIterator<Item = impl Into<OsString> + Clone>,
+    config_file_path: impl AsRef<Path>,
+) -> Result<RootConfigType, crate::Error> {
+    let arg_matches = CmdLineOptionsType::command().try_get_matches_from(args)?;
+    let cmdline_options = CmdLineOptionsType::from_arg_matches(&arg_matches)
+        .map_err(|err| err.format(&mut CmdLineOptionsType::command()))?;
+    load_and_merge_configs_traced_for(cmdline_options, Some(config_file_path.as_ref()), "", Some(&arg_matches), false).await
+        .map(|(loaded_config, _)| loaded_config.config)
+}
+
+
+#[cfg(all(test, feature = "async", feature = "ron", feature = "yaml"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn harness() {
+        let (effective_config, config_file) = with_temp_config("log_sub_config:\n  sink: stderror\n", SerdeFormat::Yaml, |config_file| async move {
+            let effective_config = run_cli::<SampleCliOptions, AppRootConfig>(["test", "--sink", "stdout"], &config_file).await;
+            (effective_config, config_file)
+        }).await;
+        assert_eq!(effective_config.expect("The synthetic command line should have been run").log_sub_config.sink, Some(Dummy::StdOut),
+                   "The command line options weren't merged into the temporary config");
+        assert!(!config_file.exists(), "The temporary config file should have been removed");
+
+        let temp_config = TempConfig::new("(log_sub_config: (sink: Some(stderror)))", SerdeFormat::Ron);
+        let effective_config = run_cli::<SampleCliOptions, AppRootConfig>(["test"], temp_config.path()).await.unwrap();
+        assert_eq!(effective_config.log_sub_config.sink, Some(Dummy::StdError), "The temporary config wasn't loaded");
+        run_cli::<SampleCliOptions, AppRootConfig>(["test", "--sink", "null", "--write-effective-config"], temp_config.path()).await.unwrap();
+        assert!(temp_config.contents().contains("Some(null)"), "The temporary config should have been rewritten: '{}'", temp_config.contents());
+        let result = run_cli::<SampleCliOptions, AppRootConfig>(["test", "--no-such-option"], temp_config.path()).await;
+        assert!(matches!(result, Err(crate::Error::CliParsing { .. })), "Bad command lines should be reported. Got {result:?}");
+        let path = temp_config.path().to_path_buf();
+        drop(temp_config);
+        let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+        let leftovers = std::fs::read_dir(std::env::temp_dir()).unwrap().flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&file_name))
+            .count();
+        assert_eq!(leftovers, 0, "The temporary config & its backups should have been removed");
+    }
+}
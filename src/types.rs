@@ -1,8 +1,109 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
 
-/// Trait to be implemented by root config types, enabling them to be written / loaded from disk
-pub trait OgreRootConfig: Debug + Serialize + for<'r> Deserialize<'r> + Sized + Default {}
+/// Trait to be implemented by root config types, enabling them to be written / loaded from disk.
+///
+/// Note to implementers: enums used both in configs & in the CLI (through `clap::ValueEnum`) should
+/// have their serde & clap spellings aligned, so values written in the config file are the same
+/// ones accepted by the command line -- like this:
+/// ```nocompile
+///   #[derive(clap::ValueEnum, Serialize, Deserialize)]
+///   #[clap(rename_all = "lower")]
+///   #[serde(rename_all = "lowercase")]
+///   pub enum Sink { Null, StdOut, StdError }
+/// ```
+///
+/// Also, for config files written by older versions of the application to remain loadable after new fields are added,
+/// config structs should fall back to their `Default` values for any missing fields -- like this:
+/// ```nocompile
+///   #[derive(Default, Serialize, Deserialize)]
+///   #[serde(default)]
+///   pub struct LogConfig { ... }
+/// ```
+/// Otherwise, a missing field is reported as [Error::MissingRequiredField].
+///
+/// As configs evolve, fields being phased out may be listed in [Self::deprecated_fields()], so their users are warned.
+/// Values needing normalization -- like paths relative to the config file -- may be adjusted by [Self::post_load()],
+/// while the ones not meant to be persisted may be left out of the written files by [Self::pre_save()].
+pub trait OgreRootConfig: Debug + Serialize + for<'r> Deserialize<'r> + Sized + Default {
+
+    /// The fields kept for compatibility, but no longer to be used -- warned about when present in config files
+    /// loaded through [crate::load_with_warnings()], as [LoadWarningKind::DeprecatedField]. Like this:
+    /// ```nocompile
+    ///   fn deprecated_fields() -> &'static [DeprecatedField] {
+    ///       &[DeprecatedField { path: "log_sub_config.file", replacement: "use `log_sub_config.sink` instead" }]
+    ///   }
+    /// ```
+    fn deprecated_fields() -> &'static [DeprecatedField] {
+        &[]
+    }
+
+    /// The docs of the config types -- like the ones a derive macro may capture from their `///` comments -- used as the
+    /// tail docs of the config files written by [crate::save_to_file()], [crate::load_or_create_default()],
+    /// [crate::parse_cmdline_and_merge_with_loaded_configs()] & [crate::handle_config_subcommand()] when they are given none.
+    /// See [crate::documented_config_models()] for extracting them from the sources, instead
+    fn docs() -> Option<&'static str> {
+        None
+    }
+
+    /// Adjusts the config right after it is loaded -- before the command line options are merged into it & before it is
+    /// rewritten by `--write-effective-config` -- for normalizing values like relative paths or hostnames. Called for every
+    /// config loaded from a file or URL, as well as for the default ones just written to new config files.
+    /// Errors are reported as [Error::LoadingConfig]. Like this:
+    /// ```nocompile
+    ///   fn post_load(&mut self, context: &LoadContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ///       if let Some(config_dir) = context.config_dir() {
+    ///           self.data_dir = config_dir.join(&self.data_dir);
+    ///       }
+    ///       Ok(())
+    ///   }
+    /// ```
+    fn post_load(&mut self, _context: &LoadContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// The config to be written in place of this one -- or `None` for this one to be written as is -- for redacting or
+    /// normalizing values before they reach the disk, like stripping fields derived at runtime. The config itself is
+    /// left untouched. Called for every config written to a file -- by [crate::save_to_file()], `--write-effective-config`
+    /// & the like -- as well as for the ones shown by the `config` subcommands. Like this:
+    /// ```nocompile
+    ///   fn pre_save(&self) -> Option<Self> {
+    ///       Some(Self { resolved_peers: vec![], ..self.clone() })
+    ///   }
+    /// ```
+    fn pre_save(&self) -> Option<Self> {
+        None
+    }
+}
+
+/// Where a config was just loaded from -- see [OgreRootConfig::post_load()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadContext {
+    /// The config file the config was loaded from (or created at) -- or the URL of a remote config
+    pub path: PathBuf,
+    pub format: crate::SerdeFormat,
+}
+
+impl LoadContext {
+    /// The directory holding the config file -- against which relative paths in the config may be resolved.
+    /// `None` for remote configs, whose [Self::path] is their URL
+    pub fn config_dir(&self) -> Option<&Path> {
+        match self.path.to_str().is_some_and(crate::logic::is_config_url) {
+            true => None,
+            false => Some(self.path.parent().unwrap_or(Path::new(""))),
+        }
+    }
+}
+
+/// A config field that is no longer to be used -- see [OgreRootConfig::deprecated_fields()]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeprecatedField {
+    /// The dotted path of the field -- like `log_sub_config.file`
+    pub path: &'static str,
+    /// What to do instead, for the user -- like "use `log_sub_config.sink` instead"
+    pub replacement: &'static str,
+}
 
 /// Trait to allow merging command line options into the application's configs
 pub trait CmdLineAndConfigIntegration<RootConfigType: OgreRootConfig>: clap::Parser + Debug {
@@ -14,7 +115,9 @@ pub trait CmdLineAndConfigIntegration<RootConfigType: OgreRootConfig>: clap::Par
     ///   - '.ron': use the RON file format
     ///   - '.yaml' & '.yml': use the YML file format.
     ///
-    /// If the specified file doesn't exist, one will be created with the default values.
+    /// If no file is specified and the default one doesn't exist, it will be created with the default values.
+    /// On the other hand, an explicitly specified file that doesn't exist is reported as [Error::ConfigFileNotFound]
+    /// -- see [Self::allow_create_at_explicit_path()].
     ///
     /// Note to implementers: use a field like this:
     /// ```nocompile
@@ -27,7 +130,9 @@ pub trait CmdLineAndConfigIntegration<RootConfigType: OgreRootConfig>: clap::Par
     ///
     /// --> Any comments or data overridden by the command line arguments will be lost.
     ///
-    /// As a backup, the old config file will be renamed by adding a '~' (tilde) at the end of its name.
+    /// As a backup, the old config file will be renamed by adding a `.bak-<timestamp>` suffix to its name
+    /// -- keeping only the most recent backups (see [MeldOptions::backup_policy]).
+    /// If the effective config holds the same values as the loaded one, the file is left untouched (comments included).
     ///
     /// Note to implementers: use a field like this:
     /// ```nocompile
@@ -35,6 +140,20 @@ pub trait CmdLineAndConfigIntegration<RootConfigType: OgreRootConfig>: clap::Par
     ///   pub write_effective_config: bool,
     fn should_write_effective_config(&self) -> bool;
 
+    /// Where the effective configuration is to be written, if anywhere: back to the config file it was loaded from -- as
+    /// described in [Self::should_write_effective_config()] -- or to another file, like one for a sidecar to read, leaving the
+    /// config file alone. Alternate files are simply (re)written -- no backups, locks nor change detection -- in the format
+    /// implied by their extensions.
+    ///
+    /// Defaults to [EffectiveConfigTarget::InPlace] if [Self::should_write_effective_config()] is set.
+    /// Note to implementers: if overridden, a field like this may be used:
+    /// ```nocompile
+    ///   #[clap(long)]
+    ///   pub write_effective_config_to: Option<PathBuf>,
+    fn effective_config_output_path(&self) -> Option<EffectiveConfigTarget> {
+        self.should_write_effective_config().then_some(EffectiveConfigTarget::InPlace)
+    }
+
     /// Makes the program dump (to stderr) the "effective configuration" being used
     /// -- the result from loading the configuration file, then applying the command line options.
     ///
@@ -44,11 +163,852 @@ pub trait CmdLineAndConfigIntegration<RootConfigType: OgreRootConfig>: clap::Par
     ///   pub show_effective_config: bool,
     fn should_show_effective_config(&self) -> bool;
 
+    /// If specified along with [Self::should_show_effective_config()], the effective configuration is dumped in YAML instead,
+    /// with each value annotated with where it came from -- the defaults, the config file, an environment variable,
+    /// a command line option or a config override. See [Provenance].
+    ///
+    /// Defaults to `false`. Note to implementers: if overridden, a field like this may be used:
+    /// ```nocompile
+    ///   #[clap(long)]
+    ///   pub annotate_effective_config: bool,
+    fn should_annotate_effective_config(&self) -> bool {
+        false
+    }
+
+    /// If specified, causes the configuration file to be regenerated with the default values & docs, then the loading stops with
+    /// [crate::Error::ConfigReset] -- for [crate::Error::exit_if_cli()] to report it & exit the program.
+    /// The existing file, if any, is backed up just like in [Self::should_write_effective_config()].
+    ///
+    /// Defaults to `false`. Note to implementers: if overridden, a field like this may be used:
+    /// ```nocompile
+    ///   #[clap(long)]
+    ///   pub reset_config: bool,
+    fn should_reset_config(&self) -> bool {
+        false
+    }
+
+    /// If specified, causes the docs of the config model -- the tail docs given to [crate::parse_cmdline_and_merge_with_loaded_configs()]
+    /// or the ones of [OgreRootConfig::docs()] -- to be returned as [crate::Error::ConfigHelp], for [crate::Error::exit_if_cli()] to print them
+    /// to stdout & exit the program. Unlike clap's `--help`,
+    /// this documents the config file fields, instead of the command line options.
+    ///
+    /// Defaults to `false`. Note to implementers: if overridden, a field like this may be used:
+    /// ```nocompile
+    ///   #[clap(long)]
+    ///   pub help_config: bool,
+    fn should_print_config_help(&self) -> bool {
+        false
+    }
+
+    /// If specified, a configuration file that can't be parsed (due to merge conflict markers, truncation, ...)
+    /// is moved away to `<name>.broken-<timestamp>` and a new one is created with the default values & docs,
+    /// allowing the program to continue with the defaults + the command line options.
+    ///
+    /// Defaults to `false`. Note to implementers: if overridden, a field like this may be used:
+    /// ```nocompile
+    ///   #[clap(long)]
+    ///   pub recover_config: bool,
+    fn should_recover_config(&self) -> bool {
+        false
+    }
+
+    /// If `true`, a missing configuration file causes [Error::ConfigFileNotFound] to be returned
+    /// instead of having a new one created with the default values -- useful for CI & production
+    /// environments, where a missing config file is most likely a mistake.
+    ///
+    /// Defaults to `false`. Note to implementers: if overridden, a field like this may be used:
+    /// ```nocompile
+    ///   #[clap(long)]
+    ///   pub require_existing_config: bool,
+    fn require_existing(&self) -> bool {
+        false
+    }
+
+    /// Where the default configuration file is looked for (and created) when none is given in the command line
+    /// -- see [ConfigLocation] & [Self::prefer_current_dir_config()].
+    ///
+    /// Defaults to [ConfigLocation::BesideExecutable], for compatibility.
+    fn config_location(&self) -> ConfigLocation {
+        ConfigLocation::BesideExecutable
+    }
+
+    /// If `true`, a config file in the current working dir (as in `./myapp.config.ron`, for users who `cd` into a project dir)
+    /// is preferred over the one at [Self::config_location()] -- which is still where it is created, if none exists.
+    /// See [ConfigSearchPath::current_dir_first()].
+    ///
+    /// Defaults to `false`, for compatibility: only the config location is searched.
+    fn prefer_current_dir_config(&self) -> bool {
+        false
+    }
+
+    /// The ordered list of places the configuration file is looked for when none is given in the command line,
+    /// along with where to create it if none exists -- see [ConfigSearchPath].
+    ///
+    /// Defaults to searching (and creating) only at [Self::config_location()] -- after the current dir, if [Self::prefer_current_dir_config()].
+    /// Note to implementers: [ConfigSearchPath::standard()] provides the usual env var -> current dir -> user dir -> system dir search order.
+    fn config_search_path(&self) -> ConfigSearchPath {
+        match self.prefer_current_dir_config() {
+            true => ConfigSearchPath::current_dir_first(self.config_location()),
+            false => ConfigSearchPath::single(self.config_location()),
+        }
+    }
+
+    /// If `true`, an explicitly specified configuration file (see [Self::config_file_path()]) that doesn't exist
+    /// will be created with the default values -- as it is done for the default config file.
+    ///
+    /// Defaults to `false`, as a missing explicit file is most likely due to a typo or a missing mount,
+    /// in which case writing the defaults there would make the application run misconfigured.
+    fn allow_create_at_explicit_path(&self) -> bool {
+        false
+    }
+
+    /// If `false`, config files automatically created with the default values (see [crate::load_or_create_default()])
+    /// won't have the (possibly large) docs block appended to them -- for a clean, minimal file.
+    /// The default config returned to the application is unaffected.
+    ///
+    /// Defaults to `true`.
+    fn include_docs_in_created_file(&self) -> bool {
+        true
+    }
+
+    /// If specified, dumps (to stderr) every candidate path probed when looking for the default configuration file
+    /// -- see [crate::probed_config_paths()] -- helping to diagnose why a config file isn't being found.
+    ///
+    /// Defaults to `false`. Note to implementers: if overridden, a field like this may be used:
+    /// ```nocompile
+    ///   #[clap(long)]
+    ///   pub debug_config_paths: bool,
+    fn should_debug_config_paths(&self) -> bool {
+        false
+    }
+
+    /// Tunes how the config file is handled when melding it with the command line options -- see [MeldOptions].
+    ///
+    /// Defaults to [MeldOptions::default()].
+    fn meld_options(&self) -> MeldOptions {
+        MeldOptions::default()
+    }
+
+    /// The generic `key=value` overrides of single config fields (like `--set log.sink=stdout`), applied to the config
+    /// after the verbosity flags and before [Self::merge_with_config()] -- so typed options still take precedence.
+    /// See [crate::apply_config_overrides()] for the syntax.
+    ///
+    /// Defaults to no overrides. Note to implementers: if overridden, a field like this may be used:
+    /// ```nocompile
+    ///   #[clap(long = "set", value_name = "KEY=VALUE")]
+    ///   pub set: Vec<String>,
+    fn config_overrides(&self) -> &[String] {
+        &[]
+    }
+
+    /// Exposes the opt-in `-v` / `-q` verbosity handling, automatically applied to the config -- before [Self::merge_with_config()],
+    /// so explicit log options still take precedence over the counted flags.
+    ///
+    /// Defaults to `None`. Note to implementers: flatten [VerbosityArgs] into your options, implement [ApplyVerbosity]
+    /// and return `Some(self)` here.
+    fn verbosity_mapping(&self) -> Option<&dyn ApplyVerbosity<RootConfigType>> {
+        None
+    }
+
     /// Given the specific `RootConfig` and `CmdLineOptionsType` types,
     /// allow the given `RootConfig` to be updated with the given command line options (from `self`)
     fn merge_with_config(self, config: RootConfigType) -> Result<RootConfigType, Error>;
+
+    /// The config fields the command line options are merged into by [Self::default_merge_with_config()], as
+    /// `(option field name, dotted config field path)` pairs -- like `("log_level", "log.level")`.
+    /// Options not listed are merged into the config fields of the same names, if there are any.
+    /// Also tells which option set each config field, for the [Provenance] -- so hand-written merges should list their options, too.
+    ///
+    /// Defaults to none.
+    fn cli_to_config_paths() -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+
+    /// A generic [Self::merge_with_config()], to be called from it: the options (serialized, for that matter) are overlaid
+    /// on the config -- at the paths given by [Self::cli_to_config_paths()] or, otherwise, on the fields of the same names.
+    /// Options holding no value -- `None`, `false` or empty lists -- are taken as not given, leaving the config values as they are,
+    /// while options with no matching config field (like the one for [Self::config_file_path()]) are skipped. Like this:
+    /// ```nocompile
+    ///   fn merge_with_config(self, config: AppConfig) -> Result<AppConfig, Error> {
+    ///       let sink = self.sink.clone();
+    ///       let mut config = self.default_merge_with_config(config)?;
+    ///       // tricky fields may still be merged by hand
+    ///       config.log.sinks.extend(sink);
+    ///       Ok(config)
+    ///   }
+    /// ```
+    /// Values that don't fit the config are reported as [Error::CliParsing].
+    fn default_merge_with_config(self, config: RootConfigType) -> Result<RootConfigType, Error>
+    where
+        Self: Serialize,
+    {
+        crate::logic::overlay_cmdline_options(&self, config, Self::cli_to_config_paths())
+    }
+
+    /// Same as [Self::merge_with_config()], but also given the `config_path` the `config` was loaded from
+    /// -- allowing, for instance, relative paths in the config to be resolved against the config file's directory.
+    ///
+    /// Defaults to delegating to [Self::merge_with_config()], ignoring the path.
+    fn merge_with_config_at(self, config: RootConfigType, config_path: &Path) -> Result<RootConfigType, Error> {
+        _ = config_path;
+        self.merge_with_config(config)
+    }
+
+    /// Same as [Self::merge_with_config()], but leaving the command line options usable afterwards -- like for logging them.
+    ///
+    /// Defaults to merging a clone of `self`. Note to implementers: override it if your options can be merged by reference,
+    /// sparing the clone.
+    fn merge_with_config_ref(&self, config: RootConfigType) -> Result<RootConfigType, Error>
+    where
+        Self: Clone,
+    {
+        self.clone().merge_with_config(config)
+    }
+}
+
+/// How a command line option is merged into its config field by the derived [CmdLineAndConfigIntegration::merge_with_config()]
+/// -- see `#[derive(CmdLineAndConfigIntegration)]`, enabled by the `derive` feature. Implemented for:
+///   - `Option`s, replacing the config value -- optional or not -- if given;
+///   - `bool` flags, turning the config value on if given;
+///   - `Vec`s, replacing the config value if not empty.
+///
+/// Note to implementers: other option types may be supported by implementing it for them.
+pub trait MergeField<ConfigFieldType> {
+    /// Merges this option into the `config_field`
+    fn merge_into(self, config_field: &mut ConfigFieldType);
+}
+
+/// Ready-to-flatten `-v` / `-q` command line options -- see [ApplyVerbosity].
+/// Use it like this:
+/// ```nocompile
+///   #[clap(flatten)]
+///   pub verbosity: VerbosityArgs,
+#[derive(clap::Args, Clone, Debug, Default, PartialEq)]
+pub struct VerbosityArgs {
+    /// Increases the verbosity of the program -- may be repeated, like in `-vv`
+    #[clap(long, short = 'v', action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// Makes the program quiet
+    #[clap(long, short = 'q')]
+    pub quiet: bool,
+}
+
+impl VerbosityArgs {
+    /// The verbosity level requested in the command line: `Some(-1)` for `--quiet`,
+    /// `Some(n)` for `n` occurrences of `-v` or `None` if no verbosity flags were given
+    pub fn level(&self) -> Option<i8> {
+        if self.quiet {
+            Some(-1)
+        } else if self.verbose > 0 {
+            Some(self.verbose.min(i8::MAX as u8) as i8)
+        } else {
+            None
+        }
+    }
+}
+
+/// Trait to allow applications to map the verbosity level given by [VerbosityArgs] onto their configs
+/// -- see [CmdLineAndConfigIntegration::verbosity_mapping()]
+pub trait ApplyVerbosity<RootConfigType: OgreRootConfig> {
+    /// The verbosity options, as parsed from the command line
+    fn verbosity_args(&self) -> &VerbosityArgs;
+
+    /// Updates the `config` (most likely, its log section) according to the verbosity `level`
+    /// (as computed by [VerbosityArgs::level()])
+    fn apply_verbosity(&self, level: i8, config: RootConfigType) -> RootConfigType;
+}
+
+/// Strategies for locating the default configuration file -- see [CmdLineAndConfigIntegration::config_location()].
+/// In all of them, the file is named after the running executable (without any `.exe` extension)
+/// + the `.config.ron` or `.config.yaml` suffixes.
+///
+/// On Windows, if the default config would be created in a directory that isn't writable (like the executable's, in
+/// `C:\Program Files\`), it goes to [Self::PlatformConfigDir] instead -- which is then also searched.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ConfigLocation {
+    /// In the same directory as the running executable -- not suitable for programs installed system-wide
+    #[default]
+    BesideExecutable,
+    /// In the program's sub-directory of the platform's config dir: `~/.config/<app>/` on Linux,
+    /// `%APPDATA%\<app>\` on Windows & `~/Library/Application Support/<app>/` on macOS.
+    /// Falls back to [Self::BesideExecutable] if the platform's config dir can't be determined.
+    PlatformConfigDir,
+    /// In the given directory
+    Custom(PathBuf),
+    /// Compatibility with older versions: the program's path, exactly as invoked (its first arg), + the config suffixes
+    /// -- depending on the current dir and keeping any `.exe` extension (`myapp.exe.config.ron`)
+    InvocationPath,
+    /// In the current working directory
+    CurrentDir,
+    /// In the program's sub-directory of the system-wide config dir: `/etc/<app>/` on Unix & `%PROGRAMDATA%\<app>\` on Windows.
+    /// Skipped (when searching) if there is no such dir in the platform.
+    SystemConfigDir,
+}
+
+/// The ordered list of places where the configuration file is looked for -- the first existing file is used --
+/// along with the location where the default one is created, if none exists.
+/// See [CmdLineAndConfigIntegration::config_search_path()].
+///
+/// In each directory, `.ron` files are preferred over `.yaml` ones, deterministically.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigSearchPath {
+    /// The places to look for the configuration file, in priority order
+    pub entries: Vec<ConfigSearchEntry>,
+    /// Where the default configuration file is created when none of the [Self::entries] exist
+    pub create_in: ConfigLocation,
+}
+
+impl ConfigSearchPath {
+    /// Searches for (and creates) the configuration file in just the given `location`
+    pub fn single(location: ConfigLocation) -> Self {
+        Self {
+            entries: vec![ConfigSearchEntry::Location(location.clone())],
+            create_in: location,
+        }
+    }
+
+    /// Searches the current working dir, then the given `location` -- where the configuration file is created, if none exists
+    pub fn current_dir_first(location: ConfigLocation) -> Self {
+        if location == ConfigLocation::CurrentDir {
+            return Self::single(location);
+        }
+        Self {
+            entries: vec![
+                ConfigSearchEntry::Location(ConfigLocation::CurrentDir),
+                ConfigSearchEntry::Location(location.clone()),
+            ],
+            create_in: location,
+        }
+    }
+
+    /// The usual search order: the file given by the `env_var` environment variable, then the current dir,
+    /// the platform's (user) config dir and, finally, the system-wide config dir.
+    /// The default file is created in the platform's config dir.
+    pub fn standard(env_var: impl Into<String>) -> Self {
+        Self {
+            entries: vec![
+                ConfigSearchEntry::EnvVar(env_var.into()),
+                ConfigSearchEntry::Location(ConfigLocation::CurrentDir),
+                ConfigSearchEntry::Location(ConfigLocation::PlatformConfigDir),
+                ConfigSearchEntry::Location(ConfigLocation::SystemConfigDir),
+            ],
+            create_in: ConfigLocation::PlatformConfigDir,
+        }
+    }
 }
 
+/// A place to look for the configuration file -- see [ConfigSearchPath]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigSearchEntry {
+    /// The config file path given by the named environment variable, if it is set
+    EnvVar(String),
+    /// The config file candidates at the given location
+    Location(ConfigLocation),
+}
+
+/// The outcome of resolving which configuration file to use -- see [crate::resolve_config_file_path()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigResolution {
+    /// The config file to use -- either an existing one or the one to be created
+    pub chosen: PathBuf,
+    /// Every candidate considered, in order
+    pub considered: Vec<PathBuf>,
+    /// Tells if [Self::chosen] was found to exist
+    pub exists: bool,
+    /// Where the config file is to be created instead of [Self::chosen], if creating it there is refused -- like in
+    /// `C:\Program Files\`, on Windows, where the per-user [ConfigLocation::PlatformConfigDir] is used instead
+    pub fallback: Option<PathBuf>,
+}
+
+/// Always holds the latest successfully parsed config, for programs reloading their configs while running
+/// -- see `watch_config()` & `reload_on_sighup()` (behind the `watch` & `sighup` features).
+/// Use `.borrow()` for the current value & `.changed().await` to be notified of reloads
+#[cfg(feature = "async")]
+pub type ConfigSubscription<RootConfigType> = tokio::sync::watch::Receiver<std::sync::Arc<RootConfigType>>;
+
+/// Builds the effective config out of layers -- config files, environment variables & the command line -- each one overlaying
+/// the ones added before it. For instance:
+/// ```nocompile
+///   let config = ConfigMeld::<AppRootConfig>::new()
+///       .file("/etc/myapp/myapp.config.yaml")
+///       .env_prefix("MYAPP")
+///       .cli::<MyCmdLineOptions>()
+///       .tail_docs(&DOCS)
+///       .strict_unknown_keys(true)
+///       .load().await?;
+/// ```
+/// See [ConfigMeld::load()] for how the layers are applied
+#[cfg(feature = "async")]
+pub struct ConfigMeld<RootConfigType, CmdLineOptionsType = NoCmdLine> {
+    pub(crate) layers: Vec<ConfigLayer>,
+    /// The command line to parse instead of the program's one -- see [ConfigMeld::args()]
+    pub(crate) args: Option<Vec<std::ffi::OsString>>,
+    pub(crate) tail_docs: String,
+    pub(crate) strict_unknown_keys: bool,
+    pub(crate) types: std::marker::PhantomData<fn() -> (RootConfigType, CmdLineOptionsType)>,
+}
+
+/// Stands for the lack of a command line layer in a [ConfigMeld] -- see [ConfigMeld::cli()]
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct NoCmdLine;
+
+/// The layers of a [ConfigMeld], in the order they are applied
+#[cfg(feature = "async")]
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum ConfigLayer {
+    /// The config file at the given path -- see [ConfigMeld::file()]
+    File(PathBuf),
+    /// The environment variables with the given prefix -- see [ConfigMeld::env_prefix()]
+    Env(String),
+    /// The command line options -- see [ConfigMeld::cli()]
+    CmdLine,
+}
+
+/// Options for melding the config file with the command line options -- see [CmdLineAndConfigIntegration::meld_options()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeldOptions {
+    /// Where & how the config file is backed up when it gets rewritten or reset -- see [crate::backup_config_file()]
+    pub backup_policy: BackupPolicy,
+    /// How the config file is saved when it gets rewritten -- see [CmdLineAndConfigIntegration::should_write_effective_config()]
+    pub save_options: SaveOptions,
+    /// When rewriting the config file, the load-merge-rewrite sequence holds its advisory lock (see [crate::lock_config_file()]):
+    /// this is how long to wait for other processes holding it before failing with [Error::ConfigLocked]. Defaults to 10s.
+    pub lock_timeout: std::time::Duration,
+    /// What to do when the default config file can't be created -- like on read-only filesystems.
+    /// Defaults to [OnCreateFailure::Fail].
+    pub on_create_failure: OnCreateFailure,
+    /// If `true`, failing to persist the rewritten config file (see [CmdLineAndConfigIntegration::should_write_effective_config()])
+    /// due to filesystem issues -- like it being read-only -- is just warned about, with the program going on with the
+    /// in-memory effective config. Other errors are still reported. See [Error::is_persistence_error()].
+    /// Defaults to `false`, where any error is reported.
+    pub best_effort_persist: bool,
+    /// If `true`, the config file is rewritten even if it was changed by someone else since it was loaded -- discarding those changes.
+    /// Defaults to `false`, where such rewrites are aborted with [Error::ConfigChangedOnDisk]. Frozen config files are never overwritten -- see [crate::is_frozen()]
+    pub force_overwrite: bool,
+    /// What to do when the config file can't be backed up before being rewritten -- see [crate::backup_config_file()].
+    /// Defaults to [OnBackupFailure::Fail].
+    pub on_backup_failure: OnBackupFailure,
+    /// How the config file is rewritten with the effective config. Defaults to [RewriteStyle::Regenerate].
+    pub rewrite_style: RewriteStyle,
+    /// The version of the program, recorded in the [RewriteHeader] of the rewritten config files -- typically
+    /// `Some(env!("CARGO_PKG_VERSION").to_string())`, expanded in the program's own crate. Defaults to `None`.
+    pub program_version: Option<String>,
+    /// How configs are fetched when the config file path is an `http://` or `https://` URL -- see [crate::load_from_url_with_options()].
+    /// Such remote configs are read-only: they can't be rewritten nor reset.
+    #[cfg(feature = "http")]
+    pub remote_options: crate::RemoteOptions,
+}
+
+impl Default for MeldOptions {
+    fn default() -> Self {
+        Self {
+            backup_policy: BackupPolicy::default(),
+            save_options: SaveOptions::default(),
+            lock_timeout: std::time::Duration::from_secs(10),
+            on_create_failure: OnCreateFailure::default(),
+            best_effort_persist: false,
+            force_overwrite: false,
+            on_backup_failure: OnBackupFailure::default(),
+            rewrite_style: RewriteStyle::default(),
+            program_version: None,
+            #[cfg(feature = "http")]
+            remote_options: crate::RemoteOptions::default(),
+        }
+    }
+}
+
+/// What to do when the default config file can't be created -- see [crate::load_or_create_default_with_policy()]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OnCreateFailure {
+    /// The error is reported
+    #[default]
+    Fail,
+    /// A warning is issued & the default config is used without being persisted -- for read-only filesystems.
+    /// Only persistence errors are tolerated (see [Error::is_persistence_error()])
+    WarnAndUseDefaults,
+}
+
+/// Where & how config files are backed up before being rewritten or reset -- see [crate::backup_config_file()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackupPolicy {
+    /// How backups are named: either a pattern with the `{name}` (the config file name) & `{timestamp}` placeholders
+    /// -- like `{name}.{timestamp}.orig` -- or a plain suffix, standing for `{name}<suffix>{timestamp}`.
+    /// Timestamps are `YYYYmmdd-HHMMSS`, followed by `-<sequence>` for backups made within the same second.
+    /// Defaults to `.bak-`.
+    pub suffix_or_pattern: String,
+    /// The directory to keep the backups in -- relative to the config file's directory, if relative -- created if missing.
+    /// Defaults to `None`, for the backups to be kept alongside the config file.
+    pub directory: Option<PathBuf>,
+    /// How many backups to keep (at least the one just made): older ones are pruned. Defaults to 5.
+    pub keep: usize,
+}
+
+impl Default for BackupPolicy {
+    fn default() -> Self {
+        Self {
+            suffix_or_pattern: ".bak-".to_string(),
+            directory: None,
+            keep: 5,
+        }
+    }
+}
+
+/// Where the effective config is written to -- see [CmdLineAndConfigIntegration::effective_config_output_path()]
+#[derive(Clone, Debug, PartialEq)]
+pub enum EffectiveConfigTarget {
+    /// The config file the config was loaded from is rewritten -- see [CmdLineAndConfigIntegration::should_write_effective_config()]
+    InPlace,
+    /// The effective config is written to this file, in the format implied by its extension -- the config file is left untouched
+    Path(PathBuf),
+}
+
+/// The machine-readable header of regenerated config files, telling how they came to be -- see [RewriteHeader::render()]
+/// & [RewriteHeader::parse()]. Only the paths of the fields changed by the command line are recorded, not their values
+#[derive(Clone, Debug, PartialEq)]
+pub struct RewriteHeader {
+    /// The version of this header's layout -- see [RewriteHeader::VERSION]
+    pub header_version: u32,
+    /// The version of `ogre-config-meld` that wrote the file
+    pub crate_version: String,
+    /// The version of the program that wrote the file -- see [MeldOptions::program_version]
+    pub program_version: Option<String>,
+    /// When the file was written, in UTC & RFC 3339 -- like `2024-05-01T12:34:56Z`
+    pub timestamp: String,
+    /// The host the file was written on, if known
+    pub hostname: Option<String>,
+    /// Where the previous file was backed up to -- `None` if there was none or if it couldn't be backed up
+    pub backup: Option<PathBuf>,
+    /// Tells if the previous file couldn't be backed up, so it was overwritten without a backup -- see [OnBackupFailure::OverwriteWithoutBackup]
+    pub backup_failed: bool,
+    /// The config file (or URL) the effective config was loaded from, when written to another file -- see [EffectiveConfigTarget::Path]
+    pub source: Option<PathBuf>,
+    /// The dotted paths of the fields the command line changed -- like `log_sub_config.sink`. See [crate::diff_configs()]
+    pub changed_fields: Vec<String>,
+}
+
+/// How the config file is rewritten with the effective config -- see [MeldOptions::rewrite_style]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RewriteStyle {
+    /// The file is regenerated from the effective config, documenting where it came from -- operators' comments are lost
+    #[default]
+    Regenerate,
+    /// Just the changed values are edited in the original text -- with new fields added after the last ones of their sections --
+    /// keeping all comments, blank lines & the ordering of the fields. Changes that can't be applied that way (like in
+    /// unusually formatted files) make the file be regenerated instead, with a warning
+    PreserveLayout,
+}
+
+/// What to do when the config file can't be backed up before being rewritten -- see [MeldOptions::on_backup_failure]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OnBackupFailure {
+    /// The error is reported & the config file is left untouched
+    #[default]
+    Fail,
+    /// A warning is issued & the config file is overwritten in place, atomically (as in [SaveOptions::durable]),
+    /// without a backup
+    OverwriteWithoutBackup,
+}
+
+/// Options for saving config files -- see [crate::save_to_file_with_options()]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SaveOptions {
+    /// If `true`, the config is written to a temporary file that is fsynced, then atomically renamed over the config file,
+    /// whose directory is also fsynced (where meaningful) -- so the new config survives power cuts.
+    /// Defaults to `false`, where the config file is simply overwritten.
+    pub durable: bool,
+    /// If `true`, struct fields equal to their `Default` values are left out of the file, for brevity
+    /// -- requiring `#[serde(default)]` on the config structs (see [OgreRootConfig]) for the file to load back.
+    /// Defaults to `false`, where all fields are written.
+    pub sparse: bool,
+    /// If `true`, struct fields holding `None` are left out of the file -- rather than written in the format's own way, like
+    /// RON's `None` or YAML's `null` -- so unset options don't clutter it. Such files still load, as missing `Option` fields are `None`
+    /// -- or their defaults, for `#[serde(default)]` structs: so `None`s whose `Default` counterparts are `Some` are still written.
+    /// Defaults to `false`, where all fields are written.
+    pub omit_none: bool,
+    /// If `true`, missing parent directories of the config file are created (like `mkdir -p`) before writing it.
+    /// Defaults to `false` -- but [crate::load_or_create_default()] always creates them, as is needed on the first run
+    /// for paths like `~/.config/myapp/myapp.config.ron`.
+    pub create_parents: bool,
+    /// If `true`, a config file that is a symlink is replaced by a regular file when saved -- detaching it from the link's target.
+    /// Defaults to `false`, where the symlink is resolved & its target is written to instead, keeping the link
+    /// (like the ones into config management stores) in place.
+    pub replace_symlink: bool,
+    /// If set, the config file's advisory lock (see [crate::lock_config_file()]) is held while saving,
+    /// waiting up to the given timeout for other processes holding it -- failing with [Error::ConfigLocked] on expiry.
+    /// Defaults to `None`, where no lock is taken.
+    pub locked: Option<std::time::Duration>,
+    /// How the tail docs are commented out in the saved file -- see [CommentStyle].
+    /// Defaults to `None`, for the format's own style: `/* */` blocks for RON & `# ` prefixed lines for YAML.
+    /// For tooling that prefers RON line comments, use `CommentStyle::LinePrefix("// ".to_string())`
+    pub comment_style: Option<CommentStyle>,
+    /// If set, the config is saved in this format -- which the extension of the config file, if known, must agree with:
+    /// contradictions (like YAML for a `.ron` file) are refused with [Error::UnsupportedConfigFileFormat].
+    /// Defaults to `None`, where the format is the one implied by the extension. See [LoadOptions::format]
+    pub format: Option<crate::SerdeFormat>,
+}
+
+/// How the tail docs are commented out in saved config files -- see [SaveOptions::comment_style].
+/// Note that styles foreign to the file's format produce files that don't load back -- unless
+/// they are embedded in something that strips them (like shell heredocs or templates)
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommentStyle {
+    /// Each line is prefixed, like with `# ` or `// `
+    LinePrefix(String),
+    /// The whole docs are enclosed between the `open` & `close` markers, each in their own lines -- like `/*` & `*/`
+    Block {
+        open: String,
+        close: String,
+    },
+}
+
+/// An advisory lock on a config file, serializing its rewrites among processes -- released when dropped.
+/// See [crate::lock_config_file()]
+#[derive(Debug)]
+pub struct ConfigFileLock {
+    pub(crate) lock_file: std::fs::File,
+}
+
+impl Drop for ConfigFileLock {
+    fn drop(&mut self) {
+        // closing the file would release it anyway
+        _ = self.lock_file.unlock();
+    }
+}
+
+/// The filesystem operations config files are read, saved & backed up through -- [RealFs] being the one the public API uses,
+/// while the tests' in-memory one keeps them off the disk, simulating failures at specific steps
+#[cfg(feature = "async")]
+pub(crate) trait ConfigFs: Send + Sync {
+    fn read(&self, path: &Path) -> impl std::future::Future<Output = std::io::Result<Vec<u8>>> + Send;
+    fn read_to_string(&self, path: &Path) -> impl std::future::Future<Output = std::io::Result<String>> + Send;
+    /// Creates or truncates the file at `path` with the given `contents`
+    fn write(&self, path: &Path, contents: &[u8]) -> impl std::future::Future<Output = std::io::Result<()>> + Send;
+    /// Moves the file at `from` to `to`, replacing whatever was there
+    fn rename(&self, from: &Path, to: &Path) -> impl std::future::Future<Output = std::io::Result<()>> + Send;
+    /// Copies the contents of the file at `from` -- or of the file a symlink there points to -- to `to`
+    fn copy(&self, from: &Path, to: &Path) -> impl std::future::Future<Output = std::io::Result<()>> + Send;
+    fn remove_file(&self, path: &Path) -> impl std::future::Future<Output = std::io::Result<()>> + Send;
+    fn create_dir_all(&self, path: &Path) -> impl std::future::Future<Output = std::io::Result<()>> + Send;
+    /// The metadata of the file at `path` -- following symlinks
+    fn metadata(&self, path: &Path) -> impl std::future::Future<Output = std::io::Result<FileMetadata>> + Send;
+    /// The metadata of the file at `path` -- of the link itself, for symlinks
+    fn symlink_metadata(&self, path: &Path) -> impl std::future::Future<Output = std::io::Result<FileMetadata>> + Send;
+    /// Tells if there is a file or directory at `path` -- following symlinks
+    fn exists(&self, path: &Path) -> impl std::future::Future<Output = bool> + Send;
+    /// The paths of the entries of the directory at `path`
+    fn read_dir(&self, path: &Path) -> impl std::future::Future<Output = std::io::Result<Vec<PathBuf>>> + Send;
+    /// Where the symlink at `path` points to -- as it is written in the link
+    fn read_link(&self, path: &Path) -> impl std::future::Future<Output = std::io::Result<PathBuf>> + Send;
+    /// Flushes the file or directory at `path` to the storage device -- see [SaveOptions::durable]
+    fn sync(&self, path: &Path) -> impl std::future::Future<Output = std::io::Result<()>> + Send;
+    /// Applies the permissions -- and, on Unix, the ownership -- from the `original` metadata of a replaced file to the one at `path`
+    fn restore_metadata(&self, path: &Path, original: &FileMetadata) -> impl std::future::Future<Output = std::io::Result<()>> + Send;
+}
+
+/// What a [ConfigFs] tells about a file -- see [ConfigFs::symlink_metadata()]
+#[cfg(feature = "async")]
+#[derive(Clone, Debug)]
+pub(crate) struct FileMetadata {
+    pub(crate) is_file: bool,
+    pub(crate) is_symlink: bool,
+    /// The metadata given by the OS, for filesystems backed by it -- like [RealFs]
+    pub(crate) os_metadata: Option<std::fs::Metadata>,
+}
+
+/// The real filesystem, as a [ConfigFs] -- encrypting & decrypting files transparently, through [crate::encryptable_tokio_fs]
+#[cfg(feature = "async")]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct RealFs;
+
+/// What a config file looked like when it was loaded -- its modification time & size -- for telling if someone else changed it
+/// since then, like before rewriting it. See [LoadedFileFingerprint::of()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadedFileFingerprint {
+    pub(crate) modified: Option<std::time::SystemTime>,
+    pub(crate) len: u64,
+}
+
+/// A loaded config along with where it came from -- so it may be rewritten to the same file & format with
+/// [LoadedConfig::save_to_file()], without the caller having to keep track of them.
+/// See [crate::load_or_create_default_traced()] & [crate::parse_cmdline_and_merge_with_loaded_configs_traced()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadedConfig<RootConfigType> {
+    pub config: RootConfigType,
+    /// The config file the config was loaded from (or created at) -- or the URL of a remote config
+    pub path: PathBuf,
+    pub format: crate::SerdeFormat,
+    /// Tells if the config file didn't exist, so it was just created with the default values -- also the case for recovered files
+    pub created_now: bool,
+    /// What the config file looked like after being loaded (or created) -- `None` for remote configs
+    /// and for files that couldn't be created (see [OnCreateFailure::WarnAndUseDefaults])
+    pub fingerprint: Option<LoadedFileFingerprint>,
+}
+
+/// Options for loading config files -- see [crate::load_from_file_with_options()]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadOptions {
+    /// What to do with YAML config files containing several `---` separated documents
+    pub yaml_multi_documents: YamlMultiDocuments,
+    /// What to do with YAML config files having the same key more than once in a mapping
+    pub duplicate_keys: DuplicateKeys,
+    /// Whether `${VAR}` / `$VAR` references to environment variables inside string values should be expanded
+    pub env_interpolation: EnvInterpolation,
+    /// If set, config files are validated against this JSON Schema before being deserialized into their types -- reporting violations
+    /// precisely as [Error::SchemaViolation]. Validation follows the [Self::env_interpolation], so values are checked as the config will hold them.
+    /// See [crate::config_json_schema()].
+    #[cfg(feature = "schema")]
+    pub schema: Option<serde_json::Value>,
+    /// If set, the config is parsed in this format -- allowing files without an extension (or with unknown ones, like `.conf`).
+    /// Known extensions implying another format are refused with [Error::UnsupportedConfigFileFormat], as in [SaveOptions::format].
+    /// Defaults to `None`, where the format is the one implied by the extension
+    pub format: Option<crate::SerdeFormat>,
+    /// If set, config files written for other versions of the config types still load: fields missing from the file take
+    /// their default values & the ones unknown to the types are ignored -- only syntax errors & values of the wrong type fail.
+    /// Defaults come from the root config's `Default`, so fields missing from sequence elements -- or from `Option` sections
+    /// defaulting to `None` -- still fail without a `#[serde(default)]`.
+    /// Fit for rolling deployments, where older & newer binaries share the same files. See [crate::load_forward_compatible()]
+    pub forward_compatible: bool,
+}
+
+/// Where each value of the effective config came from -- keyed by the dotted paths of the fields (like `log_sub_config.sink`).
+/// Only the leaves are tracked: sections are attributed through their fields -- see [crate::parse_cmdline_and_merge_with_loaded_configs_traced()]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Provenance(pub(crate) std::collections::BTreeMap<String, Source>);
+
+/// The stage of the meld pipeline that last set a value of the effective config -- see [Provenance]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// The value is the default one, from [OgreRootConfig]'s `Default` implementation
+    Default,
+    /// The value was loaded from the config file at the given path
+    ConfigFile(PathBuf),
+    /// The value was given by the named environment variable -- through a command line option bound to it, like `#[clap(env = "APP_PORT")]`
+    EnvVar(String),
+    /// The value was given by the named command line option -- like `--sink`
+    CliFlag(String),
+    /// The value was given by a `key=value` config override -- see [CmdLineAndConfigIntegration::config_overrides()]
+    Override,
+}
+
+/// Non-fatal findings gathered while loading a config file -- see [crate::load_with_warnings()]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadWarnings(pub(crate) Vec<LoadWarning>);
+
+/// A single non-fatal finding while loading a config file -- see [LoadWarnings] -- or while running, given to the sink set
+/// with [crate::set_warnings_sink()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadWarning {
+    pub kind: LoadWarningKind,
+    /// The dotted path of the field the warning is about -- like `log_sub_config.sink` -- or empty, for the whole config
+    pub field_path: String,
+    pub message: String,
+}
+
+/// The kinds of [LoadWarning]s
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadWarningKind {
+    /// The field is present in the config file, but unknown to the config types -- so its value was ignored
+    UnknownField,
+    /// The YAML config file has further `---` separated documents, ignored as per [YamlMultiDocuments::FirstOnly]
+    IgnoredYamlDocuments,
+    /// The string value references an undefined environment variable, kept as-is as per [EnvInterpolation::KeepUndefined]
+    UndefinedEnvVar,
+    /// The field is deprecated -- the message tells what to use instead. See [OgreRootConfig::deprecated_fields()]
+    DeprecatedField,
+    /// While running: a watched config file couldn't be reloaded -- the last good config is kept
+    FailedReload,
+    /// While running, a notice: a config file was reloaded -- the message tells which fields changed
+    Reloaded,
+    /// While running, a notice: the effective config wasn't written, as the config file already holds it
+    RewriteSkipped,
+    /// While running: a config file couldn't be created, written, backed up or locked -- going on without persisting it, as the message tells
+    FailedPersistence,
+    /// While running: the config couldn't be had as expected, so a fallback was used -- like the cached copy of a remote config,
+    /// another config file candidate or, for unparseable config files, the default values
+    Fallback,
+}
+
+/// A difference between two configs -- see [crate::diff_configs()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    /// The dotted path of the changed field -- like `log_sub_config.sink` -- or empty, for the whole config
+    pub path: String,
+    /// The value before the change -- `Null` for `None`s & fields that didn't exist
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+/// A problem found in a config file by [crate::doctor()]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The dotted path of the field the problem is about -- like `log_sub_config.sink` -- or empty, for the whole config file
+    pub field_path: String,
+    pub message: String,
+}
+
+/// How serious a [Diagnostic] is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The config file loads, but something in it is likely not what was meant -- like the [LoadWarnings]
+    Warning,
+    /// The config file can't be loaded
+    Error,
+}
+
+/// Behaviors for expanding `${VAR}` / `$VAR` environment variable references inside the string values of the configs
+/// (`$$` stands for a literal `$`). Keys & field names are never expanded.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EnvInterpolation {
+    /// String values are loaded as-is
+    #[default]
+    Disabled,
+    /// References to undefined variables are left untouched
+    KeepUndefined,
+    /// References to undefined variables cause the loading to fail
+    FailOnUndefined,
+}
+
+/// Behaviors for loading YAML config files containing several `---` separated documents
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum YamlMultiDocuments {
+    /// Multiple documents are refused with [Error::MultipleYamlDocuments]
+    #[default]
+    Forbid,
+    /// Only the first document is loaded -- the others are ignored
+    FirstOnly,
+    /// All documents are merged, in order: values from latter documents override the ones from the former
+    MergeAll,
+}
+
+/// Behaviors for loading YAML config files having the same key more than once in a mapping -- usually a mistake, as only one of the values is taken.
+/// RON config files are unaffected: their repeated fields are always refused by the deserialization of the config types
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DuplicateKeys {
+    /// Repeated keys are refused with [Error::DuplicateKey] -- telling the one that was "set but ignored"
+    #[default]
+    Refuse,
+    /// The last of the values of a repeated key is taken, as with most YAML parsers
+    LastWins,
+}
+
+/// A [std::time::Duration] for config fields, written in a human-friendly way -- like `"90s"`, `"5m"` or `"1h 30m"`
+/// -- in all supported formats. Parsing accepts any combination of the units `ns`, `us`, `ms`, `s`, `m`, `h`, `d`, `w`, `M` & `y`
+/// (along with their long forms, like `minutes`), while writing uses the largest units fitting the value.
+/// Use it in config structs like this:
+/// ```nocompile
+///   pub struct ServerConfig { pub idle_timeout: ogre_config_meld::HumanDuration, ... }
+/// ```
+#[cfg(feature = "humantime")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HumanDuration(pub std::time::Duration);
+
+/// A point in time for config fields, written as an ISO 8601 / RFC 3339 timestamp -- like `"2024-03-15T10:30:00-03:00"`
+/// or `"2024-03-15T13:30:00Z"` -- in all supported formats. The offset it was written with is kept, so rewrites preserve it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub chrono::DateTime<chrono::FixedOffset>);
+
 /// Error variants for the `cli-configs` trait
 #[derive(Debug)]
 pub enum Error {
@@ -63,10 +1023,12 @@ pub enum Error {
     UnsupportedConfigFileFormat {
         message: String,
     },
+    #[cfg(feature = "ron")]
     Ron {
         message: String,
         cause: ron::Error,
     },
+    #[cfg(feature = "yaml")]
     Yaml {
         message: String,
         cause: serde_yaml::Error,
@@ -78,6 +1040,161 @@ pub enum Error {
     MergingLogicViolation {
         message: String,
     },
+    MultipleYamlDocuments {
+        message: String,
+    },
+    /// A YAML config file that couldn't be parsed has tabs in the indentation of the given (1-based) `line`
+    /// -- YAML only allows spaces there
+    YamlIndentation {
+        line: usize,
+        message: String,
+    },
+    /// A YAML config file has the same `key` more than once in a mapping -- at the given (1-based) `line`.
+    /// `key` is given with its parent keys, `.` separated (like `log_sub_config.sink`)
+    DuplicateKey {
+        key: String,
+        line: usize,
+        message: String,
+    },
+    /// The config file doesn't conform to the JSON Schema of its type (see the `schema` feature):
+    /// `path` is the JSON pointer to the offending value (like `/log_sub_config/sink`)
+    SchemaViolation {
+        path: String,
+        message: String,
+    },
+    /// The config layers have `fields` unknown to the config types -- given by their dotted paths, like `log_sub_config.colour`
+    /// (or by the names of the environment variables). See [ConfigMeld::strict_unknown_keys()]
+    UnknownFields {
+        fields: Vec<String>,
+        message: String,
+    },
+    /// A field without a default value (see [OgreRootConfig]) is missing from the config file
+    MissingRequiredField {
+        field: String,
+        message: String,
+    },
+    /// The config file at `path` was required to exist, but doesn't: the one explicitly given in the command line, the default one
+    /// when [CmdLineAndConfigIntegration::require_existing()] is set, the sources of `load_existing()` & `convert_config()`, or the
+    /// ones being watched or reloaded -- or, with an empty `path`, the one [ConfigMeld::load_traced()] had no file layered to tie the config to.
+    /// `hint` tells why the file was required & what to do -- to be shown to the user
+    ConfigFileNotFound {
+        path: PathBuf,
+        hint: String,
+    },
+    /// The config couldn't be fetched from `url` -- due to network or HTTP failures. See the `remote` feature
+    RemoteConfig {
+        url: String,
+        message: String,
+        cause: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The advisory lock file at `path` was held by someone else for longer than `timeout` -- see [crate::lock_config_file()]
+    ConfigLocked {
+        path: PathBuf,
+        timeout: std::time::Duration,
+    },
+    /// The config file at `path` was changed by someone else since it was loaded, so rewriting it would discard those changes
+    /// -- see [MeldOptions::force_overwrite]
+    ConfigChangedOnDisk {
+        path: PathBuf,
+        message: String,
+    },
+    /// The config file at `path` is marked as frozen, so it may not be rewritten -- see [crate::is_frozen()]
+    ConfigFrozen {
+        path: PathBuf,
+        message: String,
+    },
+    /// The `operation` requires the async API -- like loading encrypted config files or backing them up -- but the blocking one was used.
+    /// See [crate::blocking]
+    AsyncOnly {
+        operation: String,
+    },
+    /// The command line arguments couldn't be parsed -- `rendered_help` has the explanation for the user
+    /// and `exit_hint` the suggested exit code for the program. See [Error::exit_if_cli()]
+    CliParsing {
+        rendered_help: String,
+        exit_hint: i32,
+    },
+    /// Not really an error: `--help` was requested in the command line. See [Error::exit_if_cli()]
+    CliHelp {
+        rendered_help: String,
+    },
+    /// Not really an error: `--version` was requested in the command line. See [Error::exit_if_cli()]
+    CliVersion {
+        rendered_version: String,
+    },
+    /// Not really an error: `--help-config` was requested in the command line -- `rendered_docs` documents the config fields.
+    /// See [Error::exit_if_cli()]
+    ConfigHelp {
+        rendered_docs: String,
+    },
+    /// Not really an error: `--reset-config` was requested in the command line & the config file was reset -- `rendered_report`
+    /// tells where it is & where the previous one was backed up to. See [Error::exit_if_cli()]
+    ConfigReset {
+        rendered_report: String,
+    },
+}
+
+impl Error {
+    /// Tells if this error is due to the contents of a config file not being parseable
+    pub fn is_parsing_error(&self) -> bool {
+        match self {
+            #[cfg(feature = "ron")]
+            Error::Ron { .. } => true,
+            #[cfg(feature = "yaml")]
+            Error::Yaml { .. } => true,
+            Error::MultipleYamlDocuments { .. } | Error::YamlIndentation { .. } | Error::DuplicateKey { .. } | Error::SchemaViolation { .. } | Error::MissingRequiredField { .. } => true,
+            Error::LoadingConfig { cause, .. } => cause.downcast_ref::<Error>().is_some_and(Error::is_parsing_error),
+            _ => false,
+        }
+    }
+
+    /// Tells if this error is due to the filesystem refusing to persist a config file -- like it being read-only or full
+    pub fn is_persistence_error(&self) -> bool {
+        match self {
+            Error::Io { .. } | Error::ConfigLocked { .. } => true,
+            Error::SavingConfig { cause, .. } => cause.downcast_ref::<std::io::Error>().is_some()
+                || cause.downcast_ref::<Error>().is_some_and(Error::is_persistence_error),
+            _ => false,
+        }
+    }
+
+    /// Reproduces, for binaries, the traditional `clap` behavior for the command line variants of this error:
+    /// the help, config docs, version, reset report or parsing error message is printed and the program exits. Other variants are returned as-is.
+    /// Use it like this:
+    /// ```nocompile
+    ///   let config = parse_cmdline_and_merge_with_loaded_configs::<MyCmdLineOptions, MyRootConfig>(&DOCS).await
+    ///       .map_err(Error::exit_if_cli)?;
+    pub fn exit_if_cli(self) -> Self {
+        match self {
+            Error::CliParsing { rendered_help, exit_hint } => {
+                eprint!("{rendered_help}");
+                std::process::exit(exit_hint);
+            },
+            Error::CliHelp { rendered_help: rendered } | Error::CliVersion { rendered_version: rendered }
+            | Error::ConfigHelp { rendered_docs: rendered } | Error::ConfigReset { rendered_report: rendered } => {
+                print!("{rendered}");
+                std::process::exit(0);
+            },
+            other => other,
+        }
+    }
+}
+
+impl From<clap::Error> for Error {
+    fn from(clap_error: clap::Error) -> Self {
+        match clap_error.kind() {
+            clap::error::ErrorKind::DisplayHelp => Error::CliHelp {
+                rendered_help: clap_error.render().to_string(),
+            },
+            clap::error::ErrorKind::DisplayVersion => Error::CliVersion {
+                rendered_version: clap_error.render().to_string(),
+            },
+            _ => Error::CliParsing {
+                rendered_help: clap_error.render().to_string(),
+                exit_hint: clap_error.exit_code(),
+            },
+        }
+    }
 }
 
 impl Display for Error {
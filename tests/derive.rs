@@ -0,0 +1,125 @@
+//! Tests `#[derive(CmdLineAndConfigIntegration)]` -- as used by user programs, with the `derive` feature
+
+use ogre_config_meld::clap::Parser;
+use ogre_config_meld::{CmdLineAndConfigIntegration, OgreRootConfig};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct LogConfig {
+    level: u8,
+    file: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct ServiceConfig {
+    name: String,
+    port: u16,
+    verbose: bool,
+    hosts: Vec<String>,
+    log: LogConfig,
+}
+impl OgreRootConfig for ServiceConfig {}
+
+#[derive(ogre_config_meld::clap::Args, Clone, Debug)]
+struct UnrelatedArgs {
+    #[clap(long)]
+    dry_run: bool,
+}
+
+#[derive(Parser, CmdLineAndConfigIntegration, Clone, Debug)]
+#[config(root = ServiceConfig)]
+struct ServiceOptions {
+    #[clap(long, short = 'c')]
+    #[config(file)]
+    config_file: Option<String>,
+    #[clap(long)]
+    #[config(write_effective)]
+    write_effective_config: bool,
+    #[clap(long)]
+    #[config(show_effective)]
+    show_effective_config: bool,
+    #[clap(long)]
+    #[config(skip)]
+    print_config_path: bool,
+    #[clap(long)]
+    name: Option<String>,
+    /// complex clap attributes -- even mentioning `flatten` -- shouldn't keep it from being merged
+    #[clap(long, value_parser = ogre_config_meld::clap::value_parser!(u16).range(1..), help = "the port,flatten")]
+    port: Option<u16>,
+    #[clap(long)]
+    verbose: bool,
+    #[clap(long)]
+    hosts: Vec<String>,
+    #[clap(long)]
+    #[config(path = "log.level")]
+    log_level: Option<u8>,
+    #[clap(long)]
+    #[config(path = "log.file")]
+    log_file: Option<String>,
+    #[clap(flatten)]
+    unrelated: UnrelatedArgs,
+}
+
+#[derive(Parser, CmdLineAndConfigIntegration, Debug)]
+#[config(root = ServiceConfig)]
+struct MinimalOptions {
+    #[clap(long)]
+    port: Option<u16>,
+}
+
+fn configured_service() -> ServiceConfig {
+    ServiceConfig {
+        name: "service".to_string(),
+        port: 80,
+        verbose: false,
+        hosts: vec!["localhost".to_string()],
+        log: LogConfig { level: 1, file: Some("/var/log/service.log".to_string()) },
+    }
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn derived_flags() {
+    let cmdline_options = ServiceOptions::parse_from(["test", "-c", "service.yaml", "--write-effective-config", "--print-config-path"]);
+    assert_eq!(cmdline_options.config_file_path(), Some("service.yaml"), "Wrong config file");
+    assert!(cmdline_options.should_write_effective_config(), "`--write-effective-config` was given");
+    assert!(!cmdline_options.should_show_effective_config(), "`--show-effective-config` wasn't given");
+    assert!(cmdline_options.print_config_path && !cmdline_options.unrelated.dry_run, "The options weren't parsed as expected");
+
+    let cmdline_options = MinimalOptions::parse_from(["test"]);
+    assert_eq!(cmdline_options.config_file_path(), None, "Without a `#[config(file)]` field, no config file may be given");
+    assert!(!cmdline_options.should_write_effective_config() && !cmdline_options.should_show_effective_config(),
+            "Without their fields, the flags should be off");
+}
+
+#[test]
+fn derived_merge() {
+    let cmdline_options = ServiceOptions::parse_from(["test"]);
+    assert_eq!(cmdline_options.merge_with_config(configured_service()).unwrap(), configured_service(),
+               "Options not given shouldn't change the config");
+
+    let cmdline_options = ServiceOptions::parse_from(["test", "--port", "8080", "--verbose", "--hosts", "a", "--hosts", "b",
+                                                      "--log-level", "3", "--log-file", "/tmp/service.log", "--dry-run"]);
+    let expected_config = ServiceConfig {
+        name: "service".to_string(),
+        port: 8080,
+        verbose: true,
+        hosts: vec!["a".to_string(), "b".to_string()],
+        log: LogConfig { level: 3, file: Some("/tmp/service.log".to_string()) },
+    };
+    assert_eq!(cmdline_options.merge_with_config(configured_service()).unwrap(), expected_config,
+               "The given options should have been merged into the fields of the same names -- or of the given paths");
+
+    let cmdline_options = MinimalOptions::parse_from(["test", "--port", "443"]);
+    assert_eq!(cmdline_options.merge_with_config(configured_service()).unwrap().port, 443, "Wrong merge for the minimal options");
+}
+
+#[test]
+fn derived_cli_to_config_paths() {
+    assert_eq!(ServiceOptions::cli_to_config_paths(),
+               &[("name", "name"), ("port", "port"), ("verbose", "verbose"), ("hosts", "hosts"), ("log_level", "log.level"), ("log_file", "log.file")],
+               "Every merged option should be mapped to its config field -- skipped, flattened & flag options excluded");
+    assert_eq!(MinimalOptions::cli_to_config_paths(), &[("port", "port")], "Wrong mapping for the minimal options");
+}